@@ -0,0 +1,65 @@
+// 最小的原生插件示例：立体声增益
+// 构建: cargo build -p plugin-sdk --example gain --release
+// 把生成的 libgain.so 复制到 ~/.plugin-loader/plugins 即可被扫描到
+
+use plugin_sdk::{export_plugins, Parameter, ParameterRange, Plugin};
+use std::ffi::CStr;
+
+struct Gain {
+    /// 0.0 - 1.0 对应 -24 dB 到 +24 dB
+    gain: f64,
+}
+
+impl Gain {
+    fn db(&self) -> f64 {
+        self.gain * 48.0 - 24.0
+    }
+}
+
+impl Plugin for Gain {
+    const ID: &'static CStr = c"com.example.gain";
+    const NAME: &'static CStr = c"Example Gain";
+    const VENDOR: &'static CStr = c"Example";
+    const VERSION: &'static CStr = c"1.0.0";
+
+    fn new(_sample_rate: f64) -> Self {
+        Self { gain: 0.5 }
+    }
+
+    fn process(&mut self, buffer: &mut [f32]) {
+        let factor = 10f64.powf(self.db() / 20.0) as f32;
+        for sample in buffer {
+            *sample *= factor;
+        }
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![Parameter {
+            id: 0,
+            name: "Gain".to_string(),
+            value: self.gain,
+            display: format!("{:+.1} dB", self.db()),
+        }]
+    }
+
+    fn parameter_range(&self, id: u32) -> Option<ParameterRange> {
+        (id == 0).then(|| ParameterRange::new(-24.0, 24.0, 0.0).with_unit("dB"))
+    }
+
+    fn set_parameter(&mut self, id: u32, value: f64) {
+        if id == 0 {
+            self.gain = value.clamp(0.0, 1.0);
+        }
+    }
+
+    // 增益没有随采样率变化的状态，也没有需要清空的历史
+    fn prepare(&mut self, _sample_rate: f64, _max_block_size: usize) -> bool {
+        true
+    }
+
+    fn reset(&mut self) -> bool {
+        true
+    }
+}
+
+export_plugins!(Gain);
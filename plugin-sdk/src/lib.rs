@@ -0,0 +1,594 @@
+//! plugin-loader 原生插件 SDK
+//!
+//! 用 Rust 编写效果器并编译为 cdylib，由 plugin-loader 通过 dlopen 加载。
+//! 插件作者只需实现 [`Plugin`] trait，再用 [`export_plugins!`] 导出入口函数：
+//!
+//! ```ignore
+//! use plugin_sdk::{export_plugins, Plugin};
+//! use std::ffi::CStr;
+//!
+//! struct Gain { gain: f32 }
+//!
+//! impl Plugin for Gain {
+//!     const ID: &'static CStr = c"com.example.gain";
+//!     const NAME: &'static CStr = c"Gain";
+//!     const VENDOR: &'static CStr = c"Example";
+//!     const VERSION: &'static CStr = c"1.0.0";
+//!
+//!     fn new(_sample_rate: f64) -> Self { Gain { gain: 1.0 } }
+//!     fn process(&mut self, buffer: &mut [f32]) { buffer.iter_mut().for_each(|s| *s *= self.gain) }
+//! }
+//!
+//! export_plugins!(Gain);
+//! ```
+//!
+//! ABI 约定：
+//! - 库导出 `plugin_loader_entry(index) -> *const PluginDescriptor`，返回空指针表示结束
+//! - 描述符的第一个字段是 `abi_version`，宿主只加载与 [`ABI_VERSION`] 相同的插件
+//! - 音频为交错立体声 f32，与宿主的 `AudioProcessor::process` 一致
+//! - 虚表中的 `prepare` / `reset` / `latency` 是可选的（空指针表示不支持），
+//!   不支持 prepare 或 reset 时宿主销毁并重建实例，状态通过 save_state / load_state 保留
+
+use std::ffi::{c_char, c_void, CStr};
+use std::panic::{self, AssertUnwindSafe};
+
+/// 当前 ABI 版本（描述符或虚表布局变化时递增）
+/// v2: 新增 parameter_range / parameter_value_name
+/// v3: 新增可选的 prepare / reset / latency
+pub const ABI_VERSION: u32 = 3;
+
+/// 入口函数符号名
+pub const ENTRY_SYMBOL: &[u8] = b"plugin_loader_entry\0";
+
+/// 参数名称 / 显示文本的缓冲区大小（含结尾的 0）
+pub const TEXT_SIZE: usize = 64;
+
+/// 参数信息（UTF-8，以 0 结尾）
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct ParameterInfo {
+    pub id: u32,
+    pub value: f64,
+    pub name: [c_char; TEXT_SIZE],
+    pub display: [c_char; TEXT_SIZE],
+}
+
+impl ParameterInfo {
+    pub const fn empty() -> Self {
+        Self {
+            id: 0,
+            value: 0.0,
+            name: [0; TEXT_SIZE],
+            display: [0; TEXT_SIZE],
+        }
+    }
+}
+
+/// 参数标志（ParameterRangeInfo::flags）
+pub const PARAM_AUTOMATABLE: u32 = 1 << 0;
+pub const PARAM_READ_ONLY: u32 = 1 << 1;
+pub const PARAM_BYPASS: u32 = 1 << 2;
+pub const PARAM_HIDDEN: u32 = 1 << 3;
+pub const PARAM_LOGARITHMIC: u32 = 1 << 4;
+
+/// 参数的取值范围（实际值）和显示信息
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct ParameterRangeInfo {
+    pub min: f64,
+    pub max: f64,
+    pub default: f64,
+    /// 离散步数，0 表示连续
+    pub steps: u32,
+    pub flags: u32,
+    /// 枚举值个数（通过 parameter_value_name 读取）
+    pub value_count: u32,
+    pub unit: [c_char; TEXT_SIZE],
+}
+
+impl ParameterRangeInfo {
+    pub const fn empty() -> Self {
+        Self {
+            min: 0.0,
+            max: 1.0,
+            default: 0.0,
+            steps: 0,
+            flags: 0,
+            value_count: 0,
+            unit: [0; TEXT_SIZE],
+        }
+    }
+}
+
+/// save_state 时由宿主提供的写入回调
+pub type StateWriter = unsafe extern "C" fn(context: *mut c_void, data: *const u8, len: usize);
+
+/// 采样率或最大块长变化时调用，返回 false 表示不支持（宿主改为重建实例）
+pub type PrepareFunction = unsafe extern "C" fn(instance: *mut c_void, sample_rate: f64, max_block_size: u32) -> bool;
+
+/// 清空处理状态，返回 false 表示不支持（宿主改为重建实例）
+pub type ResetFunction = unsafe extern "C" fn(instance: *mut c_void) -> bool;
+
+/// 处理延迟（采样），宿主用于延迟补偿
+pub type LatencyFunction = unsafe extern "C" fn(instance: *mut c_void) -> u32;
+
+/// 插件虚表（与宿主的 AudioProcessor 一一对应）
+#[repr(C)]
+pub struct PluginVTable {
+    pub create: unsafe extern "C" fn(sample_rate: f64) -> *mut c_void,
+    pub destroy: unsafe extern "C" fn(instance: *mut c_void),
+    pub process: unsafe extern "C" fn(instance: *mut c_void, buffer: *mut f32, len: usize),
+    pub parameter_count: unsafe extern "C" fn(instance: *mut c_void) -> u32,
+    pub parameter_info: unsafe extern "C" fn(instance: *mut c_void, index: u32, info: *mut ParameterInfo) -> bool,
+    pub set_parameter: unsafe extern "C" fn(instance: *mut c_void, id: u32, value: f64),
+    pub get_parameter: unsafe extern "C" fn(instance: *mut c_void, id: u32, value: *mut f64) -> bool,
+    pub save_state: unsafe extern "C" fn(instance: *mut c_void, context: *mut c_void, write: StateWriter),
+    pub load_state: unsafe extern "C" fn(instance: *mut c_void, data: *const u8, len: usize) -> bool,
+    /// 插件没有提供范围信息时返回 false
+    pub parameter_range: unsafe extern "C" fn(instance: *mut c_void, id: u32, info: *mut ParameterRangeInfo) -> bool,
+    /// name 指向 TEXT_SIZE 字节的缓冲区
+    pub parameter_value_name: unsafe extern "C" fn(instance: *mut c_void, id: u32, index: u32, name: *mut c_char) -> bool,
+    /// 可选（为空表示不支持）
+    pub prepare: Option<PrepareFunction>,
+    pub reset: Option<ResetFunction>,
+    pub latency: Option<LatencyFunction>,
+}
+
+/// 插件描述符
+#[repr(C)]
+pub struct PluginDescriptor {
+    /// 必须是第一个字段，宿主先检查版本再读取其余部分
+    pub abi_version: u32,
+    pub id: *const c_char,
+    pub name: *const c_char,
+    pub vendor: *const c_char,
+    pub version: *const c_char,
+    pub num_inputs: u32,
+    pub num_outputs: u32,
+    pub vtable: PluginVTable,
+}
+
+/// 入口函数类型
+pub type EntryFunction = unsafe extern "C" fn(index: u32) -> *const PluginDescriptor;
+
+/// 参数（与宿主的 PluginParameter 对应，value 为 0.0 - 1.0）
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    pub id: u32,
+    pub name: String,
+    pub value: f64,
+    pub display: String,
+}
+
+/// 参数的实际值范围和显示信息（可选，宿主用来绘制旋钮、开关和下拉框）
+/// 插件接口上的参数值仍然是 0.0 - 1.0
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParameterRange {
+    pub min: f64,
+    pub max: f64,
+    pub default: f64,
+    pub unit: String,
+    /// 离散步数，0 表示连续
+    pub steps: u32,
+    /// 枚举名，依次对应 min, min + 1, ...
+    pub value_names: Vec<String>,
+    /// PARAM_* 标志
+    pub flags: u32,
+}
+
+impl ParameterRange {
+    /// 连续、可自动化的参数
+    pub fn new(min: f64, max: f64, default: f64) -> Self {
+        Self {
+            min,
+            max,
+            default,
+            unit: String::new(),
+            steps: 0,
+            value_names: Vec::new(),
+            flags: PARAM_AUTOMATABLE,
+        }
+    }
+
+    pub fn with_unit(mut self, unit: &str) -> Self {
+        self.unit = unit.to_string();
+        self
+    }
+
+    /// 枚举参数：取值为 0, 1, ..., names.len() - 1
+    pub fn enumeration(names: &[&str], default: usize) -> Self {
+        let last = names.len().saturating_sub(1) as f64;
+        Self {
+            steps: last as u32,
+            value_names: names.iter().map(|n| n.to_string()).collect(),
+            ..Self::new(0.0, last, default as f64)
+        }
+    }
+}
+
+/// 插件作者实现的 trait
+pub trait Plugin: Send + Sized + 'static {
+    const ID: &'static CStr;
+    const NAME: &'static CStr;
+    const VENDOR: &'static CStr;
+    const VERSION: &'static CStr;
+    const NUM_INPUTS: u32 = 2;
+    const NUM_OUTPUTS: u32 = 2;
+
+    /// 创建实例
+    fn new(sample_rate: f64) -> Self;
+
+    /// 处理交错立体声音频（原地修改）
+    fn process(&mut self, buffer: &mut [f32]);
+
+    /// 采样率或最大块长变化时调用（不在音频线程中）
+    /// 返回 false 表示不支持，宿主会用新采样率重建实例并恢复状态
+    fn prepare(&mut self, _sample_rate: f64, _max_block_size: usize) -> bool {
+        false
+    }
+
+    /// 清空延迟线、滤波器历史等处理状态，参数保持不变
+    /// 返回 false 表示不支持，宿主会重建实例并恢复状态
+    fn reset(&mut self) -> bool {
+        false
+    }
+
+    /// 处理延迟（采样），有预读的插件需要报告，宿主据此做延迟补偿
+    fn latency(&self) -> u32 {
+        0
+    }
+
+    /// 所有参数
+    fn parameters(&self) -> Vec<Parameter> {
+        Vec::new()
+    }
+
+    /// 设置参数（0.0 - 1.0）
+    fn set_parameter(&mut self, _id: u32, _value: f64) {}
+
+    /// 参数的范围、单位和枚举值（默认不提供，宿主按 0.0 - 1.0 显示）
+    fn parameter_range(&self, _id: u32) -> Option<ParameterRange> {
+        None
+    }
+
+    /// 获取参数
+    fn get_parameter(&self, id: u32) -> Option<f64> {
+        self.parameters().into_iter().find(|p| p.id == id).map(|p| p.value)
+    }
+
+    /// 保存内部状态
+    fn save_state(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    /// 恢复内部状态
+    fn load_state(&mut self, _data: &[u8]) -> bool {
+        true
+    }
+}
+
+impl PluginDescriptor {
+    /// 为插件类型生成描述符（供 export_plugins! 使用）
+    pub const fn of<P: Plugin>() -> Self {
+        Self {
+            abi_version: ABI_VERSION,
+            id: P::ID.as_ptr(),
+            name: P::NAME.as_ptr(),
+            vendor: P::VENDOR.as_ptr(),
+            version: P::VERSION.as_ptr(),
+            num_inputs: P::NUM_INPUTS,
+            num_outputs: P::NUM_OUTPUTS,
+            vtable: PluginVTable {
+                create: create::<P>,
+                destroy: destroy::<P>,
+                process: process::<P>,
+                parameter_count: parameter_count::<P>,
+                parameter_info: parameter_info::<P>,
+                set_parameter: set_parameter::<P>,
+                get_parameter: get_parameter::<P>,
+                save_state: save_state::<P>,
+                load_state: load_state::<P>,
+                parameter_range: parameter_range::<P>,
+                parameter_value_name: parameter_value_name::<P>,
+                prepare: Some(prepare::<P>),
+                reset: Some(reset::<P>),
+                latency: Some(latency::<P>),
+            },
+        }
+    }
+}
+
+/// 把字符串写入固定大小的 C 缓冲区（必要时在字符边界截断）
+pub fn write_text(dst: &mut [c_char; TEXT_SIZE], text: &str) {
+    let mut len = text.len().min(TEXT_SIZE - 1);
+    while !text.is_char_boundary(len) {
+        len -= 1;
+    }
+    for (d, &b) in dst.iter_mut().zip(&text.as_bytes()[..len]) {
+        *d = b as c_char;
+    }
+    dst[len] = 0;
+}
+
+/// 读取固定大小的 C 缓冲区
+pub fn read_text(src: &[c_char; TEXT_SIZE]) -> String {
+    let bytes: Vec<u8> = src.iter().take_while(|&&c| c != 0).map(|&c| c as u8).collect();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+// ===== 虚表实现 =====
+// 插件代码中的 panic 不能穿过 C ABI，在这里捕获并返回默认值
+
+fn guard<T>(default: T, f: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(default)
+}
+
+unsafe fn instance<'a, P>(ptr: *mut c_void) -> &'a mut P {
+    &mut *(ptr as *mut P)
+}
+
+unsafe extern "C" fn create<P: Plugin>(sample_rate: f64) -> *mut c_void {
+    guard(std::ptr::null_mut(), || Box::into_raw(Box::new(P::new(sample_rate))) as *mut c_void)
+}
+
+unsafe extern "C" fn destroy<P: Plugin>(ptr: *mut c_void) {
+    if !ptr.is_null() {
+        guard((), || drop(Box::from_raw(ptr as *mut P)));
+    }
+}
+
+unsafe extern "C" fn process<P: Plugin>(ptr: *mut c_void, buffer: *mut f32, len: usize) {
+    if buffer.is_null() {
+        return;
+    }
+    let buffer = std::slice::from_raw_parts_mut(buffer, len);
+    guard((), || instance::<P>(ptr).process(buffer));
+}
+
+unsafe extern "C" fn prepare<P: Plugin>(ptr: *mut c_void, sample_rate: f64, max_block_size: u32) -> bool {
+    guard(false, || instance::<P>(ptr).prepare(sample_rate, max_block_size as usize))
+}
+
+unsafe extern "C" fn reset<P: Plugin>(ptr: *mut c_void) -> bool {
+    guard(false, || instance::<P>(ptr).reset())
+}
+
+unsafe extern "C" fn latency<P: Plugin>(ptr: *mut c_void) -> u32 {
+    guard(0, || instance::<P>(ptr).latency())
+}
+
+unsafe extern "C" fn parameter_count<P: Plugin>(ptr: *mut c_void) -> u32 {
+    guard(0, || instance::<P>(ptr).parameters().len() as u32)
+}
+
+unsafe extern "C" fn parameter_info<P: Plugin>(ptr: *mut c_void, index: u32, info: *mut ParameterInfo) -> bool {
+    guard(false, || {
+        let Some(param) = instance::<P>(ptr).parameters().into_iter().nth(index as usize) else {
+            return false;
+        };
+        let info = &mut *info;
+        info.id = param.id;
+        info.value = param.value;
+        write_text(&mut info.name, &param.name);
+        write_text(&mut info.display, &param.display);
+        true
+    })
+}
+
+unsafe extern "C" fn set_parameter<P: Plugin>(ptr: *mut c_void, id: u32, value: f64) {
+    guard((), || instance::<P>(ptr).set_parameter(id, value));
+}
+
+unsafe extern "C" fn get_parameter<P: Plugin>(ptr: *mut c_void, id: u32, value: *mut f64) -> bool {
+    guard(false, || match instance::<P>(ptr).get_parameter(id) {
+        Some(v) => {
+            *value = v;
+            true
+        }
+        None => false,
+    })
+}
+
+unsafe extern "C" fn save_state<P: Plugin>(ptr: *mut c_void, context: *mut c_void, write: StateWriter) {
+    let data = guard(Vec::new(), || instance::<P>(ptr).save_state());
+    write(context, data.as_ptr(), data.len());
+}
+
+unsafe extern "C" fn load_state<P: Plugin>(ptr: *mut c_void, data: *const u8, len: usize) -> bool {
+    let data = if data.is_null() { &[][..] } else { std::slice::from_raw_parts(data, len) };
+    guard(false, || instance::<P>(ptr).load_state(data))
+}
+
+unsafe extern "C" fn parameter_range<P: Plugin>(ptr: *mut c_void, id: u32, info: *mut ParameterRangeInfo) -> bool {
+    guard(false, || {
+        let Some(range) = instance::<P>(ptr).parameter_range(id) else {
+            return false;
+        };
+        let info = &mut *info;
+        info.min = range.min;
+        info.max = range.max;
+        info.default = range.default;
+        info.steps = range.steps;
+        info.flags = range.flags;
+        info.value_count = range.value_names.len() as u32;
+        write_text(&mut info.unit, &range.unit);
+        true
+    })
+}
+
+unsafe extern "C" fn parameter_value_name<P: Plugin>(ptr: *mut c_void, id: u32, index: u32, name: *mut c_char) -> bool {
+    if name.is_null() {
+        return false;
+    }
+    guard(false, || {
+        let range = instance::<P>(ptr).parameter_range(id);
+        let Some(value) = range.as_ref().and_then(|r| r.value_names.get(index as usize)) else {
+            return false;
+        };
+        write_text(&mut *(name as *mut [c_char; TEXT_SIZE]), value);
+        true
+    })
+}
+
+/// 导出入口函数 `plugin_loader_entry`
+///
+/// 一个库可以导出多个插件：`export_plugins!(Gain, Delay);`
+#[macro_export]
+macro_rules! export_plugins {
+    ($($plugin:ty),+ $(,)?) => {
+        #[no_mangle]
+        pub extern "C" fn plugin_loader_entry(index: u32) -> *const $crate::PluginDescriptor {
+            struct Descriptors(&'static [$crate::PluginDescriptor]);
+            // 描述符中的指针只指向常量字符串
+            unsafe impl Sync for Descriptors {}
+            static DESCRIPTORS: Descriptors = Descriptors(&[$($crate::PluginDescriptor::of::<$plugin>()),+]);
+
+            match DESCRIPTORS.0.get(index as usize) {
+                Some(descriptor) => descriptor,
+                None => ::std::ptr::null(),
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Doubler {
+        factor: f64,
+    }
+
+    impl Plugin for Doubler {
+        const ID: &'static CStr = c"test.doubler";
+        const NAME: &'static CStr = c"Doubler";
+        const VENDOR: &'static CStr = c"Test";
+        const VERSION: &'static CStr = c"0.1.0";
+
+        fn new(_sample_rate: f64) -> Self {
+            Self { factor: 0.5 }
+        }
+
+        fn process(&mut self, buffer: &mut [f32]) {
+            for sample in buffer {
+                *sample *= (self.factor * 4.0) as f32;
+            }
+        }
+
+        fn parameters(&self) -> Vec<Parameter> {
+            vec![Parameter {
+                id: 0,
+                name: "Factor".to_string(),
+                value: self.factor,
+                display: format!("{:.1}x", self.factor * 4.0),
+            }]
+        }
+
+        fn parameter_range(&self, id: u32) -> Option<ParameterRange> {
+            match id {
+                0 => Some(ParameterRange::new(0.0, 4.0, 2.0).with_unit("x")),
+                _ => None,
+            }
+        }
+
+        fn set_parameter(&mut self, id: u32, value: f64) {
+            if id == 0 {
+                self.factor = value;
+            }
+        }
+
+        fn reset(&mut self) -> bool {
+            true
+        }
+
+        fn latency(&self) -> u32 {
+            32
+        }
+
+        fn save_state(&self) -> Vec<u8> {
+            self.factor.to_le_bytes().to_vec()
+        }
+
+        fn load_state(&mut self, data: &[u8]) -> bool {
+            match data.try_into() {
+                Ok(bytes) => {
+                    self.factor = f64::from_le_bytes(bytes);
+                    true
+                }
+                Err(_) => false,
+            }
+        }
+    }
+
+    export_plugins!(Doubler);
+
+    unsafe extern "C" fn collect(context: *mut c_void, data: *const u8, len: usize) {
+        let out = &mut *(context as *mut Vec<u8>);
+        out.extend_from_slice(std::slice::from_raw_parts(data, len));
+    }
+
+    #[test]
+    fn test_descriptor_and_vtable() {
+        let descriptor = unsafe { &*plugin_loader_entry(0) };
+        assert!(plugin_loader_entry(1).is_null());
+        assert_eq!(descriptor.abi_version, ABI_VERSION);
+        assert_eq!(unsafe { CStr::from_ptr(descriptor.id) }, c"test.doubler");
+
+        let vtable = &descriptor.vtable;
+        unsafe {
+            let instance = (vtable.create)(48000.0);
+            let mut buffer = [0.25f32, -0.5];
+            (vtable.process)(instance, buffer.as_mut_ptr(), buffer.len());
+            assert_eq!(buffer, [0.5, -1.0]);
+
+            assert_eq!((vtable.parameter_count)(instance), 1);
+            let mut info = ParameterInfo::empty();
+            assert!((vtable.parameter_info)(instance, 0, &mut info));
+            assert_eq!(read_text(&info.name), "Factor");
+            assert_eq!(read_text(&info.display), "2.0x");
+
+            let mut range = ParameterRangeInfo::empty();
+            assert!((vtable.parameter_range)(instance, 0, &mut range));
+            assert_eq!((range.min, range.max, range.default), (0.0, 4.0, 2.0));
+            assert_eq!(range.flags, PARAM_AUTOMATABLE);
+            assert_eq!(read_text(&range.unit), "x");
+            assert!(!(vtable.parameter_range)(instance, 1, &mut range));
+
+            (vtable.set_parameter)(instance, 0, 0.25);
+            let mut state = Vec::new();
+            (vtable.save_state)(instance, &mut state as *mut Vec<u8> as *mut c_void, collect);
+            assert_eq!(state, 0.25f64.to_le_bytes());
+
+            (vtable.set_parameter)(instance, 0, 1.0);
+            assert!((vtable.load_state)(instance, state.as_ptr(), state.len()));
+            let mut value = 0.0;
+            assert!((vtable.get_parameter)(instance, 0, &mut value));
+            assert_eq!(value, 0.25);
+
+            // 没有实现 prepare 的插件返回 false，宿主改为重建实例
+            assert!(!(vtable.prepare.unwrap())(instance, 96000.0, 256));
+            assert!((vtable.reset.unwrap())(instance));
+            assert_eq!((vtable.latency.unwrap())(instance), 32);
+
+            (vtable.destroy)(instance);
+        }
+    }
+
+    #[test]
+    fn test_enumeration_range() {
+        let range = ParameterRange::enumeration(&["Sine", "Square", "Saw"], 1);
+        assert_eq!((range.min, range.max, range.default), (0.0, 2.0, 1.0));
+        assert_eq!(range.steps, 2);
+        assert_eq!(range.value_names[2], "Saw");
+    }
+
+    #[test]
+    fn test_write_text_truncates() {
+        let mut buffer = [0 as c_char; TEXT_SIZE];
+        let long = "音".repeat(40);
+        write_text(&mut buffer, &long);
+        let text = read_text(&buffer);
+        assert!(text.len() < TEXT_SIZE);
+        assert!(text.chars().all(|c| c == '音'));
+    }
+}
@@ -0,0 +1,435 @@
+// 非交错（平面）音频缓冲区
+// 每个声道一段连续的采样，声道数和帧数都是显式的。
+// AudioBuffer 只是一组声道切片的视图，不拥有数据，也不分配内存，可以在音频线程中切分和传递；
+// PlanarBuffer 是预分配的存储，用于把 cpal 的交错数据转成平面格式。
+
+/// AudioBuffer 最多容纳的声道数
+pub const MAX_CHANNELS: usize = 32;
+
+/// 平面音频缓冲区视图
+pub struct AudioBuffer<'a> {
+    /// 前 num_channels 个是有效声道，其余为空切片
+    channels: [&'a mut [f32]; MAX_CHANNELS],
+    num_channels: usize,
+    frames: usize,
+}
+
+impl<'a> AudioBuffer<'a> {
+    /// 由各声道的切片组成，帧数取最短的声道，超过 MAX_CHANNELS 的声道被忽略
+    pub fn from_channels<I>(slices: I) -> Self
+    where
+        I: IntoIterator<Item = &'a mut [f32]>,
+    {
+        let mut slices = slices.into_iter();
+        let mut num_channels = 0;
+        let mut channels: [&'a mut [f32]; MAX_CHANNELS] = std::array::from_fn(|_| match slices.next() {
+            Some(slice) => {
+                num_channels += 1;
+                slice
+            }
+            None => &mut [],
+        });
+
+        let frames = channels[..num_channels].iter().map(|c| c.len()).min().unwrap_or(0);
+        for channel in &mut channels[..num_channels] {
+            let slice = std::mem::take(channel);
+            *channel = &mut slice[..frames];
+        }
+
+        Self { channels, num_channels, frames }
+    }
+
+    /// 单声道数据：交错和平面格式相同，直接使用不需要复制
+    #[cfg(test)]
+    pub fn from_mono(data: &'a mut [f32]) -> Self {
+        Self::from_channels([data])
+    }
+
+    pub fn num_channels(&self) -> usize {
+        self.num_channels
+    }
+
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames == 0 || self.num_channels == 0
+    }
+
+    pub fn channel(&self, index: usize) -> &[f32] {
+        self.channels[..self.num_channels][index]
+    }
+
+    pub fn channel_mut(&mut self, index: usize) -> &mut [f32] {
+        self.channels[..self.num_channels][index]
+    }
+
+    pub fn channels(&self) -> impl Iterator<Item = &[f32]> {
+        self.channels[..self.num_channels].iter().map(|c| &**c)
+    }
+
+    pub fn channels_mut(&mut self) -> impl Iterator<Item = &mut [f32]> + use<'_, 'a> {
+        self.channels[..self.num_channels].iter_mut().map(|c| &mut **c)
+    }
+
+    /// 帧范围 [start, end) 的子视图（用于分块和按事件切分）
+    pub fn slice(&mut self, start: usize, end: usize) -> AudioBuffer<'_> {
+        let end = end.min(self.frames);
+        let start = start.min(end);
+        let num_channels = self.num_channels;
+
+        let mut iter = self.channels.iter_mut().enumerate();
+        let channels = std::array::from_fn(|_| match iter.next() {
+            Some((index, channel)) if index < num_channels => &mut channel[start..end],
+            _ => &mut [],
+        });
+        AudioBuffer { channels, num_channels, frames: end - start }
+    }
+
+    /// 所有声道清零
+    pub fn clear(&mut self) {
+        for channel in self.channels_mut() {
+            channel.fill(0.0);
+        }
+    }
+
+    /// 按声道映射规则取出 count 个声道中的第 index 个，写入 dst：
+    /// - count 为 1 时取所有声道的平均（下混为单声道）
+    /// - 缓冲区是单声道时复制到每个声道
+    /// - 缓冲区声道不够时补零
+    pub fn read_mapped(&self, index: usize, count: usize, dst: &mut [f32]) {
+        let frames = self.frames.min(dst.len());
+        let dst = &mut dst[..frames];
+        if self.num_channels == 0 {
+            dst.fill(0.0);
+        } else if count == 1 && self.num_channels > 1 {
+            let scale = 1.0 / self.num_channels as f32;
+            dst.copy_from_slice(&self.channel(0)[..dst.len()]);
+            for channel in self.channels().skip(1) {
+                for (d, &s) in dst.iter_mut().zip(channel.iter()) {
+                    *d += s;
+                }
+            }
+            for d in dst.iter_mut() {
+                *d *= scale;
+            }
+        } else if index < self.num_channels {
+            dst.copy_from_slice(&self.channel(index)[..dst.len()]);
+        } else if self.num_channels == 1 {
+            dst.copy_from_slice(&self.channel(0)[..dst.len()]);
+        } else {
+            dst.fill(0.0);
+        }
+    }
+
+    /// 按声道映射规则把 sources 写回缓冲区：
+    /// - 缓冲区是单声道而来源有多个声道时取平均
+    /// - 来源是单声道时复制到每个声道
+    /// - 来源声道不够时，多出的缓冲区声道保持不变
+    pub fn write_mapped(&mut self, sources: &[&[f32]]) {
+        let frames = self.frames;
+        if sources.is_empty() {
+            return;
+        }
+
+        if self.num_channels == 1 && sources.len() > 1 {
+            let scale = 1.0 / sources.len() as f32;
+            let dst = self.channel_mut(0);
+            dst.copy_from_slice(&sources[0][..frames]);
+            for source in &sources[1..] {
+                for (d, &s) in dst.iter_mut().zip(source.iter()) {
+                    *d += s;
+                }
+            }
+            for d in dst.iter_mut() {
+                *d *= scale;
+            }
+            return;
+        }
+
+        for (index, channel) in self.channels_mut().enumerate() {
+            let source = match sources.get(index) {
+                Some(source) => source,
+                None if sources.len() == 1 => sources[0],
+                None => continue,
+            };
+            channel.copy_from_slice(&source[..frames]);
+        }
+    }
+
+    /// 交错数据（channels 个声道）-> 本缓冲区，声道按 write_mapped 的规则映射
+    pub fn copy_from_interleaved(&mut self, data: &[f32], channels: usize) {
+        if channels == 0 {
+            return;
+        }
+        let frames = self.frames.min(data.len() / channels);
+        let num_channels = self.num_channels;
+
+        if num_channels == 1 && channels > 1 {
+            let scale = 1.0 / channels as f32;
+            for (d, frame) in self.channel_mut(0).iter_mut().zip(data.chunks_exact(channels)).take(frames) {
+                *d = frame.iter().sum::<f32>() * scale;
+            }
+            return;
+        }
+
+        for (index, channel) in self.channels_mut().enumerate() {
+            let source = if index < channels {
+                index
+            } else if channels == 1 {
+                0
+            } else {
+                continue;
+            };
+            for (d, frame) in channel.iter_mut().zip(data.chunks_exact(channels)).take(frames) {
+                *d = frame[source];
+            }
+        }
+    }
+
+    /// 本缓冲区 -> 交错数据（channels 个声道），声道按 read_mapped 的规则映射
+    pub fn copy_to_interleaved(&self, data: &mut [f32], channels: usize) {
+        if channels == 0 {
+            return;
+        }
+        let frames = self.frames.min(data.len() / channels);
+        let data = &mut data[..frames * channels];
+
+        if self.num_channels == 0 {
+            data.fill(0.0);
+            return;
+        }
+        if channels == 1 && self.num_channels > 1 {
+            let scale = 1.0 / self.num_channels as f32;
+            for (i, d) in data.iter_mut().enumerate() {
+                *d = self.channels().map(|c| c[i]).sum::<f32>() * scale;
+            }
+            return;
+        }
+
+        for index in 0..channels {
+            let source = if index < self.num_channels {
+                Some(self.channel(index))
+            } else if self.num_channels == 1 {
+                Some(self.channel(0))
+            } else {
+                None
+            };
+            let samples = data.iter_mut().skip(index).step_by(channels);
+            match source {
+                Some(source) => samples.zip(source.iter()).for_each(|(d, &s)| *d = s),
+                None => samples.for_each(|d| *d = 0.0),
+            }
+        }
+    }
+
+    /// 复制另一个缓冲区的内容（声道数相同时逐声道复制，否则按映射规则）
+    pub fn copy_from(&mut self, other: &AudioBuffer) {
+        let frames = self.frames.min(other.frames);
+        for index in 0..self.num_channels {
+            let count = self.num_channels;
+            let dst = &mut self.channels[index][..frames];
+            if other.num_channels == count {
+                dst.copy_from_slice(&other.channel(index)[..frames]);
+            } else {
+                other.read_mapped(index, count, dst);
+            }
+        }
+    }
+}
+
+/// 预分配的平面缓冲区存储
+///
+/// 在非实时线程中按声道数和最大帧数分配，音频线程中通过 as_buffer 复用。
+#[derive(Debug, Clone, Default)]
+pub struct PlanarBuffer {
+    data: Vec<f32>,
+    num_channels: usize,
+    capacity: usize,
+}
+
+impl PlanarBuffer {
+    pub fn new(num_channels: usize, capacity: usize) -> Self {
+        let num_channels = num_channels.min(MAX_CHANNELS);
+        Self {
+            data: vec![0.0; num_channels * capacity],
+            num_channels,
+            capacity,
+        }
+    }
+
+    /// 由交错数据创建（会分配内存，只用于测试）
+    #[cfg(test)]
+    pub fn from_interleaved(data: &[f32], channels: usize) -> Self {
+        let frames = data.len().checked_div(channels).unwrap_or(0);
+        let mut buffer = Self::new(channels, frames);
+        buffer.as_buffer(frames).copy_from_interleaved(data, channels);
+        buffer
+    }
+
+    /// 改变声道数和容量（会分配内存，不要在音频线程中调用）
+    pub fn resize(&mut self, num_channels: usize, capacity: usize) {
+        self.num_channels = num_channels.min(MAX_CHANNELS);
+        self.capacity = capacity;
+        self.data.resize(self.num_channels * capacity, 0.0);
+    }
+
+    /// 每个声道最多容纳的帧数
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// 前 frames 帧的视图（超过容量时截断）
+    pub fn as_buffer(&mut self, frames: usize) -> AudioBuffer<'_> {
+        self.as_buffer_channels(self.num_channels, frames)
+    }
+
+    /// 前 channels 个声道、前 frames 帧的视图
+    pub fn as_buffer_channels(&mut self, channels: usize, frames: usize) -> AudioBuffer<'_> {
+        let frames = frames.min(self.capacity);
+        let capacity = self.capacity.max(1);
+        AudioBuffer::from_channels(
+            self.data
+                .chunks_mut(capacity)
+                .take(channels.min(self.num_channels))
+                .map(|channel| &mut channel[..frames]),
+        )
+    }
+
+    /// 第一个声道的前 frames 帧复制到其余声道（单声道 -> 多声道）
+    pub fn upmix(&mut self, frames: usize) {
+        let frames = frames.min(self.capacity);
+        let mut channels = self.data.chunks_mut(self.capacity.max(1)).take(self.num_channels);
+        let Some(first) = channels.next() else {
+            return;
+        };
+        for channel in channels {
+            channel[..frames].copy_from_slice(&first[..frames]);
+        }
+    }
+
+    /// 所有声道的前 frames 帧取平均写入第一个声道（多声道 -> 单声道）
+    pub fn downmix(&mut self, frames: usize) {
+        let frames = frames.min(self.capacity);
+        if self.num_channels < 2 || self.capacity == 0 {
+            return;
+        }
+        let scale = 1.0 / self.num_channels as f32;
+        let (first, rest) = self.data.split_at_mut(self.capacity);
+        let first = &mut first[..frames];
+        for channel in rest.chunks(self.capacity).take(self.num_channels - 1) {
+            for (d, &s) in first.iter_mut().zip(&channel[..frames]) {
+                *d += s;
+            }
+        }
+        for d in first.iter_mut() {
+            *d *= scale;
+        }
+    }
+
+    /// 把交错数据转成平面格式并返回视图（不分配内存，超过容量的帧被丢弃）
+    pub fn load_interleaved(&mut self, data: &[f32], channels: usize) -> AudioBuffer<'_> {
+        let frames = data.len().checked_div(channels).unwrap_or(0);
+        let mut buffer = self.as_buffer(frames);
+        buffer.copy_from_interleaved(data, channels);
+        buffer
+    }
+
+    /// 取出前 frames 帧的交错数据（会分配内存，只用于测试）
+    #[cfg(test)]
+    pub fn to_interleaved(&self, frames: usize) -> Vec<f32> {
+        let frames = frames.min(self.capacity);
+        let channels = self.num_channels;
+        let mut data = vec![0.0; frames * channels];
+        for (index, channel) in self.data.chunks(self.capacity.max(1)).take(channels).enumerate() {
+            for (d, &s) in data.iter_mut().skip(index).step_by(channels).zip(&channel[..frames]) {
+                *d = s;
+            }
+        }
+        data
+    }
+}
+
+/// 用交错数据构造临时的平面缓冲区调用 f，再把结果写回交错数据
+/// 会分配内存，只用于测试
+#[cfg(test)]
+pub fn with_interleaved<R>(data: &mut [f32], channels: usize, f: impl FnOnce(&mut AudioBuffer) -> R) -> R {
+    let mut planar = PlanarBuffer::from_interleaved(data, channels);
+    let frames = planar.capacity();
+    let mut buffer = planar.as_buffer(frames);
+    let result = f(&mut buffer);
+    buffer.copy_to_interleaved(data, channels);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interleaved_roundtrip() {
+        let interleaved = vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+        let mut planar = PlanarBuffer::from_interleaved(&interleaved, 2);
+        {
+            let buffer = planar.as_buffer(3);
+            assert_eq!((buffer.num_channels(), buffer.frames()), (2, 3));
+            assert_eq!(buffer.channel(0), &[1.0, 3.0, 5.0]);
+            assert_eq!(buffer.channel(1), &[2.0, 4.0, 6.0]);
+        }
+        assert_eq!(planar.to_interleaved(3), interleaved);
+    }
+
+    #[test]
+    fn test_slice_views_frames() {
+        let mut left = [0.0; 8];
+        let mut right = [0.0; 8];
+        let mut buffer = AudioBuffer::from_channels([&mut left[..], &mut right[..]]);
+        {
+            let mut block = buffer.slice(2, 5);
+            assert_eq!(block.frames(), 3);
+            for channel in block.channels_mut() {
+                channel.fill(1.0);
+            }
+        }
+        assert_eq!(buffer.channel(1), &[0.0, 0.0, 1.0, 1.0, 1.0, 0.0, 0.0, 0.0]);
+        assert_eq!(buffer.slice(6, 100).frames(), 2);
+    }
+
+    #[test]
+    fn test_upmix_and_downmix() {
+        let mut planar = PlanarBuffer::from_interleaved(&[1.0, 0.0, 0.5, 0.5], 2);
+        planar.downmix(2);
+        assert_eq!(planar.as_buffer_channels(1, 2).channel(0), &[0.5, 0.5]);
+
+        let mut planar = PlanarBuffer::from_interleaved(&[0.25, 0.0, -0.25, 0.0], 2);
+        planar.upmix(2);
+        assert_eq!(planar.to_interleaved(2), vec![0.25, 0.25, -0.25, -0.25]);
+    }
+
+    #[test]
+    fn test_channel_mapping() {
+        // 单声道输入复制到立体声
+        let mut mono = vec![0.5, -0.5];
+        let buffer = AudioBuffer::from_mono(&mut mono);
+        let mut right = [0.0; 2];
+        buffer.read_mapped(1, 2, &mut right);
+        assert_eq!(right, [0.5, -0.5]);
+        let mut stereo = [0.0; 4];
+        buffer.copy_to_interleaved(&mut stereo, 2);
+        assert_eq!(stereo, [0.5, 0.5, -0.5, -0.5]);
+
+        // 立体声输出写回单声道时取平均
+        let mut mono = vec![0.0; 2];
+        let mut buffer = AudioBuffer::from_mono(&mut mono);
+        buffer.write_mapped(&[&[1.0, 1.0], &[0.0, -1.0]]);
+        assert_eq!(buffer.channel(0), &[0.5, 0.0]);
+
+        // 四声道接口：前两个声道对应立体声，其余保持不变
+        let mut channels = vec![vec![9.0; 2]; 4];
+        let mut buffer = AudioBuffer::from_channels(channels.iter_mut().map(|c| &mut c[..]));
+        buffer.copy_from_interleaved(&[1.0, 2.0, 3.0, 4.0], 2);
+        assert_eq!(buffer.channel(1), &[2.0, 4.0]);
+        assert_eq!(buffer.channel(3), &[9.0, 9.0]);
+    }
+}
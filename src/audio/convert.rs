@@ -0,0 +1,216 @@
+// 采样格式转换
+// 设备的整数和浮点格式与插件链使用的 f32 之间互相转换；整数输出可以加 TPDF 抖动
+
+use cpal::I24;
+
+/// 可以和 f32 互相转换的采样类型
+///
+/// 整数按 2^(N-1) 缩放：最小值对应 -1.0，0.0 对应中点，输出时四舍五入并限幅到类型的范围；
+/// 无符号类型以中点为零。
+pub trait ConvertSample: Copy {
+    /// 是否是整数格式（只有整数格式需要抖动）
+    const INTEGER: bool;
+
+    /// 转换成 [-1, 1] 范围内的 f32（32 位以上的整数在 f32 中会舍入）
+    fn to_f32(self) -> f32;
+
+    /// 从 f32 转换，dither 是加在量化前的抖动（单位是最低有效位）
+    fn from_f32(value: f32, dither: f32) -> Self;
+}
+
+/// 有符号整数：scale = 2^(bits-1)
+macro_rules! impl_signed {
+    ($($T:ty => $bits:expr),*) => {$(
+        impl ConvertSample for $T {
+            const INTEGER: bool = true;
+
+            fn to_f32(self) -> f32 {
+                (self as f64 / (1u64 << ($bits - 1)) as f64) as f32
+            }
+
+            fn from_f32(value: f32, dither: f32) -> Self {
+                let scaled = value as f64 * (1u64 << ($bits - 1)) as f64 + dither as f64;
+                scaled.round().clamp(<$T>::MIN as f64, <$T>::MAX as f64) as $T
+            }
+        }
+    )*};
+}
+
+/// 无符号整数：减去中点后按有符号处理
+macro_rules! impl_unsigned {
+    ($($T:ty => $bits:expr),*) => {$(
+        impl ConvertSample for $T {
+            const INTEGER: bool = true;
+
+            fn to_f32(self) -> f32 {
+                let half = (1u64 << ($bits - 1)) as f64;
+                ((self as f64 - half) / half) as f32
+            }
+
+            fn from_f32(value: f32, dither: f32) -> Self {
+                let half = (1u64 << ($bits - 1)) as f64;
+                let scaled = (value as f64 * half + dither as f64).round().clamp(-half, half - 1.0);
+                (scaled + half) as $T
+            }
+        }
+    )*};
+}
+
+impl_signed!(i8 => 8, i16 => 16, i32 => 32, i64 => 64);
+impl_unsigned!(u8 => 8, u16 => 16, u32 => 32, u64 => 64);
+
+/// 24 位整数（存放在 i32 中，范围 -2^23..2^23）
+///
+/// 只提供转换：cpal 0.15 的 SampleFormat 没有 24 位格式，build_audio_streams 不会打开 I24 的流，
+/// 等 cpal 提供 24 位流格式后再接入
+impl ConvertSample for I24 {
+    const INTEGER: bool = true;
+
+    fn to_f32(self) -> f32 {
+        self.inner() as f32 / (1 << 23) as f32
+    }
+
+    fn from_f32(value: f32, dither: f32) -> Self {
+        let scaled = value as f64 * (1 << 23) as f64 + dither as f64;
+        I24::new_unchecked(scaled.round().clamp(-(1 << 23) as f64, ((1 << 23) - 1) as f64) as i32)
+    }
+}
+
+impl ConvertSample for f32 {
+    const INTEGER: bool = false;
+
+    fn to_f32(self) -> f32 {
+        self
+    }
+
+    fn from_f32(value: f32, _dither: f32) -> Self {
+        value
+    }
+}
+
+impl ConvertSample for f64 {
+    const INTEGER: bool = false;
+
+    fn to_f32(self) -> f32 {
+        self as f32
+    }
+
+    fn from_f32(value: f32, _dither: f32) -> Self {
+        value as f64
+    }
+}
+
+/// TPDF 抖动源：两个均匀分布之差，范围 (-1, 1) 个最低有效位（音频线程中使用，不分配内存）
+pub struct Dither {
+    enabled: bool,
+    state: u32,
+}
+
+impl Dither {
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled, state: 0x9E37_79B9 }
+    }
+
+    /// 下一个抖动值（关闭时为 0）
+    pub fn next(&mut self) -> f32 {
+        if !self.enabled {
+            return 0.0;
+        }
+        self.uniform() - self.uniform()
+    }
+
+    /// [0, 1) 的均匀分布（xorshift32）
+    fn uniform(&mut self) -> f32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        (self.state >> 8) as f32 / (1 << 24) as f32
+    }
+}
+
+/// 把交错的 f32 数据转换成设备格式，整数格式按 dither 加抖动
+pub fn convert_output<T: ConvertSample>(source: &[f32], output: &mut [T], dither: &mut Dither) {
+    for (dst, &value) in output.iter_mut().zip(source) {
+        let noise = if T::INTEGER { dither.next() } else { 0.0 };
+        *dst = T::from_f32(value, noise);
+    }
+}
+
+/// 把设备格式的交错数据转换成 f32
+pub fn convert_input<T: ConvertSample>(input: &[T], output: &mut [f32]) {
+    for (dst, &sample) in output.iter_mut().zip(input) {
+        *dst = sample.to_f32();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 满幅、中点和限幅：最小值对应 -1.0，超出范围的值限幅到最大/最小值
+    fn check_integer<T: ConvertSample + PartialEq + std::fmt::Debug>(min: T, zero: T, max: T) {
+        assert_eq!(min.to_f32(), -1.0);
+        assert_eq!(zero.to_f32(), 0.0);
+        assert!(max.to_f32() <= 1.0 && max.to_f32() > 0.99);
+        assert_eq!(T::from_f32(-1.0, 0.0), min);
+        assert_eq!(T::from_f32(0.0, 0.0), zero);
+        assert_eq!(T::from_f32(1.0, 0.0), max);
+        assert_eq!(T::from_f32(2.5, 0.0), max);
+        assert_eq!(T::from_f32(-2.5, 0.0), min);
+    }
+
+    #[test]
+    fn test_integer_scaling_and_clipping() {
+        check_integer(i8::MIN, 0i8, i8::MAX);
+        check_integer(i16::MIN, 0i16, i16::MAX);
+        check_integer(I24::new_unchecked(-(1 << 23)), I24::new_unchecked(0), I24::new_unchecked((1 << 23) - 1));
+        check_integer(i32::MIN, 0i32, i32::MAX);
+        check_integer(u8::MIN, 128u8, u8::MAX);
+        check_integer(u16::MIN, 32768u16, u16::MAX);
+        check_integer(u32::MIN, 1u32 << 31, u32::MAX);
+    }
+
+    #[test]
+    fn test_roundtrip_is_exact() {
+        // 整数 -> f32 -> 整数不损失精度（24 位以内）
+        for value in [i16::MIN, -12345, -1, 0, 1, 12345, i16::MAX] {
+            assert_eq!(i16::from_f32(value.to_f32(), 0.0), value);
+        }
+        for value in [-(1 << 23), -1, 0, 1, 4_000_000, (1 << 23) - 1] {
+            let sample = I24::new_unchecked(value);
+            assert_eq!(I24::from_f32(sample.to_f32(), 0.0), sample);
+        }
+        for value in [0u8, 1, 127, 128, 200, 255] {
+            assert_eq!(u8::from_f32(value.to_f32(), 0.0), value);
+        }
+        assert_eq!(f64::from_f32(0.25, 0.7), 0.25);
+        assert_eq!(0.25f64.to_f32(), 0.25);
+    }
+
+    #[test]
+    fn test_tpdf_dither() {
+        let mut dither = Dither::new(true);
+        let values: Vec<f32> = (0..100_000).map(|_| dither.next()).collect();
+        assert!(values.iter().all(|value| value.abs() < 1.0));
+        let mean = values.iter().sum::<f32>() / values.len() as f32;
+        let variance = values.iter().map(|value| value * value).sum::<f32>() / values.len() as f32;
+        // 三角分布的方差为 1/6
+        assert!(mean.abs() < 0.01);
+        assert!((variance - 1.0 / 6.0).abs() < 0.01);
+
+        // 抖动让低于 1 LSB 的信号在平均后仍然保留
+        let level = 0.3 / 32768.0;
+        let mut output = vec![0i16; 100_000];
+        convert_output(&vec![level; output.len()], &mut output, &mut dither);
+        let average = output.iter().map(|&sample| sample as f32).sum::<f32>() / output.len() as f32;
+        assert!((average - 0.3).abs() < 0.02, "{}", average);
+
+        // 关闭时没有抖动，浮点格式不加抖动
+        let mut off = Dither::new(false);
+        convert_output(&[level; 16], &mut output[..16], &mut off);
+        assert!(output[..16].iter().all(|&sample| sample == 0));
+        let mut float = [0.0f32; 4];
+        convert_output(&[level; 4], &mut float, &mut dither);
+        assert_eq!(float, [level; 4]);
+    }
+}
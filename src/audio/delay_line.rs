@@ -0,0 +1,102 @@
+// 固定延迟线
+// 用于延迟补偿：把干信号或较快的并联支路推迟到与最慢的路径对齐
+
+use super::buffer::AudioBuffer;
+
+/// 立体声固定延迟线（每个声道一条环形缓冲区）
+///
+/// process 不分配内存；长度不超过预留容量时 set_latency 也不分配内存。
+pub struct DelayLine {
+    lines: [Vec<f32>; 2],
+    pos: usize,
+}
+
+impl DelayLine {
+    pub fn new(latency: usize) -> Self {
+        Self { lines: [vec![0.0; latency], vec![0.0; latency]], pos: 0 }
+    }
+
+    /// 延迟帧数
+    pub fn latency(&self) -> usize {
+        self.lines[0].len()
+    }
+
+    /// 预留容量（帧）
+    #[cfg(test)]
+    pub fn capacity(&self) -> usize {
+        self.lines[0].capacity().min(self.lines[1].capacity())
+    }
+
+    /// 预留最多 capacity 帧的延迟（不在音频线程调用）
+    pub fn reserve(&mut self, capacity: usize) {
+        for line in &mut self.lines {
+            line.reserve(capacity.saturating_sub(line.len()));
+        }
+    }
+
+    /// 修改延迟长度并清空；超过预留容量时会重新分配
+    pub fn set_latency(&mut self, latency: usize) {
+        if self.latency() == latency {
+            return;
+        }
+        for line in &mut self.lines {
+            line.clear();
+            line.resize(latency, 0.0);
+        }
+        self.pos = 0;
+    }
+
+    pub fn clear(&mut self) {
+        for line in &mut self.lines {
+            line.fill(0.0);
+        }
+    }
+
+    /// 原地延迟前两个声道
+    pub fn process(&mut self, buffer: &mut AudioBuffer) {
+        let latency = self.latency();
+        if latency == 0 {
+            return;
+        }
+        for (line, channel) in self.lines.iter_mut().zip(buffer.channels_mut()) {
+            let mut pos = self.pos;
+            for sample in channel.iter_mut() {
+                std::mem::swap(sample, &mut line[pos]);
+                pos += 1;
+                if pos == latency {
+                    pos = 0;
+                }
+            }
+        }
+        self.pos = (self.pos + buffer.frames()) % latency;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::with_interleaved;
+
+    #[test]
+    fn test_delay_across_blocks() {
+        let mut delay = DelayLine::new(3);
+        let mut first = vec![1.0, -1.0, 2.0, -2.0];
+        let mut second = vec![3.0, -3.0, 4.0, -4.0, 5.0, -5.0];
+        with_interleaved(&mut first, 2, |buffer| delay.process(buffer));
+        with_interleaved(&mut second, 2, |buffer| delay.process(buffer));
+        assert_eq!(first, vec![0.0; 4]);
+        assert_eq!(second, vec![0.0, 0.0, 1.0, -1.0, 2.0, -2.0]);
+
+        delay.set_latency(0);
+        let mut data = vec![7.0; 4];
+        with_interleaved(&mut data, 2, |buffer| delay.process(buffer));
+        assert_eq!(data, vec![7.0; 4]);
+
+        // 预留容量以内改变长度不需要重新分配
+        delay.reserve(64);
+        let capacity = delay.capacity();
+        assert!(capacity >= 64);
+        delay.set_latency(48);
+        assert_eq!((delay.latency(), delay.capacity()), (48, capacity));
+    }
+}
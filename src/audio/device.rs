@@ -1,25 +1,23 @@
 use anyhow::{Result, Context};
 use cpal::traits::{DeviceTrait, HostTrait};
+use cpal::{SampleFormat, StreamConfig, SupportedBufferSize, SupportedStreamConfigRange};
 use log::{info, warn};
 
-/// 音频设备配置
-#[derive(Debug, Clone)]
-#[allow(dead_code)]
-pub struct AudioDeviceConfig {
-    pub sample_rate: u32,
-    pub buffer_size: u32,
-    pub channels: u16,
-}
+use crate::plugin::AudioConfig;
 
-impl Default for AudioDeviceConfig {
-    fn default() -> Self {
-        Self {
-            sample_rate: 48000,
-            buffer_size: 256,
-            channels: 2,
-        }
-    }
-}
+/// 选择采样格式的优先顺序（越靠前越好）：优先浮点，其次精度高的整数
+const FORMAT_PREFERENCE: [SampleFormat; 10] = [
+    SampleFormat::F32,
+    SampleFormat::F64,
+    SampleFormat::I32,
+    SampleFormat::U32,
+    SampleFormat::I16,
+    SampleFormat::U16,
+    SampleFormat::I64,
+    SampleFormat::U64,
+    SampleFormat::I8,
+    SampleFormat::U8,
+];
 
 /// 列出所有可用的音频设备
 pub fn list_audio_devices() -> Result<()> {
@@ -135,6 +133,127 @@ fn print_device_info(device: &cpal::Device) -> Result<()> {
     Ok(())
 }
 
+/// 所有输入设备的名称
+pub fn input_device_names() -> Vec<String> {
+    let host = cpal::default_host();
+    match host.input_devices() {
+        Ok(devices) => devices.filter_map(|device| device.name().ok()).collect(),
+        Err(e) => {
+            warn!("无法枚举输入设备: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// 所有输出设备的名称
+pub fn output_device_names() -> Vec<String> {
+    let host = cpal::default_host();
+    match host.output_devices() {
+        Ok(devices) => devices.filter_map(|device| device.name().ok()).collect(),
+        Err(e) => {
+            warn!("无法枚举输出设备: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// 按名称打开输入设备（设备以名称标识），None 时使用默认输入设备
+pub fn find_input_device(name: Option<&str>) -> Result<cpal::Device> {
+    let Some(name) = name else {
+        return get_default_input_device();
+    };
+    let host = cpal::default_host();
+    host.input_devices()
+        .context("无法枚举输入设备")?
+        .find(|device| device.name().is_ok_and(|device_name| device_name == name))
+        .ok_or_else(|| anyhow::anyhow!("找不到输入设备 \"{}\"，可用设备: {}", name, input_device_names().join(", ")))
+}
+
+/// 按名称打开输出设备，None 时使用默认输出设备
+pub fn find_output_device(name: Option<&str>) -> Result<cpal::Device> {
+    let Some(name) = name else {
+        return get_default_output_device();
+    };
+    let host = cpal::default_host();
+    host.output_devices()
+        .context("无法枚举输出设备")?
+        .find(|device| device.name().is_ok_and(|device_name| device_name == name))
+        .ok_or_else(|| anyhow::anyhow!("找不到输出设备 \"{}\"，可用设备: {}", name, output_device_names().join(", ")))
+}
+
+/// 按请求的采样率、通道数和缓冲区大小选择输入流配置
+pub fn select_input_config(device: &cpal::Device, request: &AudioConfig) -> Result<(StreamConfig, SampleFormat)> {
+    let ranges: Vec<_> = device.supported_input_configs().context("获取输入设备支持的配置失败")?.collect();
+    choose_config(&ranges, request).context("输入设备不支持请求的配置")
+}
+
+/// 按请求的采样率、通道数和缓冲区大小选择输出流配置
+pub fn select_output_config(device: &cpal::Device, request: &AudioConfig) -> Result<(StreamConfig, SampleFormat)> {
+    let ranges: Vec<_> = device.supported_output_configs().context("获取输出设备支持的配置失败")?.collect();
+    choose_config(&ranges, request).context("输出设备不支持请求的配置")
+}
+
+/// 在设备支持的配置范围中选择满足请求的一个，优先使用 F32
+/// 找不到时错误信息列出请求和设备支持的所有配置
+fn choose_config(ranges: &[SupportedStreamConfigRange], request: &AudioConfig) -> Result<(StreamConfig, SampleFormat)> {
+    let rate = cpal::SampleRate(request.sample_rate);
+    let buffer_ok = |range: &SupportedStreamConfigRange| match *range.buffer_size() {
+        SupportedBufferSize::Range { min, max } => (min..=max).contains(&request.buffer_size),
+        // 平台无法预先报告时只能在打开流时验证
+        SupportedBufferSize::Unknown => true,
+    };
+    let matching = ranges.iter().filter(|range| {
+        range.channels() == request.channels
+            && range.min_sample_rate() <= rate
+            && rate <= range.max_sample_rate()
+            && buffer_ok(range)
+    });
+    let best = matching
+        .filter_map(|range| {
+            let rank = FORMAT_PREFERENCE.iter().position(|&format| format == range.sample_format())?;
+            Some((rank, range))
+        })
+        .min_by_key(|(rank, _)| *rank)
+        .map(|(_, range)| range);
+    
+    match best {
+        Some(range) => {
+            let config = StreamConfig {
+                channels: request.channels,
+                sample_rate: rate,
+                buffer_size: cpal::BufferSize::Fixed(request.buffer_size),
+            };
+            Ok((config, range.sample_format()))
+        }
+        None => {
+            let supported: Vec<String> = ranges.iter().map(describe_range).collect();
+            Err(anyhow::anyhow!(
+                "请求 {} Hz、{} 通道、缓冲区 {} 帧，设备支持: {}",
+                request.sample_rate,
+                request.channels,
+                request.buffer_size,
+                if supported.is_empty() { "（无）".to_string() } else { supported.join("; ") }
+            ))
+        }
+    }
+}
+
+/// 配置范围的简短描述（错误信息使用）
+fn describe_range(range: &SupportedStreamConfigRange) -> String {
+    let buffer = match *range.buffer_size() {
+        SupportedBufferSize::Range { min, max } => format!("缓冲区 {}-{} 帧", min, max),
+        SupportedBufferSize::Unknown => "缓冲区未知".to_string(),
+    };
+    format!(
+        "{} 通道 {}-{} Hz {:?} {}",
+        range.channels(),
+        range.min_sample_rate().0,
+        range.max_sample_rate().0,
+        range.sample_format(),
+        buffer
+    )
+}
+
 /// 获取默认输入设备
 pub fn get_default_input_device() -> Result<cpal::Device> {
     let host = cpal::default_host();
@@ -149,3 +268,42 @@ pub fn get_default_output_device() -> Result<cpal::Device> {
         .context("未找到默认输出设备")
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(channels: u16, min: u32, max: u32, format: SampleFormat) -> SupportedStreamConfigRange {
+        SupportedStreamConfigRange::new(
+            channels,
+            cpal::SampleRate(min),
+            cpal::SampleRate(max),
+            SupportedBufferSize::Range { min: 32, max: 2048 },
+            format,
+        )
+    }
+
+    #[test]
+    fn test_choose_config() {
+        let ranges = [
+            range(2, 44100, 48000, SampleFormat::I16),
+            range(2, 44100, 96000, SampleFormat::F32),
+            range(1, 44100, 48000, SampleFormat::F32),
+        ];
+        let request = AudioConfig { sample_rate: 48000, buffer_size: 128, channels: 2, ..AudioConfig::default() };
+        let (config, format) = choose_config(&ranges, &request).unwrap();
+        assert_eq!(format, SampleFormat::F32);
+        let integer_only = [range(2, 44100, 48000, SampleFormat::U8), range(2, 44100, 48000, SampleFormat::I32)];
+        assert_eq!(choose_config(&integer_only, &request).unwrap().1, SampleFormat::I32);
+        assert_eq!(config.sample_rate.0, 48000);
+        assert_eq!(config.buffer_size, cpal::BufferSize::Fixed(128));
+
+        // 采样率或缓冲区大小超出范围时报告设备支持的配置
+        let request = AudioConfig { sample_rate: 192000, ..request };
+        let error = choose_config(&ranges, &request).unwrap_err().to_string();
+        assert!(error.contains("192000 Hz"));
+        assert!(error.contains("2 通道 44100-96000 Hz F32"));
+        let request = AudioConfig { sample_rate: 48000, buffer_size: 4096, ..request };
+        assert!(choose_config(&ranges, &request).is_err());
+    }
+}
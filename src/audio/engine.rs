@@ -1,56 +1,380 @@
 use anyhow::{Result, Context};
 use cpal::traits::{DeviceTrait, StreamTrait};
-use cpal::{Stream, StreamConfig, SampleFormat};
-use log::{info, error};
-use std::sync::Arc;
+use cpal::{SizedSample, Stream, StreamConfig, SampleFormat};
+use log::{info, warn, error};
+use std::sync::{Arc, Mutex, MutexGuard};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::io::{self, Write};
-use std::time::Duration;
+use std::path::Path;
+use std::time::{Duration, Instant};
 
-use super::device::{get_default_input_device, get_default_output_device, list_audio_devices};
+use super::buffer::PlanarBuffer;
+use super::convert::{convert_input, convert_output, ConvertSample, Dither};
+use super::device::{
+    find_input_device, find_output_device, get_default_input_device, get_default_output_device,
+    list_audio_devices, select_input_config, select_output_config,
+};
 use super::level_meter::{LevelMeter, format_db};
+use super::midi::MidiInput;
+use super::processor::{AudioProcessorEngine, ChainProcessor, PluginFactory};
+use super::transport::{transport, TransportReader, TransportStats, TransportWriter};
+use super::tuner::{Tuner, TunerReadout, format_reading};
+use crate::plugin::{AudioConfig, ChannelLayout, Project};
 
-/// 音频引擎主函数
-pub fn run_audio_engine() -> Result<()> {
-    // 1. 列出所有音频设备
-    list_audio_devices()?;
+/// 回调一次最多处理的帧数，更长的回调分段处理
+const CALLBACK_FRAMES: usize = 4096;
+
+/// 设备使用默认缓冲区大小时插件链的最大块长
+const DEFAULT_BLOCK_SIZE: usize = 512;
+
+/// 停止音频流前等待音频线程结束插件处理的最长时间
+const SUSPEND_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// 正在运行的输入和输出流
+struct ActiveStreams {
+    _input: Stream,
+    _output: Stream,
+    config: StreamConfig,
+    transport: TransportStats,
+}
+
+/// 打开的输入输出设备和协商好的流配置（两端采样率和通道数相同，采样格式可以不同）
+struct OpenedDevices {
+    input: cpal::Device,
+    output: cpal::Device,
+    config: StreamConfig,
+    input_format: SampleFormat,
+    output_format: SampleFormat,
+}
+
+/// 实时音频引擎：拥有输入和输出流，在采集和播放之间运行插件链
+///
+/// 控制线程一侧的 AudioProcessorEngine 由命令行和图形界面共享。start 时插件链交给输入流的回调，
+/// stop 时音频流释放、插件链交回控制线程；运行期间的编辑都经过无锁命令队列。
+pub struct AudioEngine {
+    processor: Arc<Mutex<AudioProcessorEngine>>,
+    input_meter: LevelMeter,
+    output_meter: LevelMeter,
+    tuner: TunerReadout,
+    streams: Option<ActiveStreams>,
+    /// 请求的设备、采样率和缓冲区大小
+    config: AudioConfig,
+    /// 上次启动没能按请求打开设备、改用默认设备的原因
+    fallback: Option<String>,
+    /// 输入回调释放时把插件链放回这里
+    returned: Arc<Mutex<Option<ChainProcessor>>>,
+    /// MIDI 输入，poll 时把程序切换交给插件链切换预设
+    midi: Option<MidiInput>,
+}
+
+impl AudioEngine {
+    pub fn new() -> Self {
+        Self {
+            processor: Arc::new(Mutex::new(AudioProcessorEngine::new())),
+            input_meter: LevelMeter::new(),
+            output_meter: LevelMeter::new(),
+            tuner: TunerReadout::new(),
+            streams: None,
+            config: AudioConfig::default(),
+            fallback: None,
+            returned: Arc::new(Mutex::new(None)),
+            midi: None,
+        }
+    }
     
-    println!();
-    info!("=== 启动音频引擎 ===");
+    /// 控制线程一侧的插件链（编辑插件、切换预设、保存状态）
+    pub fn processor(&self) -> &Arc<Mutex<AudioProcessorEngine>> {
+        &self.processor
+    }
     
-    // 2. 获取默认设备
-    let input_device = get_default_input_device()?;
-    let output_device = get_default_output_device()?;
+    /// 输入电平（插件链之前）
+    pub fn input_meter(&self) -> &LevelMeter {
+        &self.input_meter
+    }
     
-    info!("输入设备: {}", input_device.name()?);
-    info!("输出设备: {}", output_device.name()?);
+    /// 输出电平（插件链之后）
+    pub fn output_meter(&self) -> &LevelMeter {
+        &self.output_meter
+    }
     
-    // 3. 配置音频流
-    let (config, sample_format) = get_audio_config(&input_device)?;
+    /// 调音器读数（接在输入端）
+    pub fn tuner_readout(&self) -> TunerReadout {
+        self.tuner.clone()
+    }
     
-    // 获取实际缓冲区大小（用于显示）
-    let buffer_size_str = match config.buffer_size {
-        cpal::BufferSize::Fixed(size) => format!("{} samples", size),
-        cpal::BufferSize::Default => "设备默认".to_string(),
-    };
+    pub fn is_running(&self) -> bool {
+        self.streams.is_some()
+    }
+    
+    /// 采集和播放之间的传输统计（水位、欠载和溢出次数，漂移补偿的重采样比率）
+    pub fn transport_stats(&self) -> Option<TransportStats> {
+        self.streams.as_ref().map(|streams| streams.transport.clone())
+    }
+    
+    /// 正在使用的采样率
+    pub fn sample_rate(&self) -> Option<u32> {
+        self.streams.as_ref().map(|streams| streams.config.sample_rate.0)
+    }
+    
+    /// 请求的设备、采样率和缓冲区大小
+    pub fn audio_config(&self) -> &AudioConfig {
+        &self.config
+    }
+    
+    /// 设置请求的音频配置，下次 start 或 restart 时生效
+    pub fn set_audio_config(&mut self, config: AudioConfig) {
+        self.config = config;
+    }
+    
+    /// 上次启动时请求的配置不可用、改用了默认设备和配置的原因
+    pub fn fallback_reason(&self) -> Option<&str> {
+        self.fallback.as_deref()
+    }
+    
+    /// 按请求的音频配置打开设备，prepare 插件链并启动音频流；已经在运行时什么都不做
+    ///
+    /// 请求的设备或配置不可用时记录原因（见 fallback_reason），改用默认设备和默认配置
+    pub fn start(&mut self) -> Result<()> {
+        if self.is_running() {
+            return Ok(());
+        }
+        
+        self.open_midi();
+        self.fallback = None;
+        let request = self.config.clone();
+        let result = open_requested_devices(&request).and_then(|devices| self.start_streams(devices));
+        if let Err(e) = result {
+            let reason = format!("{:#}", e);
+            warn!("无法按设置打开音频设备: {}", reason);
+            warn!("改用默认设备和默认配置");
+            self.fallback = Some(reason);
+            self.start_streams(open_default_devices()?)?;
+        }
+        Ok(())
+    }
+    
+    /// prepare 插件链，把它交给新建的音频流
+    fn start_streams(&mut self, devices: OpenedDevices) -> Result<()> {
+        let OpenedDevices { input: input_device, output: output_device, config, input_format, output_format } = devices;
+        let input_name = input_device.name()?;
+        let output_name = output_device.name()?;
+        info!("输入设备: {}", input_name);
+        info!("输出设备: {}", output_name);
+        
+        // 不同设备的时钟不同步，需要补偿漂移
+        let drift_compensation = input_name != output_name;
+        if drift_compensation {
+            info!("输入输出是不同设备，开启时钟漂移补偿");
+        }
+        
+        // 获取实际缓冲区大小（用于显示）
+        let buffer_size_str = match config.buffer_size {
+            cpal::BufferSize::Fixed(size) => format!("{} samples", size),
+            cpal::BufferSize::Default => "设备默认".to_string(),
+        };
+        
+        info!("音频配置: {} Hz, {} 通道, 缓冲区: {}, 格式: {:?} / {:?}",
+            config.sample_rate.0,
+            config.channels,
+            buffer_size_str,
+            input_format,
+            output_format
+        );
+        
+        // 如果是固定缓冲区，计算理论延迟
+        if let cpal::BufferSize::Fixed(size) = config.buffer_size {
+            let latency_ms = (size as f32 / config.sample_rate.0 as f32) * 1000.0;
+            info!("理论延迟: {:.2} ms", latency_ms);
+        }
+        
+        let block_size = match config.buffer_size {
+            cpal::BufferSize::Fixed(size) => size as usize,
+            cpal::BufferSize::Default => DEFAULT_BLOCK_SIZE,
+        };
+        let layout = if config.channels == 1 { ChannelLayout::Mono } else { ChannelLayout::Stereo };
+        let chain = {
+            let mut processor = self.lock_processor()?;
+            processor.prepare(config.sample_rate.0 as f64, block_size, layout)?;
+            processor.take_processor().ok_or_else(|| anyhow::anyhow!("插件链已经在音频线程中"))?
+        };
+        
+        // 创建失败时回调随之释放，插件链同样放回 returned
+        let dither = self.config.dither;
+        let (input, output, transport) = self.callbacks(chain, &config, block_size, drift_compensation, dither);
+        let result = build_audio_streams(&input_device, &output_device, &config, input_format, output_format, input, output);
+        match result {
+            Ok((input, output)) => {
+                self.streams = Some(ActiveStreams { _input: input, _output: output, config, transport });
+                info!("✅ 音频引擎启动成功！");
+                Ok(())
+            }
+            Err(e) => {
+                self.reclaim();
+                Err(e)
+            }
+        }
+    }
     
-    info!("音频配置: {} Hz, {} 通道, 缓冲区: {}",
-        config.sample_rate.0,
-        config.channels,
-        buffer_size_str
-    );
+    /// 停止音频流并收回插件链
+    pub fn stop(&mut self) {
+        if let Some(streams) = self.streams.take() {
+            self.suspend_processor();
+            // 回调随音频流释放，插件链放回 returned
+            drop(streams);
+            self.reclaim();
+            info!("音频引擎已停止");
+        }
+    }
     
-    // 如果是固定缓冲区，计算理论延迟
-    if let cpal::BufferSize::Fixed(size) = config.buffer_size {
-        let latency_ms = (size as f32 / config.sample_rate.0 as f32) * 1000.0;
-        info!("理论延迟: {:.2} ms", latency_ms);
+    /// 让音频线程先结束插件的处理（CLAP 的 stop_processing 必须在音频线程调用），超时后直接停止
+    fn suspend_processor(&self) {
+        let Ok(mut processor) = self.lock_processor() else {
+            return;
+        };
+        if let Err(e) = processor.suspend() {
+            warn!("{}", e);
+            return;
+        }
+        let deadline = Instant::now() + SUSPEND_TIMEOUT;
+        while !processor.is_suspended() {
+            if Instant::now() >= deadline {
+                warn!("音频线程没有响应，直接停止音频流");
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
     }
     
-    // 4. 创建电平表
-    let level_meter = LevelMeter::new();
-    let level_meter_clone = level_meter.clone();
+    /// 重新打开设备（设备或音频配置变化后调用），插件链和预设保持不变
+    pub fn restart(&mut self) -> Result<()> {
+        self.stop();
+        self.start()
+    }
+    
+    /// 处理收到的 MIDI 消息（程序切换在工作线程准备预设），切换到已经准备好的预设，
+    /// 释放音频线程退回的插件，返回释放的插件数（运行期间应定期调用）
+    pub fn poll(&self) -> usize {
+        let Ok(mut processor) = self.processor.lock() else {
+            return 0;
+        };
+        if let Some(midi) = &self.midi {
+            while let Some(message) = midi.try_recv() {
+                if let Err(e) = processor.handle_midi(message) {
+                    warn!("MIDI 切换预设失败: {}", e);
+                }
+            }
+        }
+        if let Err(e) = processor.poll_preset() {
+            warn!("MIDI 切换预设失败: {}", e);
+        }
+        processor.collect_retired()
+    }
+    
+    /// 打开工程：切换到工程的插件链并载入预设，factory 按插件 ID 创建插件
+    pub fn load_project(&mut self, project: &Project, factory: PluginFactory) -> Result<()> {
+        let mut processor = self.lock_processor()?;
+        processor.set_plugin_factory(factory);
+        processor.load_project(project)
+    }
+    
+    /// 把插件链和预设保存到工程，运行中不停止音频流（插件状态由引擎的镜像给出）
+    pub fn save_project(&self, project: &mut Project) -> Result<()> {
+        self.lock_processor()?.save_project(project)
+    }
+    
+    /// 按音频配置打开（或关闭）MIDI 输入，设备不变时保持打开；打开失败只记录警告
+    fn open_midi(&mut self) {
+        let Some(path) = self.config.midi_input.clone() else {
+            self.midi = None;
+            return;
+        };
+        if self.midi.as_ref().is_some_and(|midi| midi.path() == Path::new(&path)) {
+            return;
+        }
+        self.midi = match MidiInput::open(Path::new(&path)) {
+            Ok(midi) => Some(midi),
+            Err(e) => {
+                warn!("{:#}", e);
+                None
+            }
+        };
+    }
+    
+    fn lock_processor(&self) -> Result<MutexGuard<'_, AudioProcessorEngine>> {
+        self.processor.lock().map_err(|_| anyhow::anyhow!("插件链被占用的线程崩溃"))
+    }
+    
+    /// 输入和输出回调，中间的无锁传输按缓冲区大小 block_size 控制水位；dither 时整数输出加 TPDF 抖动
+    fn callbacks(
+        &self,
+        processor: ChainProcessor,
+        config: &StreamConfig,
+        block_size: usize,
+        drift_compensation: bool,
+        dither: bool,
+    ) -> (InputCallback, OutputCallback, TransportStats) {
+        let channels = config.channels as usize;
+        let (writer, reader, stats) = transport(channels, block_size, drift_compensation);
+        let input = InputCallback {
+            processor: Some(processor),
+            writer,
+            buffer: PlanarBuffer::new(channels, CALLBACK_FRAMES),
+            interleaved: vec![0.0; CALLBACK_FRAMES * channels],
+            converted: vec![0.0; CALLBACK_FRAMES * channels],
+            channels,
+            meter: self.input_meter.clone(),
+            tuner: Tuner::with_readout(config.sample_rate.0 as f32, self.tuner.clone()),
+            returned: self.returned.clone(),
+        };
+        let output = OutputCallback {
+            reader,
+            scratch: vec![0.0; CALLBACK_FRAMES * channels],
+            channels,
+            meter: self.output_meter.clone(),
+            dither: Dither::new(dither),
+        };
+        (input, output, stats)
+    }
+    
+    /// 收回输入回调释放时放回的插件链
+    fn reclaim(&mut self) {
+        let chain = self.returned.lock().ok().and_then(|mut returned| returned.take());
+        match (chain, self.processor.lock()) {
+            (Some(chain), Ok(mut processor)) => processor.return_processor(chain),
+            _ => warn!("插件链没有从音频线程交回"),
+        }
+    }
+}
+
+impl Default for AudioEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for AudioEngine {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+/// 命令行入口：按 config 启动音频引擎并显示电平表，按 Ctrl+C 停止
+/// project 是要打开的工程和创建插件的工厂
+pub fn run_audio_engine(config: AudioConfig, project: Option<(Project, PluginFactory)>) -> Result<()> {
+    // 1. 列出所有音频设备
+    list_audio_devices()?;
+    
+    println!();
+    info!("=== 启动音频引擎 ===");
     
-    // 5. 创建停止标志
+    // 2. 打开设备并启动音频流
+    let mut engine = AudioEngine::new();
+    engine.set_audio_config(config);
+    if let Some((project, factory)) = project {
+        engine.load_project(&project, factory)?;
+    }
+    engine.start()?;
+    
+    // 3. 创建停止标志
     let running = Arc::new(AtomicBool::new(true));
     let running_clone = running.clone();
     
@@ -60,29 +384,38 @@ pub fn run_audio_engine() -> Result<()> {
         running_clone.store(false, Ordering::Relaxed);
     }).context("设置 Ctrl+C 处理器失败")?;
     
-    // 6. 构建音频流
-    let streams = build_audio_streams(
-        &input_device,
-        &output_device,
-        &config,
-        sample_format,
-        level_meter_clone,
-    )?;
-    
-    info!("✅ 音频引擎启动成功！");
     info!("提示: 按 Ctrl+C 停止");
     println!();
     
-    // 7. 主循环 - 显示电平表
-    run_level_meter_display(&level_meter, &running)?;
+    // 4. 主循环 - 显示电平表
+    run_level_meter_display(&engine, &running)?;
     
-    // 8. 停止音频流
-    drop(streams);
-    info!("音频引擎已停止");
+    // 5. 停止音频流
+    engine.stop();
     
     Ok(())
 }
 
+/// 按请求打开输入输出设备，验证两端都支持请求的采样率、通道数和缓冲区大小
+fn open_requested_devices(request: &AudioConfig) -> Result<OpenedDevices> {
+    let input = find_input_device(request.input_device.as_deref())?;
+    let output = find_output_device(request.output_device.as_deref())?;
+    let (config, input_format) = select_input_config(&input, request)?;
+    let (_, output_format) = select_output_config(&output, request)?;
+    Ok(OpenedDevices { input, output, config, input_format, output_format })
+}
+
+/// 默认输入输出设备和输入设备的默认配置，输出使用输出设备自己的默认采样格式
+fn open_default_devices() -> Result<OpenedDevices> {
+    let input = get_default_input_device()?;
+    let output = get_default_output_device()?;
+    let (config, input_format) = get_audio_config(&input)?;
+    let output_format = output.default_output_config()
+        .context("获取默认输出配置失败")?
+        .sample_format();
+    Ok(OpenedDevices { input, output, config, input_format, output_format })
+}
+
 /// 获取音频配置
 fn get_audio_config(device: &cpal::Device) -> Result<(StreamConfig, SampleFormat)> {
     let default_config = device.default_input_config()
@@ -97,120 +430,145 @@ fn get_audio_config(device: &cpal::Device) -> Result<(StreamConfig, SampleFormat
     Ok((config, sample_format))
 }
 
-/// 构建输入和输出音频流
+/// 输入流回调的状态：采集的音频经过插件链后写入传输
+struct InputCallback {
+    /// 音频线程一侧的插件链，回调释放时交回控制线程
+    processor: Option<ChainProcessor>,
+    writer: TransportWriter,
+    /// 插件链处理用的平面缓冲区（CALLBACK_FRAMES 帧）
+    buffer: PlanarBuffer,
+    /// 处理后的交错数据
+    interleaved: Vec<f32>,
+    /// 整数格式转换后的 f32 数据
+    converted: Vec<f32>,
+    channels: usize,
+    meter: LevelMeter,
+    tuner: Tuner,
+    returned: Arc<Mutex<Option<ChainProcessor>>>,
+}
+
+impl InputCallback {
+    /// 处理一次回调的交错数据（在音频线程中调用，不分配内存）
+    fn process(&mut self, data: &[f32]) {
+        let channels = self.channels;
+        
+        // 电平表和调音器接在插件链之前
+        self.meter.process_interleaved(data, channels);
+        self.tuner.process_buffer(data, channels);
+        
+        for chunk in data.chunks(CALLBACK_FRAMES * channels) {
+            let mut buffer = self.buffer.load_interleaved(chunk, channels);
+            if let Some(processor) = self.processor.as_mut() {
+                processor.process(&mut buffer);
+            }
+            let output = &mut self.interleaved[..chunk.len()];
+            buffer.copy_to_interleaved(output, channels);
+            self.writer.write(output);
+        }
+    }
+    
+    /// 其他格式先转换成 f32 再处理（电平表和调音器看到的都是转换后的数据）
+    fn process_converted<T: ConvertSample>(&mut self, data: &[T]) {
+        let mut converted = std::mem::take(&mut self.converted);
+        for chunk in data.chunks(converted.len().max(1)) {
+            convert_input(chunk, &mut converted);
+            self.process(&converted[..chunk.len()]);
+        }
+        self.converted = converted;
+    }
+}
+
+impl Drop for InputCallback {
+    fn drop(&mut self) {
+        if let (Some(processor), Ok(mut returned)) = (self.processor.take(), self.returned.lock()) {
+            *returned = Some(processor);
+        }
+    }
+}
+
+/// 输出流回调的状态：从传输取出处理后的音频
+struct OutputCallback {
+    reader: TransportReader,
+    /// 转换成整数格式前的 f32 数据
+    scratch: Vec<f32>,
+    channels: usize,
+    meter: LevelMeter,
+    /// 整数格式量化前的抖动
+    dither: Dither,
+}
+
+impl OutputCallback {
+    /// 填充一次回调的交错数据，数据不够时补静音
+    fn render(&mut self, data: &mut [f32]) {
+        self.reader.read(data);
+        self.meter.process_interleaved(data, self.channels);
+    }
+    
+    /// 先取出 f32 数据（电平表在转换前）再转换成设备格式
+    fn render_converted<T: ConvertSample>(&mut self, data: &mut [T]) {
+        let mut scratch = std::mem::take(&mut self.scratch);
+        for chunk in data.chunks_mut(scratch.len().max(1)) {
+            let source = &mut scratch[..chunk.len()];
+            self.render(source);
+            convert_output(source, chunk, &mut self.dither);
+        }
+        self.scratch = scratch;
+    }
+}
+
+/// 构建并启动输入和输出音频流（两端的采样格式可以不同）
+/// cpal 0.15 没有 24 位的 SampleFormat，I24 只在 convert 中提供转换
 fn build_audio_streams(
     input_device: &cpal::Device,
     output_device: &cpal::Device,
     config: &StreamConfig,
-    sample_format: SampleFormat,
-    level_meter: LevelMeter,
+    input_format: SampleFormat,
+    output_format: SampleFormat,
+    mut input: InputCallback,
+    mut output: OutputCallback,
 ) -> Result<(Stream, Stream)> {
-    
-    // 使用环形缓冲区在输入和输出之间传递音频数据
-    let ring_buffer = ringbuf::HeapRb::<f32>::new(
-        config.sample_rate.0 as usize * 2 // 2 秒的缓冲
-    );
-    let (producer, mut consumer) = ring_buffer.split();
-    
-    let producer = Arc::new(std::sync::Mutex::new(producer));
-    
     // 构建输入流
-    let input_producer = producer.clone();
-    let input_level_meter = level_meter.clone();
-    
-    let input_stream = match sample_format {
+    let input_stream = match input_format {
         SampleFormat::F32 => {
             input_device.build_input_stream(
                 config,
-                move |data: &[f32], _: &cpal::InputCallbackInfo| {
-                    // 更新电平表
-                    input_level_meter.process_buffer(data);
-                    
-                    // 写入环形缓冲区
-                    if let Ok(mut prod) = input_producer.lock() {
-                        for &sample in data {
-                            let _ = prod.push(sample);
-                        }
-                    }
-                },
-                |err| error!("输入流错误: {}", err),
-                None,
-            )?
-        }
-        SampleFormat::I16 => {
-            input_device.build_input_stream(
-                config,
-                move |data: &[i16], _: &cpal::InputCallbackInfo| {
-                    if let Ok(mut prod) = input_producer.lock() {
-                        for &sample in data {
-                            let normalized = sample as f32 / i16::MAX as f32;
-                            let _ = prod.push(normalized);
-                        }
-                    }
-                },
+                move |data: &[f32], _: &cpal::InputCallbackInfo| input.process(data),
                 |err| error!("输入流错误: {}", err),
                 None,
             )?
         }
-        SampleFormat::U16 => {
-            input_device.build_input_stream(
-                config,
-                move |data: &[u16], _: &cpal::InputCallbackInfo| {
-                    if let Ok(mut prod) = input_producer.lock() {
-                        for &sample in data {
-                            let normalized = (sample as f32 / u16::MAX as f32) * 2.0 - 1.0;
-                            let _ = prod.push(normalized);
-                        }
-                    }
-                },
-                |err| error!("输入流错误: {}", err),
-                None,
-            )?
-        }
-        _ => return Err(anyhow::anyhow!("不支持的音频格式")),
+        SampleFormat::F64 => build_input_stream::<f64>(input_device, config, input)?,
+        SampleFormat::I8 => build_input_stream::<i8>(input_device, config, input)?,
+        SampleFormat::I16 => build_input_stream::<i16>(input_device, config, input)?,
+        SampleFormat::I32 => build_input_stream::<i32>(input_device, config, input)?,
+        SampleFormat::I64 => build_input_stream::<i64>(input_device, config, input)?,
+        SampleFormat::U8 => build_input_stream::<u8>(input_device, config, input)?,
+        SampleFormat::U16 => build_input_stream::<u16>(input_device, config, input)?,
+        SampleFormat::U32 => build_input_stream::<u32>(input_device, config, input)?,
+        SampleFormat::U64 => build_input_stream::<u64>(input_device, config, input)?,
+        format => return Err(anyhow::anyhow!("不支持的音频格式: {:?}", format)),
     };
     
     // 构建输出流
-    let output_stream = match sample_format {
+    let output_stream = match output_format {
         SampleFormat::F32 => {
             output_device.build_output_stream(
                 config,
-                move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
-                    for sample in data.iter_mut() {
-                        *sample = consumer.pop().unwrap_or(0.0);
-                    }
-                },
-                |err| error!("输出流错误: {}", err),
-                None,
-            )?
-        }
-        SampleFormat::I16 => {
-            output_device.build_output_stream(
-                config,
-                move |data: &mut [i16], _: &cpal::OutputCallbackInfo| {
-                    for sample in data.iter_mut() {
-                        let value = consumer.pop().unwrap_or(0.0);
-                        *sample = (value * i16::MAX as f32) as i16;
-                    }
-                },
-                |err| error!("输出流错误: {}", err),
-                None,
-            )?
-        }
-        SampleFormat::U16 => {
-            output_device.build_output_stream(
-                config,
-                move |data: &mut [u16], _: &cpal::OutputCallbackInfo| {
-                    for sample in data.iter_mut() {
-                        let value = consumer.pop().unwrap_or(0.0);
-                        *sample = ((value + 1.0) / 2.0 * u16::MAX as f32) as u16;
-                    }
-                },
+                move |data: &mut [f32], _: &cpal::OutputCallbackInfo| output.render(data),
                 |err| error!("输出流错误: {}", err),
                 None,
             )?
         }
-        _ => return Err(anyhow::anyhow!("不支持的音频格式")),
+        SampleFormat::F64 => build_output_stream::<f64>(output_device, config, output)?,
+        SampleFormat::I8 => build_output_stream::<i8>(output_device, config, output)?,
+        SampleFormat::I16 => build_output_stream::<i16>(output_device, config, output)?,
+        SampleFormat::I32 => build_output_stream::<i32>(output_device, config, output)?,
+        SampleFormat::I64 => build_output_stream::<i64>(output_device, config, output)?,
+        SampleFormat::U8 => build_output_stream::<u8>(output_device, config, output)?,
+        SampleFormat::U16 => build_output_stream::<u16>(output_device, config, output)?,
+        SampleFormat::U32 => build_output_stream::<u32>(output_device, config, output)?,
+        SampleFormat::U64 => build_output_stream::<u64>(output_device, config, output)?,
+        format => return Err(anyhow::anyhow!("不支持的音频格式: {:?}", format)),
     };
     
     // 启动音频流
@@ -220,25 +578,58 @@ fn build_audio_streams(
     Ok((input_stream, output_stream))
 }
 
+/// 按采样类型 T 构建输入流，采集的数据先转换成 f32
+fn build_input_stream<T: ConvertSample + SizedSample>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    mut input: InputCallback,
+) -> Result<Stream> {
+    let stream = device.build_input_stream(
+        config,
+        move |data: &[T], _: &cpal::InputCallbackInfo| input.process_converted(data),
+        |err| error!("输入流错误: {}", err),
+        None,
+    )?;
+    Ok(stream)
+}
+
+/// 按采样类型 T 构建输出流，f32 数据转换成设备格式
+fn build_output_stream<T: ConvertSample + SizedSample>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    mut output: OutputCallback,
+) -> Result<Stream> {
+    let stream = device.build_output_stream(
+        config,
+        move |data: &mut [T], _: &cpal::OutputCallbackInfo| output.render_converted(data),
+        |err| error!("输出流错误: {}", err),
+        None,
+    )?;
+    Ok(stream)
+}
+
 /// 运行电平表显示
-fn run_level_meter_display(level_meter: &LevelMeter, running: &Arc<AtomicBool>) -> Result<()> {
-    let mut update_counter = 0;
+fn run_level_meter_display(engine: &AudioEngine, running: &Arc<AtomicBool>) -> Result<()> {
+    let tuner = engine.tuner_readout();
     
+    // 每秒更新 10 次
     while running.load(Ordering::Relaxed) {
         std::thread::sleep(Duration::from_millis(100));
+        engine.poll();
         
-        let (left_db, right_db) = level_meter.get_peak_db();
+        let (left_db, right_db) = engine.output_meter().get_peak_db();
         
-        // 每秒更新 10 次
-        if update_counter % 1 == 0 {
-            print!("\r🎸 电平: L: {} | R: {}    ",
-                format_db_bar(left_db),
-                format_db_bar(right_db)
-            );
-            io::stdout().flush()?;
-        }
+        let xruns = engine.transport_stats()
+            .map(|stats| stats.underruns() + stats.overruns())
+            .unwrap_or(0);
         
-        update_counter += 1;
+        print!("\r🎸 电平: L: {} | R: {} | 调音: {:<8} | 断音: {}",
+            format_db_bar(left_db),
+            format_db_bar(right_db),
+            format_reading(tuner.reading()),
+            xruns
+        );
+        io::stdout().flush()?;
     }
     
     println!(); // 换行
@@ -262,3 +653,85 @@ fn format_db_bar(db: f32) -> String {
     format!("{}{} {}", bar, empty, format_db(db))
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugin::{AudioProcessor, BuiltinKind, BuiltinPlugin};
+
+    #[test]
+    fn test_callbacks_run_chain() {
+        let mut engine = AudioEngine::new();
+        let chain = {
+            let mut processor = engine.processor().lock().unwrap();
+            processor.prepare(48000.0, 64, ChannelLayout::Stereo).unwrap();
+            let mut gain = BuiltinPlugin::new(BuiltinKind::Gain);
+            gain.set_parameter(0, (-6.0 + 24.0) / 48.0);
+            gain.reset();
+            processor.add_plugin(Box::new(gain)).unwrap();
+            processor.take_processor().unwrap()
+        };
+        let config = StreamConfig {
+            channels: 2,
+            sample_rate: cpal::SampleRate(48000),
+            buffer_size: cpal::BufferSize::Fixed(64),
+        };
+        let (mut input, mut output, stats) = engine.callbacks(chain, &config, 64, false, false);
+
+        // 采集 -> 插件链 -> 传输 -> 播放，水位达到两块后开始播放
+        for _ in 0..4 {
+            input.process_converted(&[i16::MIN; 128]);
+        }
+        let mut data = vec![1i16; 256];
+        output.render_converted(&mut data);
+        let expected = -10f32.powf(-6.0 / 20.0) * 32768.0;
+        assert!((data[255] as f32 - expected).abs() < 8.0);
+        assert_eq!(stats.fill_frames(), 128);
+
+        // 数据不够时补静音并记一次欠载
+        output.render_converted(&mut data);
+        output.render_converted(&mut data);
+        assert!(data.iter().all(|&sample| sample == 0));
+        assert_eq!(stats.underruns(), 1);
+        assert!((engine.input_meter().get_peak_amplitude().0 - 1.0).abs() < 1e-6);
+        assert!((engine.output_meter().get_peak_amplitude().0 - 10f32.powf(-6.0 / 20.0)).abs() < 1e-3);
+
+        // 回调释放后插件链交回控制线程
+        drop(input);
+        engine.reclaim();
+        let processor = engine.processor().lock().unwrap();
+        assert!(!processor.is_running());
+        assert_eq!(processor.plugin_names(), vec!["Gain"]);
+    }
+
+    #[test]
+    fn test_midi_program_change_selects_project_preset() {
+        let mut project = Project::new("MIDI".to_string());
+        project.add_preset("Clean".to_string());
+        let mut gain = BuiltinPlugin::new(BuiltinKind::Gain);
+        gain.set_parameter(0, 0.25);
+        project.presets.push(crate::plugin::Preset::new("Quiet".to_string(), vec![gain.save_state()], Vec::new()));
+
+        let mut engine = AudioEngine::new();
+        let factory: PluginFactory = Box::new(|state| {
+            let kind = BuiltinKind::from_id(&state.plugin_id).ok_or_else(|| anyhow::anyhow!("未知插件"))?;
+            Ok(Box::new(BuiltinPlugin::new(kind)) as Box<dyn AudioProcessor>)
+        });
+        engine.load_project(&project, factory).unwrap();
+
+        // 原始 MIDI 设备发来程序切换 1，poll 时切换到第二个预设
+        let path = std::env::temp_dir().join("plugin_loader_engine_midi_test");
+        std::fs::write(&path, [0xF8, 0xC0, 1]).unwrap();
+        engine.set_audio_config(AudioConfig { midi_input: Some(path.to_string_lossy().into_owned()), ..AudioConfig::default() });
+        engine.open_midi();
+        let deadline = std::time::Instant::now() + Duration::from_secs(2);
+        while engine.processor().lock().unwrap().current_preset().is_none() && std::time::Instant::now() < deadline {
+            engine.poll();
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        let processor = engine.processor().lock().unwrap();
+        assert_eq!(processor.current_preset(), Some(1));
+        assert_eq!(processor.plugin_names(), vec!["Gain"]);
+        let _ = std::fs::remove_file(&path);
+    }
+}
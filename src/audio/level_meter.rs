@@ -1,62 +1,80 @@
-use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
 use std::sync::Arc;
 
+use super::buffer::MAX_CHANNELS;
+
 /// 音频电平表（线程安全）
+/// 每个声道单独记录峰值，单声道输入时左右显示同一个声道
 #[derive(Clone)]
 pub struct LevelMeter {
-    peak_left: Arc<AtomicU32>,
-    peak_right: Arc<AtomicU32>,
+    peaks: Arc<[AtomicU32; MAX_CHANNELS]>,
+    /// 最近一次处理的声道数
+    channels: Arc<AtomicUsize>,
 }
 
 impl LevelMeter {
     pub fn new() -> Self {
         Self {
-            peak_left: Arc::new(AtomicU32::new(0)),
-            peak_right: Arc::new(AtomicU32::new(0)),
+            peaks: Arc::new(std::array::from_fn(|_| AtomicU32::new(0))),
+            channels: Arc::new(AtomicUsize::new(2)),
         }
     }
     
-    /// 更新电平（在音频线程中调用）
-    pub fn update(&self, left: f32, right: f32) {
-        let left_bits = left.abs().to_bits();
-        let right_bits = right.abs().to_bits();
-        
-        // 使用原子操作更新峰值
-        self.peak_left.fetch_max(left_bits, Ordering::Relaxed);
-        self.peak_right.fetch_max(right_bits, Ordering::Relaxed);
+    /// 更新某个声道的峰值
+    fn update_channel(&self, channel: usize, peak: f32) {
+        // 非负 f32 的位模式与数值顺序一致，可以直接用整数 fetch_max
+        if let Some(slot) = self.peaks.get(channel) {
+            slot.fetch_max(peak.abs().to_bits(), Ordering::Relaxed);
+        }
     }
     
-    /// 处理音频缓冲区并更新电平
-    pub fn process_buffer(&self, buffer: &[f32]) {
-        // 假设立体声交错格式: L, R, L, R, ...
-        for chunk in buffer.chunks_exact(2) {
-            if chunk.len() >= 2 {
-                self.update(chunk[0], chunk[1]);
+    /// 直接处理交错缓冲区（不需要先转换成平面格式）
+    pub fn process_interleaved(&self, buffer: &[f32], channels: usize) {
+        if channels == 0 {
+            return;
+        }
+        self.channels.store(channels.min(MAX_CHANNELS), Ordering::Relaxed);
+        let mut peaks = [0.0f32; MAX_CHANNELS];
+        for frame in buffer.chunks_exact(channels) {
+            for (peak, sample) in peaks.iter_mut().zip(frame) {
+                *peak = peak.max(sample.abs());
             }
         }
+        for (channel, &peak) in peaks.iter().take(channels).enumerate() {
+            self.update_channel(channel, peak);
+        }
+    }
+    
+    /// 最近一次处理的声道数
+    pub fn num_channels(&self) -> usize {
+        self.channels.load(Ordering::Relaxed)
+    }
+    
+    /// 取出并清零某个声道的峰值
+    fn take_peak(&self, channel: usize) -> f32 {
+        f32::from_bits(self.peaks[channel].swap(0, Ordering::Relaxed))
     }
     
     /// 获取当前峰值电平（在 UI 线程中调用）
     pub fn get_peak_db(&self) -> (f32, f32) {
-        let left_bits = self.peak_left.swap(0, Ordering::Relaxed);
-        let right_bits = self.peak_right.swap(0, Ordering::Relaxed);
-        
-        let left = f32::from_bits(left_bits);
-        let right = f32::from_bits(right_bits);
-        
+        let (left, right) = self.get_peak_amplitude();
         (amplitude_to_db(left), amplitude_to_db(right))
     }
     
     /// 获取当前峰值幅度（0.0 - 1.0）
-    #[allow(dead_code)]
     pub fn get_peak_amplitude(&self) -> (f32, f32) {
-        let left_bits = self.peak_left.swap(0, Ordering::Relaxed);
-        let right_bits = self.peak_right.swap(0, Ordering::Relaxed);
-        
-        let left = f32::from_bits(left_bits);
-        let right = f32::from_bits(right_bits);
-        
-        (left, right)
+        let left = self.take_peak(0);
+        if self.num_channels() == 1 {
+            return (left, left);
+        }
+        (left, self.take_peak(1))
+    }
+    
+    /// 获取所有声道的峰值电平（多声道接口使用）
+    pub fn get_channel_peaks_db(&self) -> Vec<f32> {
+        (0..self.num_channels().clamp(1, MAX_CHANNELS))
+            .map(|channel| amplitude_to_db(self.take_peak(channel)))
+            .collect()
     }
 }
 
@@ -93,7 +111,7 @@ mod tests {
         let meter = LevelMeter::new();
         
         // 更新电平
-        meter.update(0.5, 0.8);
+        meter.process_interleaved(&[0.5, -0.8], 2);
         
         let (left, right) = meter.get_peak_amplitude();
         assert!((left - 0.5).abs() < 0.001);
@@ -105,6 +123,22 @@ mod tests {
         assert_eq!(right, 0.0);
     }
     
+    #[test]
+    fn test_mono_and_multichannel() {
+        let meter = LevelMeter::new();
+        
+        // 单声道吉他输入：左右都显示这一个声道
+        meter.process_interleaved(&[0.1, -0.25, 0.2], 1);
+        assert_eq!(meter.get_peak_amplitude(), (0.25, 0.25));
+        
+        // 四声道接口的交错数据
+        meter.process_interleaved(&[0.1, 0.2, 0.3, -0.4, 0.0, 0.0, 0.0, 0.5], 4);
+        let peaks = meter.get_channel_peaks_db();
+        assert_eq!(peaks.len(), 4);
+        assert!((peaks[3] - amplitude_to_db(0.5)).abs() < 1e-4);
+        assert!((peaks[0] - amplitude_to_db(0.1)).abs() < 1e-4);
+    }
+    
     #[test]
     fn test_amplitude_to_db() {
         assert!((amplitude_to_db(1.0) - 0.0).abs() < 0.001);
@@ -0,0 +1,179 @@
+// MIDI 输入
+// 从 Linux 原始 MIDI 设备（/dev/snd/midiC*D*、/dev/midi*）读取字节流，解析成短消息交给控制线程
+
+use anyhow::{Context, Result};
+use log::{info, warn};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+/// MIDI 字节流解析器：支持运行状态，忽略 SysEx 和实时消息
+pub struct MidiParser {
+    /// 当前的通道消息状态字节（运行状态），0 表示没有
+    status: u8,
+    data: [u8; 2],
+    len: usize,
+    /// 在 SysEx（0xF0 .. 0xF7）中
+    sysex: bool,
+}
+
+impl MidiParser {
+    pub fn new() -> Self {
+        Self { status: 0, data: [0; 2], len: 0, sysex: false }
+    }
+
+    /// 送入一个字节，凑齐一条通道消息时返回 [状态, 数据 1, 数据 2]（只有一个数据字节的消息，数据 2 为 0）
+    pub fn push(&mut self, byte: u8) -> Option<[u8; 3]> {
+        match byte {
+            // 实时消息可以插在任何位置，不影响运行状态
+            0xF8..=0xFF => None,
+            0xF0 => {
+                self.sysex = true;
+                self.status = 0;
+                None
+            }
+            // 系统公共消息清除运行状态
+            0xF1..=0xF7 => {
+                self.sysex = false;
+                self.status = 0;
+                None
+            }
+            0x80..=0xEF => {
+                self.sysex = false;
+                self.status = byte;
+                self.len = 0;
+                None
+            }
+            _ if self.sysex || self.status == 0 => None,
+            _ => {
+                self.data[self.len] = byte;
+                self.len += 1;
+                if self.len < data_bytes(self.status) {
+                    return None;
+                }
+                self.len = 0;
+                Some([self.status, self.data[0], if data_bytes(self.status) == 2 { self.data[1] } else { 0 }])
+            }
+        }
+    }
+}
+
+impl Default for MidiParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 通道消息的数据字节数（程序切换和通道触后只有一个）
+fn data_bytes(status: u8) -> usize {
+    match status & 0xF0 {
+        0xC0 | 0xD0 => 1,
+        _ => 2,
+    }
+}
+
+/// 打开的 MIDI 输入：读取线程解析字节流，消息经通道交给控制线程
+///
+/// 读取是阻塞的，MidiInput 释放后读取线程在下一个字节到达（或设备关闭）时退出。
+pub struct MidiInput {
+    path: PathBuf,
+    messages: Receiver<[u8; 3]>,
+}
+
+impl MidiInput {
+    /// 打开原始 MIDI 设备并启动读取线程
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut file = File::open(path).context(format!("打开 MIDI 设备失败: {:?}", path))?;
+        let (sender, messages) = mpsc::channel();
+        let name = path.to_path_buf();
+        thread::Builder::new()
+            .name("midi-input".to_string())
+            .spawn(move || {
+                let mut parser = MidiParser::new();
+                let mut buffer = [0u8; 64];
+                loop {
+                    let count = match file.read(&mut buffer) {
+                        Ok(0) => break,
+                        Ok(count) => count,
+                        Err(e) => {
+                            warn!("读取 MIDI 设备失败 {:?}: {}", name, e);
+                            break;
+                        }
+                    };
+                    for &byte in &buffer[..count] {
+                        if let Some(message) = parser.push(byte) {
+                            if sender.send(message).is_err() {
+                                return;
+                            }
+                        }
+                    }
+                }
+            })
+            .context("启动 MIDI 读取线程失败")?;
+        info!("MIDI 输入: {:?}", path);
+        Ok(Self { path: path.to_path_buf(), messages })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 取出下一条已收到的消息（没有时立即返回 None）
+    pub fn try_recv(&self) -> Option<[u8; 3]> {
+        self.messages.try_recv().ok()
+    }
+}
+
+/// 系统中的原始 MIDI 设备
+pub fn midi_device_paths() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    for (dir, prefix) in [("/dev/snd", "midiC"), ("/dev", "midi")] {
+        if let Ok(entries) = fs::read_dir(dir) {
+            paths.extend(entries.flatten().map(|entry| entry.path()).filter(|path| {
+                path.file_name().and_then(|s| s.to_str()).is_some_and(|name| name.starts_with(prefix))
+            }));
+        }
+    }
+    paths.sort();
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn parse(bytes: &[u8]) -> Vec<[u8; 3]> {
+        let mut parser = MidiParser::new();
+        bytes.iter().filter_map(|&byte| parser.push(byte)).collect()
+    }
+
+    #[test]
+    fn test_parser_running_status() {
+        // 音符开 + 运行状态的第二个音符，程序切换只有一个数据字节
+        assert_eq!(parse(&[0x90, 60, 100, 64, 90, 0xC2, 5, 6]), vec![[0x90, 60, 100], [0x90, 64, 90], [0xC2, 5, 0], [0xC2, 6, 0]]);
+        // 实时消息插在消息中间，SysEx 和没有状态的数据字节被忽略
+        assert_eq!(parse(&[0x7F, 0xB0, 7, 0xF8, 127, 0xF0, 1, 2, 0xF7, 3, 0xC0, 9]), vec![[0xB0, 7, 127], [0xC0, 9, 0]]);
+    }
+
+    #[test]
+    fn test_input_reads_device() {
+        let path = std::env::temp_dir().join("plugin_loader_midi_test");
+        fs::write(&path, [0xC0, 1, 0x90, 60, 100]).unwrap();
+        let input = MidiInput::open(&path).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(2);
+        let mut messages = Vec::new();
+        while messages.len() < 2 && Instant::now() < deadline {
+            match input.try_recv() {
+                Some(message) => messages.push(message),
+                None => thread::sleep(Duration::from_millis(1)),
+            }
+        }
+        assert_eq!(messages, vec![[0xC0, 1, 0], [0x90, 60, 100]]);
+        assert!(MidiInput::open(Path::new("/nonexistent/midi")).is_err());
+        let _ = fs::remove_file(&path);
+    }
+}
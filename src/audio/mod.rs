@@ -1,9 +1,38 @@
+mod buffer;
+mod convert;
+mod delay_line;
 mod engine;
 mod device;
 mod level_meter;
+mod midi;
 mod processor;
+mod resampler;
+mod smoother;
+mod transport;
+mod tuner;
 
-pub use engine::run_audio_engine;
+pub use engine::{run_audio_engine, AudioEngine};
 #[allow(unused_imports)]
-pub use processor::AudioProcessorEngine;
-
+pub use device::{find_input_device, find_output_device, input_device_names, output_device_names};
+#[allow(unused_imports)]
+pub use buffer::{AudioBuffer, PlanarBuffer, MAX_CHANNELS};
+#[cfg(test)]
+pub use buffer::with_interleaved;
+#[allow(unused_imports)]
+pub use convert::{convert_input, convert_output, ConvertSample, Dither};
+#[allow(unused_imports)]
+pub use delay_line::DelayLine;
+#[allow(unused_imports)]
+pub use level_meter::format_db;
+#[allow(unused_imports)]
+pub use midi::{midi_device_paths, MidiInput, MidiParser};
+#[allow(unused_imports)]
+pub use processor::{build_chain, AudioProcessorEngine, ChainEvent, PluginFactory, SwapOptions, DEFAULT_CROSSFADE_MS};
+#[allow(unused_imports)]
+pub use tuner::{Tuner, TunerReadout, TunerReading};
+#[allow(unused_imports)]
+pub use resampler::Resampler;
+#[allow(unused_imports)]
+pub use smoother::{Smoother, SmoothingStyle};
+#[allow(unused_imports)]
+pub use transport::{transport, TransportReader, TransportStats, TransportWriter};
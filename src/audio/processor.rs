@@ -1,48 +1,1368 @@
-use std::sync::{Arc, Mutex};
-use log::debug;
+use std::f32::consts::FRAC_PI_2;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use anyhow::{Context, Result};
+use log::{debug, info, warn};
+use ringbuf::{HeapConsumer, HeapProducer, HeapRb};
 
-use crate::plugin::PluginChain;
+use super::buffer::{AudioBuffer, PlanarBuffer, MAX_CHANNELS};
+use crate::plugin::{
+    restore_chain, AudioProcessor, BusTable, ChainLayouts, ChainSlot, ChannelLayout, Lane, LaneState, ParallelRack, ParameterDescriptor,
+    PluginChain, PluginParameter, PluginState, Preset, ProcessConfig, Project, ProcessEvent, RackState, SlotLayout, SlotSettings, MAX_EVENTS, MAX_LANES,
+    MAX_PLUGINS,
+};
 
-/// 音频处理器 - 管理插件链和音频处理
+/// 命令队列容量
+const COMMAND_CAPACITY: usize = 64;
+
+/// 回复队列容量（至少能放下一次清空插件链退回的所有插件）
+const REPLY_CAPACITY: usize = COMMAND_CAPACITY + MAX_PLUGINS;
+
+/// 为链的干信号延迟补偿预留的容量（秒），实时编辑时不超过它就不分配内存
+const RESERVED_LATENCY_SECONDS: f64 = 1.0;
+
+/// 切换插件链时默认的交叉淡化时长（毫秒）
+pub const DEFAULT_CROSSFADE_MS: f32 = 50.0;
+
+/// 旧插件链尾音最长保留时间（秒）
+const MAX_TAIL_SECONDS: f64 = 5.0;
+
+/// 旧插件链输出的峰值低于它（约 -80 dB）时认为是静音
+const TAIL_SILENCE: f32 = 1e-4;
+
+/// 旧插件链连续静音这么久（秒，另加链的延迟）才认为尾音结束，避免预延迟还没放出来就被截断
+const TAIL_HOLD_SECONDS: f64 = 0.2;
+
+/// 按插件状态创建插件（加载预设时使用）
+pub type PluginFactory = Box<dyn FnMut(&PluginState) -> Result<Box<dyn AudioProcessor>> + Send>;
+
+/// 切换插件链的方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SwapOptions {
+    /// 等功率交叉淡化时长（毫秒）
+    pub fade_ms: f32,
+    /// 保留旧插件链的尾音：旧链的输入淡出，延迟和混响的输出继续播放直到衰减完
+    /// 为 false 时旧链的输出随交叉淡化一起淡出
+    pub keep_tail: bool,
+}
+
+impl SwapOptions {
+    pub fn new() -> Self {
+        Self { fade_ms: DEFAULT_CROSSFADE_MS, keep_tail: true }
+    }
+}
+
+impl Default for SwapOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 发给插件链中某个位置的事件
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChainEvent {
+    /// 插件在链中的位置
+    pub slot: usize,
+    pub event: ProcessEvent,
+}
+
+/// 控制线程 -> 音频线程的插件链命令
+/// 改变插件顺序的命令带着控制线程按编辑后的顺序重新协商的布局
+enum ChainCommand {
+    Insert(usize, ChainSlot, ChainLayouts),
+    Remove(usize, ChainLayouts),
+    Move(usize, usize, ChainLayouts),
+    Replace(usize, ChainSlot, ChainLayouts),
+    Clear,
+    SetBypass(bool),
+    SetGainDb(f32),
+    SetMix(f32),
+    SetSlot(usize, SlotSettings),
+    /// 编辑指定位置的并联机架
+    Rack(usize, RackEdit),
+    /// 交叉淡化切换到已经 prepare 好的插件链
+    Swap(Box<PluginChain>, SwapOptions),
+    /// 音频流即将停止：所有插件在音频线程结束处理，之后输出静音
+    Suspend,
+}
+
+/// 并联机架的支路编辑：支路和插件位置在控制线程准备好，布局按编辑后的支路重新协商
+/// 支路中的位置写作 (支路, 位置)
+enum RackEdit {
+    AddLane(Lane),
+    RemoveLane(usize),
+    Insert(usize, usize, ChainSlot, ChainLayouts),
+    Remove(usize, usize, ChainLayouts),
+    Move(usize, usize, usize, ChainLayouts),
+    SetSlot(usize, usize, SlotSettings),
+}
+
+/// 音频线程 -> 控制线程的回复
+enum ChainReply {
+    /// 被移除或替换的位置，在控制线程释放
+    Retired(ChainSlot),
+    /// 从并联机架移除的支路，在控制线程释放
+    RetiredLane(Lane),
+    /// 淡出结束的旧插件链，在控制线程释放
+    RetiredChain(Box<PluginChain>),
+    /// 已经执行 Suspend，插件链可以交回控制线程
+    Suspended,
+}
+
+/// 切换后仍在淡出的旧插件链
+struct Outgoing {
+    chain: Box<PluginChain>,
+    /// 交叉淡化长度（帧）
+    fade: usize,
+    /// 切换后已经处理的帧数
+    position: usize,
+    keep_tail: bool,
+    /// 还能保留尾音的帧数
+    tail: usize,
+    /// 连续静音的帧数和判定尾音结束所需的帧数
+    silent: usize,
+    hold: usize,
+}
+
+impl Outgoing {
+    /// 距当前块开头 offset 帧处新链和旧链的增益（sin/cos 等功率曲线）
+    fn gains(&self, offset: usize) -> (f32, f32) {
+        let progress = (self.position + offset) as f32 / self.fade as f32;
+        if progress >= 1.0 {
+            return (1.0, 0.0);
+        }
+        let angle = progress * FRAC_PI_2;
+        (angle.sin(), angle.cos())
+    }
+}
+
+/// 音频线程一侧：拥有正在运行的插件链
+///
+/// 每个音频块开头取出控制线程的命令和事件，编辑插件链时不加锁、不分配内存，
+/// 被移除的插件通过回复队列交回控制线程释放。
+/// 切换插件链时新旧两条链并行处理一段时间，旧链淡出（并放完尾音）后同样交回控制线程。
+pub struct ChainProcessor {
+    chain: Box<PluginChain>,
+    /// 正在淡出的旧插件链
+    outgoing: Option<Outgoing>,
+    /// 旧插件链的工作缓冲区（MAX_CHANNELS x max_block_size 帧）
+    scratch: PlanarBuffer,
+    commands: HeapConsumer<ChainCommand>,
+    replies: HeapProducer<ChainReply>,
+    events: HeapConsumer<ChainEvent>,
+    /// 执行过 Suspend，重新 prepare 之前不再处理
+    suspended: bool,
+}
+
+impl ChainProcessor {
+    /// 处理音频缓冲区（任意声道数的平面缓冲区）
+    pub fn process(&mut self, buffer: &mut AudioBuffer) {
+        self.apply_commands();
+        if self.suspended {
+            while self.events.pop().is_some() {}
+            buffer.clear();
+            return;
+        }
+        while let Some(ChainEvent { slot, event }) = self.events.pop() {
+            self.chain.queue_event(slot, event);
+        }
+        if self.outgoing.is_none() {
+            // bypass 由插件链淡出后直通，空链直接返回
+            self.chain.process(buffer);
+            return;
+        }
+
+        let block_size = self.scratch.capacity().max(1);
+        let frames = buffer.frames();
+        for start in (0..frames).step_by(block_size) {
+            let end = (start + block_size).min(frames);
+            self.crossfade(&mut buffer.slice(start, end));
+        }
+    }
+
+    /// 新旧插件链并行处理一块（不超过 scratch 的容量），旧链结束后交回控制线程
+    fn crossfade(&mut self, buffer: &mut AudioBuffer) {
+        let Some(outgoing) = self.outgoing.as_mut() else {
+            self.chain.process(buffer);
+            return;
+        };
+
+        let frames = buffer.frames();
+        let mut old = self.scratch.as_buffer_channels(buffer.num_channels(), frames);
+        old.copy_from(buffer);
+        if outgoing.keep_tail {
+            // 旧链的输入淡出，已经进入延迟和混响的信号自然衰减
+            for channel in old.channels_mut() {
+                for (offset, sample) in channel.iter_mut().enumerate() {
+                    *sample *= outgoing.gains(offset).1;
+                }
+            }
+        }
+        outgoing.chain.process(&mut old);
+        self.chain.process(buffer);
+
+        let mut peak = 0.0f32;
+        for (channel, old_channel) in buffer.channels_mut().zip(old.channels()) {
+            for (offset, (sample, old_sample)) in channel.iter_mut().zip(old_channel).enumerate() {
+                let (new_gain, old_gain) = outgoing.gains(offset);
+                let old_gain = if outgoing.keep_tail { 1.0 } else { old_gain };
+                *sample = *sample * new_gain + old_sample * old_gain;
+                peak = peak.max(old_sample.abs());
+            }
+        }
+
+        outgoing.position += frames;
+        if outgoing.position < outgoing.fade {
+            return;
+        }
+        outgoing.tail = outgoing.tail.saturating_sub(frames);
+        outgoing.silent = if peak < TAIL_SILENCE { outgoing.silent + frames } else { 0 };
+        let finished = !outgoing.keep_tail || outgoing.silent >= outgoing.hold || outgoing.tail == 0;
+        // 回复队列满时旧链继续处理静音，下一块再交回
+        if finished && self.replies.free_len() > 0 {
+            self.retire_outgoing();
+        }
+    }
+
+    /// 正在运行的插件链（只读）
+    #[cfg(test)]
+    pub fn chain(&self) -> &PluginChain {
+        &self.chain
+    }
+
+    fn apply_commands(&mut self) {
+        // 回复队列放不下一次清空退回的插件时，命令留到下一块，保证不在音频线程释放插件
+        while self.replies.free_len() > MAX_PLUGINS {
+            let Some(command) = self.commands.pop() else {
+                break;
+            };
+            self.apply(command);
+        }
+    }
+
+    fn apply(&mut self, command: ChainCommand) {
+        match command {
+            ChainCommand::Insert(index, slot, layouts) => {
+                match self.chain.insert_slot(index, slot) {
+                    Some(slot) => self.retire(slot),
+                    None => self.chain.set_layouts(&layouts),
+                }
+            }
+            ChainCommand::Remove(index, layouts) => {
+                if let Some(slot) = self.chain.take_slot(index) {
+                    self.chain.set_layouts(&layouts);
+                    self.retire(slot);
+                }
+            }
+            ChainCommand::Move(from, to, layouts) => {
+                if self.chain.move_slot(from, to) {
+                    self.chain.set_layouts(&layouts);
+                }
+            }
+            ChainCommand::Replace(index, slot, layouts) => {
+                let old = self.chain.replace_slot(index, slot);
+                self.chain.set_layouts(&layouts);
+                self.retire(old);
+            }
+            ChainCommand::Clear => {
+                // 从末尾取出，避免移动后面的位置
+                while let Some(slot) = self.chain.len().checked_sub(1).and_then(|last| self.chain.take_slot(last)) {
+                    self.retire(slot);
+                }
+            }
+            ChainCommand::SetBypass(bypass) => self.chain.set_bypass(bypass),
+            ChainCommand::SetGainDb(db) => self.chain.set_gain_db(db),
+            ChainCommand::SetMix(mix) => self.chain.set_mix(mix),
+            ChainCommand::SetSlot(index, settings) => {
+                if index < self.chain.len() {
+                    let _ = self.chain.set_slot_settings(index, settings);
+                }
+            }
+            ChainCommand::Rack(index, edit) => self.edit_rack(index, edit),
+            ChainCommand::Swap(chain, options) => {
+                // 上一次切换还没结束时直接退回更早的链（截断它的尾音）
+                self.retire_outgoing();
+                let sample_rate = chain.config().sample_rate;
+                let old = std::mem::replace(&mut self.chain, chain);
+                let hold = (TAIL_HOLD_SECONDS * sample_rate) as usize + old.latency() as usize;
+                self.outgoing = Some(Outgoing {
+                    chain: old,
+                    fade: ((options.fade_ms as f64 / 1000.0 * sample_rate) as usize).max(1),
+                    position: 0,
+                    keep_tail: options.keep_tail,
+                    tail: (MAX_TAIL_SECONDS * sample_rate) as usize,
+                    silent: 0,
+                    hold,
+                });
+            }
+            ChainCommand::Suspend => {
+                self.retire_outgoing();
+                self.chain.suspend();
+                self.suspended = true;
+                let _ = self.replies.push(ChainReply::Suspended);
+            }
+        }
+    }
+
+    /// 切换是否还在进行（旧链在淡出或放尾音）
+    #[cfg(test)]
+    pub fn is_crossfading(&self) -> bool {
+        self.outgoing.is_some()
+    }
+
+    fn retire_outgoing(&mut self) {
+        if let Some(mut outgoing) = self.outgoing.take() {
+            outgoing.chain.suspend();
+            let _ = self.replies.push(ChainReply::RetiredChain(outgoing.chain));
+        }
+    }
+
+    fn retire(&mut self, mut slot: ChainSlot) {
+        slot.suspend();
+        // apply_commands 保证回复队列有空位
+        let _ = self.replies.push(ChainReply::Retired(slot));
+    }
+
+    fn retire_lane(&mut self, mut lane: Lane) {
+        lane.suspend();
+        let _ = self.replies.push(ChainReply::RetiredLane(lane));
+    }
+
+    /// 编辑并联机架（实时安全），随后重新对齐支路并更新机架所在位置的延迟
+    fn edit_rack(&mut self, index: usize, edit: RackEdit) {
+        let Some(rack) = self.chain.rack_mut(index) else {
+            // 之前的命令已经移除了机架：准备好的支路和插件原样退回
+            match edit {
+                RackEdit::AddLane(lane) => self.retire_lane(lane),
+                RackEdit::Insert(_, _, slot, _) => self.retire(slot),
+                _ => {}
+            }
+            return;
+        };
+        let retired = match edit {
+            RackEdit::AddLane(lane) => rack.insert_lane(lane).map(ChainReply::RetiredLane),
+            RackEdit::RemoveLane(lane) => rack.take_lane(lane).map(ChainReply::RetiredLane),
+            RackEdit::Insert(lane, slot_index, slot, layouts) => match rack.lane_mut(lane) {
+                Some(chain) => {
+                    let rejected = chain.insert_slot(slot_index, slot);
+                    if rejected.is_none() {
+                        chain.set_layouts(&layouts);
+                    }
+                    rejected.map(ChainReply::Retired)
+                }
+                None => Some(ChainReply::Retired(slot)),
+            },
+            RackEdit::Remove(lane, slot_index, layouts) => rack.lane_mut(lane).and_then(|chain| {
+                let slot = chain.take_slot(slot_index)?;
+                chain.set_layouts(&layouts);
+                Some(ChainReply::Retired(slot))
+            }),
+            RackEdit::Move(lane, from, to, layouts) => {
+                if let Some(chain) = rack.lane_mut(lane) {
+                    if chain.move_slot(from, to) {
+                        chain.set_layouts(&layouts);
+                    }
+                }
+                None
+            }
+            RackEdit::SetSlot(lane, slot_index, settings) => {
+                if let Some(chain) = rack.lane_mut(lane).filter(|chain| slot_index < chain.len()) {
+                    let _ = chain.set_slot_settings(slot_index, settings);
+                }
+                None
+            }
+        };
+        rack.update_latency();
+        self.chain.refresh_slot_latency(index);
+        match retired {
+            Some(ChainReply::Retired(slot)) => self.retire(slot),
+            Some(ChainReply::RetiredLane(lane)) => self.retire_lane(lane),
+            _ => {}
+        }
+    }
+
+    fn prepare(&mut self, config: ProcessConfig) {
+        // 旧链的配置已经过时，不再淡出
+        self.retire_outgoing();
+        self.suspended = false;
+        self.scratch.resize(MAX_CHANNELS, config.max_block_size);
+        self.chain.prepare(config.sample_rate, config.max_block_size, config.layout);
+        self.chain.reserve_latency(reserved_latency(config));
+    }
+}
+
+/// 延迟补偿预留的帧数
+fn reserved_latency(config: ProcessConfig) -> usize {
+    (config.sample_rate * RESERVED_LATENCY_SECONDS) as usize
+}
+
+/// MIDI 程序切换消息（0xCn）的程序号
+fn program_change(data: [u8; 3]) -> Option<u8> {
+    (data[0] & 0xF0 == 0xC0).then_some(data[1] & 0x7F)
+}
+
+/// 按配置准备一条新的插件链：加载插件、prepare 并恢复各位置的设置
+///
+/// 不涉及正在运行的插件链，可以在控制线程或任意工作线程中调用，完成后交给 swap_chain 切换。
+pub fn build_chain(
+    config: ProcessConfig,
+    plugins: &[PluginState],
+    slot_settings: &[SlotSettings],
+    load: &mut dyn FnMut(&PluginState) -> Result<Box<dyn AudioProcessor>>,
+) -> Result<PluginChain> {
+    let mut chain = PluginChain::new();
+    chain.prepare(config.sample_rate, config.max_block_size, config.layout);
+    restore_chain(&mut chain, plugins, load)?;
+    if !slot_settings.is_empty() {
+        chain.load_slot_settings(slot_settings);
+    }
+    chain.reserve_latency(reserved_latency(config));
+    Ok(chain)
+}
+
+/// 工作线程中正在准备的预设
+struct PendingPreset {
+    index: usize,
+    name: String,
+    receiver: Receiver<Result<PluginChain>>,
+}
+
+/// 控制线程中对正在运行的插件链的镜像
+struct SlotMirror {
+    name: String,
+    layout: SlotLayout,
+    /// 插件对两种上游布局的总线布局，实时编辑后据此重新协商
+    buses: BusTable,
+    latency: u32,
+    settings: SlotSettings,
+    /// 参数描述和控制线程最后设置的归一化值（界面显示用）
+    parameters: Vec<ParameterDescriptor>,
+    values: Vec<f64>,
+    /// 插件是并联机架时各支路的镜像
+    rack: Option<RackMirror>,
+    /// 插件加入插件链时保存的状态，运行中保存工程时以它为基础
+    state: PluginState,
+    /// 插件声明了 MIDI 输入，转发的 MIDI 消息只发给这些插件
+    midi: bool,
+}
+
+impl SlotMirror {
+    /// 按控制线程中的插件记录镜像（插件不在音频线程时调用）
+    fn new(plugin: &dyn AudioProcessor, layout: SlotLayout, latency: u32, settings: SlotSettings) -> Self {
+        let parameters = plugin.parameter_descriptors();
+        let values = parameters
+            .iter()
+            .map(|descriptor| plugin.get_parameter(descriptor.id).unwrap_or_else(|| descriptor.default_normalized()))
+            .collect();
+        let info = plugin.get_info();
+        let buses = BusTable::of(plugin);
+        let rack = ParallelRack::downcast(plugin).map(RackMirror::new);
+        let state = plugin.save_state();
+        let midi = plugin.accepts_midi();
+        Self { name: info.name.clone(), layout, buses, latency, settings, parameters, values, rack, state, midi }
+    }
+
+    fn from_slot(slot: &ChainSlot) -> Self {
+        Self::new(slot.plugin(), slot.layout(), slot.latency(), SlotSettings::default())
+    }
+
+    /// 不打断音频线程的插件状态：加入插件链时保存的状态，参数换成控制线程最后设置的值
+    /// 并联机架按支路的镜像重新组装；插件在音频线程中自己改变的内部状态不会反映出来
+    fn save_state(&self) -> PluginState {
+        let Some(rack) = self.rack.as_ref() else {
+            let mut state = self.state.clone();
+            for parameter in &mut state.parameters {
+                let Some(position) = self.parameters.iter().position(|descriptor| descriptor.id == parameter.id) else {
+                    continue;
+                };
+                if parameter.value != self.values[position] {
+                    parameter.value = self.values[position];
+                    parameter.display = self.parameters[position].value_to_text(parameter.value);
+                }
+            }
+            return state;
+        };
+
+        let parameters: Vec<PluginParameter> = self
+            .parameters
+            .iter()
+            .zip(&self.values)
+            .map(|(descriptor, &value)| PluginParameter {
+                id: descriptor.id,
+                name: descriptor.name.clone(),
+                value,
+                display: descriptor.value_to_text(value),
+            })
+            .collect();
+        let plain = |id: usize| self.parameters.get(id).map(|descriptor| descriptor.to_plain(self.values[id]) as f32);
+        let lanes = rack
+            .lanes
+            .iter()
+            .enumerate()
+            .map(|(lane, slots)| LaneState {
+                gain_db: plain(lane * 2).unwrap_or_default(),
+                pan: plain(lane * 2 + 1).unwrap_or_default(),
+                plugins: slots.iter().map(SlotMirror::save_state).collect(),
+                slot_settings: slots.iter().map(|slot| slot.settings).collect(),
+            })
+            .collect();
+        RackState { lanes }.to_plugin_state(parameters)
+    }
+
+    /// 支路编辑后按支路的镜像更新机架的延迟和参数（支路 i 的增益为 2i，声像为 2i + 1）
+    fn sync_rack(&mut self) {
+        let Some(rack) = self.rack.as_ref() else {
+            return;
+        };
+        self.latency = rack.latency();
+        self.parameters = ParallelRack::lane_descriptors(rack.lanes.len());
+        self.values.truncate(self.parameters.len());
+        let defaults: Vec<f64> = self.parameters[self.values.len()..].iter().map(|d| d.default_normalized()).collect();
+        self.values.extend(defaults);
+    }
+}
+
+/// 控制线程中对并联机架支路的镜像
+struct RackMirror {
+    /// 机架 prepare 时的配置，支路中的新插件按它 prepare
+    config: ProcessConfig,
+    /// 每条支路中各位置的镜像
+    lanes: Vec<Vec<SlotMirror>>,
+}
+
+impl RackMirror {
+    fn new(rack: &ParallelRack) -> Self {
+        let lanes = (0..rack.lane_count()).filter_map(|lane| rack.lane(lane)).map(chain_mirrors).collect();
+        Self { config: rack.config(), lanes }
+    }
+
+    /// 最慢支路的延迟（帧）
+    fn latency(&self) -> u32 {
+        self.lanes.iter().map(|lane| lane.iter().map(|slot| slot.latency).sum()).max().unwrap_or(0)
+    }
+}
+
+/// 按编辑后的顺序（edit 修改各位置的总线表）从输入布局 input 开始重新协商所有位置的布局
+fn negotiate(slots: &[SlotMirror], input: ChannelLayout, edit: impl FnOnce(&mut Vec<BusTable>)) -> ChainLayouts {
+    let mut tables: Vec<BusTable> = slots.iter().map(|slot| slot.buses).collect();
+    edit(&mut tables);
+    ChainLayouts::negotiate(input, &tables)
+}
+
+/// 镜像换上与命令一起发出的布局
+fn set_mirror_layouts(slots: &mut [SlotMirror], layouts: &ChainLayouts) {
+    for (slot, &layout) in slots.iter_mut().zip(layouts.as_slice()) {
+        slot.layout = layout;
+    }
+}
+
+/// 镜像中位置 index 上一级送来的布局（第一个位置收到输入布局 input）
+fn upstream_layout(slots: &[SlotMirror], index: usize, input: ChannelLayout) -> ChannelLayout {
+    match index.checked_sub(1).and_then(|i| slots.get(i)) {
+        Some(previous) => previous.layout.bus.output,
+        None => input,
+    }
+}
+
+/// 插件链所有位置的镜像
+fn chain_mirrors(chain: &PluginChain) -> Vec<SlotMirror> {
+    chain
+        .slot_layouts()
+        .iter()
+        .enumerate()
+        .filter_map(|(index, &layout)| {
+            Some(SlotMirror::new(
+                chain.plugin(index)?,
+                layout,
+                chain.slot_latency(index)?,
+                chain.slot_settings(index)?,
+            ))
+        })
+        .collect()
+}
+
+/// 音频处理器 - 控制线程一侧，管理插件链
+///
+/// 插件链由 ChainProcessor 持有：音频流启动时用 take_processor 交给音频线程，停止后用 return_processor 收回。
+/// 编辑命令经无锁队列发给音频线程，新插件在控制线程按镜像中的布局 prepare，音频线程不会因为编辑而阻塞或跳过处理。
 pub struct AudioProcessorEngine {
-    plugin_chain: Arc<Mutex<PluginChain>>,
+    /// 音频流未启动时由控制线程持有
+    processor: Option<ChainProcessor>,
+    commands: HeapProducer<ChainCommand>,
+    replies: HeapConsumer<ChainReply>,
+    /// 控制线程 -> 音频线程的事件队列（无锁环形缓冲区）
+    events: HeapProducer<ChainEvent>,
+    config: ProcessConfig,
+    /// 插件链的镜像（名称、布局和延迟），与音频线程中的插件链按相同顺序编辑
+    slots: Vec<SlotMirror>,
     bypass: bool,
+    gain_db: f32,
+    mix: f32,
+    /// 预设列表（按 MIDI 程序号切换）和当前预设
+    presets: Vec<Preset>,
+    current_preset: Option<usize>,
+    /// 加载预设时创建插件，工作线程准备预设时共用
+    factory: Option<Arc<Mutex<PluginFactory>>>,
+    /// 工作线程中正在准备的预设（MIDI 程序切换）
+    pending_preset: Option<PendingPreset>,
+    swap_options: SwapOptions,
+    /// 音频线程已经执行 Suspend
+    suspended: bool,
 }
 
 impl AudioProcessorEngine {
     pub fn new() -> Self {
+        let (commands, command_consumer) = HeapRb::new(COMMAND_CAPACITY).split();
+        let (reply_producer, replies) = HeapRb::new(REPLY_CAPACITY).split();
+        let (events, event_consumer) = HeapRb::new(MAX_EVENTS).split();
+        let mut processor = ChainProcessor {
+            chain: Box::new(PluginChain::new()),
+            outgoing: None,
+            scratch: PlanarBuffer::new(MAX_CHANNELS, 0),
+            commands: command_consumer,
+            replies: reply_producer,
+            events: event_consumer,
+            suspended: false,
+        };
+        let config = processor.chain.config();
+        processor.prepare(config);
         Self {
-            plugin_chain: Arc::new(Mutex::new(PluginChain::new())),
+            processor: Some(processor),
+            commands,
+            replies,
+            events,
+            config,
+            slots: Vec::with_capacity(MAX_PLUGINS),
             bypass: false,
+            gain_db: 0.0,
+            mix: 1.0,
+            presets: Vec::new(),
+            current_preset: None,
+            factory: None,
+            pending_preset: None,
+            swap_options: SwapOptions::new(),
+            suspended: false,
+        }
+    }
+
+    /// 取出音频线程一侧（音频流启动时调用），已经取出时返回 None
+    pub fn take_processor(&mut self) -> Option<ChainProcessor> {
+        self.processor.take()
+    }
+
+    /// 收回音频线程一侧（音频流停止后调用），处理还没执行的命令
+    pub fn return_processor(&mut self, processor: ChainProcessor) {
+        self.processor = Some(processor);
+        self.flush();
+    }
+
+    /// 请求音频线程结束所有插件的处理（停止音频流之前调用），用 is_suspended 等待音频线程执行
+    ///
+    /// 有的格式（CLAP 的 stop_processing）要求在音频线程结束处理，插件链交回后才能在控制线程停用插件
+    pub fn suspend(&mut self) -> Result<()> {
+        self.suspended = false;
+        self.send(ChainCommand::Suspend)?;
+        self.flush();
+        Ok(())
+    }
+
+    /// 音频线程是否已经执行 suspend 请求
+    pub fn is_suspended(&mut self) -> bool {
+        self.collect_retired();
+        self.suspended
+    }
+
+    /// 插件链是否已交给音频线程
+    pub fn is_running(&self) -> bool {
+        self.processor.is_none()
+    }
+
+    /// 当前处理配置
+    pub fn config(&self) -> ProcessConfig {
+        self.config
+    }
+
+    /// 音频配置变化时（打开设备、切换采样率或缓冲区大小）调用，插件链会把新配置传给所有插件
+    ///
+    /// prepare 不是实时安全的（插件可能重新激活、重建实例或读取文件），只能在插件链交回控制线程后调用
+    pub fn prepare(&mut self, sample_rate: f64, max_block_size: usize, layout: ChannelLayout) -> Result<()> {
+        self.flush();
+        let processor = self
+            .processor
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("插件链在音频线程中，请先停止音频流再重新 prepare"))?;
+        self.config = ProcessConfig { sample_rate, max_block_size: max_block_size.max(1), layout };
+        processor.prepare(self.config);
+        self.sync_mirrors();
+        Ok(())
+    }
+
+    /// 添加插件到链的末尾（插件在当前线程 prepare）
+    pub fn add_plugin(&mut self, plugin: Box<dyn AudioProcessor>) -> Result<()> {
+        self.insert_plugin(self.slots.len(), plugin)
+    }
+
+    /// 在指定位置插入插件
+    pub fn insert_plugin(&mut self, index: usize, plugin: Box<dyn AudioProcessor>) -> Result<()> {
+        if self.slots.len() >= MAX_PLUGINS {
+            return Err(anyhow::anyhow!("插件链已满（最多 {} 个）", MAX_PLUGINS));
+        }
+        if index > self.slots.len() {
+            return Err(anyhow::anyhow!("索引超出范围"));
+        }
+
+        let mut slot = ChainSlot::prepare(plugin, self.config, upstream_layout(&self.slots, index, self.config.layout));
+        slot.reserve_latency(reserved_latency(self.config));
+        let mirror = SlotMirror::from_slot(&slot);
+        let layouts = negotiate(&self.slots, self.config.layout, |tables| tables.insert(index, mirror.buses));
+        self.send(ChainCommand::Insert(index, slot, layouts))?;
+        info!("在位置 {} 插入插件: {}", index, mirror.name);
+        self.slots.insert(index, mirror);
+        set_mirror_layouts(&mut self.slots, &layouts);
+        self.flush();
+        Ok(())
+    }
+
+    /// 移除指定位置的插件，插件退回后在控制线程释放
+    pub fn remove_plugin(&mut self, index: usize) -> Result<()> {
+        if index >= self.slots.len() {
+            return Err(anyhow::anyhow!("索引超出范围"));
+        }
+        let layouts = negotiate(&self.slots, self.config.layout, |tables| {
+            tables.remove(index);
+        });
+        self.send(ChainCommand::Remove(index, layouts))?;
+        self.slots.remove(index);
+        set_mirror_layouts(&mut self.slots, &layouts);
+        self.flush();
+        Ok(())
+    }
+
+    /// 移动插件到新位置
+    pub fn move_plugin(&mut self, from: usize, to: usize) -> Result<()> {
+        if from >= self.slots.len() || to >= self.slots.len() {
+            return Err(anyhow::anyhow!("索引超出范围"));
         }
+        let layouts = negotiate(&self.slots, self.config.layout, |tables| {
+            let table = tables.remove(from);
+            tables.insert(to, table);
+        });
+        self.send(ChainCommand::Move(from, to, layouts))?;
+        let slot = self.slots.remove(from);
+        self.slots.insert(to, slot);
+        set_mirror_layouts(&mut self.slots, &layouts);
+        self.flush();
+        Ok(())
     }
-    
-    /// 获取插件链的引用
-    pub fn get_plugin_chain(&self) -> Arc<Mutex<PluginChain>> {
-        self.plugin_chain.clone()
+
+    /// 替换指定位置的插件
+    pub fn replace_plugin(&mut self, index: usize, plugin: Box<dyn AudioProcessor>) -> Result<()> {
+        if index >= self.slots.len() {
+            return Err(anyhow::anyhow!("索引超出范围"));
+        }
+
+        let mut slot = ChainSlot::prepare(plugin, self.config, upstream_layout(&self.slots, index, self.config.layout));
+        slot.reserve_latency(reserved_latency(self.config));
+        let mirror = SlotMirror::from_slot(&slot);
+        let layouts = negotiate(&self.slots, self.config.layout, |tables| tables[index] = mirror.buses);
+        self.send(ChainCommand::Replace(index, slot, layouts))?;
+        info!("替换位置 {} 的插件: {}", index, mirror.name);
+        self.slots[index] = mirror;
+        set_mirror_layouts(&mut self.slots, &layouts);
+        self.flush();
+        Ok(())
+    }
+
+    /// 清空插件链
+    pub fn clear(&mut self) -> Result<()> {
+        self.send(ChainCommand::Clear)?;
+        self.slots.clear();
+        self.flush();
+        Ok(())
+    }
+
+    /// 设置指定位置的旁通、静音、独奏和干湿比
+    pub fn set_slot_settings(&mut self, index: usize, settings: SlotSettings) -> Result<()> {
+        if index >= self.slots.len() {
+            return Err(anyhow::anyhow!("索引超出范围"));
+        }
+        self.send(ChainCommand::SetSlot(index, settings))?;
+        self.slots[index].settings = SlotSettings { mix: settings.mix.clamp(0.0, 1.0), ..settings };
+        self.flush();
+        Ok(())
+    }
+
+    /// 在指定位置的并联机架末尾添加一条空支路，返回支路编号
+    ///
+    /// 支路在控制线程按机架的配置准备，和插件链的其它编辑一样经命令队列交给音频线程，运行中也可以编辑。
+    pub fn add_lane(&mut self, slot: usize) -> Result<usize> {
+        let rack = self.rack_mirror(slot)?;
+        let lane = rack.lanes.len();
+        if lane >= MAX_LANES {
+            return Err(anyhow::anyhow!("并联支路已满（最多 {} 条）", MAX_LANES));
+        }
+        let prepared = Lane::prepare(rack.config, reserved_latency(self.config));
+        self.send(ChainCommand::Rack(slot, RackEdit::AddLane(prepared)))?;
+        info!("位置 {} 的并联机架添加支路 {}", slot, lane + 1);
+        self.edit_rack_mirror(slot, |rack| rack.lanes.push(Vec::new()));
+        Ok(lane)
+    }
+
+    /// 移除并联机架的支路，支路中的插件退回后在控制线程释放
+    pub fn remove_lane(&mut self, slot: usize, lane: usize) -> Result<()> {
+        self.lane_mirror(slot, lane)?;
+        self.send(ChainCommand::Rack(slot, RackEdit::RemoveLane(lane)))?;
+        info!("位置 {} 的并联机架移除支路 {}", slot, lane + 1);
+        // 后面支路的参数编号前移
+        self.slots[slot].values.drain(lane * 2..lane * 2 + 2);
+        self.edit_rack_mirror(slot, |rack| {
+            rack.lanes.remove(lane);
+        });
+        Ok(())
+    }
+
+    /// 在并联机架支路的末尾添加插件
+    pub fn add_lane_plugin(&mut self, slot: usize, lane: usize, plugin: Box<dyn AudioProcessor>) -> Result<()> {
+        let index = self.lane_mirror(slot, lane)?.len();
+        self.insert_lane_plugin(slot, lane, index, plugin)
+    }
+
+    /// 在并联机架支路的指定位置插入插件（插件在当前线程按机架的配置 prepare）
+    pub fn insert_lane_plugin(&mut self, slot: usize, lane: usize, index: usize, plugin: Box<dyn AudioProcessor>) -> Result<()> {
+        let config = self.rack_mirror(slot)?.config;
+        let slots = self.lane_mirror(slot, lane)?;
+        if slots.len() >= MAX_PLUGINS {
+            return Err(anyhow::anyhow!("插件链已满（最多 {} 个）", MAX_PLUGINS));
+        }
+        if index > slots.len() {
+            return Err(anyhow::anyhow!("索引超出范围"));
+        }
+
+        let mut prepared = ChainSlot::prepare(plugin, config, upstream_layout(slots, index, config.layout));
+        prepared.reserve_latency(reserved_latency(self.config));
+        let mirror = SlotMirror::from_slot(&prepared);
+        let layouts = negotiate(slots, config.layout, |tables| tables.insert(index, mirror.buses));
+        self.send(ChainCommand::Rack(slot, RackEdit::Insert(lane, index, prepared, layouts)))?;
+        info!("在并联支路 {} 的位置 {} 插入插件: {}", lane + 1, index, mirror.name);
+        self.edit_rack_mirror(slot, |rack| {
+            rack.lanes[lane].insert(index, mirror);
+            set_mirror_layouts(&mut rack.lanes[lane], &layouts);
+        });
+        Ok(())
+    }
+
+    /// 移除并联机架支路中的插件，插件退回后在控制线程释放
+    pub fn remove_lane_plugin(&mut self, slot: usize, lane: usize, index: usize) -> Result<()> {
+        let config = self.rack_mirror(slot)?.config;
+        let slots = self.lane_mirror(slot, lane)?;
+        if index >= slots.len() {
+            return Err(anyhow::anyhow!("索引超出范围"));
+        }
+        let layouts = negotiate(slots, config.layout, |tables| {
+            tables.remove(index);
+        });
+        self.send(ChainCommand::Rack(slot, RackEdit::Remove(lane, index, layouts)))?;
+        self.edit_rack_mirror(slot, |rack| {
+            rack.lanes[lane].remove(index);
+            set_mirror_layouts(&mut rack.lanes[lane], &layouts);
+        });
+        Ok(())
+    }
+
+    /// 移动并联机架支路中的插件
+    pub fn move_lane_plugin(&mut self, slot: usize, lane: usize, from: usize, to: usize) -> Result<()> {
+        let config = self.rack_mirror(slot)?.config;
+        let slots = self.lane_mirror(slot, lane)?;
+        if from >= slots.len() || to >= slots.len() {
+            return Err(anyhow::anyhow!("索引超出范围"));
+        }
+        let layouts = negotiate(slots, config.layout, |tables| {
+            let table = tables.remove(from);
+            tables.insert(to, table);
+        });
+        self.send(ChainCommand::Rack(slot, RackEdit::Move(lane, from, to, layouts)))?;
+        self.edit_rack_mirror(slot, |rack| {
+            let moved = rack.lanes[lane].remove(from);
+            rack.lanes[lane].insert(to, moved);
+            set_mirror_layouts(&mut rack.lanes[lane], &layouts);
+        });
+        Ok(())
+    }
+
+    /// 清空并联机架的支路（支路本身保留，成为干信号支路）
+    pub fn clear_lane(&mut self, slot: usize, lane: usize) -> Result<()> {
+        // 从末尾移除，前面的位置不动
+        while let Some(last) = self.lane_mirror(slot, lane)?.len().checked_sub(1) {
+            self.remove_lane_plugin(slot, lane, last)?;
+        }
+        Ok(())
+    }
+
+    /// 设置并联机架支路中某个位置的旁通、静音、独奏和干湿比
+    pub fn set_lane_slot_settings(&mut self, slot: usize, lane: usize, index: usize, settings: SlotSettings) -> Result<()> {
+        if index >= self.lane_mirror(slot, lane)?.len() {
+            return Err(anyhow::anyhow!("索引超出范围"));
+        }
+        self.send(ChainCommand::Rack(slot, RackEdit::SetSlot(lane, index, settings)))?;
+        self.edit_rack_mirror(slot, |rack| {
+            rack.lanes[lane][index].settings = SlotSettings { mix: settings.mix.clamp(0.0, 1.0), ..settings };
+        });
+        Ok(())
+    }
+
+    /// 设置支路增益（dB，-60 dB 为静音）；支路增益和声像是机架的参数，与 set_parameter 一样在下一个音频块生效
+    pub fn set_lane_gain_db(&mut self, slot: usize, lane: usize, db: f32) -> bool {
+        self.set_rack_parameter(slot, lane as u32 * 2, db as f64)
+    }
+
+    /// 设置支路声像（-1.0 = 左，1.0 = 右）
+    pub fn set_lane_pan(&mut self, slot: usize, lane: usize, pan: f32) -> bool {
+        self.set_rack_parameter(slot, lane as u32 * 2 + 1, pan as f64)
+    }
+
+    fn set_rack_parameter(&mut self, slot: usize, id: u32, plain: f64) -> bool {
+        let Ok(rack) = self.rack_slot(slot) else {
+            return false;
+        };
+        let Some(descriptor) = rack.parameters.iter().find(|descriptor| descriptor.id == id) else {
+            return false;
+        };
+        let value = descriptor.to_normalized(plain);
+        self.set_parameter(slot, id, value)
+    }
+
+    /// 镜像中机架参数的实际值
+    fn rack_parameter(&self, slot: usize, id: u32) -> Option<f32> {
+        let rack = self.rack_slot(slot).ok()?;
+        let position = rack.parameters.iter().position(|descriptor| descriptor.id == id)?;
+        Some(rack.parameters[position].to_plain(rack.values[position]) as f32)
+    }
+
+    /// 支路增益（dB）
+    pub fn lane_gain_db(&self, slot: usize, lane: usize) -> Option<f32> {
+        self.rack_parameter(slot, lane as u32 * 2)
+    }
+
+    pub fn lane_pan(&self, slot: usize, lane: usize) -> Option<f32> {
+        self.rack_parameter(slot, lane as u32 * 2 + 1)
+    }
+
+    /// 并联机架的支路数（不是机架时为 0）
+    pub fn lane_count(&self, slot: usize) -> usize {
+        self.rack_mirror(slot).map(|rack| rack.lanes.len()).unwrap_or(0)
+    }
+
+    /// 支路中的插件名称
+    pub fn lane_plugin_names(&self, slot: usize, lane: usize) -> Vec<String> {
+        self.lane_mirror(slot, lane)
+            .map(|slots| slots.iter().map(|mirror| mirror.name.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    /// 支路中某个位置的旁通、静音、独奏和干湿比
+    pub fn lane_slot_settings(&self, slot: usize, lane: usize, index: usize) -> Option<SlotSettings> {
+        self.lane_mirror(slot, lane).ok()?.get(index).map(|mirror| mirror.settings)
+    }
+
+    /// 支路中各位置处理完成时相对支路输入的累计延迟（帧）
+    pub fn lane_path_latencies(&self, slot: usize, lane: usize) -> Vec<u32> {
+        let Ok(slots) = self.lane_mirror(slot, lane) else {
+            return Vec::new();
+        };
+        slots
+            .iter()
+            .scan(0, |total, mirror| {
+                *total += mirror.latency;
+                Some(*total)
+            })
+            .collect()
     }
-    
-    /// 设置bypass状态
+
+    fn rack_slot(&self, slot: usize) -> Result<&SlotMirror> {
+        self.slots
+            .get(slot)
+            .filter(|mirror| mirror.rack.is_some())
+            .ok_or_else(|| anyhow::anyhow!("位置 {} 不是并联机架", slot))
+    }
+
+    fn rack_mirror(&self, slot: usize) -> Result<&RackMirror> {
+        self.slots
+            .get(slot)
+            .and_then(|mirror| mirror.rack.as_ref())
+            .ok_or_else(|| anyhow::anyhow!("位置 {} 不是并联机架", slot))
+    }
+
+    fn lane_mirror(&self, slot: usize, lane: usize) -> Result<&[SlotMirror]> {
+        self.rack_mirror(slot)?
+            .lanes
+            .get(lane)
+            .map(Vec::as_slice)
+            .ok_or_else(|| anyhow::anyhow!("支路 {} 不存在", lane + 1))
+    }
+
+    /// 命令发出后按同样的编辑更新机架的镜像，再同步机架的延迟和参数
+    fn edit_rack_mirror(&mut self, slot: usize, edit: impl FnOnce(&mut RackMirror)) {
+        let mirror = &mut self.slots[slot];
+        if let Some(rack) = mirror.rack.as_mut() {
+            edit(rack);
+        }
+        mirror.sync_rack();
+        self.flush();
+    }
+
+    /// 设置插件链的输出增益（dB）
+    pub fn set_gain_db(&mut self, db: f32) {
+        self.gain_db = db;
+        if let Err(e) = self.send(ChainCommand::SetGainDb(db)) {
+            warn!("{}", e);
+        }
+        self.flush();
+    }
+
+    /// 设置干湿比（0.0 = 全干，1.0 = 全湿）
+    pub fn set_mix(&mut self, mix: f32) {
+        self.mix = mix;
+        if let Err(e) = self.send(ChainCommand::SetMix(mix)) {
+            warn!("{}", e);
+        }
+        self.flush();
+    }
+
+    /// 设置bypass状态（插件链在处理信号和原始信号之间交叉淡化）
     pub fn set_bypass(&mut self, bypass: bool) {
         self.bypass = bypass;
+        if let Err(e) = self.send(ChainCommand::SetBypass(bypass)) {
+            warn!("{}", e);
+        }
+        self.flush();
     }
-    
-    /// 处理音频缓冲区
-    pub fn process_audio(&self, buffer: &mut [f32]) {
-        if self.bypass {
-            // bypass 模式 - 直通
-            return;
+
+    /// 交叉淡化切换到新的插件链（通常由 build_chain 准备）
+    /// 配置与当前不同时在当前线程重新 prepare；链的增益、干湿比和旁通沿用当前设置，
+    /// 旧链淡出后交回控制线程释放
+    pub fn swap_chain(&mut self, mut chain: PluginChain, options: SwapOptions) -> Result<()> {
+        let config = self.config;
+        if chain.config() != config {
+            chain.prepare(config.sample_rate, config.max_block_size, config.layout);
+            chain.reserve_latency(reserved_latency(config));
         }
-        
-        // 尝试获取插件链的锁
-        if let Ok(mut chain) = self.plugin_chain.try_lock() {
-            if !chain.is_empty() {
-                // 应用插件链处理
-                chain.process(buffer);
+        chain.set_gain_db(self.gain_db);
+        chain.set_mix(self.mix);
+        chain.set_bypass(self.bypass);
+        chain.reset();
+
+        let slots = chain_mirrors(&chain);
+        self.send(ChainCommand::Swap(Box::new(chain), options))?;
+        self.slots = slots;
+        self.current_preset = None;
+        self.pending_preset = None;
+        self.flush();
+        Ok(())
+    }
+
+    /// 设置预设列表（通常来自工程文件）
+    pub fn set_presets(&mut self, presets: Vec<Preset>) {
+        self.presets = presets;
+        self.current_preset = None;
+    }
+
+    pub fn presets(&self) -> &[Preset] {
+        &self.presets
+    }
+
+    /// 当前预设的程序号（切换到不是预设的插件链后为 None）
+    pub fn current_preset(&self) -> Option<usize> {
+        self.current_preset
+    }
+
+    /// 设置加载预设时创建插件的方式
+    pub fn set_plugin_factory(&mut self, factory: PluginFactory) {
+        self.factory = Some(Arc::new(Mutex::new(factory)));
+    }
+
+    /// 切换预设时的交叉淡化方式
+    pub fn set_swap_options(&mut self, options: SwapOptions) {
+        self.swap_options = options;
+    }
+
+    pub fn swap_options(&self) -> SwapOptions {
+        self.swap_options
+    }
+
+    /// 在当前线程准备预设的插件链，再交叉淡化切换过去
+    pub fn select_preset(&mut self, index: usize) -> Result<()> {
+        let preset = self.presets.get(index).cloned().ok_or_else(|| anyhow::anyhow!("预设不存在: {}", index))?;
+        let chain = self.build_with_factory(&preset.plugin_chain, &preset.slot_settings)?;
+        self.swap_chain(chain, self.swap_options)?;
+        self.current_preset = Some(index);
+        info!("切换到预设 {}: {}", index, preset.name);
+        Ok(())
+    }
+
+    /// 在工作线程准备预设的插件链，完成后由 poll_preset 切换过去
+    /// 还没完成的上一个请求被放弃；其间切换插件链（swap_chain 等）也会放弃请求
+    pub fn request_preset(&mut self, index: usize) -> Result<()> {
+        let preset = self.presets.get(index).cloned().ok_or_else(|| anyhow::anyhow!("预设不存在: {}", index))?;
+        let factory = self.factory.clone().ok_or_else(|| anyhow::anyhow!("未设置插件工厂，无法加载插件链"))?;
+        let config = self.config;
+        let name = preset.name.clone();
+        let (sender, receiver) = mpsc::channel();
+        thread::Builder::new()
+            .name("preset-loader".to_string())
+            .spawn(move || {
+                let mut factory = factory.lock().unwrap_or_else(PoisonError::into_inner);
+                let chain = build_chain(config, &preset.plugin_chain, &preset.slot_settings, &mut *factory);
+                // 请求已被放弃时新链在这里释放
+                let _ = sender.send(chain);
+            })
+            .context("启动预设加载线程失败")?;
+        self.pending_preset = Some(PendingPreset { index, name, receiver });
+        debug!("开始准备预设 {}", index);
+        Ok(())
+    }
+
+    /// 切换到工作线程已经准备好的预设，返回是否切换了预设（运行期间应定期调用）
+    pub fn poll_preset(&mut self) -> Result<bool> {
+        let Some(pending) = self.pending_preset.take() else {
+            return Ok(false);
+        };
+        let chain = match pending.receiver.try_recv() {
+            Ok(chain) => chain,
+            Err(TryRecvError::Empty) => {
+                self.pending_preset = Some(pending);
+                return Ok(false);
+            }
+            Err(TryRecvError::Disconnected) => return Err(anyhow::anyhow!("预设加载线程意外退出")),
+        };
+        self.swap_chain(chain?, self.swap_options)?;
+        self.current_preset = Some(pending.index);
+        info!("切换到预设 {}: {}", pending.index, pending.name);
+        Ok(true)
+    }
+
+    /// 打开工程：切换到工程的插件链并载入工程的预设（插件由插件工厂创建）
+    pub fn load_project(&mut self, project: &Project) -> Result<()> {
+        let chain = self.build_with_factory(project.get_plugin_chain(), project.get_slot_settings())?;
+        self.swap_chain(chain, self.swap_options)?;
+        self.set_presets(project.presets.clone());
+        info!("载入工程 {}: {} 个插件，{} 个预设", project.name, self.slots.len(), self.presets.len());
+        Ok(())
+    }
+
+    /// 把插件链状态和预设写入工程，运行中按 save_chain_state 的方式由镜像给出状态
+    pub fn save_project(&mut self, project: &mut Project) -> Result<()> {
+        let (plugins, slot_settings) = self.save_chain_state()?;
+        project.update_plugin_chain(plugins);
+        project.update_slot_settings(slot_settings);
+        project.presets = self.presets.clone();
+        Ok(())
+    }
+
+    /// 用插件工厂按配置准备一条插件链
+    fn build_with_factory(&mut self, plugins: &[PluginState], slot_settings: &[SlotSettings]) -> Result<PluginChain> {
+        let factory = self.factory.as_ref().ok_or_else(|| anyhow::anyhow!("未设置插件工厂，无法加载插件链"))?;
+        let mut factory = factory.lock().unwrap_or_else(PoisonError::into_inner);
+        build_chain(self.config, plugins, slot_settings, &mut *factory)
+    }
+
+    /// 处理 MIDI 短消息：程序切换在工作线程准备对应的预设（见 request_preset），返回是否请求了预设
+    /// 其它消息在音频流运行时转发给链中声明了 MIDI 输入的插件（插件链在控制线程时没有处理，直接丢弃）
+    pub fn handle_midi(&mut self, data: [u8; 3]) -> Result<bool> {
+        if let Some(program) = program_change(data) {
+            self.request_preset(program as usize)?;
+            return Ok(true);
+        }
+        if self.is_running() {
+            for slot in 0..self.slots.len() {
+                if self.slots[slot].midi {
+                    self.send_event(slot, ProcessEvent::midi(0, data));
+                }
             }
-        } else {
-            // 无法获取锁，直通（避免阻塞音频线程）
-            debug!("无法获取插件链锁，跳过处理");
+        }
+        Ok(false)
+    }
+
+    /// 从控制线程发送事件，在下一个音频块中按偏移生效
+    /// 队列已满时丢弃事件并返回 false
+    pub fn send_event(&mut self, slot: usize, event: ProcessEvent) -> bool {
+        if self.events.push(ChainEvent { slot, event }).is_err() {
+            warn!("事件队列已满，丢弃发给位置 {} 的事件", slot);
+            return false;
+        }
+        true
+    }
+
+    /// 设置参数（归一化值），在下一个音频块开头生效；
+    /// 插件链在控制线程时直接设置，不占用事件队列
+    pub fn set_parameter(&mut self, slot: usize, id: u32, value: f64) -> bool {
+        let applied = match self.processor.as_mut() {
+            Some(processor) => processor.chain.set_parameter(slot, id, value),
+            None => self.send_event(slot, ProcessEvent::parameter(0, id, value)),
+        };
+        if let Some(mirror) = self.slots.get_mut(slot).filter(|_| applied) {
+            if let Some(position) = mirror.parameters.iter().position(|descriptor| descriptor.id == id) {
+                mirror.values[position] = value;
+            }
+        }
+        applied
+    }
+
+    /// 指定位置的参数描述（位置无效时为空）
+    pub fn parameters(&self, slot: usize) -> &[ParameterDescriptor] {
+        self.slots.get(slot).map(|mirror| mirror.parameters.as_slice()).unwrap_or_default()
+    }
+
+    /// 参数的当前值（归一化，控制线程最后设置的值）
+    pub fn parameter_value(&self, slot: usize, id: u32) -> Option<f64> {
+        let mirror = self.slots.get(slot)?;
+        let position = mirror.parameters.iter().position(|descriptor| descriptor.id == id)?;
+        Some(mirror.values[position])
+    }
+
+    /// 参数值的显示文本：插件链在控制线程时由插件格式化，运行中按参数描述计算
+    pub fn parameter_text(&self, slot: usize, id: u32, value: f64) -> Option<String> {
+        if let Some(plugin) = self.processor.as_ref().and_then(|processor| processor.chain.plugin(slot)) {
+            return plugin.value_to_text(id, value);
+        }
+        let mirror = self.slots.get(slot)?;
+        mirror.parameters.iter().find(|descriptor| descriptor.id == id).map(|descriptor| descriptor.value_to_text(value))
+    }
+
+    /// 解析参数的输入文本，得到归一化值（与 parameter_text 相同的规则）
+    pub fn parse_parameter(&self, slot: usize, id: u32, text: &str) -> Option<f64> {
+        if let Some(plugin) = self.processor.as_ref().and_then(|processor| processor.chain.plugin(slot)) {
+            return plugin.text_to_value(id, text);
+        }
+        let mirror = self.slots.get(slot)?;
+        mirror.parameters.iter().find(|descriptor| descriptor.id == id)?.text_to_value(text)
+    }
+
+    /// 保存插件链状态
+    ///
+    /// 插件链在控制线程时由插件保存。在音频线程时不打断音频流（保存状态会分配内存，CLAP 也只允许在主线程保存），
+    /// 由各位置的镜像给出：加入插件链时保存的状态加上控制线程最后设置的参数值和位置设置
+    pub fn save_chain_state(&mut self) -> Result<(Vec<PluginState>, Vec<SlotSettings>)> {
+        self.flush();
+        if let Some(processor) = self.processor.as_ref() {
+            return Ok((processor.chain.save_chain_state(), processor.chain.save_slot_settings()));
+        }
+        let states = self.slots.iter().map(SlotMirror::save_state).collect();
+        let settings = self.slots.iter().map(|slot| slot.settings).collect();
+        Ok((states, settings))
+    }
+
+    /// 处理音频线程的回复：释放退回的插件、更新镜像，返回释放的插件数
+    /// 插件链在音频线程时，控制线程应定期调用
+    pub fn collect_retired(&mut self) -> usize {
+        let mut released = 0;
+        while let Some(reply) = self.replies.pop() {
+            match reply {
+                ChainReply::Retired(slot) => {
+                    let plugin = slot.into_plugin();
+                    debug!("释放插件: {}", plugin.get_info().name);
+                    released += 1;
+                }
+                ChainReply::RetiredLane(lane) => {
+                    let chain = lane.release();
+                    debug!("释放并联支路: {} 个插件", chain.len());
+                    released += chain.len();
+                }
+                ChainReply::RetiredChain(mut chain) => {
+                    chain.release();
+                    debug!("释放旧插件链: {} 个插件", chain.len());
+                    released += chain.len();
+                }
+                ChainReply::Suspended => self.suspended = true,
+            }
+        }
+        released
+    }
+
+    /// 按控制线程中的插件链重建镜像（布局、延迟和参数）
+    fn sync_mirrors(&mut self) {
+        if let Some(processor) = self.processor.as_ref() {
+            self.slots = chain_mirrors(&processor.chain);
+        }
+    }
+
+    /// 插件链未交给音频线程时直接执行排队的命令
+    fn flush(&mut self) {
+        if let Some(processor) = self.processor.as_mut() {
+            processor.apply_commands();
+        }
+        self.collect_retired();
+    }
+
+    fn send(&mut self, command: ChainCommand) -> Result<()> {
+        self.commands
+            .push(command)
+            .map_err(|_| anyhow::anyhow!("插件链命令队列已满"))
+    }
+
+
+    /// 插件名称（与插件链顺序一致）
+    pub fn plugin_names(&self) -> Vec<String> {
+        self.slots.iter().map(|slot| slot.name.clone()).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// 指定位置协商后的布局
+    pub fn slot_layout(&self, index: usize) -> Option<SlotLayout> {
+        self.slots.get(index).map(|slot| slot.layout)
+    }
+
+    /// 指定位置插件报告的延迟（帧）
+    pub fn slot_latency(&self, index: usize) -> Option<u32> {
+        self.slots.get(index).map(|slot| slot.latency)
+    }
+
+    /// 指定位置的旁通、静音、独奏和干湿比
+    pub fn slot_settings(&self, index: usize) -> Option<SlotSettings> {
+        self.slots.get(index).map(|slot| slot.settings)
+    }
+
+    /// 指定位置是否是并联机架（用 add_lane、add_lane_plugin 等编辑支路）
+    pub fn is_rack(&self, index: usize) -> bool {
+        self.slots.get(index).is_some_and(|slot| slot.rack.is_some())
+    }
+
+    pub fn gain_db(&self) -> f32 {
+        self.gain_db
+    }
+
+    pub fn mix(&self) -> f32 {
+        self.mix
+    }
+
+    pub fn is_bypassed(&self) -> bool {
+        self.bypass
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// 插件链的总延迟（帧）
+    pub fn latency_samples(&self) -> u32 {
+        self.slots.iter().map(|slot| slot.latency).sum()
+    }
+
+    /// 输出延迟（毫秒）：一个缓冲区加上插件链的延迟
+    pub fn latency_ms(&self) -> f64 {
+        let config = self.config;
+        (config.max_block_size as f64 + self.latency_samples() as f64) / config.sample_rate * 1000.0
+    }
+
+    /// 插件链未交给音频线程时在当前线程处理（测试用）
+    #[cfg(test)]
+    pub fn process_audio(&mut self, buffer: &mut AudioBuffer) {
+        match self.processor.as_mut() {
+            Some(processor) => processor.process(buffer),
+            None => debug!("插件链在音频线程中，跳过处理"),
         }
     }
 }
@@ -53,47 +1373,452 @@ impl Default for AudioProcessorEngine {
     }
 }
 
-impl Clone for AudioProcessorEngine {
-    fn clone(&self) -> Self {
-        Self {
-            plugin_chain: self.plugin_chain.clone(),
-            bypass: self.bypass,
-        }
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
-    
+    use crate::audio::with_interleaved;
+    use crate::plugin::{BuiltinKind, BuiltinPlugin};
+
+    /// 等待工作线程准备好预设并切换过去
+    fn wait_for_preset(engine: &mut AudioProcessorEngine) -> bool {
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(2);
+        while std::time::Instant::now() < deadline {
+            if engine.poll_preset().unwrap() {
+                return true;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        false
+    }
+
+    fn gain_plugin(db: f64) -> Box<dyn AudioProcessor> {
+        let mut plugin = BuiltinPlugin::new(BuiltinKind::Gain);
+        plugin.set_parameter(0, (db + 24.0) / 48.0);
+        plugin.reset();
+        Box::new(plugin)
+    }
+
     #[test]
     fn test_processor_creation() {
         let processor = AudioProcessorEngine::new();
         assert!(!processor.bypass);
+        assert!(!processor.is_running());
     }
-    
+
     #[test]
     fn test_bypass() {
         let mut processor = AudioProcessorEngine::new();
         processor.set_bypass(true);
         assert!(processor.bypass);
-        
+
         // bypass 模式下处理音频应该不修改数据
         let mut buffer = vec![0.5, 0.3, 0.7, 0.2];
         let original = buffer.clone();
-        processor.process_audio(&mut buffer);
+        with_interleaved(&mut buffer, 2, |buffer| processor.process_audio(buffer));
         assert_eq!(buffer, original);
     }
-    
+
+    #[test]
+    fn test_prepare_updates_chain() {
+        let mut processor = AudioProcessorEngine::new();
+        processor.prepare(44100.0, 512, ChannelLayout::Stereo).unwrap();
+
+        let audio = processor.take_processor().unwrap();
+        let config = audio.chain().config();
+        assert_eq!(config.sample_rate, 44100.0);
+        assert_eq!(config.max_block_size, 512);
+    }
+
+    #[test]
+    fn test_latency_follows_buffer_size() {
+        let mut processor = AudioProcessorEngine::new();
+        processor.prepare(48000.0, 240, ChannelLayout::Stereo).unwrap();
+        assert_eq!(processor.latency_samples(), 0);
+        assert!((processor.latency_ms() - 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_parameter_events_reach_plugin() {
+        let mut processor = AudioProcessorEngine::new();
+        processor.prepare(48000.0, 64, ChannelLayout::Stereo).unwrap();
+        processor.add_plugin(Box::new(BuiltinPlugin::new(BuiltinKind::Gain))).unwrap();
+
+        // 增益参数在第 32 帧从 0 dB 变为 -24 dB
+        assert!(processor.send_event(0, ProcessEvent::parameter(32, 0, 0.0)));
+        let mut buffer = vec![1.0; 64 * 2];
+        with_interleaved(&mut buffer, 2, |buffer| processor.process_audio(buffer));
+
+        // 从第 32 帧开始平滑地衰减
+        assert_eq!(buffer[31 * 2], 1.0);
+        assert!(buffer[32 * 2] < 1.0);
+        assert!(buffer[63 * 2] < buffer[32 * 2]);
+        let (states, _) = processor.save_chain_state().unwrap();
+        assert_eq!(states[0].parameters[0].value, 0.0);
+    }
+
     #[test]
     fn test_empty_chain() {
-        let processor = AudioProcessorEngine::new();
+        let mut processor = AudioProcessorEngine::new();
         let mut buffer = vec![0.5, 0.3, 0.7, 0.2];
         let original = buffer.clone();
-        
+
         // 空插件链应该不修改数据
-        processor.process_audio(&mut buffer);
+        with_interleaved(&mut buffer, 2, |buffer| processor.process_audio(buffer));
         assert_eq!(buffer, original);
     }
-}
 
+    #[test]
+    fn test_edits_reach_audio_thread() {
+        let mut engine = AudioProcessorEngine::new();
+        engine.prepare(48000.0, 64, ChannelLayout::Mono).unwrap();
+        let mut audio = engine.take_processor().unwrap();
+        assert!(engine.is_running());
+
+        // 控制线程按镜像中的布局 prepare，音频线程在下一块开头插入
+        engine.add_plugin(gain_plugin(-6.0)).unwrap();
+        engine.insert_plugin(0, gain_plugin(-6.0)).unwrap();
+        assert!(audio.chain().is_empty());
+        let mut mono = vec![1.0; 64];
+        audio.process(&mut AudioBuffer::from_mono(&mut mono));
+        assert_eq!(audio.chain().len(), 2);
+        assert!((mono[63] - 10f32.powf(-12.0 / 20.0)).abs() < 1e-4);
+
+        // 移除的插件退回控制线程释放
+        engine.remove_plugin(1).unwrap();
+        engine.replace_plugin(0, Box::new(BuiltinPlugin::new(BuiltinKind::Reverb))).unwrap();
+        assert_eq!(engine.collect_retired(), 0);
+        audio.process(&mut AudioBuffer::from_mono(&mut mono));
+        assert_eq!(engine.collect_retired(), 2);
+        assert_eq!(audio.chain().get_plugin_names(), engine.plugin_names());
+        assert_eq!(engine.plugin_names(), vec!["Reverb"]);
+
+        assert!(engine.remove_plugin(3).is_err());
+        engine.clear().unwrap();
+        engine.return_processor(audio);
+        assert_eq!(engine.collect_retired(), 0);
+        assert!(!engine.is_running());
+        assert!(engine.processor.as_ref().unwrap().chain().is_empty());
+    }
+
+    #[test]
+    fn test_realtime_edits_renegotiate_layouts() {
+        let mut engine = AudioProcessorEngine::new();
+        engine.prepare(48000.0, 64, ChannelLayout::Mono).unwrap();
+        engine.add_plugin(gain_plugin(0.0)).unwrap();
+        let mut audio = engine.take_processor().unwrap();
+        let mut mono = vec![0.5; 64];
+
+        // 运行中在增益前插入混响：下游的增益改为接收立体声
+        engine.insert_plugin(0, Box::new(BuiltinPlugin::new(BuiltinKind::Reverb))).unwrap();
+        audio.process(&mut AudioBuffer::from_mono(&mut mono));
+        let layouts: Vec<SlotLayout> = engine.slots.iter().map(|slot| slot.layout).collect();
+        assert_eq!(audio.chain().slot_layouts(), layouts.as_slice());
+        assert_eq!(layouts[1].upstream, ChannelLayout::Stereo);
+
+        // 移到增益之后：增益重新接收单声道
+        engine.move_plugin(0, 1).unwrap();
+        audio.process(&mut AudioBuffer::from_mono(&mut mono));
+        let layouts: Vec<SlotLayout> = engine.slots.iter().map(|slot| slot.layout).collect();
+        assert_eq!(audio.chain().slot_layouts(), layouts.as_slice());
+        assert_eq!(layouts[0].upstream, ChannelLayout::Mono);
+
+        // 替换和移除之后镜像与音频线程仍然一致
+        engine.replace_plugin(1, gain_plugin(0.0)).unwrap();
+        engine.insert_plugin(0, Box::new(BuiltinPlugin::new(BuiltinKind::Delay))).unwrap();
+        engine.remove_plugin(1).unwrap();
+        audio.process(&mut AudioBuffer::from_mono(&mut mono));
+        let layouts: Vec<SlotLayout> = engine.slots.iter().map(|slot| slot.layout).collect();
+        assert_eq!(audio.chain().slot_layouts(), layouts.as_slice());
+        assert_eq!(layouts[1].upstream, ChannelLayout::Stereo);
+
+        engine.return_processor(audio);
+        engine.collect_retired();
+    }
+
+    #[test]
+    fn test_prepare_only_on_control_thread() {
+        let mut engine = AudioProcessorEngine::new();
+        engine.prepare(48000.0, 64, ChannelLayout::Stereo).unwrap();
+        engine.add_plugin(gain_plugin(-12.0)).unwrap();
+        let audio = engine.take_processor().unwrap();
+
+        // 插件链在音频线程时拒绝 prepare，配置保持不变
+        engine.set_slot_settings(0, SlotSettings { mix: 0.5, ..SlotSettings::default() }).unwrap();
+        assert!(engine.prepare(96000.0, 128, ChannelLayout::Stereo).is_err());
+        assert_eq!(engine.config().sample_rate, 48000.0);
+
+        // 交回后在控制线程执行排队的编辑、prepare 和保存
+        engine.return_processor(audio);
+        engine.prepare(96000.0, 128, ChannelLayout::Stereo).unwrap();
+        let (states, slot_settings) = engine.save_chain_state().unwrap();
+        assert_eq!(states[0].plugin_id, "builtin:gain");
+        assert_eq!(slot_settings[0].mix, 0.5);
+        let audio = engine.take_processor().unwrap();
+        assert_eq!(audio.chain().config().sample_rate, 96000.0);
+    }
+
+    #[test]
+    fn test_save_while_running() {
+        use crate::plugin::{PluginLoader, RACK_ID};
+
+        let mut loader = PluginLoader::new();
+        let mut engine = AudioProcessorEngine::new();
+        engine.prepare(48000.0, 64, ChannelLayout::Stereo).unwrap();
+        engine.add_plugin(gain_plugin(-12.0)).unwrap();
+        engine.add_plugin(loader.load_by_id(RACK_ID, &[]).unwrap()).unwrap();
+        let mut audio = engine.take_processor().unwrap();
+
+        // 运行中的编辑只经过命令和事件队列，保存时音频线程继续处理
+        let lane = engine.add_lane(1).unwrap();
+        engine.add_lane_plugin(1, lane, gain_plugin(0.0)).unwrap();
+        assert!(engine.set_lane_gain_db(1, lane, -3.0));
+        assert!(engine.set_parameter(0, 0, 18.0 / 48.0));
+        engine.set_slot_settings(0, SlotSettings { mix: 0.5, ..SlotSettings::default() }).unwrap();
+        let (running, running_settings) = engine.save_chain_state().unwrap();
+        assert!(engine.is_running());
+
+        // 与停止后由插件保存的状态一致
+        let mut buffer = vec![0.0; 64 * 2];
+        with_interleaved(&mut buffer, 2, |buffer| audio.process(buffer));
+        engine.return_processor(audio);
+        let (stopped, stopped_settings) = engine.save_chain_state().unwrap();
+        assert_eq!(running_settings, stopped_settings);
+        for (running, stopped) in running.iter().zip(&stopped) {
+            assert_eq!(running.plugin_id, stopped.plugin_id);
+            assert_eq!(running.parameters.len(), stopped.parameters.len());
+            for (running, stopped) in running.parameters.iter().zip(&stopped.parameters) {
+                assert!((running.value - stopped.value).abs() < 1e-6);
+            }
+        }
+
+        // 镜像给出的状态可以重建插件链：参数值优先于状态数据
+        let mut chain = build_chain(engine.config(), &running, &running_settings, &mut |state: &PluginState| {
+            loader.load_by_id(&state.plugin_id, &[])
+        })
+        .unwrap();
+        assert_eq!(chain.plugin(0).unwrap().get_parameter(0), Some(18.0 / 48.0));
+        let rack = chain.rack_mut(1).unwrap();
+        assert_eq!(rack.lane_count(), 1);
+        assert!((rack.lane_gain_db(0).unwrap() + 3.0).abs() < 1e-4);
+        assert_eq!(rack.lane(0).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_suspend_before_returning() {
+        let mut engine = AudioProcessorEngine::new();
+        engine.prepare(48000.0, 64, ChannelLayout::Mono).unwrap();
+        engine.add_plugin(gain_plugin(-6.0)).unwrap();
+        let mut audio = engine.take_processor().unwrap();
+
+        // 音频线程在下一块执行 suspend 并回复，之后输出静音
+        engine.suspend().unwrap();
+        assert!(!engine.is_suspended());
+        let mut mono = vec![1.0; 64];
+        audio.process(&mut AudioBuffer::from_mono(&mut mono));
+        assert!(engine.is_suspended());
+        assert!(mono.iter().all(|&sample| sample == 0.0));
+
+        // 交回并重新 prepare 后恢复处理
+        engine.return_processor(audio);
+        engine.prepare(48000.0, 64, ChannelLayout::Mono).unwrap();
+        let mut audio = engine.take_processor().unwrap();
+        let mut mono = vec![1.0; 64];
+        audio.process(&mut AudioBuffer::from_mono(&mut mono));
+        assert!((mono[63] - 10f32.powf(-6.0 / 20.0)).abs() < 1e-4);
+        engine.return_processor(audio);
+    }
+
+    #[test]
+    fn test_swap_chain_crossfades() {
+        let mut engine = AudioProcessorEngine::new();
+        engine.prepare(48000.0, 64, ChannelLayout::Mono).unwrap();
+        engine.add_plugin(gain_plugin(-6.0)).unwrap();
+        let mut audio = engine.take_processor().unwrap();
+
+        // 48 帧的交叉淡化，从 -6 dB 的链切换到空链
+        let options = SwapOptions { fade_ms: 1.0, keep_tail: false };
+        engine.swap_chain(PluginChain::new(), options).unwrap();
+        assert!(engine.is_empty());
+        let mut mono = vec![1.0; 64];
+        audio.process(&mut AudioBuffer::from_mono(&mut mono));
+
+        let old = 10f32.powf(-6.0 / 20.0);
+        assert!((mono[0] - old).abs() < 1e-3);
+        // 中点两条链都是 -3 dB
+        let half = std::f32::consts::FRAC_1_SQRT_2;
+        assert!((mono[24] - (half + old * half)).abs() < 1e-3);
+        assert_eq!(mono[63], 1.0);
+
+        // 淡化结束后旧链退回控制线程
+        assert!(!audio.is_crossfading());
+        assert_eq!(engine.collect_retired(), 1);
+        assert!(audio.chain().is_empty());
+        engine.return_processor(audio);
+    }
+
+    #[test]
+    fn test_swap_keeps_tail() {
+        let tail_after_swap = |keep_tail: bool| {
+            let mut engine = AudioProcessorEngine::new();
+            engine.prepare(48000.0, 64, ChannelLayout::Stereo).unwrap();
+            engine.add_plugin(Box::new(BuiltinPlugin::new(BuiltinKind::Reverb))).unwrap();
+            let mut audio = engine.take_processor().unwrap();
+            for _ in 0..100 {
+                let mut buffer = vec![0.5; 64 * 2];
+                with_interleaved(&mut buffer, 2, |buffer| audio.process(buffer));
+            }
+
+            engine.swap_chain(PluginChain::new(), SwapOptions { fade_ms: 1.0, keep_tail }).unwrap();
+            let mut buffer = vec![0.0; 64 * 2];
+            with_interleaved(&mut buffer, 2, |buffer| audio.process(buffer));
+            let mut buffer = vec![0.0; 64 * 2];
+            with_interleaved(&mut buffer, 2, |buffer| audio.process(buffer));
+            let peak = buffer.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+            (peak, audio.is_crossfading())
+        };
+
+        // 保留尾音时输入已经是静音，混响仍在输出
+        let (peak, crossfading) = tail_after_swap(true);
+        assert!(peak > TAIL_SILENCE);
+        assert!(crossfading);
+
+        let (peak, crossfading) = tail_after_swap(false);
+        assert_eq!(peak, 0.0);
+        assert!(!crossfading);
+    }
+
+    #[test]
+    fn test_project_presets_reach_engine() {
+        let mut project = Project::new("Presets".to_string());
+        project.update_plugin_chain(vec![gain_plugin(-6.0).save_state()]);
+        project.add_preset("Loud".to_string());
+        project.presets.push(Preset::new("Clean".to_string(), Vec::new(), Vec::new()));
+
+        let mut engine = AudioProcessorEngine::new();
+        engine.prepare(48000.0, 64, ChannelLayout::Stereo).unwrap();
+        assert!(engine.load_project(&project).is_err());
+        engine.set_plugin_factory(Box::new(|state: &PluginState| {
+            let kind = BuiltinKind::from_id(&state.plugin_id).ok_or_else(|| anyhow::anyhow!("未知插件"))?;
+            Ok(Box::new(BuiltinPlugin::new(kind)) as Box<dyn AudioProcessor>)
+        }));
+        engine.load_project(&project).unwrap();
+        assert_eq!(engine.plugin_names(), vec!["Gain"]);
+        assert_eq!(engine.presets().len(), 2);
+
+        // 工程中的预设可以用程序切换选择，保存时写回工程
+        assert!(engine.handle_midi([0xC0, 1, 0]).unwrap());
+        assert!(wait_for_preset(&mut engine));
+        assert!(engine.is_empty());
+        let mut saved = Project::new("Saved".to_string());
+        engine.save_project(&mut saved).unwrap();
+        assert!(saved.plugin_chain.is_empty());
+        assert_eq!(saved.presets[0].plugin_chain[0].plugin_id, "builtin:gain");
+    }
+
+    #[test]
+    fn test_rack_through_loader_and_engine() {
+        use crate::plugin::{DummyPlugin, PluginLoader, RACK_ID};
+
+        let mut loader = PluginLoader::new();
+        let mut engine = AudioProcessorEngine::new();
+        engine.prepare(48000.0, 64, ChannelLayout::Stereo).unwrap();
+        engine.add_plugin(loader.load_by_id(RACK_ID, &[]).unwrap()).unwrap();
+        engine.add_plugin(gain_plugin(0.0)).unwrap();
+        assert!(engine.add_lane(1).is_err());
+
+        // 运行中编辑支路：干信号支路 + 带 50 帧延迟和 -6 dB 的支路，下一块开头生效，链的镜像随之更新
+        let mut audio = engine.take_processor().unwrap();
+        engine.add_lane(0).unwrap();
+        let wet = engine.add_lane(0).unwrap();
+        engine.add_lane_plugin(0, wet, Box::new(DummyPlugin::with_latency(BuiltinKind::Gain.metadata(), 50))).unwrap();
+        engine.add_lane_plugin(0, wet, loader.load_by_id("builtin:gain", &[]).unwrap()).unwrap();
+        assert_eq!(engine.latency_samples(), 50);
+        assert_eq!(engine.lane_path_latencies(0, wet), vec![50, 50]);
+        assert_eq!(engine.plugin_names(), vec!["Parallel", "Gain"]);
+
+        // 支路增益作为机架参数调节（参数 2 = 支路 2 的增益）
+        assert!(engine.set_lane_gain_db(0, wet, -6.0));
+        assert_eq!(engine.lane_gain_db(0, wet), Some(-6.0));
+        assert_eq!(engine.parameters(0).len(), 4);
+
+        // 移除的支路连同其中的插件退回控制线程释放
+        let extra = engine.add_lane(0).unwrap();
+        engine.add_lane_plugin(0, extra, gain_plugin(0.0)).unwrap();
+        engine.remove_lane(0, extra).unwrap();
+        assert!(engine.remove_lane(0, extra).is_err());
+        let mut buffer = vec![0.0; 64 * 2];
+        buffer[0] = 1.0;
+        with_interleaved(&mut buffer, 2, |buffer| audio.process(buffer));
+        assert_eq!(engine.collect_retired(), 1);
+        let rack = ParallelRack::downcast(audio.chain().plugin(0).unwrap()).unwrap();
+        assert_eq!(rack.lane_count(), engine.lane_count(0));
+        assert_eq!(audio.chain().latency(), engine.latency_samples());
+        engine.return_processor(audio);
+
+        // 干信号支路被推迟 50 帧，两条支路的脉冲重合；支路增益随状态保存，工厂按 ID 重建机架
+        let peaks: Vec<usize> = (0..64).filter(|&i| buffer[i * 2] != 0.0).collect();
+        assert_eq!(peaks, vec![50]);
+        let (states, slot_settings) = engine.save_chain_state().unwrap();
+        let mut chain = build_chain(engine.config(), &states, &slot_settings, &mut |state: &PluginState| {
+            loader.load_by_id(&state.plugin_id, &[])
+        })
+        .unwrap();
+        assert_eq!(chain.get_plugin_names(), vec!["Parallel", "Gain"]);
+        assert_eq!(chain.rack_mut(0).unwrap().lane_gain_db(1), Some(-6.0));
+    }
+
+    #[test]
+    fn test_program_change_selects_preset() {
+        let mut engine = AudioProcessorEngine::new();
+        engine.prepare(48000.0, 64, ChannelLayout::Stereo).unwrap();
+        let quiet = gain_plugin(-12.0).save_state();
+        engine.set_presets(vec![
+            Preset::new("Clean".to_string(), Vec::new(), Vec::new()),
+            Preset::new("Quiet".to_string(), vec![quiet], vec![SlotSettings::default()]),
+        ]);
+        assert!(engine.select_preset(1).is_err());
+
+        engine.set_plugin_factory(Box::new(|state: &PluginState| {
+            let kind = BuiltinKind::from_id(&state.plugin_id).ok_or_else(|| anyhow::anyhow!("未知插件"))?;
+            Ok(Box::new(BuiltinPlugin::new(kind)) as Box<dyn AudioProcessor>)
+        }));
+        assert!(!engine.handle_midi([0x90, 60, 100]).unwrap());
+        // 预设在工作线程准备，poll_preset 之前插件链不变
+        assert!(engine.handle_midi([0xC3, 1, 0]).unwrap());
+        assert_eq!(engine.current_preset(), None);
+        assert!(wait_for_preset(&mut engine));
+        assert!(!engine.poll_preset().unwrap());
+        assert_eq!(engine.current_preset(), Some(1));
+        assert_eq!(engine.plugin_names(), vec!["Gain"]);
+        assert!(engine.handle_midi([0xC0, 5, 0]).is_err());
+
+        // 预设的参数随插件状态恢复，淡化结束后是 -12 dB
+        let mut buffer = vec![1.0; 4800 * 2];
+        with_interleaved(&mut buffer, 2, |buffer| engine.process_audio(buffer));
+        assert!((buffer[4799 * 2] - 10f32.powf(-12.0 / 20.0)).abs() < 1e-3);
+        assert_eq!(engine.collect_retired(), 0);
+    }
+
+    #[test]
+    fn test_midi_reaches_only_midi_inputs() {
+        use crate::plugin::DummyPlugin;
+
+        let mut engine = AudioProcessorEngine::new();
+        engine.prepare(48000.0, 64, ChannelLayout::Stereo).unwrap();
+        engine.add_plugin(gain_plugin(0.0)).unwrap();
+        engine.add_plugin(Box::new(DummyPlugin::with_midi(BuiltinKind::Gain.metadata()))).unwrap();
+        engine.add_plugin(Box::new(DummyPlugin::new(BuiltinKind::Gain.metadata()))).unwrap();
+
+        // 插件链在控制线程时直接丢弃，运行中只发给声明了 MIDI 输入的位置 1
+        assert!(!engine.handle_midi([0x90, 60, 100]).unwrap());
+        assert_eq!(engine.events.len(), 0);
+        let mut audio = engine.take_processor().unwrap();
+        assert!(!engine.handle_midi([0x90, 60, 100]).unwrap());
+        assert!(!engine.handle_midi([0x80, 60, 0]).unwrap());
+        assert_eq!(engine.events.len(), 2);
+        let mut buffer = vec![0.0; 64 * 2];
+        with_interleaved(&mut buffer, 2, |buffer| audio.process(buffer));
+        assert_eq!(engine.events.len(), 0);
+        engine.return_processor(audio);
+    }
+}
@@ -0,0 +1,208 @@
+// 可变比率重采样器
+// 加窗 sinc 插值（多相表 + 相位间线性插值），用于补偿两个音频设备之间的时钟漂移
+
+use std::f64::consts::PI;
+
+/// 插值核的单侧长度（帧）
+const HALF_TAPS: usize = 16;
+
+/// 插值核长度
+const TAPS: usize = HALF_TAPS * 2;
+
+/// 多相表的相位数
+const PHASES: usize = 512;
+
+/// 截止频率（相对奈奎斯特频率），给比率的小幅变化留出余量，避免混叠
+const CUTOFF: f64 = 0.95;
+
+/// 可变比率重采样器（交错数据，process 不分配内存）
+///
+/// ratio 是每输出一帧消耗的输入帧数：大于 1 时消耗得更快（输入设备的时钟偏快），小于 1 时更慢。
+/// 固定延迟为 HALF_TAPS 帧。
+pub struct Resampler {
+    channels: usize,
+    /// (PHASES + 1) x TAPS 的插值核，每个相位归一化为单位直流增益
+    table: Vec<f32>,
+    /// 交错的输入数据
+    input: Vec<f32>,
+    /// input 中有效的帧数
+    frames: usize,
+    /// 下一个输出帧在 input 中的位置（帧，带小数）
+    position: f64,
+    ratio: f64,
+}
+
+impl Resampler {
+    /// capacity: 一次最多缓存的输入帧数（不含插值核）
+    pub fn new(channels: usize, capacity: usize) -> Self {
+        let channels = channels.max(1);
+        let mut resampler = Self {
+            channels,
+            table: build_table(),
+            input: vec![0.0; (capacity + TAPS) * channels],
+            frames: 0,
+            position: 0.0,
+            ratio: 1.0,
+        };
+        resampler.reset();
+        resampler
+    }
+
+    /// 清空缓存的输入（开头补 HALF_TAPS - 1 帧静音作为插值核的历史）
+    pub fn reset(&mut self) {
+        self.input.fill(0.0);
+        self.frames = HALF_TAPS - 1;
+        self.position = (HALF_TAPS - 1) as f64;
+    }
+
+    pub fn set_ratio(&mut self, ratio: f64) {
+        self.ratio = ratio;
+    }
+
+    /// 已经读入、还没有输出的帧数
+    pub fn buffered(&self) -> usize {
+        (self.frames as f64 - self.position).max(0.0) as usize
+    }
+
+    /// 生成 output.len() / channels 帧；输入不够时调用 source 读取更多交错数据
+    /// （source 返回写入的样本数，必须是整帧），返回实际生成的帧数
+    pub fn process(&mut self, output: &mut [f32], source: &mut dyn FnMut(&mut [f32]) -> usize) -> usize {
+        let channels = self.channels;
+        let total = output.len() / channels;
+        for (index, frame) in output.chunks_exact_mut(channels).enumerate() {
+            if self.position as usize + HALF_TAPS >= self.frames {
+                self.refill(total - index, source);
+                if self.position as usize + HALF_TAPS >= self.frames {
+                    return index;
+                }
+            }
+
+            let base = self.position as usize;
+            let phase = (self.position - base as f64) * PHASES as f64;
+            let row = (phase as usize).min(PHASES - 1);
+            let t = (phase - row as f64) as f32;
+            let (current, next) = self.table[row * TAPS..(row + 2) * TAPS].split_at(TAPS);
+            let start = (base + 1 - HALF_TAPS) * channels;
+            for (channel, sample) in frame.iter_mut().enumerate() {
+                let mut acc = 0.0f32;
+                for (k, (&a, &b)) in current.iter().zip(next).enumerate() {
+                    acc += self.input[start + k * channels + channel] * (a + (b - a) * t);
+                }
+                *sample = acc;
+            }
+            self.position += self.ratio;
+        }
+        total
+    }
+
+    /// 丢掉插值核不再需要的帧，再从 source 读取最多 wanted 帧（外加插值核的余量）
+    fn refill(&mut self, wanted: usize, source: &mut dyn FnMut(&mut [f32]) -> usize) {
+        let channels = self.channels;
+        let consumed = (self.position as usize + 1).saturating_sub(HALF_TAPS).min(self.frames);
+        self.input.copy_within(consumed * channels..self.frames * channels, 0);
+        self.frames -= consumed;
+        self.position -= consumed as f64;
+
+        let free = self.input.len() / channels - self.frames;
+        let count = (wanted + HALF_TAPS).min(free);
+        let start = self.frames * channels;
+        let read = source(&mut self.input[start..start + count * channels]);
+        self.frames += read / channels;
+    }
+}
+
+/// 加窗 sinc 多相表：第 p 行是小数位置 p / PHASES 处的插值核
+fn build_table() -> Vec<f32> {
+    let mut table = vec![0.0f32; (PHASES + 1) * TAPS];
+    for (phase, row) in table.chunks_exact_mut(TAPS).enumerate() {
+        let frac = phase as f64 / PHASES as f64;
+        let mut sum = 0.0;
+        let mut kernel = [0.0f64; TAPS];
+        for (k, value) in kernel.iter_mut().enumerate() {
+            let x = k as f64 - (HALF_TAPS - 1) as f64 - frac;
+            *value = sinc(CUTOFF * x) * blackman(x / HALF_TAPS as f64);
+            sum += *value;
+        }
+        for (dst, value) in row.iter_mut().zip(kernel) {
+            *dst = (value / sum) as f32;
+        }
+    }
+    table
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-12 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// Blackman 窗，x 在 [-1, 1] 之外为 0
+fn blackman(x: f64) -> f64 {
+    if x.abs() >= 1.0 {
+        return 0.0;
+    }
+    0.42 + 0.5 * (PI * x).cos() + 0.08 * (2.0 * PI * x).cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 每次最多读 block 帧的信号源
+    fn feed(signal: &[f32], block: usize) -> impl FnMut(&mut [f32]) -> usize + '_ {
+        let mut offset = 0;
+        move |dst: &mut [f32]| {
+            let count = dst.len().min(block).min(signal.len() - offset);
+            dst[..count].copy_from_slice(&signal[offset..offset + count]);
+            offset += count;
+            count
+        }
+    }
+
+    #[test]
+    fn test_unity_ratio_passes_audio_band() {
+        // 比率为 1 时通带内的信号原样通过（延迟已经对齐）
+        let signal: Vec<f32> = (0..500)
+            .map(|i| {
+                let t = i as f64 / 48000.0;
+                (0.5 * (2.0 * PI * 440.0 * t).sin() + 0.3 * (2.0 * PI * 9000.0 * t).sin()) as f32
+            })
+            .collect();
+        let mut resampler = Resampler::new(1, 64);
+        let mut output = vec![0.0; 400];
+        let produced = resampler.process(&mut output, &mut feed(&signal, 37));
+        assert_eq!(produced, 400);
+        for (out, input) in output.iter().zip(&signal).skip(HALF_TAPS) {
+            assert!((out - input).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_variable_ratio_follows_sine() {
+        // 1 kHz 正弦按 1.002 的比率重采样后频率相应升高
+        let rate = 48000.0;
+        let ratio = 1.002;
+        let signal: Vec<f32> = (0..4000)
+            .flat_map(|i| {
+                let value = (2.0 * PI * 1000.0 * i as f64 / rate).sin() as f32;
+                [value, -value]
+            })
+            .collect();
+        let mut resampler = Resampler::new(2, 256);
+        resampler.set_ratio(ratio);
+        let mut output = vec![0.0; 3000 * 2];
+        let produced = resampler.process(&mut output, &mut feed(&signal, 128));
+        assert_eq!(produced, 3000);
+        for (i, frame) in output.chunks_exact(2).enumerate().skip(HALF_TAPS) {
+            let expected = (2.0 * PI * 1000.0 * i as f64 * ratio / rate).sin() as f32;
+            assert!((frame[0] - expected).abs() < 1e-3, "帧 {}: {} != {}", i, frame[0], expected);
+            assert_eq!(frame[1], -frame[0]);
+        }
+
+        // 输入用完后不再输出
+        let mut rest = vec![0.0; 2000 * 2];
+        assert!(resampler.process(&mut rest, &mut feed(&signal[signal.len()..], 128)) < 2000);
+    }
+}
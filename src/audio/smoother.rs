@@ -0,0 +1,185 @@
+// 参数平滑
+// 音量、干湿比、旁通等参数在实时改变时逐采样过渡到目标值，避免拉链噪声
+
+/// 平滑曲线
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmoothingStyle {
+    /// 匀速过渡，在设定时间内正好到达目标
+    Linear,
+    /// 一阶指数趋近，开始快结尾慢；在设定时间内收敛到 -60 dB 后直接对齐目标
+    Exponential,
+}
+
+/// 逐采样平滑器（不分配内存，可在音频线程中使用）
+#[derive(Debug, Clone, Copy)]
+pub struct Smoother {
+    style: SmoothingStyle,
+    time_ms: f32,
+    sample_rate: f32,
+    /// 一次过渡的采样数
+    ramp_samples: u32,
+    current: f32,
+    target: f32,
+    /// 线性：每采样增量；指数：每采样保留的比例
+    step: f32,
+    remaining: u32,
+}
+
+impl Smoother {
+    pub fn new(style: SmoothingStyle, time_ms: f32, sample_rate: f32) -> Self {
+        let mut smoother = Self {
+            style,
+            time_ms,
+            sample_rate,
+            ramp_samples: 0,
+            current: 0.0,
+            target: 0.0,
+            step: 0.0,
+            remaining: 0,
+        };
+        smoother.update_ramp();
+        smoother
+    }
+
+    pub fn linear(time_ms: f32, sample_rate: f32) -> Self {
+        Self::new(SmoothingStyle::Linear, time_ms, sample_rate)
+    }
+
+    pub fn exponential(time_ms: f32, sample_rate: f32) -> Self {
+        Self::new(SmoothingStyle::Exponential, time_ms, sample_rate)
+    }
+
+    /// 设置初始值（不过渡）
+    pub fn with_value(mut self, value: f32) -> Self {
+        self.reset(value);
+        self
+    }
+
+    fn update_ramp(&mut self) {
+        self.ramp_samples = (self.time_ms.max(0.0) * 0.001 * self.sample_rate).round() as u32;
+    }
+
+    /// 采样率变化时调用，正在进行的过渡直接完成
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.update_ramp();
+        self.reset(self.target);
+    }
+
+    /// 设置目标值，从当前值开始过渡
+    pub fn set_target(&mut self, target: f32) {
+        if target == self.target && self.remaining == 0 {
+            return;
+        }
+        self.target = target;
+        if self.ramp_samples == 0 {
+            self.reset(target);
+            return;
+        }
+
+        self.remaining = self.ramp_samples;
+        self.step = match self.style {
+            SmoothingStyle::Linear => (target - self.current) / self.ramp_samples as f32,
+            // 过渡时间内剩余差值衰减到 1/1000
+            SmoothingStyle::Exponential => (1e-3f32.ln() / self.ramp_samples as f32).exp(),
+        };
+    }
+
+    /// 立即跳到指定值（reset 时使用）
+    pub fn reset(&mut self, value: f32) {
+        self.current = value;
+        self.target = value;
+        self.remaining = 0;
+    }
+
+    /// 直接完成正在进行的过渡
+    pub fn finish(&mut self) {
+        self.reset(self.target);
+    }
+
+    /// 前进一个采样并返回新的值
+    #[inline]
+    pub fn next_value(&mut self) -> f32 {
+        if self.remaining == 0 {
+            return self.current;
+        }
+
+        self.remaining -= 1;
+        if self.remaining == 0 {
+            self.current = self.target;
+        } else {
+            self.current = match self.style {
+                SmoothingStyle::Linear => self.current + self.step,
+                SmoothingStyle::Exponential => self.target + (self.current - self.target) * self.step,
+            };
+        }
+        self.current
+    }
+
+    pub fn current(&self) -> f32 {
+        self.current
+    }
+
+    pub fn target(&self) -> f32 {
+        self.target
+    }
+
+    /// 是否正在过渡
+    pub fn is_smoothing(&self) -> bool {
+        self.remaining > 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn max_step(values: &[f32]) -> f32 {
+        values.windows(2).map(|w| (w[1] - w[0]).abs()).fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn test_linear_reaches_target_on_time() {
+        // 10 ms @ 1 kHz = 10 个采样
+        let mut smoother = Smoother::linear(10.0, 1000.0).with_value(0.0);
+        smoother.set_target(1.0);
+
+        let values: Vec<f32> = (0..12).map(|_| smoother.next_value()).collect();
+        assert!((values[0] - 0.1).abs() < 1e-6);
+        assert_eq!(values[9], 1.0);
+        assert_eq!(values[11], 1.0);
+        assert!(!smoother.is_smoothing());
+        assert!((max_step(&values) - 0.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_exponential_converges() {
+        let mut smoother = Smoother::exponential(10.0, 48000.0).with_value(1.0);
+        smoother.set_target(0.0);
+
+        let values: Vec<f32> = (0..480).map(|_| smoother.next_value()).collect();
+        // 单调下降，最大步长远小于跳变量
+        assert!(values.windows(2).all(|w| w[1] <= w[0]));
+        assert!(max_step(&values) < 0.02);
+        assert_eq!(values[479], 0.0);
+    }
+
+    #[test]
+    fn test_retarget_mid_ramp_is_continuous() {
+        let mut smoother = Smoother::linear(10.0, 1000.0).with_value(0.0);
+        smoother.set_target(1.0);
+        let mut values: Vec<f32> = (0..5).map(|_| smoother.next_value()).collect();
+        smoother.set_target(0.0);
+        values.extend((0..10).map(|_| smoother.next_value()));
+
+        assert!(max_step(&values) <= 0.1 + 1e-6);
+        assert_eq!(*values.last().unwrap(), 0.0);
+    }
+
+    #[test]
+    fn test_zero_time_jumps() {
+        let mut smoother = Smoother::linear(0.0, 48000.0).with_value(0.0);
+        smoother.set_target(0.5);
+        assert_eq!(smoother.next_value(), 0.5);
+    }
+}
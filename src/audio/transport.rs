@@ -0,0 +1,305 @@
+// 采集和播放回调之间的无锁传输
+// 单生产者单消费者环形缓冲区，整块复制，按目标水位控制延迟；
+// 输入输出是不同设备时用可变比率重采样补偿两者的时钟漂移
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use log::debug;
+use ringbuf::{HeapConsumer, HeapProducer, HeapRb};
+
+use super::resampler::Resampler;
+
+/// 目标水位（块）：播放端开始读取前缓冲区里至少有这么多数据
+const TARGET_BLOCKS: usize = 2;
+
+/// 水位超过目标这么多（块）时丢弃多出的数据，回到目标水位
+const DRIFT_BLOCKS: usize = 2;
+
+/// 环形缓冲区容量（块）
+const CAPACITY_BLOCKS: usize = 8;
+
+/// 环形缓冲区的最小容量（帧），设备按默认缓冲区大小回调时留出余量
+const MIN_CAPACITY_FRAMES: usize = 8192;
+
+/// 漂移补偿时比率偏离 1 的上限（0.5%）
+const MAX_DEVIATION: f64 = 0.005;
+
+/// 水位误差的平滑系数（每次回调）
+const FILL_SMOOTHING: f64 = 0.01;
+
+/// 比率控制器的比例增益（每帧水位误差）和积分增益（每帧水位误差、每播放一帧）
+const DRIFT_KP: f64 = 4e-5;
+const DRIFT_KI: f64 = 4e-10;
+
+/// 传输统计（线程安全，音频线程写入、UI 线程读取）
+#[derive(Clone, Default)]
+pub struct TransportStats {
+    inner: Arc<StatsInner>,
+}
+
+#[derive(Default)]
+struct StatsInner {
+    underruns: AtomicU64,
+    overruns: AtomicU64,
+    recenters: AtomicU64,
+    /// 播放端读取后缓冲区里剩下的帧数
+    fill: AtomicUsize,
+    target: AtomicUsize,
+    /// 两端见过的最大回调长度（帧）
+    max_chunk: AtomicUsize,
+    /// 重采样比率（f64 的位模式）
+    ratio: AtomicU64,
+    /// 平滑后的水位误差（帧，f64 的位模式）
+    drift: AtomicU64,
+}
+
+impl TransportStats {
+    /// 播放端数据不够、补了静音的次数
+    pub fn underruns(&self) -> u64 {
+        self.inner.underruns.load(Ordering::Relaxed)
+    }
+
+    /// 采集端缓冲区已满、丢弃数据的次数
+    pub fn overruns(&self) -> u64 {
+        self.inner.overruns.load(Ordering::Relaxed)
+    }
+
+    /// 水位漂移后重新回到目标水位的次数
+    pub fn recenters(&self) -> u64 {
+        self.inner.recenters.load(Ordering::Relaxed)
+    }
+
+    /// 当前水位（帧）
+    pub fn fill_frames(&self) -> usize {
+        self.inner.fill.load(Ordering::Relaxed)
+    }
+
+    /// 目标水位（帧）
+    pub fn target_frames(&self) -> usize {
+        self.inner.target.load(Ordering::Relaxed)
+    }
+
+    /// 当前重采样比率（每播放一帧消耗的采集帧数，没有漂移补偿时为 1）
+    pub fn ratio(&self) -> f64 {
+        f64::from_bits(self.inner.ratio.load(Ordering::Relaxed))
+    }
+
+    /// 比率偏离 1 的程度（百万分之一），正数表示采集端的时钟偏快
+    pub fn ratio_ppm(&self) -> f64 {
+        (self.ratio() - 1.0) * 1e6
+    }
+
+    /// 平滑后的水位与目标之差（帧）
+    pub fn fill_error(&self) -> f64 {
+        f64::from_bits(self.inner.drift.load(Ordering::Relaxed))
+    }
+
+    fn observe_chunk(&self, frames: usize) {
+        self.inner.max_chunk.fetch_max(frames, Ordering::Relaxed);
+    }
+
+    fn count(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// 按缓冲区大小创建传输：block_frames 是设备的缓冲区大小（帧）
+/// drift_compensation: 输入输出是不同设备（时钟不同步）时开启
+pub fn transport(
+    channels: usize,
+    block_frames: usize,
+    drift_compensation: bool,
+) -> (TransportWriter, TransportReader, TransportStats) {
+    let channels = channels.max(1);
+    let block_frames = block_frames.max(1);
+    let capacity = (block_frames * CAPACITY_BLOCKS).max(MIN_CAPACITY_FRAMES);
+    let (producer, consumer) = HeapRb::new(capacity * channels).split();
+    let stats = TransportStats::default();
+    stats.inner.target.store(block_frames * TARGET_BLOCKS, Ordering::Relaxed);
+    stats.inner.ratio.store(1f64.to_bits(), Ordering::Relaxed);
+    let writer = TransportWriter { producer, channels, stats: stats.clone() };
+    let reader = TransportReader {
+        consumer,
+        channels,
+        block_frames,
+        capacity,
+        priming: true,
+        resampler: drift_compensation.then(|| Resampler::new(channels, capacity)),
+        average: 0.0,
+        integral: 0.0,
+        stats: stats.clone(),
+    };
+    (writer, reader, stats)
+}
+
+/// 采集端（输入回调中使用）
+pub struct TransportWriter {
+    producer: HeapProducer<f32>,
+    channels: usize,
+    stats: TransportStats,
+}
+
+impl TransportWriter {
+    /// 写入一块交错数据；放不下时只写入完整的帧并记一次溢出
+    pub fn write(&mut self, data: &[f32]) {
+        self.stats.observe_chunk(data.len() / self.channels);
+        let free = self.producer.free_len() / self.channels * self.channels;
+        let count = data.len().min(free);
+        self.producer.push_slice(&data[..count]);
+        if count < data.len() {
+            TransportStats::count(&self.stats.inner.overruns);
+        }
+    }
+}
+
+/// 播放端（输出回调中使用）
+pub struct TransportReader {
+    consumer: HeapConsumer<f32>,
+    channels: usize,
+    block_frames: usize,
+    /// 容量（帧）
+    capacity: usize,
+    /// 等待水位达到目标后才开始读取（启动和欠载之后）
+    priming: bool,
+    /// 漂移补偿的重采样器和比率控制器的状态
+    resampler: Option<Resampler>,
+    /// 平滑后的水位误差（帧）
+    average: f64,
+    integral: f64,
+    stats: TransportStats,
+}
+
+impl TransportReader {
+    /// 读取一块交错数据，数据不够时补静音
+    pub fn read(&mut self, data: &mut [f32]) {
+        let channels = self.channels;
+        self.stats.observe_chunk(data.len() / channels);
+        let target = self.target_frames();
+        let buffered = self.resampler.as_ref().map_or(0, Resampler::buffered);
+        let available = self.consumer.len() / channels + buffered;
+
+        if self.priming {
+            if available < target {
+                data.fill(0.0);
+                self.stats.inner.fill.store(available, Ordering::Relaxed);
+                return;
+            }
+            self.priming = false;
+        }
+
+        // 采集端比播放端快，水位漂移过高：丢弃最旧的数据回到目标水位
+        let mut available = available;
+        if available > target + self.block_frames * DRIFT_BLOCKS {
+            let excess = available - target;
+            let skipped = self.consumer.skip(excess * channels) / channels;
+            available -= skipped;
+            self.average = 0.0;
+            TransportStats::count(&self.stats.inner.recenters);
+            debug!("传输水位漂移到 {} 帧，丢弃 {} 帧", available + skipped, skipped);
+        }
+
+        let read = if self.resampler.is_some() {
+            let ratio = self.update_ratio(available, target, data.len() / channels);
+            let consumer = &mut self.consumer;
+            let resampler = self.resampler.as_mut().expect("漂移补偿已开启");
+            resampler.set_ratio(ratio);
+            resampler.process(data, &mut |dst| consumer.pop_slice(dst)) * channels
+        } else {
+            self.consumer.pop_slice(data)
+        };
+        if read < data.len() {
+            // 采集端比播放端慢：补静音，等水位回到目标后再继续
+            data[read..].fill(0.0);
+            self.priming = true;
+            TransportStats::count(&self.stats.inner.underruns);
+        }
+        let buffered = self.resampler.as_ref().map_or(0, Resampler::buffered);
+        self.stats.inner.fill.store(self.consumer.len() / channels + buffered, Ordering::Relaxed);
+    }
+
+    /// 按平滑后的水位误差更新重采样比率（PI 控制）：水位偏高时消耗得快一些，偏低时慢一些
+    fn update_ratio(&mut self, available: usize, target: usize, frames: usize) -> f64 {
+        let error = available as f64 - target as f64;
+        self.average += (error - self.average) * FILL_SMOOTHING;
+        self.integral = (self.integral + self.average * DRIFT_KI * frames as f64).clamp(-MAX_DEVIATION, MAX_DEVIATION);
+        let ratio = 1.0 + (self.average * DRIFT_KP + self.integral).clamp(-MAX_DEVIATION, MAX_DEVIATION);
+        self.stats.inner.ratio.store(ratio.to_bits(), Ordering::Relaxed);
+        self.stats.inner.drift.store(self.average.to_bits(), Ordering::Relaxed);
+        ratio
+    }
+
+    /// 目标水位（帧）：至少能容纳两端最长的回调，不超过容量的一半
+    fn target_frames(&self) -> usize {
+        let chunk = self.stats.inner.max_chunk.load(Ordering::Relaxed).max(self.block_frames);
+        let target = (chunk * TARGET_BLOCKS).min(self.capacity / 2);
+        self.stats.inner.target.store(target, Ordering::Relaxed);
+        target
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_priming_and_underrun() {
+        let (mut writer, mut reader, stats) = transport(2, 4, false);
+        let mut out = vec![1.0; 8];
+
+        // 水位没有达到 2 块之前输出静音
+        writer.write(&[0.5; 8]);
+        reader.read(&mut out);
+        assert_eq!(out, vec![0.0; 8]);
+        writer.write(&[0.5; 8]);
+        reader.read(&mut out);
+        assert_eq!(out, vec![0.5; 8]);
+        assert_eq!(stats.fill_frames(), 4);
+
+        reader.read(&mut out);
+        reader.read(&mut out);
+        assert_eq!(out, vec![0.0; 8]);
+        assert_eq!(stats.underruns(), 1);
+        assert_eq!(stats.overruns(), 0);
+    }
+
+    #[test]
+    fn test_recenter_and_overrun() {
+        let (mut writer, mut reader, stats) = transport(1, 64, false);
+        let capacity = MIN_CAPACITY_FRAMES;
+
+        // 播放端一直没有读取：水位越过目标，最后溢出
+        let block: Vec<f32> = (0..64).map(|i| i as f32).collect();
+        for _ in 0..capacity / 64 + 1 {
+            writer.write(&block);
+        }
+        assert_eq!(stats.overruns(), 1);
+
+        // 第一次读取时丢弃多出的数据，只留目标水位
+        let mut out = vec![0.0; 64];
+        reader.read(&mut out);
+        assert_eq!(stats.recenters(), 1);
+        assert_eq!(stats.fill_frames(), stats.target_frames() - 64);
+        assert_eq!(out, block);
+    }
+
+    #[test]
+    fn test_drift_compensation() {
+        // 采集端每 16 块多一帧（约 +977 ppm）
+        let (mut writer, mut reader, stats) = transport(1, 64, true);
+        let block = vec![0.25; 65];
+        let mut out = vec![0.0; 64];
+        for i in 0..8000 {
+            writer.write(&block[..if i % 16 == 15 { 65 } else { 64 }]);
+            reader.read(&mut out);
+        }
+
+        // 比率收敛到漂移量，水位保持在目标附近，不需要丢弃数据
+        let drift_ppm = 1e6 / 1024.0;
+        assert!((stats.ratio_ppm() - drift_ppm).abs() < 100.0, "{} ppm", stats.ratio_ppm());
+        assert!(stats.fill_error().abs() < 16.0);
+        assert_eq!(stats.recenters(), 0);
+        assert_eq!(stats.underruns(), 0);
+        assert!(out.iter().all(|&sample| (sample - 0.25).abs() < 1e-3));
+    }
+}
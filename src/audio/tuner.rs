@@ -0,0 +1,304 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+/// 检测范围下限（低于吉他降调后的最低音）
+const MIN_FREQUENCY: f32 = 30.0;
+/// 检测范围上限
+const MAX_FREQUENCY: f32 = 1500.0;
+/// 降采样后的目标采样率（足够覆盖检测范围，且计算量小）
+const ANALYSIS_RATE: f32 = 11000.0;
+/// 分析窗口长度（降采样后的采样数）
+const WINDOW_SIZE: usize = 1024;
+/// 每隔多少个降采样后的采样分析一次
+const HOP_SIZE: usize = 256;
+/// YIN 累积均值归一化差分函数的阈值
+const YIN_THRESHOLD: f32 = 0.15;
+/// 低于此 RMS 不做检测
+const SILENCE_RMS: f32 = 0.003;
+
+const NOTE_NAMES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+
+/// 一次调音读数
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TunerReading {
+    /// 检测到的频率 (Hz)
+    pub frequency: f32,
+    /// 最近的音名
+    pub note: &'static str,
+    pub octave: i32,
+    /// 相对最近音的偏差（-50 到 +50 音分）
+    pub cents: f32,
+    /// 检测置信度（0.0 - 1.0）
+    pub clarity: f32,
+}
+
+impl TunerReading {
+    /// 根据频率和 A4 参考频率计算音名
+    pub fn from_frequency(frequency: f32, reference: f32, clarity: f32) -> Self {
+        let midi = 69.0 + 12.0 * (frequency / reference).log2();
+        let nearest = midi.round();
+        let index = nearest as i32;
+
+        Self {
+            frequency,
+            note: NOTE_NAMES[index.rem_euclid(12) as usize],
+            octave: index.div_euclid(12) - 1,
+            cents: (midi - nearest) * 100.0,
+            clarity,
+        }
+    }
+}
+
+/// 调音器读数（线程安全，音频线程写入、UI 线程读取）
+#[derive(Clone)]
+pub struct TunerReadout {
+    /// 0 表示当前没有检测到音高
+    frequency: Arc<AtomicU32>,
+    clarity: Arc<AtomicU32>,
+    reference: Arc<AtomicU32>,
+}
+
+impl TunerReadout {
+    pub fn new() -> Self {
+        Self {
+            frequency: Arc::new(AtomicU32::new(0)),
+            clarity: Arc::new(AtomicU32::new(0)),
+            reference: Arc::new(AtomicU32::new(440f32.to_bits())),
+        }
+    }
+
+    /// 发布检测结果（在音频线程中调用）
+    pub fn publish(&self, pitch: Option<(f32, f32)>) {
+        let (frequency, clarity) = pitch.unwrap_or((0.0, 0.0));
+        self.clarity.store(clarity.to_bits(), Ordering::Relaxed);
+        self.frequency.store(frequency.to_bits(), Ordering::Relaxed);
+    }
+
+    /// 获取当前读数（在 UI 线程中调用）
+    pub fn reading(&self) -> Option<TunerReading> {
+        let frequency = f32::from_bits(self.frequency.load(Ordering::Relaxed));
+        if frequency <= 0.0 {
+            return None;
+        }
+        let clarity = f32::from_bits(self.clarity.load(Ordering::Relaxed));
+        Some(TunerReading::from_frequency(frequency, self.reference(), clarity))
+    }
+
+    /// A4 参考频率
+    pub fn reference(&self) -> f32 {
+        f32::from_bits(self.reference.load(Ordering::Relaxed))
+    }
+
+    pub fn set_reference(&self, hz: f32) {
+        self.reference.store(hz.to_bits(), Ordering::Relaxed);
+    }
+}
+
+impl Default for TunerReadout {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 用 YIN 算法检测一帧的基频，返回 (频率, 置信度)
+/// diff 是预分配的工作区，长度决定最大延迟
+pub fn detect_pitch(frame: &[f32], sample_rate: f32, diff: &mut [f32]) -> Option<(f32, f32)> {
+    let max_lag = ((sample_rate / MIN_FREQUENCY) as usize).min(diff.len() - 1).min(frame.len() / 2);
+    let min_lag = ((sample_rate / MAX_FREQUENCY) as usize).max(2);
+    if max_lag <= min_lag + 1 {
+        return None;
+    }
+
+    let rms = (frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32).sqrt();
+    if rms < SILENCE_RMS {
+        return None;
+    }
+
+    // 差分函数 + 累积均值归一化
+    let length = frame.len() - max_lag;
+    diff[0] = 1.0;
+    let mut running_sum = 0.0;
+    for lag in 1..=max_lag {
+        let d: f32 = frame[..length]
+            .iter()
+            .zip(&frame[lag..lag + length])
+            .map(|(a, b)| (a - b) * (a - b))
+            .sum();
+        running_sum += d;
+        diff[lag] = if running_sum > 0.0 { d * lag as f32 / running_sum } else { 1.0 };
+    }
+
+    // 第一个低于阈值的谷
+    let mut lag = (min_lag..max_lag).find(|&lag| diff[lag] < YIN_THRESHOLD)?;
+    while lag + 1 < max_lag && diff[lag + 1] < diff[lag] {
+        lag += 1;
+    }
+
+    // 抛物线插值得到亚采样精度
+    let (prev, cur, next) = (diff[lag - 1], diff[lag], diff[lag + 1]);
+    let denominator = prev - 2.0 * cur + next;
+    let offset = if denominator.abs() > f32::EPSILON {
+        (0.5 * (prev - next) / denominator).clamp(-0.5, 0.5)
+    } else {
+        0.0
+    };
+
+    let frequency = sample_rate / (lag as f32 + offset);
+    Some((frequency, (1.0 - cur).clamp(0.0, 1.0)))
+}
+
+/// 调音器：对输入降采样后周期性做音高检测，结果写入 TunerReadout
+/// 处理过程中不分配内存
+pub struct Tuner {
+    readout: TunerReadout,
+    /// 降采样倍数
+    decimation: usize,
+    analysis_rate: f32,
+    accumulator: f32,
+    accumulated: usize,
+    /// 降采样后的环形缓冲区
+    history: Vec<f32>,
+    history_pos: usize,
+    hop_counter: usize,
+    frame: Vec<f32>,
+    diff: Vec<f32>,
+}
+
+impl Tuner {
+    #[cfg(test)]
+    pub fn new(sample_rate: f32) -> Self {
+        Self::with_readout(sample_rate, TunerReadout::new())
+    }
+
+    /// 使用已有的读数句柄（例如由 UI 先创建）
+    pub fn with_readout(sample_rate: f32, readout: TunerReadout) -> Self {
+        let decimation = ((sample_rate / ANALYSIS_RATE) as usize).max(1);
+        let analysis_rate = sample_rate / decimation as f32;
+        let max_lag = (analysis_rate / MIN_FREQUENCY) as usize + 2;
+
+        Self {
+            readout,
+            decimation,
+            analysis_rate,
+            accumulator: 0.0,
+            accumulated: 0,
+            history: vec![0.0; WINDOW_SIZE],
+            history_pos: 0,
+            hop_counter: 0,
+            frame: vec![0.0; WINDOW_SIZE],
+            diff: vec![0.0; max_lag],
+        }
+    }
+
+    /// 获取读数句柄（可克隆给 UI 线程）
+    pub fn readout(&self) -> TunerReadout {
+        self.readout.clone()
+    }
+
+    /// 处理交错格式的输入，多声道混合为单声道分析
+    pub fn process_buffer(&mut self, buffer: &[f32], channels: usize) {
+        let channels = channels.max(1);
+        for frame in buffer.chunks_exact(channels) {
+            self.accumulator += frame.iter().sum::<f32>() / channels as f32;
+            self.accumulated += 1;
+            if self.accumulated < self.decimation {
+                continue;
+            }
+
+            // 求平均同时起到简单的抗混叠作用
+            self.history[self.history_pos] = self.accumulator / self.decimation as f32;
+            self.history_pos = (self.history_pos + 1) % WINDOW_SIZE;
+            self.accumulator = 0.0;
+            self.accumulated = 0;
+
+            self.hop_counter += 1;
+            if self.hop_counter >= HOP_SIZE {
+                self.hop_counter = 0;
+                self.analyze();
+            }
+        }
+    }
+
+    /// 清空历史采样并撤销当前读数
+    pub fn reset(&mut self) {
+        self.accumulator = 0.0;
+        self.accumulated = 0;
+        self.history.fill(0.0);
+        self.history_pos = 0;
+        self.hop_counter = 0;
+        self.readout.publish(None);
+    }
+
+    fn analyze(&mut self) {
+        // 把环形缓冲区按时间顺序展开
+        let (older, newer) = self.history.split_at(self.history_pos);
+        self.frame[..newer.len()].copy_from_slice(newer);
+        self.frame[newer.len()..].copy_from_slice(older);
+
+        let pitch = detect_pitch(&self.frame, self.analysis_rate, &mut self.diff);
+        self.readout.publish(pitch);
+    }
+}
+
+/// 格式化读数，例如 "A4 +3¢"
+pub fn format_reading(reading: Option<TunerReading>) -> String {
+    match reading {
+        Some(r) => format!("{}{} {:+.0}¢", r.note, r.octave, r.cents),
+        None => "--".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    fn tone(freq: f32, sample_rate: f32, frames: usize) -> Vec<f32> {
+        // 带两个泛音，更接近拨弦
+        (0..frames)
+            .flat_map(|i| {
+                let t = i as f32 / sample_rate;
+                let s = 0.5 * (2.0 * PI * freq * t).sin()
+                    + 0.25 * (2.0 * PI * 2.0 * freq * t).sin()
+                    + 0.1 * (2.0 * PI * 3.0 * freq * t).sin();
+                [s, s]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_tuner_detects_notes() {
+        for (freq, note, octave) in [(82.41, "E", 2), (110.0, "A", 2), (440.0, "A", 4), (329.63, "E", 4)] {
+            let mut tuner = Tuner::new(48000.0);
+            tuner.process_buffer(&tone(freq, 48000.0, 24000), 2);
+
+            let reading = tuner.readout().reading().expect("应当检测到音高");
+            assert_eq!(reading.note, note);
+            assert_eq!(reading.octave, octave);
+            assert!(reading.cents.abs() < 3.0, "{} Hz 偏差 {} 音分", freq, reading.cents);
+        }
+    }
+
+    #[test]
+    fn test_silence_has_no_reading() {
+        let mut tuner = Tuner::new(44100.0);
+        tuner.process_buffer(&tone(440.0, 44100.0, 22050), 2);
+        assert!(tuner.readout().reading().is_some());
+
+        tuner.process_buffer(&vec![0.0; 44100 * 2], 2);
+        assert!(tuner.readout().reading().is_none());
+    }
+
+    #[test]
+    fn test_reading_from_frequency() {
+        let reading = TunerReading::from_frequency(446.0, 440.0, 1.0);
+        assert_eq!(reading.note, "A");
+        assert_eq!(reading.octave, 4);
+        assert!((reading.cents - 23.45).abs() < 0.1);
+
+        // 参考频率改为 446 Hz 后同样的音是准的
+        let reading = TunerReading::from_frequency(446.0, 446.0, 1.0);
+        assert!(reading.cents.abs() < 0.01);
+        assert_eq!(format_reading(Some(reading)), "A4 +0¢");
+    }
+}
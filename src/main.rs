@@ -1,9 +1,13 @@
 mod audio;
 mod plugin;
+mod settings;
+mod ui;
 
 use anyhow::Result;
 use log::{info, error};
 
+use settings::UserSettings;
+
 fn main() -> Result<()> {
     // 初始化日志系统
     env_logger::Builder::from_default_env()
@@ -13,14 +17,38 @@ fn main() -> Result<()> {
     info!("Plugin Loader 启动中...");
     info!("版本: {}", env!("CARGO_PKG_VERSION"));
     
+    // --gui 启动图形界面，否则运行命令行音频引擎
+    if std::env::args().any(|arg| arg == "--gui") {
+        return run_gui();
+    }
+    
+    // 音频设置：用户设置文件 + 命令行参数（--input/--output/--sample-rate/--buffer-size/--midi）
+    let mut settings = UserSettings::load_or_default();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if apply_audio_args(&mut settings.audio, &args)? {
+        if let Err(e) = settings.save(&UserSettings::default_path()) {
+            error!("保存用户设置失败: {:#}", e);
+        }
+    }
+    
     // 测试插件扫描（Phase 2）
-    test_plugin_scan();
+    let plugins = test_plugin_scan();
+    
+    // --project 打开工程：插件链和预设交给音频引擎，MIDI 程序切换选择预设
+    let project = match args.iter().position(|arg| arg == "--project") {
+        Some(index) => {
+            let path = args.get(index + 1).ok_or_else(|| anyhow::anyhow!("参数 --project 缺少取值"))?;
+            Some(plugin::Project::load(std::path::Path::new(path))?)
+        }
+        None => None,
+    };
 
     println!();
     info!("=== Phase 1: 音频引擎测试 ===");
     
     // Phase 1: 基础音频引擎测试
-    match audio::run_audio_engine() {
+    let project = project.map(|project| (project, plugin::plugin_factory(plugins)));
+    match audio::run_audio_engine(settings.audio, project) {
         Ok(_) => {
             info!("音频引擎正常退出");
             Ok(())
@@ -31,8 +59,42 @@ fn main() -> Result<()> {
         }
     }
 }
-/// 测试插件扫描功能（Phase 2）
-fn test_plugin_scan() {
+
+/// 把命令行中的音频参数写入 config，返回是否有改动
+fn apply_audio_args(config: &mut plugin::AudioConfig, args: &[String]) -> Result<bool> {
+    let mut changed = false;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if !matches!(arg.as_str(), "--input" | "--output" | "--sample-rate" | "--buffer-size" | "--midi") {
+            continue;
+        }
+        let value = iter.next().ok_or_else(|| anyhow::anyhow!("参数 {} 缺少取值", arg))?;
+        let number = || value.parse::<u32>().map_err(|_| anyhow::anyhow!("参数 {} 的取值无效: {}", arg, value));
+        match arg.as_str() {
+            "--input" => config.input_device = Some(value.clone()),
+            "--output" => config.output_device = Some(value.clone()),
+            "--midi" => config.midi_input = Some(value.clone()),
+            "--sample-rate" => config.sample_rate = number()?,
+            _ => config.buffer_size = number()?,
+        }
+        changed = true;
+    }
+    Ok(changed)
+}
+
+/// 启动图形界面（Phase 3），音频引擎由界面中的按钮控制
+fn run_gui() -> Result<()> {
+    info!("=== Phase 3: 图形界面 ===");
+    eframe::run_native(
+        "Plugin Loader",
+        eframe::NativeOptions::default(),
+        Box::new(|_cc| Ok(Box::new(ui::PluginLoaderApp::new()))),
+    )
+    .map_err(|e| anyhow::anyhow!("图形界面错误: {}", e))
+}
+
+/// 测试插件扫描功能（Phase 2），返回扫描结果（打开工程时按它创建插件）
+fn test_plugin_scan() -> Vec<plugin::PluginInfo> {
     info!("=== Phase 2: 插件系统测试 ===");
     
     let scanner = plugin::PluginScanner::new();
@@ -51,6 +113,7 @@ fn test_plugin_scan() {
             if plugins.len() > 5 {
                 info!("  ... 还有 {} 个插件", plugins.len() - 5);
             }
+            plugins
         }
         _ => {
             info!("未找到插件缓存，开始扫描...");
@@ -67,9 +130,11 @@ fn test_plugin_scan() {
                     if plugins.len() > 5 {
                         info!("  ... 还有 {} 个插件", plugins.len() - 5);
                     }
+                    plugins
                 }
                 Err(e) => {
                     error!("插件扫描失败: {}", e);
+                    Vec::new()
                 }
             }
         }
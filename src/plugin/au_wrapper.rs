@@ -5,10 +5,14 @@ use anyhow::Result;
 use log::{info, warn};
 use std::path::Path;
 
-use super::types::{PluginMetadata, AudioProcessor, PluginParameter, PluginState, PluginFormat};
+use crate::audio::AudioBuffer;
+
+use super::types::{PluginMetadata, AudioProcessor, PluginParameter, PluginState, PluginFormat, ChannelLayout, ProcessConfig, ParameterDescriptor};
 
 // AudioComponent 类型定义
+// 这里的类型、常量和 enumerate_audio_units 留给 macOS 上接入 AudioComponent API 时使用
 #[repr(C)]
+#[allow(dead_code)]
 struct AudioComponentDescription {
     component_type: u32,
     component_sub_type: u32,
@@ -18,9 +22,13 @@ struct AudioComponentDescription {
 }
 
 // 常量定义
+#[allow(dead_code)]
 const K_AUDIO_UNIT_TYPE_EFFECT: u32 = 0x61756678; // 'aufx'
+#[allow(dead_code)]
 const K_AUDIO_UNIT_TYPE_MUSIC_EFFECT: u32 = 0x61756d78; // 'aumx'  
+#[allow(dead_code)]
 const K_AUDIO_UNIT_TYPE_GENERATOR: u32 = 0x61756765; // 'aumu'
+#[allow(dead_code)]
 const K_AUDIO_UNIT_MANUFACTURER_ANY: u32 = 0;
 
 // 外部 C 函数声明（简化版本）
@@ -32,6 +40,8 @@ extern "C" {
 /// Audio Unit 插件包装器
 pub struct AudioUnitPlugin {
     metadata: PluginMetadata,
+    /// 最近一次 prepare 的配置（AU 初始化时需要），release 后为 None
+    config: Option<ProcessConfig>,
     // 实际的 AU 实例会在这里
     // component: Option<AudioComponent>,
     // unit: Option<AudioUnit>,
@@ -70,6 +80,7 @@ impl AudioUnitPlugin {
         
         Ok(Self {
             metadata,
+            config: None,
         })
     }
     
@@ -80,7 +91,26 @@ impl AudioUnitPlugin {
 }
 
 impl AudioProcessor for AudioUnitPlugin {
-    fn process(&mut self, _buffer: &mut [f32]) {
+    fn prepare(&mut self, sample_rate: f64, max_block_size: usize, layout: ChannelLayout) {
+        // TODO: 实际的 AU 初始化
+        // 步骤：
+        // 1. 已初始化时先 AudioUnitUninitialize
+        // 2. 设置 kAudioUnitProperty_SampleRate / StreamFormat（按 layout 的声道数）
+        // 3. 设置 kAudioUnitProperty_MaximumFramesPerSlice
+        // 4. AudioUnitInitialize
+        self.config = Some(ProcessConfig { sample_rate, max_block_size, layout });
+    }
+    
+    fn reset(&mut self) {
+        // TODO: AudioUnitReset(unit, kAudioUnitScope_Global, 0)
+    }
+    
+    fn release(&mut self) {
+        // TODO: AudioUnitUninitialize
+        self.config = None;
+    }
+    
+    fn process(&mut self, _buffer: &mut AudioBuffer) {
         // TODO: 实际的音频处理
         // 步骤：
         // 1. 准备 AudioBufferList
@@ -90,6 +120,9 @@ impl AudioProcessor for AudioUnitPlugin {
         // 目前是 bypass（直通）
     }
     
+    // TODO: 重写 process_with_events，参数事件用 AudioUnitScheduleParameters
+    // （kParameterEvent_Immediate + inBufferOffsetInFrames），MIDI 用 MusicDeviceMIDIEvent 的 inOffsetSampleFrame
+    
     fn get_info(&self) -> &PluginMetadata {
         &self.metadata
     }
@@ -108,6 +141,12 @@ impl AudioProcessor for AudioUnitPlugin {
         Vec::new()
     }
     
+    fn parameter_descriptors(&self) -> Vec<ParameterDescriptor> {
+        // TODO: 使用 kAudioUnitProperty_ParameterInfo 读取范围、单位和标志，
+        // kAudioUnitProperty_ParameterValueStrings 读取枚举值
+        Vec::new()
+    }
+    
     fn save_state(&self) -> PluginState {
         // TODO: 使用 AudioUnitGetProperty 获取状态
         PluginState {
@@ -123,6 +162,7 @@ impl AudioProcessor for AudioUnitPlugin {
 }
 
 /// 枚举系统中的 Audio Unit 组件
+#[allow(dead_code)]
 pub fn enumerate_audio_units() -> Result<Vec<PluginMetadata>> {
     info!("枚举系统 Audio Unit 组件...");
     
@@ -154,8 +194,14 @@ mod tests {
             num_outputs: 2,
         };
         
-        // 测试 from_metadata（目前会失败因为路径不存在，但测试结构）
-        assert!(AudioUnitPlugin::from_metadata(metadata).is_err() || true);
+        // AU 加载器尚未实现，目前总是返回模拟插件
+        let mut plugin = AudioUnitPlugin::from_metadata(metadata).unwrap();
+        assert_eq!(plugin.get_info().name, "test");
+        
+        plugin.prepare(44100.0, 512, ChannelLayout::Stereo);
+        assert_eq!(plugin.config.map(|c| c.sample_rate), Some(44100.0));
+        plugin.release();
+        assert!(plugin.config.is_none());
     }
 }
 
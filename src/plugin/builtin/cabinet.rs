@@ -0,0 +1,264 @@
+// 箱体模拟：加载音箱 IR（WAV）做卷积
+
+use anyhow::{Context, Result};
+use log::{info, warn};
+use std::path::{Path, PathBuf};
+
+use crate::audio::Smoother;
+use super::convolver::PartitionedConvolver;
+use super::dsp::{db_to_gain, resample, smoother};
+use super::{BuiltinEffect, ParamSpec};
+
+pub const PARAMS: &[ParamSpec] = &[
+    ParamSpec::linear("Trim", -24.0, 12.0, 0.0, "dB"),
+    ParamSpec::log("Length", 10.0, 1000.0, 500.0, "ms"),
+];
+
+/// 卷积分段长度，也就是引入的延迟
+const BLOCK_SIZE: usize = 64;
+
+/// IR 最大长度，超出部分截断
+const MAX_IR_SECONDS: f32 = 1.0;
+
+/// 读取 IR 文件，返回每个声道的数据（已重采样到 sample_rate）
+pub fn load_impulse_response(path: &Path, sample_rate: f32) -> Result<Vec<Vec<f32>>> {
+    let mut reader = hound::WavReader::open(path)
+        .context(format!("打开 IR 文件失败: {:?}", path))?;
+    let spec = reader.spec();
+    let channels = spec.channels as usize;
+    if channels != 1 && channels != 2 {
+        return Err(anyhow::anyhow!("IR 只支持单声道或立体声，文件有 {} 个声道", channels));
+    }
+
+    let samples: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>()?,
+        hound::SampleFormat::Int => {
+            let scale = 1.0 / (1u64 << (spec.bits_per_sample - 1)) as f32;
+            reader.samples::<i32>()
+                .map(|s| s.map(|s| s as f32 * scale))
+                .collect::<Result<_, _>>()?
+        }
+    };
+    if samples.is_empty() {
+        return Err(anyhow::anyhow!("IR 文件为空: {:?}", path));
+    }
+
+    let max_len = (MAX_IR_SECONDS * sample_rate) as usize;
+    let ir: Vec<Vec<f32>> = (0..channels)
+        .map(|ch| {
+            let channel: Vec<f32> = samples.iter().skip(ch).step_by(channels).copied().collect();
+            let mut channel = resample(&channel, spec.sample_rate as f32, sample_rate);
+            if channel.len() > max_len {
+                warn!("IR 超过 {} 秒，已截断: {:?}", MAX_IR_SECONDS, path);
+                channel.truncate(max_len);
+            }
+            channel
+        })
+        .collect();
+
+    info!(
+        "IR 已加载: {:?} ({} 声道, {} Hz -> {} Hz, {} 采样)",
+        path, channels, spec.sample_rate, sample_rate, ir[0].len()
+    );
+    Ok(ir)
+}
+
+/// 完整的 IR 在加载时（控制线程）送入卷积器并算好所有分段的频谱，
+/// 调整 Length 只改变使用的段数、重算最后一段（截断时整段淡出），可以在音频线程中进行
+pub struct Cabinet {
+    sample_rate: f32,
+    path: Option<PathBuf>,
+    /// 是否已加载 IR（没有 IR 时直通）
+    loaded: bool,
+    trim: Smoother,
+    length_ms: f32,
+    convolvers: [PartitionedConvolver; 2],
+}
+
+impl Cabinet {
+    pub fn new(sample_rate: f32) -> Self {
+        let max_len = (MAX_IR_SECONDS * sample_rate) as usize;
+        Self {
+            sample_rate,
+            path: None,
+            loaded: false,
+            trim: smoother(sample_rate, 1.0),
+            length_ms: 0.0,
+            convolvers: [
+                PartitionedConvolver::new(BLOCK_SIZE, max_len),
+                PartitionedConvolver::new(BLOCK_SIZE, max_len),
+            ],
+        }
+    }
+
+    /// 按 Length 截断卷积器使用的 IR（不分配内存）
+    fn apply_length(&mut self) {
+        let length = ((self.length_ms * 0.001 * self.sample_rate) as usize).max(1);
+        for convolver in &mut self.convolvers {
+            convolver.set_length(length);
+        }
+    }
+}
+
+impl BuiltinEffect for Cabinet {
+    fn set_param(&mut self, id: u32, value: f32) {
+        match id {
+            0 => self.trim.set_target(db_to_gain(value)),
+            1 => {
+                self.length_ms = value;
+                self.apply_length();
+            }
+            _ => {}
+        }
+    }
+
+    fn process(&mut self, buffer: &mut [f32]) {
+        // 没有 IR 时直通
+        if !self.loaded {
+            return;
+        }
+        for frame in buffer.chunks_exact_mut(2) {
+            let trim = self.trim.next_value();
+            for (ch, sample) in frame.iter_mut().enumerate() {
+                *sample = self.convolvers[ch].process_sample(*sample) * trim;
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.trim.finish();
+        for convolver in &mut self.convolvers {
+            convolver.reset();
+        }
+    }
+
+    fn file(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    fn latency(&self) -> u32 {
+        // 没有 IR 时直通，没有延迟
+        if !self.loaded {
+            0
+        } else {
+            self.convolvers[0].latency() as u32
+        }
+    }
+
+    fn load_file(&mut self, path: &Path) -> Result<()> {
+        let impulse = load_impulse_response(path, self.sample_rate)?;
+        for (ch, convolver) in self.convolvers.iter_mut().enumerate() {
+            // 单声道 IR 两个声道共用
+            convolver.set_impulse(&impulse[ch.min(impulse.len() - 1)]);
+            convolver.reset();
+        }
+        self.loaded = true;
+        self.path = Some(path.to_path_buf());
+        self.apply_length();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn write_ir(name: &str, channels: u16, sample_rate: u32, frames: &[[f32; 2]]) -> PathBuf {
+        let path = env::temp_dir().join(name);
+        let spec = hound::WavSpec {
+            channels,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for frame in frames {
+            for &sample in &frame[..channels as usize] {
+                writer.write_sample((sample * 32767.0) as i16).unwrap();
+            }
+        }
+        writer.finalize().unwrap();
+        path
+    }
+
+    #[test]
+    fn test_load_stereo_ir() {
+        // 左声道单位冲激，右声道半幅冲激
+        let mut frames = vec![[0.0, 0.0]; 100];
+        frames[0] = [1.0, 0.5];
+        let path = write_ir("test_cab_stereo.wav", 2, 48000, &frames);
+
+        let mut cab = Cabinet::new(48000.0);
+        for (id, spec) in PARAMS.iter().enumerate() {
+            cab.set_param(id as u32, spec.default);
+        }
+        assert_eq!(cab.latency(), 0);
+        cab.load_file(&path).unwrap();
+        assert_eq!(cab.latency(), BLOCK_SIZE as u32);
+        assert_eq!(cab.file(), Some(path.as_path()));
+
+        let mut buffer = vec![0.0; 256 * 2];
+        buffer[0] = 1.0;
+        buffer[1] = 1.0;
+        cab.process(&mut buffer);
+
+        let latency = BLOCK_SIZE * 2;
+        assert!((buffer[latency] - 1.0).abs() < 1e-3);
+        assert!((buffer[latency + 1] - 0.5).abs() < 1e-3);
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_length_change_while_processing() {
+        // 0.5 秒的平直 IR，处理过程中来回调整 Length
+        let frames = vec![[0.5, 0.5]; 24000];
+        let path = write_ir("test_cab_length.wav", 2, 48000, &frames);
+        let mut cab = Cabinet::new(48000.0);
+        cab.set_param(0, 0.0);
+        cab.set_param(1, 500.0);
+        cab.load_file(&path).unwrap();
+
+        let mut buffer = vec![0.25; 128 * 2];
+        for (i, length) in [10.0, 800.0, 40.0, 250.0, 10.0].iter().cycle().take(50).enumerate() {
+            cab.set_param(1, *length);
+            cab.process(&mut buffer);
+            assert!(buffer.iter().all(|sample| sample.is_finite()), "块 {}", i);
+            buffer.fill(0.25);
+        }
+
+        // 10 ms = 480 采样，向上取整到 8 段（512 采样），最后一段淡出：冲激响应在 512 采样后为 0
+        cab.set_param(1, 10.0);
+        cab.reset();
+        let mut buffer = vec![0.0; 1024 * 2];
+        buffer[0] = 1.0;
+        buffer[1] = 1.0;
+        cab.process(&mut buffer);
+        let response: Vec<f32> = buffer.iter().step_by(2).skip(BLOCK_SIZE).copied().collect();
+        assert!((response[100] - 0.5).abs() < 1e-3);
+        assert!(response[448] < 0.5 && response[448] > 0.0);
+        assert!(response[512..].iter().all(|sample| sample.abs() < 1e-4));
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_ir_is_resampled() {
+        let frames = vec![[0.1, 0.1]; 441];
+        let path = write_ir("test_cab_mono.wav", 1, 44100, &frames);
+
+        let ir = load_impulse_response(&path, 48000.0).unwrap();
+        assert_eq!(ir.len(), 1);
+        assert_eq!(ir[0].len(), 480);
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_missing_ir_file() {
+        let mut cab = Cabinet::new(48000.0);
+        assert!(cab.load_file(Path::new("/nonexistent/cab.wav")).is_err());
+        assert!(cab.file().is_none());
+    }
+}
@@ -0,0 +1,123 @@
+// 压缩器（前馈、立体声联动、软拐点）
+
+use crate::audio::Smoother;
+use super::dsp::{db_to_gain, gain_to_db, smoother, time_coefficient};
+use super::{BuiltinEffect, ParamSpec};
+
+pub const PARAMS: &[ParamSpec] = &[
+    ParamSpec::linear("Threshold", -60.0, 0.0, -18.0, "dB"),
+    ParamSpec::log("Ratio", 1.0, 20.0, 4.0, ":1"),
+    ParamSpec::log("Attack", 0.1, 100.0, 10.0, "ms"),
+    ParamSpec::log("Release", 10.0, 1000.0, 100.0, "ms"),
+    ParamSpec::linear("Knee", 0.0, 12.0, 6.0, "dB"),
+    ParamSpec::linear("Makeup", 0.0, 24.0, 0.0, "dB"),
+];
+
+pub struct Compressor {
+    sample_rate: f32,
+    threshold: f32,
+    ratio: f32,
+    attack: f32,
+    release: f32,
+    knee: f32,
+    makeup: Smoother,
+    /// 当前增益衰减量（dB，非负）
+    reduction: f32,
+}
+
+impl Compressor {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            sample_rate,
+            threshold: 0.0,
+            ratio: 1.0,
+            attack: 0.0,
+            release: 0.0,
+            knee: 0.0,
+            makeup: smoother(sample_rate, 1.0),
+            reduction: 0.0,
+        }
+    }
+
+    /// 静态增益曲线：输入电平 (dB) -> 需要的衰减量 (dB)
+    fn compute_reduction(&self, level_db: f32) -> f32 {
+        let over = level_db - self.threshold;
+        let slope = 1.0 - 1.0 / self.ratio;
+
+        if 2.0 * over <= -self.knee {
+            0.0
+        } else if 2.0 * over < self.knee {
+            // 拐点区间内二次过渡
+            slope * (over + self.knee / 2.0).powi(2) / (2.0 * self.knee)
+        } else {
+            slope * over
+        }
+    }
+}
+
+impl BuiltinEffect for Compressor {
+    fn set_param(&mut self, id: u32, value: f32) {
+        match id {
+            0 => self.threshold = value,
+            1 => self.ratio = value.max(1.0),
+            2 => self.attack = time_coefficient(value, self.sample_rate),
+            3 => self.release = time_coefficient(value, self.sample_rate),
+            4 => self.knee = value,
+            5 => self.makeup.set_target(db_to_gain(value)),
+            _ => {}
+        }
+    }
+
+    fn process(&mut self, buffer: &mut [f32]) {
+        for frame in buffer.chunks_exact_mut(2) {
+            let level = frame[0].abs().max(frame[1].abs());
+            let target = self.compute_reduction(gain_to_db(level));
+
+            // 衰减量增加用 attack，减少用 release
+            let coefficient = if target > self.reduction { self.attack } else { self.release };
+            self.reduction = target + (self.reduction - target) * coefficient;
+
+            let gain = db_to_gain(-self.reduction) * self.makeup.next_value();
+            frame[0] *= gain;
+            frame[1] *= gain;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.reduction = 0.0;
+        self.makeup.finish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::tests::{peak, sine};
+
+    #[test]
+    fn test_static_curve() {
+        let mut comp = Compressor::new(48000.0);
+        comp.set_param(0, -20.0);
+        comp.set_param(1, 4.0);
+        comp.set_param(4, 0.0);
+
+        assert_eq!(comp.compute_reduction(-30.0), 0.0);
+        // 超过阈值 8 dB，4:1 时输出只高出 2 dB
+        assert!((comp.compute_reduction(-12.0) - 6.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_compression_reduces_level() {
+        let mut comp = Compressor::new(48000.0);
+        for (id, spec) in PARAMS.iter().enumerate() {
+            comp.set_param(id as u32, spec.default);
+        }
+
+        let mut buffer = sine(1000.0, 1.0, 48000);
+        comp.process(&mut buffer);
+        // 0 dBFS、阈值 -18 dB、4:1：理论稳态 -13.5 dB
+        // 逐采样峰值检测在正弦过零处会释放一点，允许 1.5 dB 误差
+        let settled = gain_to_db(peak(&buffer[48000..]));
+        assert!((settled + 13.5).abs() < 1.5, "稳态电平 {}", settled);
+    }
+}
@@ -0,0 +1,194 @@
+// 均匀分段 FFT 卷积（overlap-save）
+// IR 被切成长度为 block 的若干段，每段预先算好频谱；
+// 输入每满一个 block 做一次 FFT，与历史输入频谱逐段相乘累加后反变换。
+// 延迟固定为 block 个采样，处理过程中不分配内存。
+// 截断 IR（set_length）只重算最后一段的频谱，可以在音频线程中调用。
+
+use realfft::num_complex::Complex;
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+use std::sync::Arc;
+
+pub struct PartitionedConvolver {
+    block: usize,
+    fft: Arc<dyn RealToComplex<f32>>,
+    ifft: Arc<dyn ComplexToReal<f32>>,
+    /// 完整 IR 的时域数据（容量按最长 IR 预分配）
+    impulse: Vec<f32>,
+    /// 完整 IR 每段的频谱（容量按最长 IR 预分配）
+    partitions: Vec<Vec<Complex<f32>>>,
+    /// 当前使用的段数（截断后可能少于完整 IR 的段数）
+    active: usize,
+    /// 最后一段使用的频谱（截断时带淡出）
+    tail: Vec<Complex<f32>>,
+    /// 最近若干块输入的频谱（环形）
+    history: Vec<Vec<Complex<f32>>>,
+    history_pos: usize,
+    /// [上一块 | 当前块] 的时域输入
+    input: Vec<f32>,
+    fft_buffer: Vec<f32>,
+    spectrum: Vec<Complex<f32>>,
+    scratch: Vec<Complex<f32>>,
+    /// 上一块的卷积结果，逐采样输出
+    output: Vec<f32>,
+    fill: usize,
+}
+
+impl PartitionedConvolver {
+    /// block: 分段长度（即延迟）；max_len: IR 最大长度
+    pub fn new(block: usize, max_len: usize) -> Self {
+        let mut planner = RealFftPlanner::<f32>::new();
+        let fft = planner.plan_fft_forward(block * 2);
+        let ifft = planner.plan_fft_inverse(block * 2);
+        let bins = block + 1;
+        let count = max_len.div_ceil(block).max(1);
+        let scratch_len = fft.get_scratch_len().max(ifft.get_scratch_len());
+
+        Self {
+            block,
+            fft,
+            ifft,
+            impulse: Vec::with_capacity(count * block),
+            partitions: vec![vec![Complex::default(); bins]; count],
+            active: 0,
+            tail: vec![Complex::default(); bins],
+            history: vec![vec![Complex::default(); bins]; count],
+            history_pos: 0,
+            input: vec![0.0; block * 2],
+            fft_buffer: vec![0.0; block * 2],
+            spectrum: vec![Complex::default(); bins],
+            scratch: vec![Complex::default(); scratch_len],
+            output: vec![0.0; block],
+            fill: 0,
+        }
+    }
+
+    /// 延迟（采样）
+    pub fn latency(&self) -> usize {
+        self.block
+    }
+
+    /// 设置 IR（超过容量的部分被截断），计算所有段的频谱并使用完整的 IR
+    pub fn set_impulse(&mut self, impulse: &[f32]) {
+        let impulse = &impulse[..impulse.len().min(self.partitions.len() * self.block)];
+        // 容量已经预分配，不会重新分配
+        self.impulse.clear();
+        self.impulse.extend_from_slice(impulse);
+
+        for (segment, spectrum) in impulse.chunks(self.block).zip(&mut self.partitions) {
+            self.fft_buffer.fill(0.0);
+            self.fft_buffer[..segment.len()].copy_from_slice(segment);
+            // 长度都是按计划预分配的，不会出错
+            let _ = self.fft.process_with_scratch(&mut self.fft_buffer, spectrum, &mut self.scratch);
+        }
+        self.set_length(impulse.len());
+    }
+
+    /// 只使用 IR 的前 length 个采样（向上取整到整段）；比完整 IR 短时最后一段整段淡出
+    ///
+    /// 只做一次 FFT、不分配内存，可以在音频线程中调用
+    pub fn set_length(&mut self, length: usize) {
+        let total = self.impulse.len().div_ceil(self.block);
+        if total == 0 {
+            self.active = 0;
+            return;
+        }
+        self.active = length.div_ceil(self.block).clamp(1, total);
+
+        let start = (self.active - 1) * self.block;
+        let segment = &self.impulse[start..(start + self.block).min(self.impulse.len())];
+        self.fft_buffer.fill(0.0);
+        self.fft_buffer[..segment.len()].copy_from_slice(segment);
+        if self.active < total {
+            let fade = segment.len() as f32;
+            for (i, sample) in self.fft_buffer[..segment.len()].iter_mut().enumerate() {
+                *sample *= 1.0 - (i + 1) as f32 / fade;
+            }
+        }
+        let _ = self.fft.process_with_scratch(&mut self.fft_buffer, &mut self.tail, &mut self.scratch);
+    }
+
+    pub fn process_sample(&mut self, input: f32) -> f32 {
+        let output = self.output[self.fill];
+        self.input[self.block + self.fill] = input;
+        self.fill += 1;
+        if self.fill == self.block {
+            self.process_block();
+            self.fill = 0;
+        }
+        output
+    }
+
+    fn process_block(&mut self) {
+        let count = self.history.len();
+        self.fft_buffer.copy_from_slice(&self.input);
+        let _ = self.fft.process_with_scratch(
+            &mut self.fft_buffer,
+            &mut self.history[self.history_pos],
+            &mut self.scratch,
+        );
+
+        // 第 p 段 IR 与 p 块之前的输入相乘
+        self.spectrum.fill(Complex::default());
+        for p in 0..self.active {
+            let input = &self.history[(self.history_pos + count - p) % count];
+            let partition = if p + 1 == self.active { &self.tail } else { &self.partitions[p] };
+            for (acc, (x, h)) in self.spectrum.iter_mut().zip(input.iter().zip(partition)) {
+                *acc += x * h;
+            }
+        }
+        // 实信号频谱的首尾虚部必须为 0
+        self.spectrum[0].im = 0.0;
+        self.spectrum[self.block].im = 0.0;
+        let _ = self.ifft.process_with_scratch(&mut self.spectrum, &mut self.fft_buffer, &mut self.scratch);
+
+        // overlap-save：只保留后半部分，并补上 realfft 未做的 1/N 归一化
+        let scale = 1.0 / (self.block * 2) as f32;
+        for (out, &y) in self.output.iter_mut().zip(&self.fft_buffer[self.block..]) {
+            *out = y * scale;
+        }
+
+        self.input.copy_within(self.block.., 0);
+        self.history_pos = (self.history_pos + 1) % count;
+    }
+
+    /// 清空历史输入和待输出的样本
+    pub fn reset(&mut self) {
+        for spectrum in &mut self.history {
+            spectrum.fill(Complex::default());
+        }
+        self.input.fill(0.0);
+        self.output.fill(0.0);
+        self.fill = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_direct_convolution() {
+        // 伪随机 IR 和输入
+        let mut seed = 1u32;
+        let mut noise = || {
+            seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+            (seed >> 8) as f32 / (1u32 << 24) as f32 - 0.5
+        };
+        let impulse: Vec<f32> = (0..300).map(|_| noise()).collect();
+        let input: Vec<f32> = (0..1000).map(|_| noise()).collect();
+
+        let mut convolver = PartitionedConvolver::new(64, 512);
+        convolver.set_impulse(&impulse);
+        let output: Vec<f32> = input.iter().map(|&x| convolver.process_sample(x)).collect();
+
+        let latency = convolver.latency();
+        for (n, &sample) in output.iter().enumerate().skip(latency) {
+            let t = n - latency;
+            let expected: f32 = (0..impulse.len())
+                .filter(|&k| k <= t)
+                .map(|k| impulse[k] * input[t - k])
+                .sum();
+            assert!((sample - expected).abs() < 1e-4, "采样 {}: {} != {}", n, sample, expected);
+        }
+    }
+}
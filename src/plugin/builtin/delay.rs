@@ -0,0 +1,155 @@
+// 立体声延迟（带反馈阻尼）
+
+use crate::audio::Smoother;
+use super::dsp::{smoother, OnePole};
+use super::{BuiltinEffect, ParamSpec};
+
+pub const PARAMS: &[ParamSpec] = &[
+    ParamSpec::log("Time", 1.0, 2000.0, 350.0, "ms"),
+    ParamSpec::linear("Feedback", 0.0, 95.0, 35.0, "%"),
+    ParamSpec::linear("Mix", 0.0, 100.0, 30.0, "%"),
+    ParamSpec::log("Tone", 1000.0, 20000.0, 8000.0, "Hz"),
+    ParamSpec::toggle("Ping-Pong", false),
+];
+
+/// 延迟线最大长度
+const MAX_DELAY_SECONDS: f32 = 2.0;
+
+/// 延迟时间变化的平滑系数（每个采样）
+const TIME_SMOOTHING: f32 = 0.0005;
+
+pub struct Delay {
+    sample_rate: f32,
+    /// 预分配的交错立体声延迟线
+    line: Vec<f32>,
+    write_pos: usize,
+    target_delay: f32,
+    current_delay: f32,
+    feedback: Smoother,
+    mix: Smoother,
+    ping_pong: bool,
+    damping: [OnePole; 2],
+}
+
+impl Delay {
+    pub fn new(sample_rate: f32) -> Self {
+        let frames = (MAX_DELAY_SECONDS * sample_rate) as usize + 2;
+        Self {
+            sample_rate,
+            line: vec![0.0; frames * 2],
+            write_pos: 0,
+            target_delay: 1.0,
+            current_delay: 1.0,
+            feedback: smoother(sample_rate, 0.0),
+            mix: smoother(sample_rate, 0.0),
+            ping_pong: false,
+            damping: [OnePole::default(); 2],
+        }
+    }
+
+    fn frames(&self) -> usize {
+        self.line.len() / 2
+    }
+
+    /// 线性插值读取 delay 帧之前的样本
+    fn read(&self, ch: usize, delay: f32) -> f32 {
+        let frames = self.frames();
+        let whole = delay.floor() as usize;
+        let frac = delay - whole as f32;
+        let a = (self.write_pos + frames - whole) % frames;
+        let b = (a + frames - 1) % frames;
+        self.line[a * 2 + ch] * (1.0 - frac) + self.line[b * 2 + ch] * frac
+    }
+}
+
+impl BuiltinEffect for Delay {
+    fn set_param(&mut self, id: u32, value: f32) {
+        match id {
+            0 => {
+                let max = (self.frames() - 2) as f32;
+                self.target_delay = (value * 0.001 * self.sample_rate).clamp(1.0, max);
+            }
+            1 => self.feedback.set_target(value / 100.0),
+            2 => self.mix.set_target(value / 100.0),
+            3 => {
+                for filter in &mut self.damping {
+                    filter.set_cutoff(value, self.sample_rate);
+                }
+            }
+            4 => self.ping_pong = value >= 0.5,
+            _ => {}
+        }
+    }
+
+    fn process(&mut self, buffer: &mut [f32]) {
+        let frames = self.frames();
+        for frame in buffer.chunks_exact_mut(2) {
+            self.current_delay += (self.target_delay - self.current_delay) * TIME_SMOOTHING;
+            let feedback = self.feedback.next_value();
+            let mix = self.mix.next_value();
+
+            let wet = [
+                self.read(0, self.current_delay),
+                self.read(1, self.current_delay),
+            ];
+            let fed = [
+                self.damping[0].process(wet[0]) * feedback,
+                self.damping[1].process(wet[1]) * feedback,
+            ];
+
+            let pos = self.write_pos * 2;
+            if self.ping_pong {
+                // 单声道输入送进左声道，回声在两侧交替
+                self.line[pos] = (frame[0] + frame[1]) * 0.5 + fed[1];
+                self.line[pos + 1] = fed[0];
+            } else {
+                self.line[pos] = frame[0] + fed[0];
+                self.line[pos + 1] = frame[1] + fed[1];
+            }
+            self.write_pos = (self.write_pos + 1) % frames;
+
+            for (ch, sample) in frame.iter_mut().enumerate() {
+                *sample = *sample * (1.0 - mix) + wet[ch] * mix;
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.line.fill(0.0);
+        self.write_pos = 0;
+        self.current_delay = self.target_delay;
+        self.feedback.finish();
+        self.mix.finish();
+        for filter in &mut self.damping {
+            filter.reset();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay_echo_position() {
+        let mut delay = Delay::new(48000.0);
+        for (id, spec) in PARAMS.iter().enumerate() {
+            delay.set_param(id as u32, spec.default);
+        }
+        delay.set_param(0, 10.0);
+        delay.set_param(1, 0.0);
+        delay.set_param(2, 100.0);
+        delay.set_param(3, 20000.0);
+        // 跳过平滑，直接使用目标延迟和干湿比
+        delay.reset();
+
+        let mut buffer = vec![0.0; 1000 * 2];
+        buffer[0] = 1.0;
+        buffer[1] = 1.0;
+        delay.process(&mut buffer);
+
+        // 10 ms @ 48 kHz = 480 帧
+        let echo = buffer.chunks(2).position(|f| f[0] > 0.5).unwrap();
+        assert_eq!(echo, 480);
+    }
+}
@@ -0,0 +1,230 @@
+// 内置效果器共用的 DSP 工具
+
+use std::f32::consts::PI;
+
+use crate::audio::Smoother;
+
+/// 音量、干湿比等参数的平滑时间
+pub const SMOOTHING_MS: f32 = 20.0;
+
+/// dB -> 线性增益
+pub fn db_to_gain(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+/// 线性增益 -> dB（0 对应 -180 dB）
+pub fn gain_to_db(gain: f32) -> f32 {
+    20.0 * gain.abs().max(1e-9).log10()
+}
+
+/// 指数平滑系数：时间常数为 ms 毫秒
+pub fn time_coefficient(ms: f32, sample_rate: f32) -> f32 {
+    if ms <= 0.0 {
+        0.0
+    } else {
+        (-1.0 / (ms * 0.001 * sample_rate)).exp()
+    }
+}
+
+/// 参数平滑器（线性，SMOOTHING_MS），初始值为 value
+pub fn smoother(sample_rate: f32, value: f32) -> Smoother {
+    Smoother::linear(SMOOTHING_MS, sample_rate).with_value(value)
+}
+
+/// 一阶低通
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OnePole {
+    coefficient: f32,
+    state: f32,
+}
+
+impl OnePole {
+    pub fn set_cutoff(&mut self, freq: f32, sample_rate: f32) {
+        let freq = freq.clamp(1.0, sample_rate * 0.49);
+        self.coefficient = 1.0 - (-2.0 * PI * freq / sample_rate).exp();
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        self.state += (input - self.state) * self.coefficient;
+        self.state
+    }
+
+    pub fn reset(&mut self) {
+        self.state = 0.0;
+    }
+}
+
+/// 双二阶滤波器（RBJ Audio EQ Cookbook，转置直接 II 型）
+#[derive(Debug, Clone, Copy)]
+pub struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    z1: f32,
+    z2: f32,
+}
+
+impl Default for Biquad {
+    fn default() -> Self {
+        // 默认是直通
+        Self { b0: 1.0, b1: 0.0, b2: 0.0, a1: 0.0, a2: 0.0, z1: 0.0, z2: 0.0 }
+    }
+}
+
+impl Biquad {
+    fn set(&mut self, b0: f32, b1: f32, b2: f32, a0: f32, a1: f32, a2: f32) {
+        self.b0 = b0 / a0;
+        self.b1 = b1 / a0;
+        self.b2 = b2 / a0;
+        self.a1 = a1 / a0;
+        self.a2 = a2 / a0;
+    }
+
+    fn omega(freq: f32, sample_rate: f32) -> (f32, f32) {
+        let w0 = 2.0 * PI * freq.clamp(10.0, sample_rate * 0.49) / sample_rate;
+        (w0.cos(), w0.sin())
+    }
+
+    /// 峰值（钟形）滤波
+    pub fn set_peaking(&mut self, freq: f32, q: f32, gain_db: f32, sample_rate: f32) {
+        let a = 10f32.powf(gain_db / 40.0);
+        let (cos, sin) = Self::omega(freq, sample_rate);
+        let alpha = sin / (2.0 * q.max(0.05));
+        self.set(
+            1.0 + alpha * a, -2.0 * cos, 1.0 - alpha * a,
+            1.0 + alpha / a, -2.0 * cos, 1.0 - alpha / a,
+        );
+    }
+
+    /// 低架滤波（斜率 S = 1）
+    pub fn set_low_shelf(&mut self, freq: f32, gain_db: f32, sample_rate: f32) {
+        let a = 10f32.powf(gain_db / 40.0);
+        let (cos, sin) = Self::omega(freq, sample_rate);
+        let alpha = sin / 2.0 * 2f32.sqrt();
+        let sqrt_a = 2.0 * a.sqrt() * alpha;
+        self.set(
+            a * ((a + 1.0) - (a - 1.0) * cos + sqrt_a),
+            2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+            a * ((a + 1.0) - (a - 1.0) * cos - sqrt_a),
+            (a + 1.0) + (a - 1.0) * cos + sqrt_a,
+            -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+            (a + 1.0) + (a - 1.0) * cos - sqrt_a,
+        );
+    }
+
+    /// 高架滤波（斜率 S = 1）
+    pub fn set_high_shelf(&mut self, freq: f32, gain_db: f32, sample_rate: f32) {
+        let a = 10f32.powf(gain_db / 40.0);
+        let (cos, sin) = Self::omega(freq, sample_rate);
+        let alpha = sin / 2.0 * 2f32.sqrt();
+        let sqrt_a = 2.0 * a.sqrt() * alpha;
+        self.set(
+            a * ((a + 1.0) + (a - 1.0) * cos + sqrt_a),
+            -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+            a * ((a + 1.0) + (a - 1.0) * cos - sqrt_a),
+            (a + 1.0) - (a - 1.0) * cos + sqrt_a,
+            2.0 * ((a - 1.0) - (a + 1.0) * cos),
+            (a + 1.0) - (a - 1.0) * cos - sqrt_a,
+        );
+    }
+
+    /// 二阶高通
+    pub fn set_highpass(&mut self, freq: f32, q: f32, sample_rate: f32) {
+        let (cos, sin) = Self::omega(freq, sample_rate);
+        let alpha = sin / (2.0 * q.max(0.05));
+        self.set(
+            (1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0,
+            1.0 + alpha, -2.0 * cos, 1.0 - alpha,
+        );
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        let output = self.b0 * input + self.z1;
+        self.z1 = self.b1 * input - self.a1 * output + self.z2;
+        self.z2 = self.b2 * input - self.a2 * output;
+        output
+    }
+
+    pub fn reset(&mut self) {
+        self.z1 = 0.0;
+        self.z2 = 0.0;
+    }
+}
+
+/// 离线重采样（加窗 sinc 插值），用于 IR 这类一次性数据
+pub fn resample(input: &[f32], from_rate: f32, to_rate: f32) -> Vec<f32> {
+    if input.is_empty() || from_rate == to_rate {
+        return input.to_vec();
+    }
+
+    const HALF_TAPS: f64 = 32.0;
+    let ratio = to_rate as f64 / from_rate as f64;
+    // 降采样时降低截止频率，避免混叠
+    let cutoff = ratio.min(1.0);
+    let half_width = HALF_TAPS / cutoff;
+    let out_len = (input.len() as f64 * ratio).round() as usize;
+
+    (0..out_len)
+        .map(|n| {
+            let t = n as f64 / ratio;
+            let first = (t - half_width).ceil().max(0.0) as usize;
+            let last = ((t + half_width).floor() as usize).min(input.len() - 1);
+
+            let mut acc = 0.0f64;
+            for (k, &x) in input.iter().enumerate().take(last + 1).skip(first) {
+                let offset = t - k as f64;
+                let arg = std::f64::consts::PI * cutoff * offset;
+                let sinc = if arg.abs() < 1e-9 { 1.0 } else { arg.sin() / arg };
+                let window = 0.5 + 0.5 * (std::f64::consts::PI * offset / half_width).cos();
+                acc += x as f64 * cutoff * sinc * window;
+            }
+            acc as f32
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_db_conversion() {
+        assert!((db_to_gain(-6.0) - 0.501).abs() < 0.001);
+        assert!((gain_to_db(db_to_gain(12.0)) - 12.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_peaking_gain_at_center() {
+        // 1 kHz 正弦经过 +6 dB 峰值滤波后幅度约翻倍
+        let sample_rate = 48000.0;
+        let mut filter = Biquad::default();
+        filter.set_peaking(1000.0, 1.0, 6.0, sample_rate);
+
+        let mut peak = 0.0f32;
+        for i in 0..48000 {
+            let x = (2.0 * PI * 1000.0 * i as f32 / sample_rate).sin();
+            let y = filter.process(x);
+            if i > 24000 {
+                peak = peak.max(y.abs());
+            }
+        }
+        assert!((gain_to_db(peak) - 6.0).abs() < 0.2);
+    }
+
+    #[test]
+    fn test_resample_keeps_frequency() {
+        // 44.1 kHz 的 1 kHz 正弦重采样到 48 kHz 后，应与直接生成的 48 kHz 正弦一致
+        let input: Vec<f32> = (0..4410)
+            .map(|i| (2.0 * PI * 1000.0 * i as f32 / 44100.0).sin())
+            .collect();
+        let output = resample(&input, 44100.0, 48000.0);
+        assert_eq!(output.len(), 4800);
+
+        for (i, &sample) in output.iter().enumerate().take(4600).skip(200) {
+            let expected = (2.0 * PI * 1000.0 * i as f32 / 48000.0).sin();
+            assert!((sample - expected).abs() < 0.01, "采样 {} 偏差过大", i);
+        }
+    }
+}
@@ -0,0 +1,150 @@
+// 四段参量均衡：低架 + 两段峰值 + 高架
+
+use crate::audio::Smoother;
+use super::dsp::{smoother, Biquad};
+use super::{BuiltinEffect, ParamSpec};
+
+pub const PARAMS: &[ParamSpec] = &[
+    ParamSpec::log("Low Freq", 20.0, 500.0, 100.0, "Hz"),
+    ParamSpec::linear("Low Gain", -18.0, 18.0, 0.0, "dB"),
+    ParamSpec::log("Mid 1 Freq", 100.0, 5000.0, 500.0, "Hz"),
+    ParamSpec::linear("Mid 1 Gain", -18.0, 18.0, 0.0, "dB"),
+    ParamSpec::log("Mid 1 Q", 0.1, 10.0, 1.0, ""),
+    ParamSpec::log("Mid 2 Freq", 500.0, 15000.0, 2500.0, "Hz"),
+    ParamSpec::linear("Mid 2 Gain", -18.0, 18.0, 0.0, "dB"),
+    ParamSpec::log("Mid 2 Q", 0.1, 10.0, 1.0, ""),
+    ParamSpec::log("High Freq", 1000.0, 20000.0, 8000.0, "Hz"),
+    ParamSpec::linear("High Gain", -18.0, 18.0, 0.0, "dB"),
+];
+
+/// 增益平滑期间每隔多少帧重新计算一次滤波器系数
+const COEFFICIENT_INTERVAL: usize = 32;
+
+/// 每段的频率和 Q（增益单独平滑）
+#[derive(Debug, Clone, Copy)]
+struct Band {
+    freq: f32,
+    q: f32,
+}
+
+pub struct Equalizer {
+    sample_rate: f32,
+    bands: [Band; 4],
+    /// 每段实际使用的增益（dB，平滑）
+    gains: [Smoother; 4],
+    /// [段][声道]
+    filters: [[Biquad; 2]; 4],
+}
+
+impl Equalizer {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            sample_rate,
+            bands: [Band { freq: 1000.0, q: 1.0 }; 4],
+            gains: [smoother(sample_rate, 0.0); 4],
+            filters: [[Biquad::default(); 2]; 4],
+        }
+    }
+
+    fn update_band(&mut self, index: usize) {
+        let Band { freq, q } = self.bands[index];
+        let gain = self.gains[index].current();
+        for filter in &mut self.filters[index] {
+            match index {
+                0 => filter.set_low_shelf(freq, gain, self.sample_rate),
+                3 => filter.set_high_shelf(freq, gain, self.sample_rate),
+                _ => filter.set_peaking(freq, q, gain, self.sample_rate),
+            }
+        }
+    }
+}
+
+impl BuiltinEffect for Equalizer {
+    fn set_param(&mut self, id: u32, value: f32) {
+        // 参数编号 -> (段, 字段)
+        let (band, field) = match id {
+            0 => (0, 0),
+            1 => (0, 1),
+            2..=7 => (1 + (id as usize - 2) / 3, (id as usize - 2) % 3),
+            8 => (3, 0),
+            9 => (3, 1),
+            _ => return,
+        };
+        match field {
+            0 => self.bands[band].freq = value,
+            1 => {
+                // 增益在 process 中逐步过渡
+                self.gains[band].set_target(value);
+                return;
+            }
+            _ => self.bands[band].q = value,
+        }
+        self.update_band(band);
+    }
+
+    fn process(&mut self, buffer: &mut [f32]) {
+        for block in buffer.chunks_mut(COEFFICIENT_INTERVAL * 2) {
+            for band in 0..self.bands.len() {
+                if self.gains[band].is_smoothing() {
+                    for _ in 0..block.len() / 2 {
+                        self.gains[band].next_value();
+                    }
+                    self.update_band(band);
+                }
+            }
+
+            for frame in block.chunks_exact_mut(2) {
+                for (ch, sample) in frame.iter_mut().enumerate() {
+                    *sample = self.filters.iter_mut().fold(*sample, |x, band| band[ch].process(x));
+                }
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        for band in 0..self.bands.len() {
+            self.gains[band].finish();
+            self.update_band(band);
+        }
+        for filter in self.filters.iter_mut().flatten() {
+            filter.reset();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::dsp::gain_to_db;
+    use super::super::tests::{peak, sine};
+
+    #[test]
+    fn test_flat_eq_is_transparent() {
+        let mut eq = Equalizer::new(48000.0);
+        for (id, spec) in PARAMS.iter().enumerate() {
+            eq.set_param(id as u32, spec.default);
+        }
+
+        let input = sine(1000.0, 0.5, 4800);
+        let mut output = input.clone();
+        eq.process(&mut output);
+        for (a, b) in input.iter().zip(&output) {
+            assert!((a - b).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_mid_band_boost() {
+        let mut eq = Equalizer::new(48000.0);
+        for (id, spec) in PARAMS.iter().enumerate() {
+            eq.set_param(id as u32, spec.default);
+        }
+        eq.set_param(2, 1000.0);
+        eq.set_param(3, 12.0);
+
+        let mut buffer = sine(1000.0, 0.1, 9600);
+        eq.process(&mut buffer);
+        let boost = gain_to_db(peak(&buffer[9600..]) / 0.1);
+        assert!((boost - 12.0).abs() < 0.5, "增益 {}", boost);
+    }
+}
@@ -0,0 +1,99 @@
+// 增益 / 微调
+
+use crate::audio::Smoother;
+use super::dsp::{db_to_gain, smoother};
+use super::{BuiltinEffect, ParamSpec};
+
+pub const PARAMS: &[ParamSpec] = &[
+    ParamSpec::linear("Gain", -24.0, 24.0, 0.0, "dB"),
+    ParamSpec::linear("Balance", -100.0, 100.0, 0.0, "%"),
+    ParamSpec::toggle("Invert Phase", false),
+];
+
+pub struct Gain {
+    gain: f32,
+    balance: f32,
+    invert: bool,
+    /// 左右声道的最终增益（平滑）
+    left: Smoother,
+    right: Smoother,
+}
+
+impl Gain {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            gain: 1.0,
+            balance: 0.0,
+            invert: false,
+            left: smoother(sample_rate, 1.0),
+            right: smoother(sample_rate, 1.0),
+        }
+    }
+
+    fn update_targets(&mut self) {
+        let gain = if self.invert { -self.gain } else { self.gain };
+        // 平衡只衰减另一侧，居中时两侧都是单位增益
+        self.left.set_target(gain * (1.0 - self.balance.max(0.0)));
+        self.right.set_target(gain * (1.0 + self.balance.min(0.0)));
+    }
+}
+
+impl BuiltinEffect for Gain {
+    fn set_param(&mut self, id: u32, value: f32) {
+        match id {
+            0 => self.gain = db_to_gain(value),
+            1 => self.balance = value / 100.0,
+            2 => self.invert = value >= 0.5,
+            _ => {}
+        }
+        self.update_targets();
+    }
+
+    fn process(&mut self, buffer: &mut [f32]) {
+        for frame in buffer.chunks_exact_mut(2) {
+            frame[0] *= self.left.next_value();
+            frame[1] *= self.right.next_value();
+        }
+    }
+
+    fn reset(&mut self) {
+        self.left.finish();
+        self.right.finish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gain_and_balance() {
+        let mut gain = Gain::new(48000.0);
+        gain.set_param(0, -6.0);
+        gain.set_param(1, 100.0);
+        gain.set_param(2, 1.0);
+        // 跳过平滑
+        gain.reset();
+
+        let mut buffer = vec![1.0, 1.0];
+        gain.process(&mut buffer);
+        assert_eq!(buffer[0], 0.0);
+        assert!((buffer[1] + 0.501).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_gain_jump_is_smooth() {
+        let mut gain = Gain::new(48000.0);
+        let mut buffer = vec![1.0; 2000 * 2];
+        gain.set_param(0, -24.0);
+        gain.set_param(2, 1.0);
+        gain.process(&mut buffer);
+
+        // 1.0 -> -0.063 分摊在 20 ms（960 帧）内
+        let max_step = buffer.chunks(2).collect::<Vec<_>>().windows(2)
+            .map(|w| (w[1][0] - w[0][0]).abs())
+            .fold(0.0, f32::max);
+        assert!(max_step < 0.002, "最大跳变 {}", max_step);
+        assert!((buffer[1999 * 2] + 0.063).abs() < 0.001);
+    }
+}
@@ -0,0 +1,120 @@
+// 噪声门
+
+use super::dsp::{db_to_gain, time_coefficient};
+use super::{BuiltinEffect, ParamSpec};
+
+pub const PARAMS: &[ParamSpec] = &[
+    ParamSpec::linear("Threshold", -80.0, 0.0, -50.0, "dB"),
+    ParamSpec::log("Attack", 0.1, 50.0, 1.0, "ms"),
+    ParamSpec::linear("Hold", 0.0, 500.0, 50.0, "ms"),
+    ParamSpec::log("Release", 5.0, 1000.0, 100.0, "ms"),
+    ParamSpec::linear("Range", -80.0, 0.0, -80.0, "dB"),
+];
+
+/// 包络检测的释放时间
+const DETECTOR_RELEASE_MS: f32 = 10.0;
+
+pub struct Gate {
+    sample_rate: f32,
+    threshold: f32,
+    attack: f32,
+    hold_samples: usize,
+    release: f32,
+    floor: f32,
+    detector_release: f32,
+    envelope: f32,
+    hold_counter: usize,
+    gain: f32,
+}
+
+impl Gate {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            sample_rate,
+            threshold: 0.0,
+            attack: 0.0,
+            hold_samples: 0,
+            release: 0.0,
+            floor: 0.0,
+            detector_release: time_coefficient(DETECTOR_RELEASE_MS, sample_rate),
+            envelope: 0.0,
+            hold_counter: 0,
+            gain: 0.0,
+        }
+    }
+}
+
+impl BuiltinEffect for Gate {
+    fn set_param(&mut self, id: u32, value: f32) {
+        match id {
+            0 => self.threshold = db_to_gain(value),
+            1 => self.attack = time_coefficient(value, self.sample_rate),
+            2 => self.hold_samples = (value * 0.001 * self.sample_rate) as usize,
+            3 => self.release = time_coefficient(value, self.sample_rate),
+            4 => self.floor = db_to_gain(value),
+            _ => {}
+        }
+    }
+
+    fn process(&mut self, buffer: &mut [f32]) {
+        for frame in buffer.chunks_exact_mut(2) {
+            // 立体声联动的峰值包络
+            let level = frame[0].abs().max(frame[1].abs());
+            self.envelope = if level > self.envelope {
+                level
+            } else {
+                level + (self.envelope - level) * self.detector_release
+            };
+
+            let open = if self.envelope >= self.threshold {
+                self.hold_counter = self.hold_samples;
+                true
+            } else if self.hold_counter > 0 {
+                self.hold_counter -= 1;
+                true
+            } else {
+                false
+            };
+
+            let (target, coefficient) = if open {
+                (1.0, self.attack)
+            } else {
+                (self.floor, self.release)
+            };
+            self.gain = target + (self.gain - target) * coefficient;
+
+            frame[0] *= self.gain;
+            frame[1] *= self.gain;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.envelope = 0.0;
+        self.hold_counter = 0;
+        self.gain = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::tests::{peak, sine};
+
+    #[test]
+    fn test_gate_opens_and_closes() {
+        let mut gate = Gate::new(48000.0);
+        for (id, spec) in PARAMS.iter().enumerate() {
+            gate.set_param(id as u32, spec.default);
+        }
+
+        // 低于阈值的噪声被压掉
+        let mut quiet = sine(440.0, 0.001, 4800);
+        gate.process(&mut quiet);
+        assert!(peak(&quiet[4800..]) < 1e-5);
+
+        // 高于阈值的信号通过
+        let mut loud = sine(440.0, 0.5, 4800);
+        gate.process(&mut loud);
+        assert!(peak(&loud[4800..]) > 0.49);
+    }
+}
@@ -0,0 +1,637 @@
+// 内置效果器
+// 不需要外部二进制，直接以 AudioProcessor 的形式提供常用效果
+
+mod dsp;
+mod gain;
+mod gate;
+mod compressor;
+mod overdrive;
+mod equalizer;
+mod delay;
+mod reverb;
+mod convolver;
+mod cabinet;
+mod tuner;
+
+use anyhow::Result;
+use base64::Engine as _;
+use log::{info, warn};
+use std::path::{Path, PathBuf};
+
+use crate::audio::{AudioBuffer, TunerReadout};
+
+use super::types::{PluginMetadata, AudioProcessor, PluginParameter, PluginState, PluginFormat, ChannelLayout, BusLayout, ProcessConfig, ParameterDescriptor};
+
+/// 内置效果器的采样率
+const DEFAULT_SAMPLE_RATE: f32 = 48000.0;
+
+/// 参数的取值方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParamScale {
+    /// 线性
+    Linear,
+    /// 对数（频率、时间等）
+    Log,
+    /// 开关
+    Toggle,
+}
+
+/// 内置效果器参数定义
+#[derive(Debug, Clone, Copy)]
+pub struct ParamSpec {
+    pub name: &'static str,
+    pub min: f32,
+    pub max: f32,
+    pub default: f32,
+    pub unit: &'static str,
+    pub scale: ParamScale,
+}
+
+impl ParamSpec {
+    pub const fn linear(name: &'static str, min: f32, max: f32, default: f32, unit: &'static str) -> Self {
+        Self { name, min, max, default, unit, scale: ParamScale::Linear }
+    }
+
+    pub const fn log(name: &'static str, min: f32, max: f32, default: f32, unit: &'static str) -> Self {
+        Self { name, min, max, default, unit, scale: ParamScale::Log }
+    }
+
+    pub const fn toggle(name: &'static str, default: bool) -> Self {
+        let default = if default { 1.0 } else { 0.0 };
+        Self { name, min: 0.0, max: 1.0, default, unit: "", scale: ParamScale::Toggle }
+    }
+
+    /// 归一化值 (0.0 - 1.0) -> 实际值
+    pub fn to_plain(self, normalized: f64) -> f32 {
+        let n = normalized.clamp(0.0, 1.0) as f32;
+        match self.scale {
+            ParamScale::Linear => self.min + (self.max - self.min) * n,
+            ParamScale::Log => self.min * (self.max / self.min).powf(n),
+            ParamScale::Toggle => if n >= 0.5 { 1.0 } else { 0.0 },
+        }
+    }
+
+    /// 实际值 -> 归一化值
+    pub fn to_normalized(self, plain: f32) -> f64 {
+        let plain = plain.clamp(self.min, self.max);
+        let n = match self.scale {
+            ParamScale::Linear => (plain - self.min) / (self.max - self.min),
+            ParamScale::Log => (plain / self.min).ln() / (self.max / self.min).ln(),
+            ParamScale::Toggle => if plain >= 0.5 { 1.0 } else { 0.0 },
+        };
+        n as f64
+    }
+
+    /// 显示文本
+    pub fn display(&self, plain: f32) -> String {
+        if self.scale == ParamScale::Toggle {
+            return if plain >= 0.5 { "On" } else { "Off" }.to_string();
+        }
+        let text = if plain.abs() >= 100.0 {
+            format!("{:.0}", plain)
+        } else if plain.abs() >= 10.0 {
+            format!("{:.1}", plain)
+        } else {
+            format!("{:.2}", plain)
+        };
+        if self.unit.is_empty() {
+            text
+        } else {
+            format!("{} {}", text, self.unit)
+        }
+    }
+
+    /// 转换为通用的参数描述
+    pub fn descriptor(&self, id: u32) -> ParameterDescriptor {
+        if self.scale == ParamScale::Toggle {
+            return ParameterDescriptor::toggle(id, self.name, self.default >= 0.5);
+        }
+        ParameterDescriptor::new(id, self.name, self.min as f64, self.max as f64, self.default as f64)
+            .with_unit(self.unit)
+            .with_logarithmic(self.scale == ParamScale::Log)
+    }
+}
+
+/// 内置效果器的 DSP 部分
+/// 参数以实际值传入，编号即在参数表中的下标
+pub trait BuiltinEffect: Send {
+    fn set_param(&mut self, id: u32, value: f32);
+
+    /// 处理交错立体声缓冲区
+    fn process(&mut self, buffer: &mut [f32]);
+
+    /// 清空延迟线、包络等处理状态，参数保持不变
+    fn reset(&mut self);
+
+    /// 当前使用的外部文件（例如箱体 IR）
+    fn file(&self) -> Option<&Path> {
+        None
+    }
+
+    /// 加载外部文件
+    fn load_file(&mut self, _path: &Path) -> Result<()> {
+        Err(anyhow::anyhow!("该效果器不使用外部文件"))
+    }
+
+    /// 调音器读数（只有调音器提供）
+    fn tuner(&self) -> Option<TunerReadout> {
+        None
+    }
+
+    /// 处理延迟（帧）
+    fn latency(&self) -> u32 {
+        0
+    }
+}
+
+/// 内置效果器种类
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuiltinKind {
+    Gain,
+    Gate,
+    Compressor,
+    Overdrive,
+    Equalizer,
+    Delay,
+    Reverb,
+    Cabinet,
+    Tuner,
+}
+
+impl BuiltinKind {
+    pub const ALL: [BuiltinKind; 9] = [
+        BuiltinKind::Gain,
+        BuiltinKind::Gate,
+        BuiltinKind::Compressor,
+        BuiltinKind::Overdrive,
+        BuiltinKind::Equalizer,
+        BuiltinKind::Delay,
+        BuiltinKind::Reverb,
+        BuiltinKind::Cabinet,
+        BuiltinKind::Tuner,
+    ];
+
+    /// 插件 ID 中的名字部分
+    pub fn key(&self) -> &'static str {
+        match self {
+            BuiltinKind::Gain => "gain",
+            BuiltinKind::Gate => "gate",
+            BuiltinKind::Compressor => "compressor",
+            BuiltinKind::Overdrive => "overdrive",
+            BuiltinKind::Equalizer => "eq",
+            BuiltinKind::Delay => "delay",
+            BuiltinKind::Reverb => "reverb",
+            BuiltinKind::Cabinet => "cabinet",
+            BuiltinKind::Tuner => "tuner",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BuiltinKind::Gain => "Gain",
+            BuiltinKind::Gate => "Noise Gate",
+            BuiltinKind::Compressor => "Compressor",
+            BuiltinKind::Overdrive => "Overdrive",
+            BuiltinKind::Equalizer => "Parametric EQ",
+            BuiltinKind::Delay => "Delay",
+            BuiltinKind::Reverb => "Reverb",
+            BuiltinKind::Cabinet => "Cabinet IR",
+            BuiltinKind::Tuner => "Tuner",
+        }
+    }
+
+    pub fn id(&self) -> String {
+        format!("builtin:{}", self.key())
+    }
+
+    pub fn params(&self) -> &'static [ParamSpec] {
+        match self {
+            BuiltinKind::Gain => gain::PARAMS,
+            BuiltinKind::Gate => gate::PARAMS,
+            BuiltinKind::Compressor => compressor::PARAMS,
+            BuiltinKind::Overdrive => overdrive::PARAMS,
+            BuiltinKind::Equalizer => equalizer::PARAMS,
+            BuiltinKind::Delay => delay::PARAMS,
+            BuiltinKind::Reverb => reverb::PARAMS,
+            BuiltinKind::Cabinet => cabinet::PARAMS,
+            BuiltinKind::Tuner => tuner::PARAMS,
+        }
+    }
+
+    /// 根据插件 ID 查找（接受带或不带 "builtin:" 前缀）
+    pub fn from_id(id: &str) -> Option<Self> {
+        let key = id.strip_prefix("builtin:").unwrap_or(id);
+        Self::ALL.into_iter().find(|kind| kind.key() == key)
+    }
+
+    /// readout: 调音器沿用的读数句柄
+    fn create(&self, sample_rate: f32, readout: Option<TunerReadout>) -> Box<dyn BuiltinEffect> {
+        match self {
+            BuiltinKind::Gain => Box::new(gain::Gain::new(sample_rate)),
+            BuiltinKind::Gate => Box::new(gate::Gate::new(sample_rate)),
+            BuiltinKind::Compressor => Box::new(compressor::Compressor::new(sample_rate)),
+            BuiltinKind::Overdrive => Box::new(overdrive::Overdrive::new(sample_rate)),
+            BuiltinKind::Equalizer => Box::new(equalizer::Equalizer::new(sample_rate)),
+            BuiltinKind::Delay => Box::new(delay::Delay::new(sample_rate)),
+            BuiltinKind::Reverb => Box::new(reverb::Reverb::new(sample_rate)),
+            BuiltinKind::Cabinet => Box::new(cabinet::Cabinet::new(sample_rate)),
+            BuiltinKind::Tuner => Box::new(tuner::TunerEffect::with_readout(sample_rate, readout.unwrap_or_default())),
+        }
+    }
+
+    pub fn metadata(&self) -> PluginMetadata {
+        PluginMetadata {
+            id: self.id(),
+            name: self.name().to_string(),
+            vendor: "Plugin Loader".to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            path: PathBuf::new(),
+            format: PluginFormat::Builtin,
+            num_inputs: 2,
+            num_outputs: 2,
+        }
+    }
+}
+
+/// 所有内置效果器的元数据（供扫描器列出）
+pub fn builtin_plugins() -> Vec<PluginMetadata> {
+    BuiltinKind::ALL.iter().map(BuiltinKind::metadata).collect()
+}
+
+/// 内置效果器包装器
+pub struct BuiltinPlugin {
+    kind: BuiltinKind,
+    metadata: PluginMetadata,
+    /// 归一化参数值
+    values: Vec<f64>,
+    effect: Box<dyn BuiltinEffect>,
+    sample_rate: f32,
+    /// release 之后到下次 prepare 之前直通
+    active: bool,
+    /// 效果器处理交错立体声，平面缓冲区在这里转换（max_block_size * 2）
+    scratch: Vec<f32>,
+}
+
+impl BuiltinPlugin {
+    pub fn new(kind: BuiltinKind) -> Self {
+        let specs = kind.params();
+        let mut effect = kind.create(DEFAULT_SAMPLE_RATE, None);
+        for (id, spec) in specs.iter().enumerate() {
+            effect.set_param(id as u32, spec.default);
+        }
+        // 初始参数不需要平滑过渡
+        effect.reset();
+
+        Self {
+            kind,
+            metadata: kind.metadata(),
+            values: specs.iter().map(|spec| spec.to_normalized(spec.default)).collect(),
+            effect,
+            sample_rate: DEFAULT_SAMPLE_RATE,
+            active: true,
+            scratch: vec![0.0; ProcessConfig::default().max_block_size * 2],
+        }
+    }
+
+    /// 以新采样率重建效果器，重新应用参数并重新加载外部文件
+    fn rebuild(&mut self, sample_rate: f32) {
+        let file = self.file().map(Path::to_path_buf);
+        let mut effect = self.kind.create(sample_rate, self.effect.tuner());
+        for (id, (spec, &value)) in self.kind.params().iter().zip(&self.values).enumerate() {
+            effect.set_param(id as u32, spec.to_plain(value));
+        }
+        if let Some(path) = file {
+            if let Err(e) = effect.load_file(&path) {
+                warn!("内置效果器重新加载文件失败 {:?}: {}", path, e);
+            }
+        }
+        effect.reset();
+
+        self.effect = effect;
+        self.sample_rate = sample_rate;
+    }
+
+    /// 从扫描信息创建
+    pub fn from_metadata(metadata: &PluginMetadata) -> Result<Self> {
+        let kind = BuiltinKind::from_id(&metadata.id)
+            .ok_or_else(|| anyhow::anyhow!("未知的内置效果器: {}", metadata.id))?;
+        info!("创建内置效果器: {}", kind.name());
+        Ok(Self::new(kind))
+    }
+
+    /// 加载效果器使用的外部文件（例如箱体 IR 的 WAV）
+    pub fn load_file(&mut self, path: &Path) -> Result<()> {
+        self.effect.load_file(path)
+    }
+
+    pub fn file(&self) -> Option<&Path> {
+        self.effect.file()
+    }
+
+    /// 调音器的读数句柄（界面显示的是音频引擎的调音器，这里只供测试读取）
+    #[cfg(test)]
+    pub fn tuner_readout(&self) -> Option<TunerReadout> {
+        self.effect.tuner()
+    }
+}
+
+impl AudioProcessor for BuiltinPlugin {
+    fn prepare(&mut self, sample_rate: f64, max_block_size: usize, _layout: ChannelLayout) {
+        // 效果器逐帧处理，块大小只决定转换缓冲区的大小
+        if sample_rate as f32 != self.sample_rate {
+            self.rebuild(sample_rate as f32);
+        } else {
+            self.effect.reset();
+        }
+        self.scratch.resize(max_block_size.max(1) * 2, 0.0);
+        self.active = true;
+    }
+
+    fn reset(&mut self) {
+        self.effect.reset();
+    }
+
+    fn release(&mut self) {
+        self.active = false;
+    }
+
+    fn bus_layout(&self, upstream: ChannelLayout) -> BusLayout {
+        // 效果器逐声道处理，跟随上游布局；延迟（乒乓）和混响把单声道展开成立体声
+        match self.kind {
+            BuiltinKind::Delay | BuiltinKind::Reverb => BusLayout::new(upstream, ChannelLayout::Stereo),
+            _ => BusLayout::uniform(upstream),
+        }
+    }
+
+    fn latency_samples(&self) -> u32 {
+        self.effect.latency()
+    }
+
+    fn process(&mut self, buffer: &mut AudioBuffer) {
+        if !self.active {
+            return;
+        }
+        let block_size = self.scratch.len() / 2;
+        for start in (0..buffer.frames()).step_by(block_size) {
+            let mut block = buffer.slice(start, start + block_size);
+            let scratch = &mut self.scratch[..block.frames() * 2];
+            block.copy_to_interleaved(scratch, 2);
+            self.effect.process(scratch);
+            block.copy_from_interleaved(scratch, 2);
+        }
+    }
+
+    fn get_info(&self) -> &PluginMetadata {
+        &self.metadata
+    }
+
+    fn set_parameter(&mut self, id: u32, value: f64) {
+        let Some(spec) = self.kind.params().get(id as usize) else {
+            return;
+        };
+        let value = value.clamp(0.0, 1.0);
+        self.values[id as usize] = value;
+        self.effect.set_param(id, spec.to_plain(value));
+    }
+
+    fn get_parameter(&self, id: u32) -> Option<f64> {
+        self.values.get(id as usize).copied()
+    }
+
+    fn parameter_descriptors(&self) -> Vec<ParameterDescriptor> {
+        self.kind.params()
+            .iter()
+            .enumerate()
+            .map(|(id, spec)| spec.descriptor(id as u32))
+            .collect()
+    }
+
+    fn get_all_parameters(&self) -> Vec<PluginParameter> {
+        self.kind.params()
+            .iter()
+            .zip(&self.values)
+            .enumerate()
+            .map(|(id, (spec, &value))| PluginParameter {
+                id: id as u32,
+                name: spec.name.to_string(),
+                value,
+                display: spec.display(spec.to_plain(value)),
+            })
+            .collect()
+    }
+
+    fn save_state(&self) -> PluginState {
+        // 除参数外只需要记住外部文件的路径
+        let state_data = self.file()
+            .map(|path| base64::engine::general_purpose::STANDARD.encode(path.to_string_lossy().as_bytes()))
+            .unwrap_or_default();
+
+        PluginState {
+            plugin_id: self.metadata.id.clone(),
+            parameters: self.get_all_parameters(),
+            state_data,
+        }
+    }
+
+    fn load_state(&mut self, state: &PluginState) {
+        for param in &state.parameters {
+            self.set_parameter(param.id, param.value);
+        }
+
+        if state.state_data.is_empty() {
+            return;
+        }
+        let path = base64::engine::general_purpose::STANDARD.decode(&state.state_data)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok());
+        match path {
+            Some(path) => {
+                if let Err(e) = self.load_file(Path::new(&path)) {
+                    warn!("内置效果器加载文件失败 {}: {}", path, e);
+                }
+            }
+            None => warn!("内置效果器状态数据无效: {}", self.metadata.name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::with_interleaved;
+    use std::f32::consts::PI;
+
+    /// 生成交错立体声正弦
+    pub(super) fn sine(freq: f32, amplitude: f32, frames: usize) -> Vec<f32> {
+        (0..frames)
+            .flat_map(|i| {
+                let s = amplitude * (2.0 * PI * freq * i as f32 / DEFAULT_SAMPLE_RATE).sin();
+                [s, s]
+            })
+            .collect()
+    }
+
+    pub(super) fn peak(buffer: &[f32]) -> f32 {
+        buffer.iter().fold(0.0f32, |acc, s| acc.max(s.abs()))
+    }
+
+    #[test]
+    fn test_builtin_registry() {
+        let plugins = builtin_plugins();
+        assert_eq!(plugins.len(), BuiltinKind::ALL.len());
+        assert!(plugins.iter().all(|p| p.format == PluginFormat::Builtin && p.id.starts_with("builtin:")));
+
+        assert_eq!(BuiltinKind::from_id("builtin:reverb"), Some(BuiltinKind::Reverb));
+        assert_eq!(BuiltinKind::from_id("eq"), Some(BuiltinKind::Equalizer));
+        assert_eq!(BuiltinKind::from_id("builtin:flanger"), None);
+    }
+
+    /// 最大二阶差分：平滑信号很小，阶跃（咔哒声）时等于跳变量
+    fn max_discontinuity(buffer: &[f32]) -> f32 {
+        let left: Vec<f32> = buffer.iter().step_by(2).copied().collect();
+        left.windows(3)
+            .map(|w| (w[2] - 2.0 * w[1] + w[0]).abs())
+            .fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn test_parameter_jumps_are_smoothed() {
+        // (效果器, 参数, 跳变前后的归一化值)
+        let cases = [
+            (BuiltinKind::Gain, 0, 0.0, 1.0),
+            (BuiltinKind::Gain, 2, 0.0, 1.0),
+            (BuiltinKind::Compressor, 5, 1.0, 0.0),
+            (BuiltinKind::Overdrive, 2, 0.0, 1.0),
+            (BuiltinKind::Equalizer, 3, 0.0, 1.0),
+            (BuiltinKind::Delay, 2, 0.0, 1.0),
+            (BuiltinKind::Reverb, 3, 0.0, 1.0),
+        ];
+
+        for (kind, id, from, to) in cases {
+            let mut plugin = BuiltinPlugin::new(kind);
+            plugin.set_parameter(id, from);
+            plugin.reset();
+
+            // 在正弦波峰处跳变，不平滑时二阶差分接近跳变量
+            let input = sine(50.0, 0.25, 9600);
+            let (before, after) = input.split_at(5040 * 2);
+            let mut before = before.to_vec();
+            let mut after = after.to_vec();
+            with_interleaved(&mut before, 2, |buffer| plugin.process(buffer));
+            plugin.set_parameter(id, to);
+            with_interleaved(&mut after, 2, |buffer| plugin.process(buffer));
+
+            let mut output = before[before.len() - 4..].to_vec();
+            output.extend_from_slice(&after);
+            let jump = max_discontinuity(&output);
+            assert!(jump < 0.01, "{} 参数 {} 跳变 {}", kind.name(), id, jump);
+        }
+    }
+
+    #[test]
+    fn test_param_spec_roundtrip() {
+        let spec = ParamSpec::log("Freq", 20.0, 20000.0, 1000.0, "Hz");
+        let n = spec.to_normalized(1000.0);
+        assert!((spec.to_plain(n) - 1000.0).abs() < 0.01);
+        assert_eq!(spec.display(1000.0), "1000 Hz");
+
+        let toggle = ParamSpec::toggle("Invert", false);
+        assert_eq!(toggle.to_plain(0.7), 1.0);
+        assert_eq!(toggle.display(0.0), "Off");
+    }
+
+    #[test]
+    fn test_descriptors_match_param_specs() {
+        for kind in BuiltinKind::ALL {
+            let plugin = BuiltinPlugin::new(kind);
+            let descriptors = plugin.parameter_descriptors();
+            assert_eq!(descriptors.len(), kind.params().len());
+
+            for (descriptor, param) in descriptors.iter().zip(plugin.get_all_parameters()) {
+                assert_eq!(descriptor.id, param.id);
+                assert!((descriptor.default_normalized() - param.value).abs() < 1e-6, "{}", descriptor.name);
+                assert_eq!(descriptor.value_to_text(param.value), param.display);
+            }
+        }
+
+        let plugin = BuiltinPlugin::new(BuiltinKind::Delay);
+        assert_eq!(plugin.text_to_value(4, "On"), Some(1.0));
+        let time = plugin.text_to_value(0, "250 ms").unwrap();
+        assert_eq!(plugin.value_to_text(0, time).unwrap(), "250 ms");
+    }
+
+    #[test]
+    fn test_all_effects_stay_finite() {
+        // 满量程输入、所有参数拉到最大，输出不能出现 NaN / Inf
+        for kind in BuiltinKind::ALL {
+            let mut plugin = BuiltinPlugin::new(kind);
+            for id in 0..kind.params().len() as u32 {
+                plugin.set_parameter(id, 1.0);
+            }
+            let mut buffer = sine(220.0, 1.0, 4800);
+            with_interleaved(&mut buffer, 2, |buffer| plugin.process(buffer));
+            assert!(buffer.iter().all(|s| s.is_finite()), "{} 输出无效", kind.name());
+        }
+    }
+
+    #[test]
+    fn test_reset_clears_delay_tail() {
+        let mut plugin = BuiltinPlugin::new(BuiltinKind::Delay);
+        with_interleaved(&mut sine(440.0, 1.0, 4800), 2, |buffer| plugin.process(buffer));
+
+        plugin.reset();
+        let mut silence = vec![0.0; 48000 * 2];
+        with_interleaved(&mut silence, 2, |buffer| plugin.process(buffer));
+        assert_eq!(peak(&silence), 0.0);
+    }
+
+    #[test]
+    fn test_prepare_rebuilds_at_new_rate() {
+        let mut plugin = BuiltinPlugin::new(BuiltinKind::Tuner);
+        let readout = plugin.tuner_readout().unwrap();
+        plugin.set_parameter(1, 1.0);
+
+        plugin.prepare(96000.0, 256, ChannelLayout::Stereo);
+        assert_eq!(plugin.sample_rate, 96000.0);
+        assert_eq!(plugin.get_parameter(1), Some(1.0));
+        // UI 之前取出的读数句柄依然有效
+        assert_eq!(readout.reference(), 450.0);
+
+        plugin.release();
+        let mut buffer = vec![0.5; 8];
+        with_interleaved(&mut buffer, 2, |buffer| plugin.process(buffer));
+        assert_eq!(buffer, vec![0.5; 8]);
+    }
+
+    #[test]
+    fn test_builtin_state_roundtrip() {
+        let mut plugin = BuiltinPlugin::new(BuiltinKind::Compressor);
+        plugin.set_parameter(0, 0.25);
+        plugin.set_parameter(1, 0.8);
+        let state = plugin.save_state();
+        assert_eq!(state.plugin_id, "builtin:compressor");
+
+        let mut restored = BuiltinPlugin::from_metadata(&BuiltinKind::Compressor.metadata()).unwrap();
+        restored.load_state(&state);
+        assert_eq!(restored.get_parameter(0), Some(0.25));
+        assert_eq!(restored.get_parameter(1), Some(0.8));
+    }
+
+    #[test]
+    fn test_file_path_in_state() {
+        let plugin = BuiltinPlugin::new(BuiltinKind::Cabinet);
+        assert!(plugin.file().is_none());
+        assert!(plugin.save_state().state_data.is_empty());
+
+        // 状态中的 IR 文件不存在时只记录警告，参数照常恢复
+        let mut state = plugin.save_state();
+        state.parameters[0].value = 0.5;
+        state.state_data = base64::engine::general_purpose::STANDARD.encode("/nonexistent/cab.wav");
+        let mut restored = BuiltinPlugin::new(BuiltinKind::Cabinet);
+        restored.load_state(&state);
+        assert_eq!(restored.get_parameter(0), Some(0.5));
+        assert!(restored.file().is_none());
+
+        assert!(BuiltinPlugin::new(BuiltinKind::Gain).load_file(Path::new("x.wav")).is_err());
+    }
+}
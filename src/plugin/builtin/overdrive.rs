@@ -0,0 +1,97 @@
+// 过载失真：输入高通 -> tanh 软削波 -> 音色低通 -> 输出电平
+
+use crate::audio::Smoother;
+use super::dsp::{db_to_gain, smoother, Biquad, OnePole};
+use super::{BuiltinEffect, ParamSpec};
+
+pub const PARAMS: &[ParamSpec] = &[
+    ParamSpec::linear("Drive", 0.0, 40.0, 12.0, "dB"),
+    ParamSpec::log("Tone", 500.0, 10000.0, 3000.0, "Hz"),
+    ParamSpec::linear("Level", -24.0, 6.0, -6.0, "dB"),
+];
+
+/// 削波前去掉低频，避免失真发闷
+const INPUT_HIGHPASS_HZ: f32 = 80.0;
+
+pub struct Overdrive {
+    sample_rate: f32,
+    drive: Smoother,
+    level: Smoother,
+    highpass: [Biquad; 2],
+    tone: [OnePole; 2],
+}
+
+impl Overdrive {
+    pub fn new(sample_rate: f32) -> Self {
+        let mut highpass = [Biquad::default(); 2];
+        for filter in &mut highpass {
+            filter.set_highpass(INPUT_HIGHPASS_HZ, std::f32::consts::FRAC_1_SQRT_2, sample_rate);
+        }
+
+        Self {
+            sample_rate,
+            drive: smoother(sample_rate, 1.0),
+            level: smoother(sample_rate, 1.0),
+            highpass,
+            tone: [OnePole::default(); 2],
+        }
+    }
+}
+
+impl BuiltinEffect for Overdrive {
+    fn set_param(&mut self, id: u32, value: f32) {
+        match id {
+            0 => self.drive.set_target(db_to_gain(value)),
+            1 => {
+                for filter in &mut self.tone {
+                    filter.set_cutoff(value, self.sample_rate);
+                }
+            }
+            2 => self.level.set_target(db_to_gain(value)),
+            _ => {}
+        }
+    }
+
+    fn process(&mut self, buffer: &mut [f32]) {
+        for frame in buffer.chunks_exact_mut(2) {
+            let drive = self.drive.next_value();
+            let level = self.level.next_value();
+            for (ch, sample) in frame.iter_mut().enumerate() {
+                let x = self.highpass[ch].process(*sample) * drive;
+                let shaped = x.tanh();
+                *sample = self.tone[ch].process(shaped) * level;
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.drive.finish();
+        self.level.finish();
+        for filter in &mut self.highpass {
+            filter.reset();
+        }
+        for filter in &mut self.tone {
+            filter.reset();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::tests::{peak, sine};
+
+    #[test]
+    fn test_overdrive_is_bounded() {
+        let mut drive = Overdrive::new(48000.0);
+        drive.set_param(0, 40.0);
+        drive.set_param(1, 10000.0);
+        drive.set_param(2, 0.0);
+
+        let mut buffer = sine(220.0, 1.0, 4800);
+        drive.process(&mut buffer);
+        // tanh 削波后不会超过满量程
+        assert!(peak(&buffer) <= 1.0);
+        assert!(peak(&buffer) > 0.8);
+    }
+}
@@ -0,0 +1,172 @@
+// 混响（Freeverb 结构：8 个并联梳状滤波 + 4 个串联全通）
+
+use crate::audio::Smoother;
+use super::dsp::smoother;
+use super::{BuiltinEffect, ParamSpec};
+
+pub const PARAMS: &[ParamSpec] = &[
+    ParamSpec::linear("Room Size", 0.0, 100.0, 50.0, "%"),
+    ParamSpec::linear("Damping", 0.0, 100.0, 50.0, "%"),
+    ParamSpec::linear("Width", 0.0, 100.0, 100.0, "%"),
+    ParamSpec::linear("Mix", 0.0, 100.0, 25.0, "%"),
+];
+
+/// 44.1 kHz 下的延迟长度（采样）
+const COMB_TUNING: [usize; 8] = [1116, 1188, 1277, 1356, 1422, 1491, 1557, 1617];
+const ALLPASS_TUNING: [usize; 4] = [556, 441, 341, 225];
+/// 右声道的额外延迟，制造立体声
+const STEREO_SPREAD: usize = 23;
+
+const FIXED_GAIN: f32 = 0.015;
+const ALLPASS_FEEDBACK: f32 = 0.5;
+
+struct Comb {
+    buffer: Vec<f32>,
+    pos: usize,
+    store: f32,
+}
+
+impl Comb {
+    fn new(len: usize) -> Self {
+        Self { buffer: vec![0.0; len.max(1)], pos: 0, store: 0.0 }
+    }
+
+    fn process(&mut self, input: f32, feedback: f32, damp: f32) -> f32 {
+        let output = self.buffer[self.pos];
+        self.store = output * (1.0 - damp) + self.store * damp;
+        self.buffer[self.pos] = input + self.store * feedback;
+        self.pos = (self.pos + 1) % self.buffer.len();
+        output
+    }
+}
+
+struct Allpass {
+    buffer: Vec<f32>,
+    pos: usize,
+}
+
+impl Allpass {
+    fn new(len: usize) -> Self {
+        Self { buffer: vec![0.0; len.max(1)], pos: 0 }
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        let delayed = self.buffer[self.pos];
+        self.buffer[self.pos] = input + delayed * ALLPASS_FEEDBACK;
+        self.pos = (self.pos + 1) % self.buffer.len();
+        delayed - input
+    }
+}
+
+pub struct Reverb {
+    /// [声道]
+    combs: [Vec<Comb>; 2],
+    allpasses: [Vec<Allpass>; 2],
+    feedback: f32,
+    damp: f32,
+    width: Smoother,
+    mix: Smoother,
+}
+
+impl Reverb {
+    pub fn new(sample_rate: f32) -> Self {
+        let scale = |len: usize| (len as f32 * sample_rate / 44100.0) as usize;
+        let channel = |spread: usize| {
+            (
+                COMB_TUNING.iter().map(|&len| Comb::new(scale(len + spread))).collect(),
+                ALLPASS_TUNING.iter().map(|&len| Allpass::new(scale(len + spread))).collect(),
+            )
+        };
+        let (combs_l, allpasses_l) = channel(0);
+        let (combs_r, allpasses_r) = channel(STEREO_SPREAD);
+
+        Self {
+            combs: [combs_l, combs_r],
+            allpasses: [allpasses_l, allpasses_r],
+            feedback: 0.0,
+            damp: 0.0,
+            width: smoother(sample_rate, 1.0),
+            mix: smoother(sample_rate, 0.0),
+        }
+    }
+}
+
+impl BuiltinEffect for Reverb {
+    fn set_param(&mut self, id: u32, value: f32) {
+        let value = value / 100.0;
+        match id {
+            // Freeverb 的房间大小映射到 0.7 - 0.98 的梳状反馈
+            0 => self.feedback = 0.7 + value * 0.28,
+            1 => self.damp = value * 0.4,
+            2 => self.width.set_target(value),
+            3 => self.mix.set_target(value),
+            _ => {}
+        }
+    }
+
+    fn process(&mut self, buffer: &mut [f32]) {
+        for frame in buffer.chunks_exact_mut(2) {
+            let width = self.width.next_value();
+            let mix = self.mix.next_value();
+            let wet1 = width / 2.0 + 0.5;
+            let wet2 = (1.0 - width) / 2.0;
+
+            let input = (frame[0] + frame[1]) * FIXED_GAIN;
+            let mut out = [0.0f32; 2];
+
+            for (ch, value) in out.iter_mut().enumerate() {
+                let mut sum: f32 = self.combs[ch]
+                    .iter_mut()
+                    .map(|comb| comb.process(input, self.feedback, self.damp))
+                    .sum();
+                for allpass in &mut self.allpasses[ch] {
+                    sum = allpass.process(sum);
+                }
+                *value = sum;
+            }
+
+            let wet_l = out[0] * wet1 + out[1] * wet2;
+            let wet_r = out[1] * wet1 + out[0] * wet2;
+            frame[0] = frame[0] * (1.0 - mix) + wet_l * mix;
+            frame[1] = frame[1] * (1.0 - mix) + wet_r * mix;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.width.finish();
+        self.mix.finish();
+        for comb in self.combs.iter_mut().flatten() {
+            comb.buffer.fill(0.0);
+            comb.store = 0.0;
+        }
+        for allpass in self.allpasses.iter_mut().flatten() {
+            allpass.buffer.fill(0.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::tests::peak;
+
+    #[test]
+    fn test_reverb_tail() {
+        let mut reverb = Reverb::new(48000.0);
+        for (id, spec) in PARAMS.iter().enumerate() {
+            reverb.set_param(id as u32, spec.default);
+        }
+        reverb.set_param(3, 100.0);
+
+        let mut buffer = vec![0.0; 48000 * 2];
+        buffer[0] = 1.0;
+        buffer[1] = 1.0;
+        reverb.process(&mut buffer);
+
+        // 冲激之后应当有持续的尾音，并且逐渐衰减
+        let early = peak(&buffer[4800 * 2..9600 * 2]);
+        let late = peak(&buffer[40000 * 2..]);
+        assert!(early > 1e-3);
+        assert!(late < early);
+    }
+}
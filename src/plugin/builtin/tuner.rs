@@ -0,0 +1,76 @@
+// 调音器：分析输入音高，可选静音输出
+
+use crate::audio::{Tuner, TunerReadout};
+
+use super::{BuiltinEffect, ParamSpec};
+
+pub const PARAMS: &[ParamSpec] = &[
+    ParamSpec::toggle("Mute", false),
+    ParamSpec::linear("Reference", 430.0, 450.0, 440.0, "Hz"),
+];
+
+pub struct TunerEffect {
+    tuner: Tuner,
+    mute: bool,
+}
+
+impl TunerEffect {
+    #[cfg(test)]
+    pub fn new(sample_rate: f32) -> Self {
+        Self::with_readout(sample_rate, TunerReadout::new())
+    }
+
+    /// 沿用已有的读数句柄（采样率变化重建时 UI 持有的句柄仍然有效）
+    pub fn with_readout(sample_rate: f32, readout: TunerReadout) -> Self {
+        Self {
+            tuner: Tuner::with_readout(sample_rate, readout),
+            mute: false,
+        }
+    }
+}
+
+impl BuiltinEffect for TunerEffect {
+    fn set_param(&mut self, id: u32, value: f32) {
+        match id {
+            0 => self.mute = value >= 0.5,
+            1 => self.tuner.readout().set_reference(value),
+            _ => {}
+        }
+    }
+
+    fn process(&mut self, buffer: &mut [f32]) {
+        self.tuner.process_buffer(buffer, 2);
+        if self.mute {
+            buffer.fill(0.0);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.tuner.reset();
+    }
+
+    fn tuner(&self) -> Option<TunerReadout> {
+        Some(self.tuner.readout())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::tests::{peak, sine};
+
+    #[test]
+    fn test_tuner_mute() {
+        let mut effect = TunerEffect::new(48000.0);
+        effect.set_param(1, 440.0);
+
+        let mut buffer = sine(440.0, 0.5, 24000);
+        effect.process(&mut buffer);
+        assert!(peak(&buffer) > 0.4);
+        assert_eq!(effect.tuner().unwrap().reading().unwrap().note, "A");
+
+        effect.set_param(0, 1.0);
+        effect.process(&mut buffer);
+        assert_eq!(peak(&buffer), 0.0);
+    }
+}
@@ -1,38 +1,298 @@
 use anyhow::Result;
 use log::info;
 
-use super::types::{AudioProcessor, PluginState};
+use crate::audio::{AudioBuffer, DelayLine, PlanarBuffer, Smoother};
+use super::events::{EventKind, EventList, ProcessEvent};
+use super::routing::ParallelRack;
+use super::types::{AudioProcessor, BusLayout, BusTable, PluginState, ChannelLayout, ProcessConfig, SlotLayout, SlotSettings};
+
+/// 宿主侧增益、干湿比和旁通的平滑时间
+const SMOOTHING_MS: f32 = 20.0;
+
+/// 插件链最多的插件数
+pub const MAX_PLUGINS: usize = 8;
+
+/// 实时编辑后各位置的布局：控制线程按编辑后的顺序协商，随命令交给音频线程（定长，不分配内存）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChainLayouts {
+    layouts: [SlotLayout; MAX_PLUGINS],
+    len: usize,
+}
+
+impl ChainLayouts {
+    /// 从链的输入布局 input 开始，按各位置插件的总线表依次协商（超过 MAX_PLUGINS 的位置忽略）
+    pub fn negotiate(input: ChannelLayout, tables: &[BusTable]) -> Self {
+        let mut layouts = [SlotLayout { upstream: input, bus: BusLayout::uniform(input) }; MAX_PLUGINS];
+        let mut upstream = input;
+        for (layout, table) in layouts.iter_mut().zip(tables) {
+            *layout = table.negotiate(upstream);
+            upstream = layout.bus.output;
+        }
+        Self { layouts, len: tables.len().min(MAX_PLUGINS) }
+    }
+    
+    pub fn as_slice(&self) -> &[SlotLayout] {
+        &self.layouts[..self.len]
+    }
+}
+
+/// 单个位置的旁通、静音和干湿比状态
+struct SlotState {
+    settings: SlotSettings,
+    /// 旁通交叉淡化（1.0 = 处理，0.0 = 旁通），独奏其它位置时也会旁通
+    active: Smoother,
+    /// 干湿比
+    mix: Smoother,
+    /// 输出电平（0.0 = 静音）
+    level: Smoother,
+    /// 干信号延迟，等于插件报告的延迟
+    delay: DelayLine,
+}
+
+impl SlotState {
+    fn new(sample_rate: f64, latency: u32) -> Self {
+        let smoother = |value| Smoother::linear(SMOOTHING_MS, sample_rate as f32).with_value(value);
+        Self {
+            settings: SlotSettings::default(),
+            active: smoother(1.0),
+            mix: smoother(1.0),
+            level: smoother(1.0),
+            delay: DelayLine::new(latency as usize),
+        }
+    }
+    
+    fn smoothers(&mut self) -> [&mut Smoother; 3] {
+        [&mut self.active, &mut self.mix, &mut self.level]
+    }
+    
+    fn is_smoothing(&self) -> bool {
+        self.active.is_smoothing() || self.mix.is_smoothing() || self.level.is_smoothing()
+    }
+    
+    /// 稳定在全湿且不静音：不需要干信号
+    fn is_transparent(&self) -> bool {
+        !self.is_smoothing() && self.active.current() * self.mix.current() * self.level.current() == 1.0
+    }
+    
+    /// 稳定在旁通：插件不需要处理
+    fn is_bypassed(&self) -> bool {
+        !self.active.is_smoothing() && self.active.current() == 0.0
+    }
+    
+    /// 插件延迟变化时重新分配延迟线（超过预留容量时会分配内存）
+    fn set_latency(&mut self, latency: u32) {
+        self.delay.set_latency(latency as usize);
+    }
+}
+
+/// 并联机架的延迟会随运行中的支路编辑变化：为它所在位置的干信号延迟和它的支路预留容量
+fn reserve_rack(plugin: &mut dyn AudioProcessor, state: &mut SlotState, frames: usize) {
+    if let Some(rack) = ParallelRack::downcast_mut(plugin) {
+        rack.reserve_latency(frames);
+        state.delay.reserve(frames);
+    }
+}
+
+/// 在控制线程准备好的插件位置：布局已协商、插件已 prepare、延迟线已分配
+///
+/// 可以在音频线程用 insert_slot 放进插件链而不分配内存；
+/// 从链中取出的位置也以这种形式交回控制线程，由 into_plugin 释放。
+pub struct ChainSlot {
+    plugin: Box<dyn AudioProcessor>,
+    layout: SlotLayout,
+    state: SlotState,
+}
+
+impl ChainSlot {
+    /// 按处理配置和上一级送来的布局准备插件（不在音频线程调用）
+    pub fn prepare(mut plugin: Box<dyn AudioProcessor>, config: ProcessConfig, upstream: ChannelLayout) -> Self {
+        let layout = SlotLayout { upstream, bus: plugin.bus_layout(upstream) };
+        plugin.prepare(config.sample_rate, config.max_block_size, layout.bus.input);
+        let state = SlotState::new(config.sample_rate, plugin.latency_samples());
+        Self { plugin, layout, state }
+    }
+    
+    pub fn layout(&self) -> SlotLayout {
+        self.layout
+    }
+    
+    pub fn latency(&self) -> u32 {
+        self.state.delay.latency() as u32
+    }
+    
+    pub fn plugin(&self) -> &dyn AudioProcessor {
+        self.plugin.as_ref()
+    }
+    
+    /// 插件是并联机架时为支路编辑预留延迟容量（与 PluginChain::reserve_latency 相同），其它插件不变
+    pub fn reserve_latency(&mut self, frames: usize) {
+        reserve_rack(self.plugin.as_mut(), &mut self.state, frames);
+    }
+    
+    /// 位置离开音频线程之前在音频线程中调用，插件结束处理
+    pub fn suspend(&mut self) {
+        self.plugin.suspend();
+    }
+    
+    /// 释放插件的处理资源并取出插件
+    pub fn into_plugin(mut self) -> Box<dyn AudioProcessor> {
+        self.plugin.release();
+        self.plugin
+    }
+}
 
 /// 插件串联链
+///
+/// 相邻插件之间协商声道布局：每个位置的插件从上一级收到单声道或立体声，
+/// 插件需要的输入布局不同时自动上混（复制）或下混（取平均）。
+///
+/// 延迟补偿：每个位置的干信号按该插件报告的延迟推迟，链的干信号按总延迟推迟，
+/// 旁通、干湿比和整条链的旁通都不会改变时间位置。
 pub struct PluginChain {
     plugins: Vec<Box<dyn AudioProcessor>>,
+    /// 每个位置协商后的布局（与 plugins 一一对应）
+    layouts: Vec<SlotLayout>,
+    /// 每个位置的旁通、静音、独奏和干湿比（与 plugins 一一对应）
+    slots: Vec<SlotState>,
     max_plugins: usize,
+    /// 当前处理配置，新加入的插件按此 prepare
+    config: ProcessConfig,
+    /// 每个位置待处理的事件（偏移相对下一次 process 的缓冲区）
+    events: Vec<EventList>,
+    /// 分块处理时当前块的事件
+    block_events: EventList,
+    /// 插件链输出增益（线性，只作用于处理后的信号）
+    gain: Smoother,
+    /// 干湿比（1.0 = 全湿）
+    mix: Smoother,
+    /// 旁通交叉淡化（1.0 = 处理，0.0 = 旁通）
+    active: Smoother,
+    /// 工作缓冲区（两个声道 x max_block_size 帧），插件在其中按协商的布局处理
+    work: PlanarBuffer,
+    /// 预分配的干信号副本（转换成链的输出布局）
+    dry: PlanarBuffer,
+    /// 链的干信号延迟，等于所有插件延迟之和
+    dry_delay: DelayLine,
+    /// 当前位置插件之前的信号，用于位置的旁通和干湿比
+    slot_dry: PlanarBuffer,
+    /// 每帧的增益和湿信号比例（max_block_size 帧）
+    gain_ramp: Vec<f32>,
+    wet_ramp: Vec<f32>,
 }
 
 impl PluginChain {
     /// 创建新的插件链
     pub fn new() -> Self {
+        let max_plugins = MAX_PLUGINS;
+        let config = ProcessConfig::default();
+        let smoother = |value| Smoother::linear(SMOOTHING_MS, config.sample_rate as f32).with_value(value);
         Self {
-            plugins: Vec::new(),
-            max_plugins: 8, // 最多支持 8 个插件
+            plugins: Vec::with_capacity(max_plugins),
+            layouts: Vec::with_capacity(max_plugins),
+            slots: Vec::with_capacity(max_plugins),
+            max_plugins,
+            config,
+            events: (0..max_plugins).map(|_| EventList::new()).collect(),
+            block_events: EventList::new(),
+            gain: smoother(1.0),
+            mix: smoother(1.0),
+            active: smoother(1.0),
+            work: PlanarBuffer::new(2, config.max_block_size),
+            dry: PlanarBuffer::new(2, config.max_block_size),
+            dry_delay: DelayLine::new(0),
+            slot_dry: PlanarBuffer::new(2, config.max_block_size),
+            gain_ramp: vec![0.0; config.max_block_size],
+            wet_ramp: vec![0.0; config.max_block_size],
+        }
+    }
+    
+    /// 音频配置变化时调用：记录配置，重新协商布局并 prepare 所有插件
+    /// layout 是插件链的输入布局（例如单声道吉他输入）
+    pub fn prepare(&mut self, sample_rate: f64, max_block_size: usize, layout: ChannelLayout) {
+        self.config = ProcessConfig { sample_rate, max_block_size: max_block_size.max(1), layout };
+        self.work.resize(2, self.config.max_block_size);
+        self.dry.resize(2, self.config.max_block_size);
+        self.slot_dry.resize(2, self.config.max_block_size);
+        self.gain_ramp.resize(self.config.max_block_size, 0.0);
+        self.wet_ramp.resize(self.config.max_block_size, 0.0);
+        for smoother in [&mut self.gain, &mut self.mix, &mut self.active] {
+            smoother.set_sample_rate(sample_rate as f32);
+        }
+        self.negotiate(None);
+        for (plugin, slot) in self.plugins.iter_mut().zip(&self.layouts) {
+            plugin.prepare(sample_rate, self.config.max_block_size, slot.bus.input);
+        }
+        for state in &mut self.slots {
+            for smoother in state.smoothers() {
+                smoother.set_sample_rate(sample_rate as f32);
+            }
+        }
+        self.refresh_latency();
+        info!("插件链已准备: {} Hz, 最大 {} 帧, {:?}", sample_rate, self.config.max_block_size, layout);
+    }
+    
+    /// 清空所有插件的内部状态
+    pub fn reset(&mut self) {
+        for smoother in [&mut self.gain, &mut self.mix, &mut self.active] {
+            smoother.finish();
+        }
+        for state in &mut self.slots {
+            for smoother in state.smoothers() {
+                smoother.finish();
+            }
+            state.delay.clear();
+        }
+        self.dry_delay.clear();
+        for plugin in &mut self.plugins {
+            plugin.reset();
         }
     }
     
+    /// 设置插件链的输出增益（dB），平滑过渡
+    pub fn set_gain_db(&mut self, db: f32) {
+        self.gain.set_target(10f32.powf(db / 20.0));
+    }
+    
+    /// 设置干湿比（0.0 = 全干，1.0 = 全湿），平滑过渡
+    pub fn set_mix(&mut self, mix: f32) {
+        self.mix.set_target(mix.clamp(0.0, 1.0));
+    }
+    
+    /// 旁通整条插件链，处理信号和原始信号之间交叉淡化
+    pub fn set_bypass(&mut self, bypass: bool) {
+        self.active.set_target(if bypass { 0.0 } else { 1.0 });
+    }
+    
+    pub fn is_bypassed(&self) -> bool {
+        self.active.target() == 0.0
+    }
+    
+    /// 释放所有插件的处理资源（音频停止时调用）
+    pub fn release(&mut self) {
+        for plugin in &mut self.plugins {
+            plugin.release();
+        }
+    }
+    
+    /// 插件链离开音频线程之前在音频线程中调用，所有插件结束处理
+    pub fn suspend(&mut self) {
+        for plugin in &mut self.plugins {
+            plugin.suspend();
+        }
+    }
+    
+    /// 当前处理配置
+    pub fn config(&self) -> ProcessConfig {
+        self.config
+    }
+    
     /// 添加插件到链的末尾
     pub fn add_plugin(&mut self, plugin: Box<dyn AudioProcessor>) -> Result<()> {
-        if self.plugins.len() >= self.max_plugins {
-            return Err(anyhow::anyhow!("插件链已满（最多 {} 个）", self.max_plugins));
-        }
-        
-        let name = plugin.get_info().name.clone();
-        self.plugins.push(plugin);
-        info!("添加插件到链: {}", name);
-        
-        Ok(())
+        self.insert_plugin(self.plugins.len(), plugin)
     }
     
     /// 在指定位置插入插件
-    pub fn insert_plugin(&mut self, index: usize, plugin: Box<dyn AudioProcessor>) -> Result<()> {
+    pub fn insert_plugin(&mut self, index: usize, mut plugin: Box<dyn AudioProcessor>) -> Result<()> {
         if self.plugins.len() >= self.max_plugins {
             return Err(anyhow::anyhow!("插件链已满（最多 {} 个）", self.max_plugins));
         }
@@ -41,26 +301,310 @@ impl PluginChain {
             return Err(anyhow::anyhow!("索引超出范围"));
         }
         
+        let slot = self.prepare_slot(index, plugin.as_mut());
         let name = plugin.get_info().name.clone();
+        self.slots.insert(index, SlotState::new(self.config.sample_rate, plugin.latency_samples()));
         self.plugins.insert(index, plugin);
-        info!("在位置 {} 插入插件: {}", index, name);
+        self.layouts.insert(index, slot);
+        self.update_slot_targets();
+        self.negotiate(Some(index + 1));
+        self.update_latency();
+        self.discard_events();
+        info!("在位置 {} 插入插件: {} ({:?} -> {:?})", index, name, slot.bus.input, slot.bus.output);
         
         Ok(())
     }
     
     /// 移除指定位置的插件
+    #[cfg(test)]
     pub fn remove_plugin(&mut self, index: usize) -> Result<Box<dyn AudioProcessor>> {
         if index >= self.plugins.len() {
             return Err(anyhow::anyhow!("索引超出范围"));
         }
         
-        let plugin = self.plugins.remove(index);
+        let mut plugin = self.plugins.remove(index);
+        self.layouts.remove(index);
+        self.slots.remove(index);
+        self.update_slot_targets();
+        plugin.release();
+        self.negotiate(Some(index));
+        self.update_latency();
+        self.discard_events();
         info!("移除插件: {}", plugin.get_info().name);
         
         Ok(plugin)
     }
     
+    /// 插入准备好的位置（实时安全：不分配内存、不重新 prepare、不写日志）
+    ///
+    /// 下游的布局随后由 set_layouts 换成控制线程重新协商的结果。
+    /// 链已满或位置无效时原样返回，成功时返回 None。
+    pub fn insert_slot(&mut self, index: usize, slot: ChainSlot) -> Option<ChainSlot> {
+        if self.plugins.len() >= self.max_plugins || index > self.plugins.len() {
+            return Some(slot);
+        }
+        self.plugins.insert(index, slot.plugin);
+        self.layouts.insert(index, slot.layout);
+        self.slots.insert(index, slot.state);
+        self.relink(index);
+        None
+    }
+    
+    /// 取出指定位置（实时安全），插件不会被释放，交给控制线程处理
+    pub fn take_slot(&mut self, index: usize) -> Option<ChainSlot> {
+        if index >= self.plugins.len() {
+            return None;
+        }
+        let slot = ChainSlot {
+            plugin: self.plugins.remove(index),
+            layout: self.layouts.remove(index),
+            state: self.slots.remove(index),
+        };
+        self.relink(index);
+        Some(slot)
+    }
+    
+    /// 用准备好的位置替换指定位置（实时安全）
+    /// 返回需要退回的位置：被替换的旧位置，位置无效时是传入的位置
+    pub fn replace_slot(&mut self, index: usize, slot: ChainSlot) -> ChainSlot {
+        if index >= self.plugins.len() {
+            return slot;
+        }
+        let old = ChainSlot {
+            plugin: std::mem::replace(&mut self.plugins[index], slot.plugin),
+            layout: std::mem::replace(&mut self.layouts[index], slot.layout),
+            state: std::mem::replace(&mut self.slots[index], slot.state),
+        };
+        self.relink(index);
+        old
+    }
+    
+    /// 移动位置（实时安全）
+    pub fn move_slot(&mut self, from: usize, to: usize) -> bool {
+        if from >= self.plugins.len() || to >= self.plugins.len() {
+            return false;
+        }
+        let plugin = self.plugins.remove(from);
+        self.plugins.insert(to, plugin);
+        let layout = self.layouts.remove(from);
+        self.layouts.insert(to, layout);
+        let state = self.slots.remove(from);
+        self.slots.insert(to, state);
+        self.relink(from.min(to));
+        true
+    }
+    
+    /// 实时编辑后先按原来的总线布局更新各位置的上游布局，再由 set_layouts 换成重新协商的结果
+    fn relink(&mut self, from: usize) {
+        let mut upstream = self.upstream_layout(from);
+        for layout in &mut self.layouts[from..] {
+            layout.upstream = upstream;
+            upstream = layout.bus.output;
+        }
+        self.update_slot_targets();
+        self.update_latency();
+        self.discard_events();
+    }
+    
+    /// 换上控制线程按实时编辑后的顺序重新协商的布局（实时安全），数量与插件数不符时忽略
+    ///
+    /// 输入布局变化的下游插件不重新 prepare，按 AudioBuffer 的映射规则处理新的声道数，下一次 prepare 时按新布局准备
+    pub fn set_layouts(&mut self, layouts: &ChainLayouts) {
+        let layouts = layouts.as_slice();
+        if layouts.len() == self.layouts.len() {
+            self.layouts.copy_from_slice(layouts);
+        }
+    }
+    
+    /// 为链的干信号延迟预留容量，实时编辑时总延迟不超过它就不会分配内存
+    /// 链中的并联机架同样预留，支路编辑改变机架的延迟时也不分配内存
+    pub fn reserve_latency(&mut self, frames: usize) {
+        self.dry_delay.reserve(frames);
+        for (plugin, state) in self.plugins.iter_mut().zip(&mut self.slots) {
+            reserve_rack(plugin.as_mut(), state, frames);
+        }
+    }
+    
+    /// 插件位置变化后，排队中的事件不再对应原来的插件
+    fn discard_events(&mut self) {
+        for events in &mut self.events {
+            events.clear();
+        }
+    }
+    
+    /// 位置 index 上一级送来的布局
+    fn upstream_layout(&self, index: usize) -> ChannelLayout {
+        match index.checked_sub(1).and_then(|i| self.layouts.get(i)) {
+            Some(previous) => previous.bus.output,
+            None => self.config.layout,
+        }
+    }
+    
+    /// 为将要放到位置 index 的插件协商布局并 prepare
+    fn prepare_slot(&self, index: usize, plugin: &mut dyn AudioProcessor) -> SlotLayout {
+        let upstream = self.upstream_layout(index);
+        let slot = SlotLayout { upstream, bus: plugin.bus_layout(upstream) };
+        let config = self.config;
+        plugin.prepare(config.sample_rate, config.max_block_size, slot.bus.input);
+        slot
+    }
+    
+    /// 从位置 from 开始依次重新协商布局
+    /// from 为 Some 时，输入布局发生变化的插件重新 prepare；为 None 时只协商（由调用方统一 prepare）
+    fn negotiate(&mut self, from: Option<usize>) {
+        let start = from.unwrap_or(0);
+        let mut upstream = self.upstream_layout(start);
+        for index in start..self.plugins.len() {
+            let plugin = &mut self.plugins[index];
+            let slot = SlotLayout { upstream, bus: plugin.bus_layout(upstream) };
+            if from.is_some() && slot.bus.input != self.layouts[index].bus.input {
+                let config = self.config;
+                plugin.prepare(config.sample_rate, config.max_block_size, slot.bus.input);
+                self.slots[index].set_latency(plugin.latency_samples());
+            }
+            self.layouts[index] = slot;
+            upstream = slot.bus.output;
+        }
+    }
+    
+    /// 各位置协商后的布局
+    pub fn slot_layouts(&self) -> &[SlotLayout] {
+        &self.layouts
+    }
+    
+    /// 插件链的输出布局（最后一个插件的输出，空链时等于输入布局）
+    pub fn output_layout(&self) -> ChannelLayout {
+        self.upstream_layout(self.layouts.len())
+    }
+    
+    /// 旁通指定位置的插件（保留插件状态），交叉淡化到该位置的输入信号
+    #[cfg(test)]
+    pub fn set_slot_bypass(&mut self, index: usize, bypass: bool) -> Result<()> {
+        self.slot_mut(index)?.settings.bypass = bypass;
+        self.update_slot_targets();
+        Ok(())
+    }
+    
+    /// 静音指定位置的输出
+    #[cfg(test)]
+    pub fn set_slot_mute(&mut self, index: usize, mute: bool) -> Result<()> {
+        self.slot_mut(index)?.settings.mute = mute;
+        self.update_slot_targets();
+        Ok(())
+    }
+    
+    /// 独奏指定位置：有位置独奏时，其余位置都被旁通
+    #[cfg(test)]
+    pub fn set_slot_solo(&mut self, index: usize, solo: bool) -> Result<()> {
+        self.slot_mut(index)?.settings.solo = solo;
+        self.update_slot_targets();
+        Ok(())
+    }
+    
+    /// 设置指定位置的干湿比（0.0 = 全干，1.0 = 全湿），平滑过渡
+    #[cfg(test)]
+    pub fn set_slot_mix(&mut self, index: usize, mix: f32) -> Result<()> {
+        self.slot_mut(index)?.settings.mix = mix.clamp(0.0, 1.0);
+        self.update_slot_targets();
+        Ok(())
+    }
+    
+    /// 一次设置指定位置的全部选项
+    pub fn set_slot_settings(&mut self, index: usize, settings: SlotSettings) -> Result<()> {
+        let state = self.slot_mut(index)?;
+        state.settings = settings;
+        state.settings.mix = settings.mix.clamp(0.0, 1.0);
+        self.update_slot_targets();
+        Ok(())
+    }
+    
+    /// 指定位置的设置
+    pub fn slot_settings(&self, index: usize) -> Option<SlotSettings> {
+        self.slots.get(index).map(|state| state.settings)
+    }
+    
+    /// 指定位置的插件
+    pub fn plugin(&self, index: usize) -> Option<&dyn AudioProcessor> {
+        self.plugins.get(index).map(|plugin| plugin.as_ref())
+    }
+    
+    /// 直接设置指定位置的插件参数（不在音频线程调用，运行中的参数变化用 queue_event）
+    pub fn set_parameter(&mut self, index: usize, id: u32, value: f64) -> bool {
+        match self.plugins.get_mut(index) {
+            Some(plugin) => {
+                plugin.set_parameter(id, value);
+                true
+            }
+            None => false,
+        }
+    }
+    
+    /// 指定位置插件报告的延迟（帧）
+    pub fn slot_latency(&self, index: usize) -> Option<u32> {
+        self.slots.get(index).map(|state| state.delay.latency() as u32)
+    }
+    
+    /// 指定位置的并联机架（不是机架时为 None），编辑后调用 refresh_latency 或 refresh_slot_latency
+    pub fn rack_mut(&mut self, index: usize) -> Option<&mut ParallelRack> {
+        ParallelRack::downcast_mut(self.plugins.get_mut(index)?.as_mut())
+    }
+    
+    /// 重新读取一个位置报告的延迟（并联机架的支路编辑后在音频线程调用），不超过预留容量时不分配内存
+    pub fn refresh_slot_latency(&mut self, index: usize) {
+        if let Some((plugin, state)) = self.plugins.get(index).zip(self.slots.get_mut(index)) {
+            state.set_latency(plugin.latency_samples());
+            self.update_latency();
+        }
+    }
+    
+    /// 重新读取所有插件报告的延迟（插件加载 IR 等改变延迟后调用，不在音频线程调用）
+    pub fn refresh_latency(&mut self) {
+        for (plugin, state) in self.plugins.iter().zip(&mut self.slots) {
+            state.set_latency(plugin.latency_samples());
+        }
+        self.update_latency();
+    }
+    
+    /// 整条链的延迟（帧），即所有插件延迟之和
+    pub fn latency(&self) -> u32 {
+        self.slots.iter().map(|state| state.delay.latency() as u32).sum()
+    }
+    
+    /// 各位置处理完成时相对输入的累计延迟（帧）
+    #[cfg(test)]
+    pub fn path_latencies(&self) -> Vec<u32> {
+        self.slots
+            .iter()
+            .scan(0, |total, state| {
+                *total += state.delay.latency() as u32;
+                Some(*total)
+            })
+            .collect()
+    }
+    
+    /// 各位置的延迟变化后，调整链的干信号延迟
+    fn update_latency(&mut self) {
+        self.dry_delay.set_latency(self.latency() as usize);
+    }
+    
+    fn slot_mut(&mut self, index: usize) -> Result<&mut SlotState> {
+        self.slots.get_mut(index).ok_or_else(|| anyhow::anyhow!("索引超出范围"))
+    }
+    
+    /// 根据旁通、静音、独奏和干湿比更新每个位置的平滑目标
+    fn update_slot_targets(&mut self) {
+        let any_solo = self.slots.iter().any(|state| state.settings.solo);
+        for state in &mut self.slots {
+            let settings = state.settings;
+            let bypass = settings.bypass || (any_solo && !settings.solo);
+            state.active.set_target(if bypass { 0.0 } else { 1.0 });
+            state.mix.set_target(settings.mix);
+            state.level.set_target(if settings.mute { 0.0 } else { 1.0 });
+        }
+    }
+    
     /// 移动插件到新位置
+    #[cfg(test)]
     pub fn move_plugin(&mut self, from: usize, to: usize) -> Result<()> {
         if from >= self.plugins.len() || to >= self.plugins.len() {
             return Err(anyhow::anyhow!("索引超出范围"));
@@ -68,17 +612,17 @@ impl PluginChain {
         
         let plugin = self.plugins.remove(from);
         self.plugins.insert(to, plugin);
+        let slot = self.layouts.remove(from);
+        self.layouts.insert(to, slot);
+        let state = self.slots.remove(from);
+        self.slots.insert(to, state);
+        self.negotiate(Some(from.min(to)));
+        self.discard_events();
         info!("移动插件从 {} 到 {}", from, to);
         
         Ok(())
     }
     
-    /// 清空插件链
-    pub fn clear(&mut self) {
-        self.plugins.clear();
-        info!("清空插件链");
-    }
-    
     /// 获取插件数量
     pub fn len(&self) -> usize {
         self.plugins.len()
@@ -89,20 +633,173 @@ impl PluginChain {
         self.plugins.is_empty()
     }
     
+    /// 为指定位置的插件排队一个事件，在下一次 process 时按偏移生效
+    /// 位置无效或事件列表已满时返回 false
+    pub fn queue_event(&mut self, index: usize, event: ProcessEvent) -> bool {
+        if index >= self.plugins.len() {
+            return false;
+        }
+        self.events[index].push(event)
+    }
+    
     /// 处理音频（串联所有插件）
-    pub fn process(&mut self, buffer: &mut [f32]) {
-        if self.plugins.is_empty() {
+    pub fn process(&mut self, buffer: &mut AudioBuffer) {
+        if self.is_empty() {
             // 没有插件，直接 bypass
             return;
         }
         
-        // 顺序处理每个插件
-        for plugin in &mut self.plugins {
-            plugin.process(buffer);
+        // 完全旁通时不处理音频，只让参数生效；有延迟时干信号仍要经过延迟线
+        let bypassed = self.is_bypassed() && !self.active.is_smoothing();
+        if bypassed && self.dry_delay.latency() == 0 {
+            for (plugin, events) in self.plugins.iter_mut().zip(&self.events) {
+                for event in events.iter() {
+                    if let EventKind::Parameter { id, value } = event.kind {
+                        plugin.set_parameter(id, value);
+                    }
+                }
+            }
+            self.discard_events();
+            return;
+        }
+        
+        let input = self.config.layout;
+        let output = self.output_layout();
+        
+        // 按 prepare 时约定的最大帧数分块，顺序处理每个插件
+        let block_size = self.config.max_block_size;
+        let frames = buffer.frames();
+        for start in (0..frames).step_by(block_size) {
+            let end = (start + block_size).min(frames);
+            let mut block = buffer.slice(start, end);
+            let block_frames = block.frames();
+            // 超出缓冲区的事件放进最后一块
+            let event_end = if end == frames { u32::MAX } else { end as u32 };
+            
+            // 设备缓冲区 -> 链的输入布局，同时保留一份干信号
+            for planar in [&mut self.work, &mut self.dry] {
+                let mut view = planar.as_buffer_channels(input.channels(), block_frames);
+                for (index, channel) in view.channels_mut().enumerate() {
+                    block.read_mapped(index, input.channels(), channel);
+                }
+            }
+            convert_layout(&mut self.dry, block_frames, input, output);
+            self.dry_delay.process(&mut self.dry.as_buffer_channels(output.channels(), block_frames));
+            
+            if bypassed {
+                for (plugin, events) in self.plugins.iter_mut().zip(&self.events) {
+                    for event in events.range(start as u32, event_end) {
+                        if let EventKind::Parameter { id, value } = event.kind {
+                            plugin.set_parameter(id, value);
+                        }
+                    }
+                }
+                let dry = self.dry.as_buffer_channels(output.channels(), block_frames);
+                let sources = [dry.channel(0), dry.channel(output.channels() - 1)];
+                block.write_mapped(&sources[..output.channels()]);
+                continue;
+            }
+            
+            let mut current = input;
+            let slots = self.plugins.iter_mut().zip(&self.events).zip(&self.layouts).zip(&mut self.slots);
+            for (((plugin, events), slot), state) in slots {
+                let bus = slot.bus;
+                convert_layout(&mut self.work, block_frames, current, bus.input);
+                // 单声道输入、立体声输出的插件拿到两个声道，输入复制到两边
+                if bus.channels() > bus.input.channels() {
+                    self.work.upmix(block_frames);
+                }
+                current = bus.output;
+                
+                // 有延迟时干信号一直经过延迟线，切换旁通时才能与处理后的信号对齐
+                let transparent = state.is_transparent();
+                if !transparent || state.delay.latency() > 0 {
+                    let work = self.work.as_buffer_channels(bus.channels(), block_frames);
+                    self.slot_dry.as_buffer_channels(bus.channels(), block_frames).copy_from(&work);
+                    convert_layout(&mut self.slot_dry, block_frames, bus.input, bus.output);
+                    state.delay.process(&mut self.slot_dry.as_buffer_channels(bus.output.channels(), block_frames));
+                }
+                
+                if state.is_bypassed() {
+                    // 旁通时插件不处理，只让参数生效
+                    for event in events.range(start as u32, event_end) {
+                        if let EventKind::Parameter { id, value } = event.kind {
+                            plugin.set_parameter(id, value);
+                        }
+                    }
+                    let dry = self.slot_dry.as_buffer_channels(bus.output.channels(), block_frames);
+                    self.work.as_buffer_channels(bus.output.channels(), block_frames).copy_from(&dry);
+                } else {
+                    let mut work = self.work.as_buffer_channels(bus.channels(), block_frames);
+                    if events.is_empty() {
+                        plugin.process(&mut work);
+                    } else {
+                        self.block_events.fill_from(events, start as u32, event_end);
+                        plugin.process_with_events(&mut work, &self.block_events);
+                    }
+                }
+                
+                if !transparent {
+                    let ramps = (&mut self.gain_ramp[..block_frames], &mut self.wet_ramp[..block_frames]);
+                    mix_slot(&mut self.work, &mut self.slot_dry, state, bus.output.channels(), ramps);
+                }
+            }
+            
+            self.mix_block(output.channels(), block_frames);
+            
+            // 链的输出布局 -> 设备缓冲区
+            let work = self.work.as_buffer_channels(output.channels(), block_frames);
+            let sources = [work.channel(0), work.channel(output.channels() - 1)];
+            block.write_mapped(&sources[..output.channels()]);
+        }
+        
+        self.discard_events();
+    }
+    
+    /// 对工作缓冲区中处理后的块应用输出增益、干湿比和旁通淡化
+    fn mix_block(&mut self, channels: usize, frames: usize) {
+        let steady = !self.gain.is_smoothing() && !self.mix.is_smoothing() && !self.active.is_smoothing();
+        if steady && self.gain.current() == 1.0 && self.mix.current() * self.active.current() == 1.0 {
+            return;
+        }
+        
+        let gains = &mut self.gain_ramp[..frames];
+        let wets = &mut self.wet_ramp[..frames];
+        for (gain, wet) in gains.iter_mut().zip(wets.iter_mut()) {
+            *gain = self.gain.next_value();
+            *wet = self.mix.next_value() * self.active.next_value();
+        }
+        
+        let mut wet = self.work.as_buffer_channels(channels, frames);
+        let dry = self.dry.as_buffer_channels(channels, frames);
+        for (channel, dry) in wet.channels_mut().zip(dry.channels()) {
+            let samples = channel.iter_mut().zip(dry).zip(gains.iter().zip(wets.iter()));
+            for ((sample, &dry), (&gain, &wet)) in samples {
+                *sample = dry + (*sample * gain - dry) * wet;
+            }
+        }
+    }
+    
+    /// 保存各位置的设置（与 save_chain_state 的顺序一致）
+    pub fn save_slot_settings(&self) -> Vec<SlotSettings> {
+        self.slots.iter().map(|state| state.settings).collect()
+    }
+    
+    /// 加载各位置的设置
+    pub fn load_slot_settings(&mut self, settings: &[SlotSettings]) {
+        if settings.len() != self.slots.len() {
+            log::warn!("位置设置数量与插件数量不匹配");
+            return;
         }
+        
+        for (state, settings) in self.slots.iter_mut().zip(settings) {
+            state.settings = *settings;
+        }
+        self.update_slot_targets();
     }
     
     /// 获取所有插件的名称
+    #[cfg(test)]
     pub fn get_plugin_names(&self) -> Vec<String> {
         self.plugins
             .iter()
@@ -133,6 +830,34 @@ impl PluginChain {
     }
 }
 
+/// 按位置的旁通、干湿比和静音混合处理后的信号与该位置的干信号
+fn mix_slot(work: &mut PlanarBuffer, dry: &mut PlanarBuffer, state: &mut SlotState, channels: usize, ramps: (&mut [f32], &mut [f32])) {
+    let (wets, levels) = ramps;
+    let frames = wets.len();
+    for (wet, level) in wets.iter_mut().zip(levels.iter_mut()) {
+        *wet = state.active.next_value() * state.mix.next_value();
+        *level = state.level.next_value();
+    }
+    
+    let mut work = work.as_buffer_channels(channels, frames);
+    let dry = dry.as_buffer_channels(channels, frames);
+    for (channel, dry) in work.channels_mut().zip(dry.channels()) {
+        let samples = channel.iter_mut().zip(dry).zip(wets.iter().zip(levels.iter()));
+        for ((sample, &dry), (&wet, &level)) in samples {
+            *sample = (dry + (*sample - dry) * wet) * level;
+        }
+    }
+}
+
+/// 在两个声道的工作缓冲区中把布局从 from 转换成 to
+fn convert_layout(buffer: &mut PlanarBuffer, frames: usize, from: ChannelLayout, to: ChannelLayout) {
+    match (from, to) {
+        (ChannelLayout::Mono, ChannelLayout::Stereo) => buffer.upmix(frames),
+        (ChannelLayout::Stereo, ChannelLayout::Mono) => buffer.downmix(frames),
+        _ => {}
+    }
+}
+
 impl Default for PluginChain {
     fn default() -> Self {
         Self::new()
@@ -142,9 +867,11 @@ impl Default for PluginChain {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::audio::with_interleaved;
     use crate::plugin::loader::DummyPlugin;
-    use crate::plugin::types::{PluginMetadata, PluginFormat};
+    use crate::plugin::types::{PluginMetadata, PluginFormat, PluginParameter, ParameterDescriptor};
     use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
     
     fn create_dummy_plugin(name: &str) -> Box<dyn AudioProcessor> {
         Box::new(DummyPlugin::new(PluginMetadata {
@@ -194,5 +921,372 @@ mod tests {
         assert_eq!(removed.get_info().name, "Plugin1");
         assert_eq!(chain.len(), 1);
     }
+    
+    /// 记录生命周期调用的测试插件
+    struct LifecycleProbe {
+        metadata: PluginMetadata,
+        log: Arc<Mutex<Vec<String>>>,
+    }
+    
+    impl AudioProcessor for LifecycleProbe {
+        fn prepare(&mut self, sample_rate: f64, max_block_size: usize, layout: ChannelLayout) {
+            self.log.lock().unwrap().push(format!("prepare {} {} {:?}", sample_rate, max_block_size, layout));
+        }
+        fn reset(&mut self) {
+            self.log.lock().unwrap().push("reset".to_string());
+        }
+        fn release(&mut self) {
+            self.log.lock().unwrap().push("release".to_string());
+        }
+        fn process(&mut self, buffer: &mut AudioBuffer) {
+            self.log.lock().unwrap().push(format!("process {}", buffer.frames()));
+        }
+        fn get_info(&self) -> &PluginMetadata {
+            &self.metadata
+        }
+        fn set_parameter(&mut self, id: u32, value: f64) {
+            self.log.lock().unwrap().push(format!("set {} {}", id, value));
+        }
+        fn get_parameter(&self, _id: u32) -> Option<f64> {
+            None
+        }
+        fn get_all_parameters(&self) -> Vec<PluginParameter> {
+            Vec::new()
+        }
+        fn parameter_descriptors(&self) -> Vec<ParameterDescriptor> {
+            Vec::new()
+        }
+        fn save_state(&self) -> PluginState {
+            PluginState { plugin_id: self.metadata.id.clone(), parameters: Vec::new(), state_data: String::new() }
+        }
+        fn load_state(&mut self, _state: &PluginState) {}
+    }
+    
+    /// 用交错立体声数据调用 process
+    fn process_stereo(chain: &mut PluginChain, buffer: &mut [f32]) {
+        with_interleaved(buffer, 2, |buffer| chain.process(buffer));
+    }
+    
+    fn create_probe(log: &Arc<Mutex<Vec<String>>>) -> Box<dyn AudioProcessor> {
+        create_probe_with_channels(log, 2, 2)
+    }
+    
+    fn create_probe_with_channels(log: &Arc<Mutex<Vec<String>>>, inputs: u32, outputs: u32) -> Box<dyn AudioProcessor> {
+        let mut metadata = create_dummy_plugin("Probe").get_info().clone();
+        metadata.num_inputs = inputs;
+        metadata.num_outputs = outputs;
+        Box::new(LifecycleProbe { metadata, log: log.clone() })
+    }
+    
+    #[test]
+    fn test_lifecycle_propagation() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let probe = LifecycleProbe {
+            metadata: create_dummy_plugin("Probe").get_info().clone(),
+            log: log.clone(),
+        };
+        
+        let mut chain = PluginChain::new();
+        chain.prepare(44100.0, 256, ChannelLayout::Stereo);
+        chain.add_plugin(Box::new(probe)).unwrap();
+        chain.prepare(96000.0, 128, ChannelLayout::Mono);
+        
+        // 超过最大块大小的缓冲区被拆开处理
+        let mut buffer = vec![0.0; 300 * 2];
+        process_stereo(&mut chain, &mut buffer);
+        chain.reset();
+        let _ = chain.remove_plugin(0).unwrap();
+        
+        // 立体声插件按协商后的布局 prepare，单声道输入由插件链上混
+        assert_eq!(*log.lock().unwrap(), vec![
+            "prepare 44100 256 Stereo",
+            "prepare 96000 128 Stereo",
+            "process 128",
+            "process 128",
+            "process 44",
+            "reset",
+            "release",
+        ]);
+    }
+    
+    #[test]
+    fn test_events_split_at_offsets() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut chain = PluginChain::new();
+        chain.prepare(48000.0, 64, ChannelLayout::Stereo);
+        chain.add_plugin(create_probe(&log)).unwrap();
+        
+        assert!(chain.queue_event(0, ProcessEvent::parameter(100, 1, 0.5)));
+        assert!(chain.queue_event(0, ProcessEvent::parameter(10, 0, 0.25)));
+        assert!(!chain.queue_event(1, ProcessEvent::parameter(0, 0, 0.0)));
+        
+        let mut buffer = vec![0.0; 128 * 2];
+        process_stereo(&mut chain, &mut buffer);
+        // 下一次 process 不再包含已处理的事件
+        process_stereo(&mut chain, &mut buffer[..16 * 2]);
+        
+        assert_eq!(log.lock().unwrap()[1..], [
+            "process 10",
+            "set 0 0.25",
+            "process 54",
+            "process 36",
+            "set 1 0.5",
+            "process 28",
+            "process 16",
+        ]);
+    }
+    
+    /// 最大二阶差分（阶跃时等于跳变量）
+    fn max_discontinuity(buffer: &[f32]) -> f32 {
+        let left: Vec<f32> = buffer.iter().step_by(2).copied().collect();
+        left.windows(3)
+            .map(|w| (w[2] - 2.0 * w[1] + w[0]).abs())
+            .fold(0.0, f32::max)
+    }
+    
+    fn gain_plugin(db: f64) -> Box<dyn AudioProcessor> {
+        use crate::plugin::{BuiltinKind, BuiltinPlugin};
+        
+        let mut plugin = BuiltinPlugin::new(BuiltinKind::Gain);
+        plugin.set_parameter(0, (db + 24.0) / 48.0);
+        plugin.reset();
+        Box::new(plugin)
+    }
+    
+    #[test]
+    fn test_bypass_crossfade_is_smooth() {
+        let mut chain = PluginChain::new();
+        chain.prepare(48000.0, 256, ChannelLayout::Stereo);
+        chain.add_plugin(gain_plugin(-24.0)).unwrap();
+        
+        let mut buffer = vec![0.5; 4800 * 2];
+        process_stereo(&mut chain, &mut buffer[..960 * 2]);
+        chain.set_bypass(true);
+        process_stereo(&mut chain, &mut buffer[960 * 2..]);
+        
+        assert!(chain.is_bypassed());
+        assert!(max_discontinuity(&buffer) < 0.001);
+        // 淡化结束后是原始信号
+        assert_eq!(buffer[4799 * 2], 0.5);
+        
+        // 旁通期间不处理，取消旁通后再淡入
+        chain.set_bypass(false);
+        let mut buffer = vec![0.5; 1920 * 2];
+        process_stereo(&mut chain, &mut buffer);
+        assert!(max_discontinuity(&buffer) < 0.001);
+        assert!((buffer[1919 * 2] - 0.5 * 0.063).abs() < 0.001);
+    }
+    
+    #[test]
+    fn test_mono_and_multichannel_buffers() {
+        let mut chain = PluginChain::new();
+        chain.prepare(48000.0, 64, ChannelLayout::Mono);
+        chain.add_plugin(gain_plugin(-6.0)).unwrap();
+        let expected = 10f32.powf(-6.0 / 20.0);
+        
+        // 单声道吉他输入：cpal 的数据直接作为平面缓冲区
+        let mut mono = vec![1.0; 200];
+        chain.process(&mut AudioBuffer::from_mono(&mut mono));
+        assert!(mono.iter().all(|&s| (s - expected).abs() < 1e-4));
+        
+        // 四声道接口按立体声处理：前两个声道经过插件，其余声道保持不变
+        chain.prepare(48000.0, 64, ChannelLayout::Stereo);
+        let mut channels = vec![vec![1.0; 100]; 4];
+        chain.process(&mut AudioBuffer::from_channels(channels.iter_mut().map(|c| &mut c[..])));
+        assert!((channels[1][99] - expected).abs() < 1e-4);
+        assert_eq!(channels[3][99], 1.0);
+    }
+    
+    #[test]
+    fn test_layout_negotiation() {
+        use crate::plugin::{BuiltinKind, BuiltinPlugin, BusLayout, LayoutConversion};
+        use ChannelLayout::{Mono, Stereo};
+        
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut chain = PluginChain::new();
+        chain.prepare(48000.0, 64, Mono);
+        
+        // 单声道吉他输入 -> 立体声插件 -> 混响（跟随上游）-> 单声道插件
+        chain.add_plugin(create_probe_with_channels(&log, 2, 2)).unwrap();
+        chain.add_plugin(Box::new(BuiltinPlugin::new(BuiltinKind::Reverb))).unwrap();
+        chain.add_plugin(create_probe_with_channels(&log, 1, 1)).unwrap();
+        
+        let layouts = chain.slot_layouts();
+        assert_eq!(layouts[0].bus, BusLayout::uniform(Stereo));
+        assert_eq!(layouts[0].conversion(), LayoutConversion::Upmix);
+        assert_eq!(layouts[1].bus, BusLayout::uniform(Stereo));
+        assert_eq!(layouts[2].bus, BusLayout::uniform(Mono));
+        assert_eq!(layouts[2].conversion(), LayoutConversion::Downmix);
+        assert_eq!(chain.output_layout(), Mono);
+        assert_eq!(*log.lock().unwrap(), vec!["prepare 48000 64 Stereo", "prepare 48000 64 Mono"]);
+        
+        // 移除第一个插件后混响变成单声道进、立体声出，只有它的输入布局变了
+        let _ = chain.remove_plugin(0).unwrap();
+        assert_eq!(chain.slot_layouts()[0].bus, BusLayout::new(Mono, Stereo));
+        assert_eq!(chain.slot_layouts()[1].conversion(), LayoutConversion::Downmix);
+        assert_eq!(log.lock().unwrap().len(), 3);
+        
+        let mut mono = vec![0.5; 256];
+        chain.process(&mut AudioBuffer::from_mono(&mut mono));
+        assert!(mono.iter().all(|s| s.is_finite()));
+    }
+    
+    #[test]
+    fn test_mono_input_upmixed_for_stereo_plugin() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut chain = PluginChain::new();
+        chain.prepare(48000.0, 64, ChannelLayout::Mono);
+        chain.add_plugin(create_probe(&log)).unwrap();
+        assert_eq!(chain.output_layout(), ChannelLayout::Stereo);
+        
+        // 立体声设备、单声道输入布局：两个声道先平均，再复制到插件的两个声道
+        let mut buffer = vec![1.0, 0.0, 1.0, 0.0];
+        process_stereo(&mut chain, &mut buffer);
+        assert_eq!(buffer, vec![0.5; 4]);
+    }
+    
+    #[test]
+    fn test_gain_and_mix_are_smooth() {
+        let mut chain = PluginChain::new();
+        chain.prepare(48000.0, 128, ChannelLayout::Stereo);
+        chain.add_plugin(gain_plugin(0.0)).unwrap();
+        
+        let mut buffer = vec![1.0; 2400 * 2];
+        chain.set_gain_db(-12.0);
+        chain.set_mix(0.5);
+        process_stereo(&mut chain, &mut buffer);
+        
+        assert!(max_discontinuity(&buffer) < 0.001);
+        // 0.5 * 干 + 0.5 * (-12 dB) 湿
+        let expected = 0.5 + 0.5 * 10f32.powf(-12.0 / 20.0);
+        assert!((buffer[2399 * 2] - expected).abs() < 1e-4);
+    }
+    
+    #[test]
+    fn test_slot_bypass_crossfade_is_smooth() {
+        let mut chain = PluginChain::new();
+        chain.prepare(48000.0, 256, ChannelLayout::Stereo);
+        chain.add_plugin(gain_plugin(-24.0)).unwrap();
+        chain.add_plugin(gain_plugin(-6.0)).unwrap();
+        assert!(chain.set_slot_bypass(2, true).is_err());
+        
+        let mut buffer = vec![0.5; 4800 * 2];
+        process_stereo(&mut chain, &mut buffer[..960 * 2]);
+        chain.set_slot_bypass(0, true).unwrap();
+        process_stereo(&mut chain, &mut buffer[960 * 2..]);
+        
+        assert!(chain.slot_settings(0).unwrap().bypass);
+        assert!(max_discontinuity(&buffer) < 0.001);
+        // 只剩第二个插件的 -6 dB
+        assert!((buffer[4799 * 2] - 0.5 * 10f32.powf(-6.0 / 20.0)).abs() < 1e-4);
+    }
+    
+    #[test]
+    fn test_slot_solo_mute_and_mix() {
+        let mut chain = PluginChain::new();
+        chain.prepare(48000.0, 256, ChannelLayout::Stereo);
+        chain.add_plugin(gain_plugin(-24.0)).unwrap();
+        chain.add_plugin(gain_plugin(-6.0)).unwrap();
+        chain.reset();
+        let half = 10f32.powf(-6.0 / 20.0);
+        
+        // 独奏第二个位置：第一个位置被旁通
+        chain.set_slot_solo(1, true).unwrap();
+        let mut buffer = vec![1.0; 2400 * 2];
+        process_stereo(&mut chain, &mut buffer);
+        assert!((buffer[2399 * 2] - half).abs() < 1e-4);
+        
+        // 干湿比一半：干信号是该位置的输入
+        chain.set_slot_mix(1, 0.5).unwrap();
+        let mut buffer = vec![1.0; 2400 * 2];
+        process_stereo(&mut chain, &mut buffer);
+        assert!((buffer[2399 * 2] - (0.5 + 0.5 * half)).abs() < 1e-4);
+        
+        chain.set_slot_mute(0, true).unwrap();
+        let mut buffer = vec![1.0; 2400 * 2];
+        process_stereo(&mut chain, &mut buffer);
+        assert!(max_discontinuity(&buffer) < 0.001);
+        assert_eq!(buffer[2399 * 2], 0.0);
+        
+        // 设置跟随插件移动，保存后可以恢复
+        chain.move_plugin(0, 1).unwrap();
+        let settings = chain.save_slot_settings();
+        assert!(settings[1].mute && settings[0].solo);
+        chain.load_slot_settings(&[SlotSettings::default(); 2]);
+        assert_eq!(chain.slot_settings(0), Some(SlotSettings::default()));
+        chain.load_slot_settings(&settings);
+        assert_eq!(chain.save_slot_settings(), settings);
+    }
+    
+    fn latency_plugin(latency: u32) -> Box<dyn AudioProcessor> {
+        let metadata = create_dummy_plugin("Latency").get_info().clone();
+        Box::new(DummyPlugin::with_latency(metadata, latency))
+    }
+    
+    /// 第一个非零样本（左声道）的位置
+    fn impulse_position(buffer: &[f32]) -> Option<usize> {
+        buffer.iter().step_by(2).position(|&s| s != 0.0)
+    }
+    
+    #[test]
+    fn test_slot_dry_follows_plugin_latency() {
+        let mut chain = PluginChain::new();
+        chain.prepare(48000.0, 64, ChannelLayout::Stereo);
+        chain.add_plugin(latency_plugin(100)).unwrap();
+        assert_eq!(chain.slot_latency(0), Some(100));
+        
+        let impulse = impulse_position;
+        let mut buffer = vec![0.0; 256 * 2];
+        buffer[0] = 1.0;
+        process_stereo(&mut chain, &mut buffer);
+        assert_eq!(impulse(&buffer), Some(100));
+        
+        // 干湿各一半时两路对齐，脉冲不会变成两个
+        chain.set_slot_mix(0, 0.5).unwrap();
+        chain.reset();
+        let mut buffer = vec![0.0; 256 * 2];
+        buffer[0] = 1.0;
+        process_stereo(&mut chain, &mut buffer);
+        assert_eq!(impulse(&buffer), Some(100));
+        assert!((buffer[200] - 1.0).abs() < 1e-6);
+        
+        // 旁通后时间位置不变
+        chain.set_slot_bypass(0, true).unwrap();
+        chain.reset();
+        let mut buffer = vec![0.0; 256 * 2];
+        buffer[0] = 1.0;
+        process_stereo(&mut chain, &mut buffer);
+        assert_eq!(impulse(&buffer), Some(100));
+    }
+    
+    #[test]
+    fn test_chain_latency_compensates_dry_path() {
+        let mut chain = PluginChain::new();
+        chain.prepare(48000.0, 64, ChannelLayout::Stereo);
+        chain.add_plugin(latency_plugin(100)).unwrap();
+        chain.add_plugin(gain_plugin(0.0)).unwrap();
+        chain.insert_plugin(1, latency_plugin(28)).unwrap();
+        assert_eq!(chain.latency(), 128);
+        assert_eq!(chain.path_latencies(), vec![100, 128, 128]);
+        
+        // 链的干湿比：干信号按总延迟推迟
+        chain.set_mix(0.5);
+        chain.reset();
+        let mut buffer = vec![0.0; 256 * 2];
+        buffer[0] = 1.0;
+        process_stereo(&mut chain, &mut buffer);
+        assert_eq!(impulse_position(&buffer), Some(128));
+        assert!((buffer[128 * 2] - 1.0).abs() < 1e-6);
+        
+        // 整条链旁通后时间位置不变
+        chain.set_bypass(true);
+        chain.reset();
+        let mut buffer = vec![0.0; 256 * 2];
+        buffer[0] = 1.0;
+        process_stereo(&mut chain, &mut buffer);
+        assert_eq!(impulse_position(&buffer), Some(128));
+        
+        let _ = chain.remove_plugin(0).unwrap();
+        assert_eq!(chain.latency(), 28);
+    }
 }
-
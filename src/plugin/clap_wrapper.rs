@@ -0,0 +1,1447 @@
+
+// CLAP 插件宿主
+// 通过 dlopen 加载 .clap 动态库，经由 clap_plugin_factory 创建实例，
+// 并以 AudioProcessor 的形式接入插件链
+
+use anyhow::{Result, Context};
+use base64::Engine as _;
+use clap_sys::audio_buffer::clap_audio_buffer;
+use clap_sys::entry::clap_plugin_entry;
+use clap_sys::events::{
+    clap_event_header, clap_event_midi, clap_event_param_value, clap_input_events, clap_output_events,
+    CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_MIDI, CLAP_EVENT_PARAM_VALUE,
+};
+use clap_sys::ext::audio_ports::{
+    clap_audio_port_info, clap_plugin_audio_ports, CLAP_EXT_AUDIO_PORTS,
+};
+use clap_sys::ext::params::{
+    clap_param_info, clap_plugin_params, CLAP_EXT_PARAMS, CLAP_PARAM_IS_AUTOMATABLE, CLAP_PARAM_IS_BYPASS,
+    CLAP_PARAM_IS_ENUM, CLAP_PARAM_IS_HIDDEN, CLAP_PARAM_IS_READONLY, CLAP_PARAM_IS_STEPPED,
+};
+use clap_sys::ext::latency::{clap_plugin_latency, CLAP_EXT_LATENCY};
+use clap_sys::ext::note_ports::{clap_note_port_info, clap_plugin_note_ports, CLAP_EXT_NOTE_PORTS, CLAP_NOTE_DIALECT_MIDI};
+use clap_sys::ext::state::{clap_plugin_state, CLAP_EXT_STATE};
+use clap_sys::factory::plugin_factory::{clap_plugin_factory, CLAP_PLUGIN_FACTORY_ID};
+use clap_sys::host::clap_host;
+use clap_sys::plugin::{clap_plugin, clap_plugin_descriptor};
+use clap_sys::process::{clap_process, CLAP_PROCESS_ERROR};
+use clap_sys::stream::{clap_istream, clap_ostream};
+use clap_sys::version::{clap_version_is_compatible, CLAP_VERSION};
+use libloading::Library;
+use log::{info, warn, debug};
+use std::collections::HashMap;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::sync::{Arc, LazyLock, Mutex, MutexGuard, PoisonError, Weak};
+use std::thread;
+
+use crate::audio::AudioBuffer;
+
+use super::events::{EventKind, EventList, MAX_EVENTS};
+use super::types::{PluginMetadata, AudioProcessor, PluginParameter, PluginState, PluginFormat, ChannelLayout, ParameterDescriptor, ParameterFlags};
+
+/// 激活插件时使用的采样率
+const DEFAULT_SAMPLE_RATE: f64 = 48000.0;
+
+/// 单次调用插件 process 的最大帧数
+const MAX_BLOCK_SIZE: usize = 4096;
+
+/// 已打开的 CLAP 库，按路径缓存
+///
+/// dlopen 同一文件得到的是同一个 DSO，CLAP 要求 clap_entry.init 只调用一次、
+/// 最后一个实例销毁后才 deinit，所以扫描和所有实例共享同一个 ClapLibrary。
+static LIBRARIES: LazyLock<Mutex<HashMap<PathBuf, Weak<ClapLibrary>>>> = LazyLock::new(Default::default);
+
+fn libraries() -> MutexGuard<'static, HashMap<PathBuf, Weak<ClapLibrary>>> {
+    LIBRARIES.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// 已加载的 CLAP 动态库（同一文件的扫描和所有实例共享）
+struct ClapLibrary {
+    path: PathBuf,
+    entry: *const clap_plugin_entry,
+    // 必须在 deinit 之后才能卸载
+    _library: Option<Library>,
+}
+
+// clap_entry 要求线程安全
+unsafe impl Send for ClapLibrary {}
+unsafe impl Sync for ClapLibrary {}
+
+impl ClapLibrary {
+    /// 打开 .clap 文件并初始化入口（已打开时共享同一个库）
+    fn open(path: &Path) -> Result<Arc<Self>> {
+        Self::shared(path, || {
+            let library = unsafe { Library::new(path) }
+                .context(format!("加载动态库失败: {:?}", path))?;
+
+            let entry = unsafe {
+                let symbol = library
+                    .get::<*const clap_plugin_entry>(b"clap_entry\0")
+                    .context("找不到 clap_entry 符号")?;
+                *symbol
+            };
+
+            Self::init(entry, Some(library), path)
+        })
+    }
+
+    /// 取出 path 已缓存的库，没有时用 load 打开并初始化后放入缓存
+    fn shared(path: &Path, load: impl FnOnce() -> Result<Self>) -> Result<Arc<Self>> {
+        let mut cache = libraries();
+        loop {
+            match cache.get(path).map(Weak::upgrade) {
+                Some(Some(library)) => return Ok(library),
+                // 最后一个引用刚释放，等它 deinit 并移出缓存后再重新 init
+                Some(None) => {
+                    drop(cache);
+                    thread::yield_now();
+                    cache = libraries();
+                }
+                None => break,
+            }
+        }
+
+        let library = Arc::new(load()?);
+        cache.insert(path.to_path_buf(), Arc::downgrade(&library));
+        Ok(library)
+    }
+
+    /// 调用 clap_entry.init
+    fn init(entry: *const clap_plugin_entry, library: Option<Library>, path: &Path) -> Result<Self> {
+        if entry.is_null() {
+            return Err(anyhow::anyhow!("clap_entry 为空"));
+        }
+
+        let entry_ref = unsafe { &*entry };
+        if !clap_version_is_compatible(entry_ref.clap_version) {
+            return Err(anyhow::anyhow!(
+                "不兼容的 CLAP 版本: {}.{}.{}",
+                entry_ref.clap_version.major,
+                entry_ref.clap_version.minor,
+                entry_ref.clap_version.revision
+            ));
+        }
+
+        let init = entry_ref.init.ok_or_else(|| anyhow::anyhow!("clap_entry 缺少 init"))?;
+        let path_c = CString::new(path.to_string_lossy().as_bytes())
+            .context("插件路径包含非法字符")?;
+
+        if !unsafe { init(path_c.as_ptr()) } {
+            return Err(anyhow::anyhow!("clap_entry 初始化失败"));
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            entry,
+            _library: library,
+        })
+    }
+
+    /// 获取插件工厂
+    fn factory(&self) -> Result<&clap_plugin_factory> {
+        let entry = unsafe { &*self.entry };
+        let get_factory = entry.get_factory
+            .ok_or_else(|| anyhow::anyhow!("clap_entry 缺少 get_factory"))?;
+
+        let factory = unsafe { get_factory(CLAP_PLUGIN_FACTORY_ID.as_ptr()) } as *const clap_plugin_factory;
+        if factory.is_null() {
+            return Err(anyhow::anyhow!("插件未提供 clap.plugin-factory"));
+        }
+
+        Ok(unsafe { &*factory })
+    }
+
+    /// 枚举工厂中的所有插件描述
+    fn descriptors(&self) -> Result<Vec<&clap_plugin_descriptor>> {
+        let factory = self.factory()?;
+        let (Some(count), Some(get)) = (factory.get_plugin_count, factory.get_plugin_descriptor) else {
+            return Err(anyhow::anyhow!("插件工厂不完整"));
+        };
+
+        let mut descriptors = Vec::new();
+        for index in 0..unsafe { count(factory) } {
+            let desc = unsafe { get(factory, index) };
+            if !desc.is_null() {
+                descriptors.push(unsafe { &*desc });
+            }
+        }
+
+        Ok(descriptors)
+    }
+}
+
+impl Drop for ClapLibrary {
+    fn drop(&mut self) {
+        // 持有缓存锁完成 deinit，同一路径的 open 在这之后才会重新 init
+        let mut cache = libraries();
+        if cache.get(&self.path).is_some_and(|weak| ptr::eq(weak.as_ptr(), self)) {
+            cache.remove(&self.path);
+        }
+        if let Some(deinit) = unsafe { (*self.entry).deinit } {
+            unsafe { deinit() };
+        }
+    }
+}
+
+/// 读取 C 字符串（空指针返回空串）
+fn c_str_to_string(ptr: *const c_char) -> String {
+    if ptr.is_null() {
+        String::new()
+    } else {
+        unsafe { CStr::from_ptr(ptr) }.to_string_lossy().into_owned()
+    }
+}
+
+/// 读取定长 C 字符数组
+fn c_array_to_string(chars: &[c_char]) -> String {
+    let bytes: Vec<u8> = chars.iter()
+        .take_while(|&&c| c != 0)
+        .map(|&c| c as u8)
+        .collect();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// 根据插件描述构建元数据
+fn descriptor_to_metadata(desc: &clap_plugin_descriptor, path: &Path) -> PluginMetadata {
+    PluginMetadata {
+        id: format!("clap:{}", c_str_to_string(desc.id)),
+        name: c_str_to_string(desc.name),
+        vendor: c_str_to_string(desc.vendor),
+        version: c_str_to_string(desc.version),
+        path: path.to_path_buf(),
+        format: PluginFormat::Clap,
+        num_inputs: 2,
+        num_outputs: 2,
+    }
+}
+
+/// 列出 .clap 文件中包含的所有插件
+pub fn scan_clap_file(path: &Path) -> Result<Vec<PluginMetadata>> {
+    let library = ClapLibrary::open(path)?;
+
+    let plugins = library.descriptors()?
+        .into_iter()
+        .map(|desc| descriptor_to_metadata(desc, path))
+        .collect();
+
+    Ok(plugins)
+}
+
+// ===== 宿主回调 =====
+
+unsafe extern "C" fn host_get_extension(_host: *const clap_host, _id: *const c_char) -> *const c_void {
+    ptr::null()
+}
+
+unsafe extern "C" fn host_request_restart(_host: *const clap_host) {
+    debug!("CLAP 插件请求重启");
+}
+
+unsafe extern "C" fn host_request_process(_host: *const clap_host) {}
+
+unsafe extern "C" fn host_request_callback(_host: *const clap_host) {}
+
+fn new_host() -> Box<clap_host> {
+    Box::new(clap_host {
+        clap_version: CLAP_VERSION,
+        host_data: ptr::null_mut(),
+        name: c"Plugin Loader".as_ptr(),
+        vendor: c"Plugin Loader".as_ptr(),
+        url: c"".as_ptr(),
+        version: c"0.1.0".as_ptr(),
+        get_extension: Some(host_get_extension),
+        request_restart: Some(host_request_restart),
+        request_process: Some(host_request_process),
+        request_callback: Some(host_request_callback),
+    })
+}
+
+// ===== 事件列表 =====
+
+/// 发给插件的输入事件
+enum ClapEvent {
+    Param(clap_event_param_value),
+    Midi(clap_event_midi),
+}
+
+impl ClapEvent {
+    fn header(&self) -> &clap_event_header {
+        match self {
+            ClapEvent::Param(event) => &event.header,
+            ClapEvent::Midi(event) => &event.header,
+        }
+    }
+}
+
+fn event_header<T>(time: u32, type_: u16) -> clap_event_header {
+    clap_event_header {
+        size: std::mem::size_of::<T>() as u32,
+        time,
+        space_id: CLAP_CORE_EVENT_SPACE_ID,
+        type_,
+        flags: 0,
+    }
+}
+
+unsafe extern "C" fn input_events_size(list: *const clap_input_events) -> u32 {
+    let events = &*((*list).ctx as *const Vec<ClapEvent>);
+    events.len() as u32
+}
+
+unsafe extern "C" fn input_events_get(list: *const clap_input_events, index: u32) -> *const clap_event_header {
+    let events = &*((*list).ctx as *const Vec<ClapEvent>);
+    match events.get(index as usize) {
+        Some(event) => event.header(),
+        None => ptr::null(),
+    }
+}
+
+unsafe extern "C" fn output_events_try_push(_list: *const clap_output_events, _event: *const clap_event_header) -> bool {
+    // 暂不处理插件输出的事件
+    true
+}
+
+// ===== 状态流 =====
+
+unsafe extern "C" fn ostream_write(stream: *const clap_ostream, buffer: *const c_void, size: u64) -> i64 {
+    let data = &mut *((*stream).ctx as *mut Vec<u8>);
+    data.extend_from_slice(std::slice::from_raw_parts(buffer as *const u8, size as usize));
+    size as i64
+}
+
+/// 读取游标
+struct StreamReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+unsafe extern "C" fn istream_read(stream: *const clap_istream, buffer: *mut c_void, size: u64) -> i64 {
+    let reader = &mut *((*stream).ctx as *mut StreamReader);
+    let remaining = &reader.data[reader.pos..];
+    let len = remaining.len().min(size as usize);
+    ptr::copy_nonoverlapping(remaining.as_ptr(), buffer as *mut u8, len);
+    reader.pos += len;
+    len as i64
+}
+
+/// 枚举参数最多列出多少个取值名称
+const MAX_ENUM_VALUES: u32 = 128;
+
+/// 参数信息缓存
+struct ClapParamInfo {
+    id: u32,
+    name: String,
+    min: f64,
+    max: f64,
+    default: f64,
+    flags: u32,
+}
+
+impl ClapParamInfo {
+    fn to_plain(&self, normalized: f64) -> f64 {
+        self.min + normalized.clamp(0.0, 1.0) * (self.max - self.min)
+    }
+
+    fn to_normalized(&self, plain: f64) -> f64 {
+        if self.max > self.min {
+            ((plain - self.min) / (self.max - self.min)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+/// CLAP 插件包装器
+pub struct ClapPlugin {
+    metadata: PluginMetadata,
+    plugin: *const clap_plugin,
+    params_ext: *const clap_plugin_params,
+    state_ext: *const clap_plugin_state,
+    latency_ext: *const clap_plugin_latency,
+    param_infos: Vec<ClapParamInfo>,
+
+    /// 待发送给插件的事件（按 time 排序，预分配 MAX_EVENTS 个，见 queue_event）
+    pending_events: Vec<ClapEvent>,
+
+    /// 主输入/输出端口的通道数
+    input_channels: u32,
+    output_channels: u32,
+    /// 有支持 MIDI 方言的音符输入端口
+    midi_input: bool,
+
+    /// 非交错的临时缓冲区
+    input_buffers: [Vec<f32>; 2],
+    output_buffers: [Vec<f32>; 2],
+
+    /// prepare 设定的采样率和最大块大小
+    sample_rate: f64,
+    max_block_size: usize,
+
+    activated: bool,
+    processing: bool,
+    /// reset 只能在音频线程调用，控制线程的请求留给下一次 process
+    reset_pending: bool,
+    steady_time: i64,
+
+    // 以下字段必须比插件实例活得更久
+    _host: Box<clap_host>,
+    _library: Arc<ClapLibrary>,
+}
+
+// 插件实例只会被持有者独占访问
+unsafe impl Send for ClapPlugin {}
+
+impl ClapPlugin {
+    /// 从 .clap 文件加载插件
+    /// plugin_id 为 None 时加载文件中的第一个插件
+    pub fn load(path: &Path, plugin_id: Option<&str>) -> Result<Self> {
+        info!("加载 CLAP 插件: {:?}", path);
+
+        let library = ClapLibrary::open(path)?;
+        Self::instantiate(library, path, plugin_id)
+    }
+
+    /// 从扫描信息加载插件
+    pub fn from_metadata(metadata: &PluginMetadata) -> Result<Self> {
+        let plugin_id = metadata.id.strip_prefix("clap:").unwrap_or(&metadata.id);
+        Self::load(&metadata.path, Some(plugin_id))
+    }
+
+    /// 通过工厂创建、初始化并激活插件实例
+    fn instantiate(library: Arc<ClapLibrary>, path: &Path, plugin_id: Option<&str>) -> Result<Self> {
+        let descriptors = library.descriptors()?;
+        let desc = match plugin_id {
+            Some(id) => descriptors.into_iter()
+                .find(|d| c_str_to_string(d.id) == id)
+                .ok_or_else(|| anyhow::anyhow!("找不到插件: {}", id))?,
+            None => descriptors.into_iter()
+                .next()
+                .ok_or_else(|| anyhow::anyhow!("文件中没有插件"))?,
+        };
+        let metadata = descriptor_to_metadata(desc, path);
+
+        let factory = library.factory()?;
+        let create_plugin = factory.create_plugin
+            .ok_or_else(|| anyhow::anyhow!("插件工厂缺少 create_plugin"))?;
+
+        let host = new_host();
+        let plugin = unsafe { create_plugin(factory, &*host, desc.id) };
+        if plugin.is_null() {
+            return Err(anyhow::anyhow!("创建插件实例失败: {}", metadata.name));
+        }
+
+        let mut instance = Self {
+            metadata,
+            plugin,
+            params_ext: ptr::null(),
+            state_ext: ptr::null(),
+            latency_ext: ptr::null(),
+            param_infos: Vec::new(),
+            pending_events: Vec::with_capacity(MAX_EVENTS),
+            input_channels: 2,
+            output_channels: 2,
+            midi_input: false,
+            input_buffers: [vec![0.0; MAX_BLOCK_SIZE], vec![0.0; MAX_BLOCK_SIZE]],
+            output_buffers: [vec![0.0; MAX_BLOCK_SIZE], vec![0.0; MAX_BLOCK_SIZE]],
+            sample_rate: DEFAULT_SAMPLE_RATE,
+            max_block_size: MAX_BLOCK_SIZE,
+            activated: false,
+            processing: false,
+            reset_pending: false,
+            steady_time: 0,
+            _host: host,
+            _library: library,
+        };
+
+        // 从这里开始，出错时由 Drop 负责销毁实例
+        let raw = instance.raw();
+        let init = raw.init.ok_or_else(|| anyhow::anyhow!("插件缺少 init"))?;
+        if !unsafe { init(plugin) } {
+            return Err(anyhow::anyhow!("插件初始化失败: {}", instance.metadata.name));
+        }
+
+        instance.params_ext = instance.extension(CLAP_EXT_PARAMS) as *const clap_plugin_params;
+        instance.state_ext = instance.extension(CLAP_EXT_STATE) as *const clap_plugin_state;
+        instance.latency_ext = instance.extension(CLAP_EXT_LATENCY) as *const clap_plugin_latency;
+        instance.query_audio_ports();
+        instance.query_note_ports();
+        instance.query_parameters();
+
+        instance.activate()?;
+
+        info!("CLAP 插件已加载: {} ({} 个参数)", instance.metadata.name, instance.param_infos.len());
+        Ok(instance)
+    }
+
+    fn raw(&self) -> clap_plugin {
+        unsafe { *self.plugin }
+    }
+
+    /// 按当前采样率和最大块大小激活插件
+    fn activate(&mut self) -> Result<()> {
+        let activate = self.raw().activate.ok_or_else(|| anyhow::anyhow!("插件缺少 activate"))?;
+        if !unsafe { activate(self.plugin, self.sample_rate, 1, self.max_block_size as u32) } {
+            return Err(anyhow::anyhow!("插件激活失败: {}", self.metadata.name));
+        }
+        self.activated = true;
+        Ok(())
+    }
+
+    /// 停用插件（控制线程），处理状态应该已经由 suspend 在音频线程结束
+    fn deactivate(&mut self) {
+        // 音频流出错直接释放时音频线程来不及 suspend，只能在这里补上 stop_processing
+        self.stop_processing();
+        let raw = self.raw();
+        unsafe {
+            if self.activated {
+                if let Some(deactivate) = raw.deactivate {
+                    deactivate(self.plugin);
+                }
+                self.activated = false;
+            }
+        }
+        // 重新激活的插件本来就是干净的状态
+        self.reset_pending = false;
+    }
+
+    fn extension(&self, id: &CStr) -> *const c_void {
+        match self.raw().get_extension {
+            Some(get_extension) => unsafe { get_extension(self.plugin, id.as_ptr()) },
+            None => ptr::null(),
+        }
+    }
+
+    /// 读取主输入/输出端口的通道数
+    fn query_audio_ports(&mut self) {
+        let ports = self.extension(CLAP_EXT_AUDIO_PORTS) as *const clap_plugin_audio_ports;
+        if ports.is_null() {
+            debug!("插件未提供 audio-ports 扩展，假定立体声");
+            return;
+        }
+
+        let ports = unsafe { &*ports };
+        let (Some(count), Some(get)) = (ports.count, ports.get) else {
+            return;
+        };
+
+        let main_channels = |is_input: bool| -> u32 {
+            if unsafe { count(self.plugin, is_input) } == 0 {
+                return 0;
+            }
+            let mut port_info: clap_audio_port_info = unsafe { std::mem::zeroed() };
+            if unsafe { get(self.plugin, 0, is_input, &mut port_info) } {
+                port_info.channel_count.min(2)
+            } else {
+                2
+            }
+        };
+
+        self.input_channels = main_channels(true);
+        self.output_channels = main_channels(false);
+        self.metadata.num_inputs = self.input_channels;
+        self.metadata.num_outputs = self.output_channels;
+    }
+
+    /// 检查是否有接受 MIDI 的音符输入端口
+    fn query_note_ports(&mut self) {
+        let ports = self.extension(CLAP_EXT_NOTE_PORTS) as *const clap_plugin_note_ports;
+        if ports.is_null() {
+            return;
+        }
+
+        let ports = unsafe { &*ports };
+        let (Some(count), Some(get)) = (ports.count, ports.get) else {
+            return;
+        };
+
+        self.midi_input = (0..unsafe { count(self.plugin, true) }).any(|index| {
+            let mut port_info: clap_note_port_info = unsafe { std::mem::zeroed() };
+            let found = unsafe { get(self.plugin, index, true, &mut port_info) };
+            found && port_info.supported_dialects & CLAP_NOTE_DIALECT_MIDI != 0
+        });
+    }
+
+    /// 缓存参数列表
+    fn query_parameters(&mut self) {
+        let Some(params) = self.params() else {
+            return;
+        };
+        let (Some(count), Some(get_info)) = (params.count, params.get_info) else {
+            return;
+        };
+
+        for index in 0..unsafe { count(self.plugin) } {
+            let mut param_info: clap_param_info = unsafe { std::mem::zeroed() };
+            if unsafe { get_info(self.plugin, index, &mut param_info) } {
+                self.param_infos.push(ClapParamInfo {
+                    id: param_info.id,
+                    name: c_array_to_string(&param_info.name),
+                    min: param_info.min_value,
+                    max: param_info.max_value,
+                    default: param_info.default_value,
+                    flags: param_info.flags,
+                });
+            }
+        }
+    }
+
+    fn params(&self) -> Option<&clap_plugin_params> {
+        if self.params_ext.is_null() {
+            None
+        } else {
+            Some(unsafe { &*self.params_ext })
+        }
+    }
+
+    fn param_info(&self, id: u32) -> Option<&ClapParamInfo> {
+        self.param_infos.iter().find(|p| p.id == id)
+    }
+
+    /// 读取参数的实际值
+    fn plain_value(&self, id: u32) -> Option<f64> {
+        let get_value = self.params()?.get_value?;
+        let mut value = 0.0;
+        if unsafe { get_value(self.plugin, id, &mut value) } {
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    /// 参数值的显示文本
+    fn value_text(&self, id: u32, value: f64) -> String {
+        if let Some(value_to_text) = self.params().and_then(|p| p.value_to_text) {
+            let mut text = [0 as c_char; 64];
+            if unsafe { value_to_text(self.plugin, id, value, text.as_mut_ptr(), text.len() as u32) } {
+                return c_array_to_string(&text);
+            }
+        }
+        format!("{:.2}", value)
+    }
+
+    fn descriptor(&self, param: &ClapParamInfo) -> ParameterDescriptor {
+        let flags = ParameterFlags {
+            automatable: param.flags & CLAP_PARAM_IS_AUTOMATABLE != 0,
+            read_only: param.flags & CLAP_PARAM_IS_READONLY != 0,
+            bypass: param.flags & CLAP_PARAM_IS_BYPASS != 0,
+            hidden: param.flags & CLAP_PARAM_IS_HIDDEN != 0,
+        };
+        let mut descriptor = ParameterDescriptor::new(param.id, param.name.clone(), param.min, param.max, param.default)
+            .with_flags(flags);
+
+        // CLAP 没有单位字段，步进参数的每个整数值都是一步
+        if param.flags & CLAP_PARAM_IS_STEPPED != 0 {
+            let steps = (param.max - param.min).round().max(1.0) as u32;
+            descriptor = descriptor.with_steps(steps);
+            if param.flags & CLAP_PARAM_IS_ENUM != 0 && steps < MAX_ENUM_VALUES {
+                let names = (0..=steps)
+                    .map(|step| {
+                        let value = param.min + step as f64;
+                        (value, self.value_text(param.id, value))
+                    })
+                    .collect();
+                descriptor = descriptor.with_value_names(names);
+            }
+        }
+        descriptor
+    }
+
+    /// 把事件放进待发送队列（可能在音频线程调用，不分配内存）
+    /// 队列已满时同一参数的新值覆盖已排队的值，其它事件丢弃
+    fn queue_event(&mut self, event: ClapEvent) {
+        if self.pending_events.len() < MAX_EVENTS {
+            self.pending_events.push(event);
+            return;
+        }
+        if let ClapEvent::Param(param) = event {
+            let queued = self.pending_events.iter_mut().rev().find_map(|queued| match queued {
+                ClapEvent::Param(queued) if queued.param_id == param.param_id => Some(queued),
+                _ => None,
+            });
+            if let Some(queued) = queued {
+                queued.value = param.value;
+            }
+        }
+    }
+
+    /// 在非处理状态下立即把待发送的参数事件交给插件
+    fn flush_events(&mut self) {
+        let Some(flush) = self.params().and_then(|p| p.flush) else {
+            return;
+        };
+
+        let in_events = self.input_events();
+        let out_events = Self::output_events();
+        unsafe { flush(self.plugin, &in_events, &out_events) };
+        self.pending_events.clear();
+    }
+
+    fn input_events(&mut self) -> clap_input_events {
+        clap_input_events {
+            ctx: &mut self.pending_events as *mut Vec<ClapEvent> as *mut c_void,
+            size: Some(input_events_size),
+            get: Some(input_events_get),
+        }
+    }
+
+    fn output_events() -> clap_output_events {
+        clap_output_events {
+            ctx: ptr::null_mut(),
+            try_push: Some(output_events_try_push),
+        }
+    }
+
+    /// 把通用事件翻译成 CLAP 事件，time 为块内的帧偏移
+    fn translate_event(&self, time: u32, kind: &EventKind) -> Option<ClapEvent> {
+        match *kind {
+            EventKind::Parameter { id, value } => {
+                let param = self.param_info(id)?;
+                Some(ClapEvent::Param(clap_event_param_value {
+                    header: event_header::<clap_event_param_value>(time, CLAP_EVENT_PARAM_VALUE),
+                    param_id: id,
+                    cookie: ptr::null_mut(),
+                    note_id: -1,
+                    port_index: -1,
+                    channel: -1,
+                    key: -1,
+                    value: param.to_plain(value),
+                }))
+            }
+            EventKind::Midi { data } => Some(ClapEvent::Midi(clap_event_midi {
+                header: event_header::<clap_event_midi>(time, CLAP_EVENT_MIDI),
+                port_index: 0,
+                data,
+            })),
+        }
+    }
+
+    /// 确保插件处于处理状态，未激活时返回 false（直通）
+    fn start_processing(&mut self) -> bool {
+        if !self.activated {
+            // 未激活（release 之后或激活失败）时直通
+            return false;
+        }
+        if !self.processing {
+            // start_processing 必须在音频线程中调用
+            let started = match self.raw().start_processing {
+                Some(start) => unsafe { start(self.plugin) },
+                None => true,
+            };
+            if !started {
+                return false;
+            }
+            self.processing = true;
+        }
+        true
+    }
+
+    /// 结束处理状态（音频线程）
+    fn stop_processing(&mut self) {
+        if self.processing {
+            if let Some(stop) = self.raw().stop_processing {
+                unsafe { stop(self.plugin) };
+            }
+            self.processing = false;
+        }
+    }
+
+    /// 执行控制线程请求的 reset（音频线程，插件已经处于处理状态）
+    fn apply_pending_reset(&mut self) {
+        if std::mem::take(&mut self.reset_pending) {
+            if let Some(reset) = self.raw().reset {
+                unsafe { reset(self.plugin) };
+            }
+        }
+    }
+
+    /// 处理一个不超过 max_block_size 帧的块
+    fn process_block(&mut self, block: &mut AudioBuffer) {
+        let frames = block.frames();
+        let Some(process) = self.raw().process else {
+            return;
+        };
+
+        // 缓冲区声道 -> 插件输入声道
+        let inputs = self.input_channels as usize;
+        for (channel, buffer) in self.input_buffers.iter_mut().take(inputs).enumerate() {
+            block.read_mapped(channel, inputs, &mut buffer[..frames]);
+        }
+
+        let mut input_ptrs = [self.input_buffers[0].as_mut_ptr(), self.input_buffers[1].as_mut_ptr()];
+        let mut output_ptrs = [self.output_buffers[0].as_mut_ptr(), self.output_buffers[1].as_mut_ptr()];
+
+        let input = clap_audio_buffer {
+            data32: input_ptrs.as_mut_ptr(),
+            data64: ptr::null_mut(),
+            channel_count: self.input_channels,
+            latency: 0,
+            constant_mask: 0,
+        };
+        let mut output = clap_audio_buffer {
+            data32: output_ptrs.as_mut_ptr(),
+            data64: ptr::null_mut(),
+            channel_count: self.output_channels,
+            latency: 0,
+            constant_mask: 0,
+        };
+
+        let in_events = self.input_events();
+        let out_events = Self::output_events();
+
+        let process_data = clap_process {
+            steady_time: self.steady_time,
+            frames_count: frames as u32,
+            transport: ptr::null(),
+            audio_inputs: &input,
+            audio_outputs: &mut output,
+            audio_inputs_count: u32::from(self.input_channels > 0),
+            audio_outputs_count: u32::from(self.output_channels > 0),
+            in_events: &in_events,
+            out_events: &out_events,
+        };
+
+        let status = unsafe { process(self.plugin, &process_data) };
+        self.pending_events.clear();
+        self.steady_time += frames as i64;
+
+        if status == CLAP_PROCESS_ERROR || self.output_channels == 0 {
+            // 出错时保持原始音频（直通）
+            return;
+        }
+
+        // 插件输出声道 -> 缓冲区声道
+        let [out_left, out_right] = &self.output_buffers;
+        let outputs = self.output_channels as usize;
+        block.write_mapped(&[&out_left[..frames], &out_right[..frames]][..outputs]);
+    }
+}
+
+impl AudioProcessor for ClapPlugin {
+    fn prepare(&mut self, sample_rate: f64, max_block_size: usize, _layout: ChannelLayout) {
+        // CLAP 只能在停用状态下改变采样率和块大小
+        self.deactivate();
+        self.sample_rate = sample_rate;
+        self.max_block_size = max_block_size.max(1);
+        for buffer in self.input_buffers.iter_mut().chain(&mut self.output_buffers) {
+            buffer.resize(self.max_block_size, 0.0);
+        }
+
+        if let Err(e) = self.activate() {
+            warn!("{}", e);
+        }
+    }
+
+    fn reset(&mut self) {
+        // CLAP 规定 reset 在音频线程调用，留给下一次 process
+        self.reset_pending = self.activated;
+    }
+
+    fn release(&mut self) {
+        self.deactivate();
+    }
+
+    fn suspend(&mut self) {
+        self.stop_processing();
+    }
+
+    fn latency_samples(&self) -> u32 {
+        // CLAP 规定只在激活后查询
+        if !self.activated || self.latency_ext.is_null() {
+            return 0;
+        }
+        match unsafe { (*self.latency_ext).get } {
+            Some(get) => unsafe { get(self.plugin) },
+            None => 0,
+        }
+    }
+
+    fn process(&mut self, buffer: &mut AudioBuffer) {
+        if !self.start_processing() {
+            return;
+        }
+        self.apply_pending_reset();
+
+        let block_size = self.max_block_size;
+        for start in (0..buffer.frames()).step_by(block_size) {
+            self.process_block(&mut buffer.slice(start, start + block_size));
+        }
+    }
+
+    fn process_with_events(&mut self, buffer: &mut AudioBuffer, events: &EventList) {
+        if !self.start_processing() {
+            // 不处理音频时参数仍然要生效
+            for event in events.iter() {
+                if let EventKind::Parameter { id, value } = event.kind {
+                    self.set_parameter(id, value);
+                }
+            }
+            return;
+        }
+        self.apply_pending_reset();
+
+        // 事件带着块内偏移随 process 一起交给插件（CLAP 原生的采样级事件）
+        let block_size = self.max_block_size;
+        let frames = buffer.frames();
+        for block_start in (0..frames).step_by(block_size) {
+            let mut block = buffer.slice(block_start, block_start + block_size);
+            let start = block_start as u32;
+            let end = if block_start + block.frames() == frames { u32::MAX } else { start + block_size as u32 };
+            let last_frame = block.frames().saturating_sub(1) as u32;
+
+            for event in events.range(start, end) {
+                let time = (event.offset - start).min(last_frame);
+                if let Some(event) = self.translate_event(time, &event.kind) {
+                    self.queue_event(event);
+                }
+            }
+            self.process_block(&mut block);
+        }
+    }
+
+    fn accepts_midi(&self) -> bool {
+        self.midi_input
+    }
+
+    fn get_info(&self) -> &PluginMetadata {
+        &self.metadata
+    }
+
+    fn set_parameter(&mut self, id: u32, value: f64) {
+        // 旁通时在音频线程调用：未知的参数直接忽略，不写日志
+        let Some(event) = self.translate_event(0, &EventKind::Parameter { id, value }) else {
+            return;
+        };
+        self.queue_event(event);
+
+        if !self.processing {
+            self.flush_events();
+        }
+    }
+
+    fn get_parameter(&self, id: u32) -> Option<f64> {
+        let param = self.param_info(id)?;
+        self.plain_value(id).map(|value| param.to_normalized(value))
+    }
+
+    fn parameter_descriptors(&self) -> Vec<ParameterDescriptor> {
+        self.param_infos.iter().map(|param| self.descriptor(param)).collect()
+    }
+
+    fn value_to_text(&self, id: u32, value: f64) -> Option<String> {
+        let param = self.param_info(id)?;
+        Some(self.value_text(id, param.to_plain(value)))
+    }
+
+    fn text_to_value(&self, id: u32, text: &str) -> Option<f64> {
+        let param = self.param_info(id)?;
+        if let Some(text_to_value) = self.params().and_then(|p| p.text_to_value) {
+            let text_c = CString::new(text).ok()?;
+            let mut value = 0.0;
+            if unsafe { text_to_value(self.plugin, id, text_c.as_ptr(), &mut value) } {
+                return Some(param.to_normalized(value));
+            }
+        }
+        self.descriptor(param).text_to_value(text)
+    }
+
+    fn get_all_parameters(&self) -> Vec<PluginParameter> {
+        self.param_infos
+            .iter()
+            .map(|param| {
+                let plain = self.plain_value(param.id).unwrap_or(param.min);
+                PluginParameter {
+                    id: param.id,
+                    name: param.name.clone(),
+                    value: param.to_normalized(plain),
+                    display: self.value_text(param.id, plain),
+                }
+            })
+            .collect()
+    }
+
+    fn save_state(&self) -> PluginState {
+        let mut data: Vec<u8> = Vec::new();
+
+        if !self.state_ext.is_null() {
+            if let Some(save) = unsafe { (*self.state_ext).save } {
+                let stream = clap_ostream {
+                    ctx: &mut data as *mut Vec<u8> as *mut c_void,
+                    write: Some(ostream_write),
+                };
+                if !unsafe { save(self.plugin, &stream) } {
+                    warn!("CLAP 插件保存状态失败: {}", self.metadata.name);
+                    data.clear();
+                }
+            }
+        }
+
+        PluginState {
+            plugin_id: self.metadata.id.clone(),
+            parameters: self.get_all_parameters(),
+            state_data: base64::engine::general_purpose::STANDARD.encode(&data),
+        }
+    }
+
+    fn load_state(&mut self, state: &PluginState) {
+        let data = match base64::engine::general_purpose::STANDARD.decode(&state.state_data) {
+            Ok(data) => data,
+            Err(e) => {
+                warn!("状态数据解码失败: {}", e);
+                Vec::new()
+            }
+        };
+
+        let load = if self.state_ext.is_null() {
+            None
+        } else {
+            unsafe { (*self.state_ext).load }
+        };
+
+        if let (Some(load), false) = (load, data.is_empty()) {
+            let mut reader = StreamReader { data: &data, pos: 0 };
+            let stream = clap_istream {
+                ctx: &mut reader as *mut StreamReader as *mut c_void,
+                read: Some(istream_read),
+            };
+            if unsafe { load(self.plugin, &stream) } {
+                return;
+            }
+            warn!("CLAP 插件加载状态失败，改为恢复参数: {}", self.metadata.name);
+        }
+
+        // 没有二进制状态时逐个恢复参数
+        for param in &state.parameters {
+            self.set_parameter(param.id, param.value);
+        }
+    }
+}
+
+impl Drop for ClapPlugin {
+    fn drop(&mut self) {
+        self.deactivate();
+        if let Some(destroy) = self.raw().destroy {
+            unsafe { destroy(self.plugin) };
+        }
+        debug!("CLAP 插件已销毁: {}", self.metadata.name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::with_interleaved;
+    use crate::plugin::events::ProcessEvent;
+    use clap_sys::ext::note_ports::CLAP_NOTE_DIALECT_CLAP;
+    use clap_sys::process::{clap_process_status, CLAP_PROCESS_CONTINUE};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // 一个进程内的最小 CLAP 增益插件，用于测试宿主逻辑
+
+    struct TestGain {
+        gain: f64,
+        sample_rate: f64,
+        max_frames: u32,
+        processing: bool,
+        resets: u32,
+        /// 处理状态下被停用（宿主没有先 stop_processing）
+        deactivated_while_processing: bool,
+    }
+
+    static DESCRIPTOR: clap_plugin_descriptor = clap_plugin_descriptor {
+        clap_version: CLAP_VERSION,
+        id: c"test.gain".as_ptr(),
+        name: c"Test Gain".as_ptr(),
+        vendor: c"Test".as_ptr(),
+        url: c"".as_ptr(),
+        manual_url: c"".as_ptr(),
+        support_url: c"".as_ptr(),
+        version: c"1.0".as_ptr(),
+        description: c"".as_ptr(),
+        features: ptr::null(),
+    };
+
+    static FACTORY: clap_plugin_factory = clap_plugin_factory {
+        get_plugin_count: Some(factory_count),
+        get_plugin_descriptor: Some(factory_descriptor),
+        create_plugin: Some(factory_create),
+    };
+
+    static ENTRY: clap_plugin_entry = clap_plugin_entry {
+        clap_version: CLAP_VERSION,
+        init: Some(entry_init),
+        deinit: Some(entry_deinit),
+        get_factory: Some(entry_get_factory),
+    };
+
+    static PARAMS: clap_plugin_params = clap_plugin_params {
+        count: Some(params_count),
+        get_info: Some(params_get_info),
+        get_value: Some(params_get_value),
+        value_to_text: None,
+        text_to_value: None,
+        flush: Some(params_flush),
+    };
+
+    static STATE: clap_plugin_state = clap_plugin_state {
+        save: Some(state_save),
+        load: Some(state_load),
+    };
+
+    // 一个只支持 CLAP 音符方言的输入端口和一个 MIDI 输入端口
+    static NOTE_PORTS: clap_plugin_note_ports = clap_plugin_note_ports {
+        count: Some(note_ports_count),
+        get: Some(note_ports_get),
+    };
+
+    unsafe extern "C" fn note_ports_count(_p: *const clap_plugin, is_input: bool) -> u32 {
+        if is_input { 2 } else { 0 }
+    }
+    unsafe extern "C" fn note_ports_get(_p: *const clap_plugin, index: u32, _is_input: bool, info: *mut clap_note_port_info) -> bool {
+        let info = &mut *info;
+        info.id = index;
+        info.supported_dialects = if index == 0 { CLAP_NOTE_DIALECT_CLAP } else { CLAP_NOTE_DIALECT_MIDI };
+        info.preferred_dialect = info.supported_dialects;
+        true
+    }
+
+    unsafe extern "C" fn entry_init(_path: *const c_char) -> bool { true }
+    unsafe extern "C" fn entry_deinit() {}
+
+    // 只给共享测试使用的入口，记录 init / deinit 的次数
+    static COUNTED_ENTRY: clap_plugin_entry = clap_plugin_entry {
+        clap_version: CLAP_VERSION,
+        init: Some(counted_init),
+        deinit: Some(counted_deinit),
+        get_factory: Some(entry_get_factory),
+    };
+    static INIT_COUNT: AtomicUsize = AtomicUsize::new(0);
+    static DEINIT_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    unsafe extern "C" fn counted_init(_path: *const c_char) -> bool {
+        INIT_COUNT.fetch_add(1, Ordering::SeqCst);
+        true
+    }
+    unsafe extern "C" fn counted_deinit() {
+        DEINIT_COUNT.fetch_add(1, Ordering::SeqCst);
+    }
+    unsafe extern "C" fn entry_get_factory(id: *const c_char) -> *const c_void {
+        if CStr::from_ptr(id) == CLAP_PLUGIN_FACTORY_ID {
+            &FACTORY as *const clap_plugin_factory as *const c_void
+        } else {
+            ptr::null()
+        }
+    }
+
+    unsafe extern "C" fn factory_count(_f: *const clap_plugin_factory) -> u32 { 1 }
+    unsafe extern "C" fn factory_descriptor(_f: *const clap_plugin_factory, _i: u32) -> *const clap_plugin_descriptor {
+        &DESCRIPTOR
+    }
+    unsafe extern "C" fn factory_create(_f: *const clap_plugin_factory, _h: *const clap_host, _id: *const c_char) -> *const clap_plugin {
+        Box::into_raw(Box::new(clap_plugin {
+            desc: &DESCRIPTOR,
+            plugin_data: Box::into_raw(Box::new(TestGain {
+                gain: 1.0,
+                sample_rate: 0.0,
+                max_frames: 0,
+                processing: false,
+                resets: 0,
+                deactivated_while_processing: false,
+            })) as *mut c_void,
+            init: Some(plugin_init),
+            destroy: Some(plugin_destroy),
+            activate: Some(plugin_activate),
+            deactivate: Some(plugin_deactivate),
+            start_processing: Some(plugin_start),
+            stop_processing: Some(plugin_stop),
+            reset: Some(plugin_reset),
+            process: Some(plugin_process),
+            get_extension: Some(plugin_get_extension),
+            on_main_thread: Some(plugin_noop),
+        }))
+    }
+
+    unsafe fn gain_of<'a>(plugin: *const clap_plugin) -> &'a mut TestGain {
+        &mut *((*plugin).plugin_data as *mut TestGain)
+    }
+
+    unsafe fn apply_events(plugin: *const clap_plugin, events: *const clap_input_events) {
+        let events = &*events;
+        for i in 0..events.size.unwrap()(events) {
+            let header = events.get.unwrap()(events, i);
+            if (*header).type_ == CLAP_EVENT_PARAM_VALUE {
+                gain_of(plugin).gain = (*(header as *const clap_event_param_value)).value;
+            }
+        }
+    }
+
+    unsafe extern "C" fn plugin_init(_p: *const clap_plugin) -> bool { true }
+    unsafe extern "C" fn plugin_noop(_p: *const clap_plugin) {}
+    unsafe extern "C" fn plugin_destroy(plugin: *const clap_plugin) {
+        let plugin = Box::from_raw(plugin as *mut clap_plugin);
+        drop(Box::from_raw(plugin.plugin_data as *mut TestGain));
+    }
+    unsafe extern "C" fn plugin_activate(plugin: *const clap_plugin, sr: f64, _min: u32, max: u32) -> bool {
+        gain_of(plugin).sample_rate = sr;
+        gain_of(plugin).max_frames = max;
+        true
+    }
+    unsafe extern "C" fn plugin_deactivate(plugin: *const clap_plugin) {
+        let gain = gain_of(plugin);
+        gain.deactivated_while_processing |= gain.processing;
+    }
+    unsafe extern "C" fn plugin_start(plugin: *const clap_plugin) -> bool {
+        gain_of(plugin).processing = true;
+        true
+    }
+    unsafe extern "C" fn plugin_stop(plugin: *const clap_plugin) {
+        gain_of(plugin).processing = false;
+    }
+    unsafe extern "C" fn plugin_reset(plugin: *const clap_plugin) {
+        gain_of(plugin).resets += 1;
+    }
+    unsafe extern "C" fn plugin_process(plugin: *const clap_plugin, process: *const clap_process) -> clap_process_status {
+        let process = &*process;
+        if process.frames_count > gain_of(plugin).max_frames {
+            return CLAP_PROCESS_ERROR;
+        }
+        // 按事件的 time 在块内生效
+        let events = &*process.in_events;
+        let count = events.size.unwrap()(events);
+        let mut next = 0;
+        let input = &*process.audio_inputs;
+        let output = &*process.audio_outputs;
+        for i in 0..process.frames_count {
+            while next < count {
+                let header = events.get.unwrap()(events, next);
+                if (*header).time > i {
+                    break;
+                }
+                if (*header).type_ == CLAP_EVENT_PARAM_VALUE {
+                    gain_of(plugin).gain = (*(header as *const clap_event_param_value)).value;
+                }
+                next += 1;
+            }
+            let gain = gain_of(plugin).gain as f32;
+            for ch in 0..2 {
+                let src = *input.data32.add(ch);
+                let dst = *output.data32.add(ch);
+                *dst.add(i as usize) = *src.add(i as usize) * gain;
+            }
+        }
+        CLAP_PROCESS_CONTINUE
+    }
+    unsafe extern "C" fn plugin_get_extension(_p: *const clap_plugin, id: *const c_char) -> *const c_void {
+        let id = CStr::from_ptr(id);
+        if id == CLAP_EXT_PARAMS {
+            &PARAMS as *const clap_plugin_params as *const c_void
+        } else if id == CLAP_EXT_STATE {
+            &STATE as *const clap_plugin_state as *const c_void
+        } else if id == CLAP_EXT_NOTE_PORTS {
+            &NOTE_PORTS as *const clap_plugin_note_ports as *const c_void
+        } else {
+            ptr::null()
+        }
+    }
+
+    unsafe extern "C" fn params_count(_p: *const clap_plugin) -> u32 { 1 }
+    unsafe extern "C" fn params_get_info(_p: *const clap_plugin, _index: u32, info: *mut clap_param_info) -> bool {
+        let info = &mut *info;
+        info.id = 0;
+        info.flags = CLAP_PARAM_IS_AUTOMATABLE;
+        for (dst, src) in info.name.iter_mut().zip(b"Gain\0") {
+            *dst = *src as c_char;
+        }
+        info.min_value = 0.0;
+        info.max_value = 2.0;
+        info.default_value = 1.0;
+        true
+    }
+    unsafe extern "C" fn params_get_value(plugin: *const clap_plugin, _id: u32, value: *mut f64) -> bool {
+        *value = gain_of(plugin).gain;
+        true
+    }
+    unsafe extern "C" fn params_flush(plugin: *const clap_plugin, events: *const clap_input_events, _out: *const clap_output_events) {
+        apply_events(plugin, events);
+    }
+
+    unsafe extern "C" fn state_save(plugin: *const clap_plugin, stream: *const clap_ostream) -> bool {
+        let bytes = gain_of(plugin).gain.to_le_bytes();
+        (*stream).write.unwrap()(stream, bytes.as_ptr() as *const c_void, 8) == 8
+    }
+    unsafe extern "C" fn state_load(plugin: *const clap_plugin, stream: *const clap_istream) -> bool {
+        let mut bytes = [0u8; 8];
+        if (*stream).read.unwrap()(stream, bytes.as_mut_ptr() as *mut c_void, 8) != 8 {
+            return false;
+        }
+        gain_of(plugin).gain = f64::from_le_bytes(bytes);
+        true
+    }
+
+    fn load_test_plugin() -> ClapPlugin {
+        let path = Path::new("/test/gain.clap");
+        let library = ClapLibrary::shared(path, || ClapLibrary::init(&ENTRY, None, path)).unwrap();
+        ClapPlugin::instantiate(library, path, None).unwrap()
+    }
+
+    #[test]
+    fn test_clap_metadata() {
+        let plugin = load_test_plugin();
+        assert_eq!(plugin.get_info().id, "clap:test.gain");
+        assert_eq!(plugin.get_info().format, PluginFormat::Clap);
+        assert!(plugin.accepts_midi());
+
+        let params = plugin.get_all_parameters();
+        assert_eq!(params.len(), 1);
+        assert_eq!(params[0].name, "Gain");
+        assert!((params[0].value - 0.5).abs() < 1e-9);
+
+        let descriptors = plugin.parameter_descriptors();
+        assert_eq!(descriptors.len(), 1);
+        assert_eq!((descriptors[0].min, descriptors[0].max, descriptors[0].default), (0.0, 2.0, 1.0));
+        assert!(descriptors[0].flags.automatable);
+        assert!(!descriptors[0].flags.read_only);
+        // 插件没有 value_to_text / text_to_value 时退回到描述的换算
+        assert_eq!(plugin.value_to_text(0, 0.25).unwrap(), "0.50");
+        assert_eq!(plugin.text_to_value(0, "1.5"), Some(0.75));
+    }
+
+    #[test]
+    fn test_clap_process_gain() {
+        let mut plugin = load_test_plugin();
+
+        // 处理开始前通过 flush 生效
+        plugin.set_parameter(0, 0.25);
+        let mut buffer = vec![1.0; 8];
+        with_interleaved(&mut buffer, 2, |buffer| plugin.process(buffer));
+        assert!(buffer.iter().all(|&s| (s - 0.5).abs() < 1e-6));
+
+        // 处理开始后通过事件生效
+        plugin.set_parameter(0, 1.0);
+        let mut buffer = vec![1.0; 8];
+        with_interleaved(&mut buffer, 2, |buffer| plugin.process(buffer));
+        assert!(buffer.iter().all(|&s| (s - 2.0).abs() < 1e-6));
+        assert!((plugin.get_parameter(0).unwrap() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_clap_sample_accurate_events() {
+        let mut plugin = load_test_plugin();
+        plugin.prepare(48000.0, 4, ChannelLayout::Stereo);
+
+        // 第 6 帧（第二个块内的第 2 帧）把增益改为 2.0
+        let mut events = EventList::new();
+        events.push(ProcessEvent::parameter(6, 0, 1.0));
+        events.push(ProcessEvent::midi(1, [0x90, 60, 100]));
+        let mut buffer = vec![1.0; 8 * 2];
+        with_interleaved(&mut buffer, 2, |buffer| plugin.process_with_events(buffer, &events));
+
+        let left: Vec<f32> = buffer.chunks(2).map(|f| f[0]).collect();
+        assert_eq!(left, vec![1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 2.0, 2.0]);
+        assert!((plugin.get_parameter(0).unwrap() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_clap_event_queue_is_bounded() {
+        let mut plugin = load_test_plugin();
+        let mut buffer = vec![1.0; 8];
+        with_interleaved(&mut buffer, 2, |buffer| plugin.process(buffer));
+
+        // 处理状态下的参数排队到下一次 process，队列满后同一参数的新值覆盖旧值
+        for _ in 0..MAX_EVENTS * 2 {
+            plugin.set_parameter(0, 1.0);
+        }
+        plugin.set_parameter(0, 0.25);
+        assert_eq!(plugin.pending_events.len(), MAX_EVENTS);
+
+        let mut buffer = vec![1.0; 8];
+        with_interleaved(&mut buffer, 2, |buffer| plugin.process(buffer));
+        assert!(buffer.iter().all(|&s| (s - 0.5).abs() < 1e-6));
+    }
+
+    #[test]
+    fn test_clap_state_roundtrip() {
+        let mut plugin = load_test_plugin();
+        plugin.set_parameter(0, 0.75);
+        let state = plugin.save_state();
+        assert!(!state.state_data.is_empty());
+
+        let mut restored = load_test_plugin();
+        restored.load_state(&state);
+        assert!((restored.get_parameter(0).unwrap() - 0.75).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_clap_prepare_and_release() {
+        let mut plugin = load_test_plugin();
+        plugin.set_parameter(0, 0.25);
+
+        plugin.prepare(96000.0, 16, ChannelLayout::Stereo);
+        let gain = unsafe { gain_of(plugin.plugin) };
+        assert_eq!(gain.sample_rate, 96000.0);
+        assert_eq!(gain.max_frames, 16);
+
+        // 大于最大块大小的缓冲区被拆开处理
+        let mut buffer = vec![1.0; 100 * 2];
+        with_interleaved(&mut buffer, 2, |buffer| plugin.process(buffer));
+        assert!(buffer.iter().all(|&s| (s - 0.5).abs() < 1e-6));
+
+        // release 之后直通
+        plugin.release();
+        let mut buffer = vec![1.0; 8];
+        with_interleaved(&mut buffer, 2, |buffer| plugin.process(buffer));
+        assert!(buffer.iter().all(|&s| s == 1.0));
+    }
+
+    #[test]
+    fn test_clap_reset_and_stop_on_audio_thread() {
+        let mut plugin = load_test_plugin();
+        let mut buffer = vec![1.0; 8];
+        with_interleaved(&mut buffer, 2, |buffer| plugin.process(buffer));
+        assert!(unsafe { gain_of(plugin.plugin) }.processing);
+
+        // reset 留到下一次 process（音频线程）执行
+        plugin.reset();
+        assert_eq!(unsafe { gain_of(plugin.plugin) }.resets, 0);
+        with_interleaved(&mut buffer, 2, |buffer| plugin.process(buffer));
+        assert_eq!(unsafe { gain_of(plugin.plugin) }.resets, 1);
+
+        // suspend 在音频线程结束处理，控制线程的 prepare 只停用和激活
+        plugin.suspend();
+        assert!(!unsafe { gain_of(plugin.plugin) }.processing);
+        plugin.prepare(44100.0, 32, ChannelLayout::Stereo);
+        plugin.release();
+        assert!(!unsafe { gain_of(plugin.plugin) }.deactivated_while_processing);
+    }
+
+    #[test]
+    fn test_clap_library_shared_between_instances() {
+        let path = Path::new("/test/counted.clap");
+        let open = || ClapLibrary::shared(path, || ClapLibrary::init(&COUNTED_ENTRY, None, path)).unwrap();
+        let mut first = ClapPlugin::instantiate(open(), path, None).unwrap();
+        let mut second = ClapPlugin::instantiate(open(), path, None).unwrap();
+        assert_eq!(INIT_COUNT.load(Ordering::SeqCst), 1);
+
+        // 移除一个实例后入口仍然有效，另一个实例继续处理
+        let mut buffer = vec![1.0; 8];
+        with_interleaved(&mut buffer, 2, |buffer| first.process(buffer));
+        drop(first);
+        assert_eq!(DEINIT_COUNT.load(Ordering::SeqCst), 0);
+        second.set_parameter(0, 0.25);
+        let mut buffer = vec![1.0; 8];
+        with_interleaved(&mut buffer, 2, |buffer| second.process(buffer));
+        assert!(buffer.iter().all(|&s| (s - 0.5).abs() < 1e-6));
+
+        // 最后一个实例销毁后才 deinit，再次打开时重新 init
+        drop(second);
+        assert_eq!(DEINIT_COUNT.load(Ordering::SeqCst), 1);
+        drop(open());
+        assert_eq!((INIT_COUNT.load(Ordering::SeqCst), DEINIT_COUNT.load(Ordering::SeqCst)), (2, 2));
+    }
+
+    #[test]
+    fn test_load_missing_file() {
+        assert!(ClapPlugin::load(Path::new("/nonexistent/missing.clap"), None).is_err());
+    }
+}
@@ -0,0 +1,125 @@
+// 处理事件
+// 参数变化（以及 MIDI 消息）带着在当前缓冲区内的采样偏移传给 process，
+// 各插件格式再把它们翻译成自己的事件机制
+
+/// 每个事件列表预分配的容量，音频线程中不会扩容
+pub const MAX_EVENTS: usize = 512;
+
+/// 事件内容
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EventKind {
+    /// 参数变化（归一化值 0.0 - 1.0）
+    Parameter { id: u32, value: f64 },
+    /// MIDI 短消息（音符、控制器、程序切换等）
+    Midi { data: [u8; 3] },
+}
+
+/// 带采样偏移的处理事件
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProcessEvent {
+    /// 相对当前缓冲区开头的帧偏移
+    pub offset: u32,
+    pub kind: EventKind,
+}
+
+impl ProcessEvent {
+    pub fn parameter(offset: u32, id: u32, value: f64) -> Self {
+        Self { offset, kind: EventKind::Parameter { id, value } }
+    }
+
+    pub fn midi(offset: u32, data: [u8; 3]) -> Self {
+        Self { offset, kind: EventKind::Midi { data } }
+    }
+}
+
+/// 按偏移排序的事件列表（容量固定，不在音频线程中分配内存）
+#[derive(Debug, Clone)]
+pub struct EventList {
+    events: Vec<ProcessEvent>,
+}
+
+impl EventList {
+    pub fn new() -> Self {
+        Self::with_capacity(MAX_EVENTS)
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self { events: Vec::with_capacity(capacity) }
+    }
+
+    /// 按偏移插入事件，同一偏移的事件保持加入顺序
+    /// 列表已满时丢弃事件并返回 false
+    pub fn push(&mut self, event: ProcessEvent) -> bool {
+        if self.events.len() == self.events.capacity() {
+            return false;
+        }
+        let index = self.events.partition_point(|e| e.offset <= event.offset);
+        self.events.insert(index, event);
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ProcessEvent> {
+        self.events.iter()
+    }
+
+    /// 偏移在 [start, end) 内的事件
+    pub fn range(&self, start: u32, end: u32) -> impl Iterator<Item = &ProcessEvent> {
+        let first = self.events.partition_point(|e| e.offset < start);
+        self.events[first..].iter().take_while(move |e| e.offset < end)
+    }
+
+    /// 用 source 中 [start, end) 的事件填充本列表，偏移改为相对 start
+    pub fn fill_from(&mut self, source: &EventList, start: u32, end: u32) {
+        self.clear();
+        for event in source.range(start, end) {
+            self.push(ProcessEvent { offset: event.offset - start, ..*event });
+        }
+    }
+}
+
+impl Default for EventList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_keeps_order() {
+        let mut events = EventList::with_capacity(4);
+        assert!(events.push(ProcessEvent::parameter(10, 0, 0.1)));
+        assert!(events.push(ProcessEvent::parameter(2, 0, 0.2)));
+        assert!(events.push(ProcessEvent::parameter(10, 1, 0.3)));
+        assert!(events.push(ProcessEvent::midi(0, [0x90, 60, 100])));
+        // 容量用完后丢弃
+        assert!(!events.push(ProcessEvent::parameter(5, 0, 0.4)));
+
+        let offsets: Vec<u32> = events.iter().map(|e| e.offset).collect();
+        assert_eq!(offsets, vec![0, 2, 10, 10]);
+        assert_eq!(events.iter().nth(2).unwrap().kind, EventKind::Parameter { id: 0, value: 0.1 });
+    }
+
+    #[test]
+    fn test_fill_from_rebases_offsets() {
+        let mut source = EventList::new();
+        for offset in [0, 63, 64, 100, 200] {
+            source.push(ProcessEvent::parameter(offset, 0, 0.0));
+        }
+
+        let mut block = EventList::new();
+        block.fill_from(&source, 64, 128);
+        let offsets: Vec<u32> = block.iter().map(|e| e.offset).collect();
+        assert_eq!(offsets, vec![0, 36]);
+    }
+}
@@ -0,0 +1,845 @@
+// LADSPA 插件宿主
+// 枚举 .so 中的 ladspa_descriptor，按端口提示 (hint) 计算控制端口的取值范围，
+// 以 AudioProcessor 的形式接入插件链
+
+use anyhow::{Result, Context};
+use libloading::Library;
+use log::{info, warn, debug};
+use std::ffi::{c_char, c_int, c_ulong, c_void, CStr};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::audio::AudioBuffer;
+
+use super::types::{PluginMetadata, AudioProcessor, PluginParameter, PluginState, PluginFormat, ChannelLayout, BusLayout, ParameterDescriptor, ParameterFlags};
+
+/// 实例化插件时使用的采样率
+const DEFAULT_SAMPLE_RATE: f64 = 48000.0;
+
+/// 单次调用 run 的最大帧数
+const MAX_BLOCK_SIZE: usize = 4096;
+
+// ===== LADSPA C ABI (ladspa.h 1.1) =====
+
+type LadspaData = f32;
+type LadspaHandle = *mut c_void;
+
+const PORT_INPUT: c_int = 0x1;
+// 输出和控制端口按 PORT_INPUT / PORT_AUDIO 未置位判断，这两个值只在测试的插件描述中使用
+#[cfg(test)]
+const PORT_OUTPUT: c_int = 0x2;
+#[cfg(test)]
+const PORT_CONTROL: c_int = 0x4;
+const PORT_AUDIO: c_int = 0x8;
+
+const HINT_BOUNDED_BELOW: c_int = 0x1;
+const HINT_BOUNDED_ABOVE: c_int = 0x2;
+const HINT_TOGGLED: c_int = 0x4;
+const HINT_SAMPLE_RATE: c_int = 0x8;
+const HINT_LOGARITHMIC: c_int = 0x10;
+const HINT_INTEGER: c_int = 0x20;
+const HINT_DEFAULT_MASK: c_int = 0x3C0;
+const HINT_DEFAULT_MINIMUM: c_int = 0x40;
+const HINT_DEFAULT_LOW: c_int = 0x80;
+const HINT_DEFAULT_MIDDLE: c_int = 0xC0;
+const HINT_DEFAULT_HIGH: c_int = 0x100;
+const HINT_DEFAULT_MAXIMUM: c_int = 0x140;
+const HINT_DEFAULT_0: c_int = 0x200;
+const HINT_DEFAULT_1: c_int = 0x240;
+const HINT_DEFAULT_100: c_int = 0x280;
+const HINT_DEFAULT_440: c_int = 0x2C0;
+
+#[repr(C)]
+struct LadspaPortRangeHint {
+    hint_descriptor: c_int,
+    lower_bound: LadspaData,
+    upper_bound: LadspaData,
+}
+
+#[repr(C)]
+struct LadspaDescriptor {
+    unique_id: c_ulong,
+    label: *const c_char,
+    properties: c_int,
+    name: *const c_char,
+    maker: *const c_char,
+    copyright: *const c_char,
+    port_count: c_ulong,
+    port_descriptors: *const c_int,
+    port_names: *const *const c_char,
+    port_range_hints: *const LadspaPortRangeHint,
+    implementation_data: *mut c_void,
+    instantiate: Option<unsafe extern "C" fn(descriptor: *const LadspaDescriptor, sample_rate: c_ulong) -> LadspaHandle>,
+    connect_port: Option<unsafe extern "C" fn(instance: LadspaHandle, port: c_ulong, data: *mut LadspaData)>,
+    activate: Option<unsafe extern "C" fn(instance: LadspaHandle)>,
+    run: Option<unsafe extern "C" fn(instance: LadspaHandle, sample_count: c_ulong)>,
+    run_adding: Option<unsafe extern "C" fn(instance: LadspaHandle, sample_count: c_ulong)>,
+    set_run_adding_gain: Option<unsafe extern "C" fn(instance: LadspaHandle, gain: LadspaData)>,
+    deactivate: Option<unsafe extern "C" fn(instance: LadspaHandle)>,
+    cleanup: Option<unsafe extern "C" fn(instance: LadspaHandle)>,
+}
+
+type LadspaDescriptorFunction = unsafe extern "C" fn(index: c_ulong) -> *const LadspaDescriptor;
+
+fn c_str_to_string(ptr: *const c_char) -> String {
+    if ptr.is_null() {
+        String::new()
+    } else {
+        unsafe { CStr::from_ptr(ptr) }.to_string_lossy().into_owned()
+    }
+}
+
+/// 共享库导出的所有描述符
+fn descriptors(descriptor_fn: LadspaDescriptorFunction) -> Vec<*const LadspaDescriptor> {
+    (0..)
+        .map(|index| unsafe { descriptor_fn(index) })
+        .take_while(|desc| !desc.is_null())
+        .collect()
+}
+
+// ===== 端口信息 =====
+
+/// LADSPA 端口信息
+#[derive(Debug, Clone)]
+pub struct LadspaPortInfo {
+    pub index: u32,
+    pub name: String,
+    pub is_input: bool,
+    pub is_audio: bool,
+    pub hint: i32,
+    pub lower: f32,
+    pub upper: f32,
+}
+
+impl LadspaPortInfo {
+    fn has(&self, hint: c_int) -> bool {
+        self.hint & hint != 0
+    }
+
+    /// 实际取值范围（考虑 SAMPLE_RATE 提示和缺失的边界）
+    pub fn range(&self, sample_rate: f64) -> (f32, f32) {
+        if self.has(HINT_TOGGLED) {
+            return (0.0, 1.0);
+        }
+
+        let scale = if self.has(HINT_SAMPLE_RATE) { sample_rate as f32 } else { 1.0 };
+        let minimum = if self.has(HINT_BOUNDED_BELOW) { self.lower * scale } else { 0.0 };
+        let maximum = if self.has(HINT_BOUNDED_ABOVE) { self.upper * scale } else { minimum.max(0.0) + 1.0 };
+
+        if maximum > minimum {
+            (minimum, maximum)
+        } else {
+            (minimum, minimum + 1.0)
+        }
+    }
+
+    /// 对数刻度只在范围全部为正时有效
+    fn logarithmic(&self, sample_rate: f64) -> bool {
+        self.has(HINT_LOGARITHMIC) && self.range(sample_rate).0 > 0.0
+    }
+
+    /// 按 DEFAULT_* 提示计算默认值
+    pub fn default_value(&self, sample_rate: f64) -> f32 {
+        let (minimum, maximum) = self.range(sample_rate);
+        let log = self.logarithmic(sample_rate);
+        let between = |weight: f32| {
+            if log {
+                (minimum.ln() * (1.0 - weight) + maximum.ln() * weight).exp()
+            } else {
+                minimum * (1.0 - weight) + maximum * weight
+            }
+        };
+
+        let value = match self.hint & HINT_DEFAULT_MASK {
+            HINT_DEFAULT_MINIMUM => minimum,
+            HINT_DEFAULT_LOW => between(0.25),
+            HINT_DEFAULT_MIDDLE => between(0.5),
+            HINT_DEFAULT_HIGH => between(0.75),
+            HINT_DEFAULT_MAXIMUM => maximum,
+            HINT_DEFAULT_0 => 0.0,
+            HINT_DEFAULT_1 => 1.0,
+            HINT_DEFAULT_100 => 100.0,
+            HINT_DEFAULT_440 => 440.0,
+            _ => 0.0f32.clamp(minimum, maximum),
+        };
+
+        if self.has(HINT_INTEGER) { value.round() } else { value }
+    }
+
+    fn to_plain(&self, normalized: f64, sample_rate: f64) -> f32 {
+        let normalized = normalized.clamp(0.0, 1.0) as f32;
+        let (minimum, maximum) = self.range(sample_rate);
+
+        if self.has(HINT_TOGGLED) {
+            return if normalized >= 0.5 { 1.0 } else { 0.0 };
+        }
+
+        let value = if self.logarithmic(sample_rate) {
+            (minimum.ln() + normalized * (maximum.ln() - minimum.ln())).exp()
+        } else {
+            minimum + normalized * (maximum - minimum)
+        };
+
+        if self.has(HINT_INTEGER) { value.round() } else { value }
+    }
+
+    fn to_normalized(&self, plain: f32, sample_rate: f64) -> f64 {
+        let (minimum, maximum) = self.range(sample_rate);
+        let plain = plain.clamp(minimum, maximum);
+
+        let normalized = if self.logarithmic(sample_rate) {
+            (plain.ln() - minimum.ln()) / (maximum.ln() - minimum.ln())
+        } else {
+            (plain - minimum) / (maximum - minimum)
+        };
+        normalized.clamp(0.0, 1.0) as f64
+    }
+
+    /// 控制端口的参数描述（LADSPA 没有单位和枚举值）
+    pub fn descriptor(&self, sample_rate: f64) -> ParameterDescriptor {
+        if self.has(HINT_TOGGLED) {
+            return ParameterDescriptor::toggle(self.index, self.name.clone(), self.default_value(sample_rate) > 0.0);
+        }
+
+        let (minimum, maximum) = self.range(sample_rate);
+        let flags = ParameterFlags {
+            read_only: !self.is_input,
+            ..ParameterFlags::AUTOMATABLE
+        };
+        let mut descriptor = ParameterDescriptor::new(
+            self.index,
+            self.name.clone(),
+            minimum as f64,
+            maximum as f64,
+            self.default_value(sample_rate) as f64,
+        )
+        .with_logarithmic(self.logarithmic(sample_rate))
+        .with_flags(flags);
+
+        if self.has(HINT_INTEGER) {
+            descriptor = descriptor.with_steps((maximum - minimum).round().max(1.0) as u32);
+        }
+        descriptor
+    }
+
+    fn display_value(&self, plain: f32) -> String {
+        if self.has(HINT_TOGGLED) {
+            return if plain > 0.0 { "On" } else { "Off" }.to_string();
+        }
+        if self.has(HINT_INTEGER) {
+            format!("{}", plain as i64)
+        } else {
+            format!("{:.2}", plain)
+        }
+    }
+}
+
+/// 从描述符读取端口列表
+fn ports_from_descriptor(desc: &LadspaDescriptor) -> Vec<LadspaPortInfo> {
+    (0..desc.port_count as usize)
+        .map(|i| unsafe {
+            let kind = *desc.port_descriptors.add(i);
+            let (hint, lower, upper) = if desc.port_range_hints.is_null() {
+                (0, 0.0, 0.0)
+            } else {
+                let h = &*desc.port_range_hints.add(i);
+                (h.hint_descriptor, h.lower_bound, h.upper_bound)
+            };
+            let name = if desc.port_names.is_null() {
+                format!("Port {}", i)
+            } else {
+                c_str_to_string(*desc.port_names.add(i))
+            };
+
+            LadspaPortInfo {
+                index: i as u32,
+                name,
+                is_input: kind & PORT_INPUT != 0,
+                is_audio: kind & PORT_AUDIO != 0,
+                hint,
+                lower,
+                upper,
+            }
+        })
+        .collect()
+}
+
+fn descriptor_to_metadata(desc: &LadspaDescriptor, path: &Path) -> PluginMetadata {
+    let ports = ports_from_descriptor(desc);
+    let count = |input: bool| ports.iter().filter(|p| p.is_audio && p.is_input == input).count() as u32;
+
+    PluginMetadata {
+        id: format!("ladspa:{}", desc.unique_id),
+        name: c_str_to_string(desc.name),
+        vendor: c_str_to_string(desc.maker),
+        version: String::new(),
+        path: path.to_path_buf(),
+        format: PluginFormat::Ladspa,
+        num_inputs: count(true),
+        num_outputs: count(false),
+    }
+}
+
+fn open_library(path: &Path) -> Result<(Library, LadspaDescriptorFunction)> {
+    let library = unsafe { Library::new(path) }
+        .context(format!("加载动态库失败: {:?}", path))?;
+    let descriptor_fn = unsafe {
+        *library.get::<LadspaDescriptorFunction>(b"ladspa_descriptor\0")
+            .context("找不到 ladspa_descriptor 符号")?
+    };
+    Ok((library, descriptor_fn))
+}
+
+/// 列出 .so 文件中的所有插件（每个描述符一个）
+pub fn scan_ladspa_file(path: &Path) -> Result<Vec<PluginMetadata>> {
+    let (_library, descriptor_fn) = open_library(path)?;
+    Ok(descriptors(descriptor_fn)
+        .into_iter()
+        .map(|desc| descriptor_to_metadata(unsafe { &*desc }, path))
+        .collect())
+}
+
+// ===== 插件实例 =====
+
+/// 一个 LADSPA 实例及其音频缓冲区
+struct LadspaInstance {
+    handle: LadspaHandle,
+    inputs: Vec<Vec<f32>>,
+    outputs: Vec<Vec<f32>>,
+}
+
+/// LADSPA 插件包装器
+pub struct LadspaPlugin {
+    metadata: PluginMetadata,
+    descriptor: *const LadspaDescriptor,
+    ports: Vec<LadspaPortInfo>,
+
+    /// 单声道插件处理立体声时创建两个实例（每个声道一个）
+    instances: Vec<LadspaInstance>,
+
+    /// prepare 时协商的输入布局，决定单声道插件是否使用双实例
+    layout: ChannelLayout,
+
+    /// 控制端口的值（按端口下标，所有实例共享）
+    control_values: Box<[f32]>,
+
+    sample_rate: f64,
+    max_block_size: usize,
+    activated: bool,
+    _library: Option<Arc<Library>>,
+}
+
+// 插件实例只会被持有者独占访问
+unsafe impl Send for LadspaPlugin {}
+
+impl LadspaPlugin {
+    /// 从 .so 文件加载插件
+    /// unique_id 为 None 时加载文件中的第一个插件
+    pub fn load(path: &Path, unique_id: Option<u64>) -> Result<Self> {
+        info!("加载 LADSPA 插件: {:?}", path);
+
+        let (library, descriptor_fn) = open_library(path)?;
+        Self::instantiate(descriptor_fn, path, unique_id, Some(Arc::new(library)))
+    }
+
+    /// 从扫描信息加载插件
+    pub fn from_metadata(metadata: &PluginMetadata) -> Result<Self> {
+        let id = metadata.id.strip_prefix("ladspa:").unwrap_or(&metadata.id);
+        let unique_id = id.parse::<u64>()
+            .map_err(|_| anyhow::anyhow!("无效的 LADSPA 插件 ID: {}", metadata.id))?;
+        Self::load(&metadata.path, Some(unique_id))
+    }
+
+    fn instantiate(
+        descriptor_fn: LadspaDescriptorFunction,
+        path: &Path,
+        unique_id: Option<u64>,
+        library: Option<Arc<Library>>,
+    ) -> Result<Self> {
+        let all = descriptors(descriptor_fn);
+        let descriptor = match unique_id {
+            Some(id) => all.into_iter()
+                .find(|&d| unsafe { (*d).unique_id } == id as c_ulong)
+                .ok_or_else(|| anyhow::anyhow!("找不到插件: {}", id))?,
+            None => all.into_iter()
+                .next()
+                .ok_or_else(|| anyhow::anyhow!("文件中没有 LADSPA 插件"))?,
+        };
+
+        let desc = unsafe { &*descriptor };
+        if desc.instantiate.is_none() {
+            return Err(anyhow::anyhow!("插件缺少 instantiate"));
+        }
+        if desc.connect_port.is_none() {
+            return Err(anyhow::anyhow!("插件缺少 connect_port"));
+        }
+        if desc.run.is_none() {
+            return Err(anyhow::anyhow!("插件缺少 run"));
+        }
+
+        let metadata = descriptor_to_metadata(desc, path);
+        let ports = ports_from_descriptor(desc);
+        let sample_rate = DEFAULT_SAMPLE_RATE;
+
+        let mut control_values = vec![0.0f32; ports.len()].into_boxed_slice();
+        for port in ports.iter().filter(|p| !p.is_audio) {
+            control_values[port.index as usize] = port.default_value(sample_rate);
+        }
+
+        let mut plugin = Self {
+            metadata,
+            descriptor,
+            ports,
+            instances: Vec::new(),
+            control_values,
+            sample_rate,
+            max_block_size: MAX_BLOCK_SIZE,
+            layout: ChannelLayout::Stereo,
+            activated: false,
+            _library: library,
+        };
+        plugin.create_instances()?;
+        plugin.activate();
+
+        debug!("LADSPA 插件 {} 使用 {} 个实例", plugin.metadata.name, plugin.instances.len());
+        info!("LADSPA 插件已加载: {} ({} 个端口)", plugin.metadata.name, plugin.ports.len());
+        Ok(plugin)
+    }
+
+    /// 按当前采样率和块大小创建实例并连接所有端口
+    fn create_instances(&mut self) -> Result<()> {
+        let desc = unsafe { &*self.descriptor };
+        let (Some(instantiate), Some(connect_port)) = (desc.instantiate, desc.connect_port) else {
+            return Err(anyhow::anyhow!("插件缺少 instantiate 或 connect_port"));
+        };
+
+        let audio_inputs: Vec<u32> = self.ports.iter().filter(|p| p.is_audio && p.is_input).map(|p| p.index).collect();
+        let audio_outputs: Vec<u32> = self.ports.iter().filter(|p| p.is_audio && !p.is_input).map(|p| p.index).collect();
+        let dual_mono = audio_inputs.len() == 1 && audio_outputs.len() == 1 && self.layout == ChannelLayout::Stereo;
+
+        for _ in 0..if dual_mono { 2 } else { 1 } {
+            let handle = unsafe { instantiate(self.descriptor, self.sample_rate as c_ulong) };
+            if handle.is_null() {
+                self.destroy_instances();
+                return Err(anyhow::anyhow!("插件实例化失败: {}", self.metadata.name));
+            }
+
+            let mut instance = LadspaInstance {
+                handle,
+                inputs: audio_inputs.iter().map(|_| vec![0.0; self.max_block_size]).collect(),
+                outputs: audio_outputs.iter().map(|_| vec![0.0; self.max_block_size]).collect(),
+            };
+
+            // 连接所有端口（缓冲区地址在实例销毁前保持不变）
+            unsafe {
+                for port in self.ports.iter().filter(|p| !p.is_audio) {
+                    let data = &mut self.control_values[port.index as usize] as *mut f32;
+                    connect_port(handle, port.index as c_ulong, data);
+                }
+                for (buffer, &port) in instance.inputs.iter_mut().zip(&audio_inputs) {
+                    connect_port(handle, port as c_ulong, buffer.as_mut_ptr());
+                }
+                for (buffer, &port) in instance.outputs.iter_mut().zip(&audio_outputs) {
+                    connect_port(handle, port as c_ulong, buffer.as_mut_ptr());
+                }
+            }
+
+            self.instances.push(instance);
+        }
+        Ok(())
+    }
+
+    fn destroy_instances(&mut self) {
+        self.deactivate();
+        if let Some(cleanup) = unsafe { (*self.descriptor).cleanup } {
+            for instance in self.instances.drain(..) {
+                unsafe { cleanup(instance.handle) };
+            }
+        }
+        self.instances.clear();
+    }
+
+    fn activate(&mut self) {
+        if self.activated || self.instances.is_empty() {
+            return;
+        }
+        if let Some(activate) = unsafe { (*self.descriptor).activate } {
+            for instance in &self.instances {
+                unsafe { activate(instance.handle) };
+            }
+        }
+        self.activated = true;
+    }
+
+    fn deactivate(&mut self) {
+        if !self.activated {
+            return;
+        }
+        if let Some(deactivate) = unsafe { (*self.descriptor).deactivate } {
+            for instance in &self.instances {
+                unsafe { deactivate(instance.handle) };
+            }
+        }
+        self.activated = false;
+    }
+
+    fn control_input(&self, index: u32) -> Option<&LadspaPortInfo> {
+        self.ports
+            .iter()
+            .find(|p| p.index == index && !p.is_audio && p.is_input)
+    }
+
+    /// 处理一个不超过 max_block_size 帧的块
+    fn process_block(&mut self, block: &mut AudioBuffer) {
+        let frames = block.frames();
+        let Some(run) = (unsafe { (*self.descriptor).run }) else {
+            return;
+        };
+
+        if self.instances.len() == 2 {
+            // 双单声道：每个实例处理一个声道
+            for (channel, instance) in self.instances.iter_mut().enumerate() {
+                block.read_mapped(channel, 2, &mut instance.inputs[0][..frames]);
+                unsafe { run(instance.handle, frames as c_ulong) };
+            }
+            let (left, right) = (&self.instances[0].outputs[0], &self.instances[1].outputs[0]);
+            block.write_mapped(&[&left[..frames], &right[..frames]]);
+            return;
+        }
+
+        let instance = &mut self.instances[0];
+
+        // 缓冲区声道 -> 输入端口
+        let inputs = instance.inputs.len();
+        for (channel, buffer) in instance.inputs.iter_mut().enumerate() {
+            block.read_mapped(channel, inputs, &mut buffer[..frames]);
+        }
+
+        unsafe { run(instance.handle, frames as c_ulong) };
+
+        // 输出端口 -> 缓冲区声道（没有音频输出时保持原始音频）
+        let Some(left) = instance.outputs.first() else {
+            return;
+        };
+        let right = instance.outputs.get(1).unwrap_or(left);
+        let outputs = instance.outputs.len().min(2);
+        block.write_mapped(&[&left[..frames], &right[..frames]][..outputs]);
+    }
+}
+
+impl AudioProcessor for LadspaPlugin {
+    fn prepare(&mut self, sample_rate: f64, max_block_size: usize, layout: ChannelLayout) {
+        // LADSPA 只在实例化时接收采样率，缓冲区大小变化也需要重新连接端口，
+        // 所以直接重建实例；控制端口的值保存在宿主这边，不受影响
+        self.destroy_instances();
+        self.sample_rate = sample_rate;
+        self.max_block_size = max_block_size.max(1);
+        self.layout = layout;
+        match self.create_instances() {
+            Ok(()) => self.activate(),
+            Err(e) => warn!("LADSPA 插件准备失败: {}", e),
+        }
+    }
+
+    fn reset(&mut self) {
+        // LADSPA 规定 deactivate + activate 会清空处理状态
+        if self.activated {
+            self.deactivate();
+            self.activate();
+        }
+    }
+
+    fn release(&mut self) {
+        self.deactivate();
+    }
+
+    fn bus_layout(&self, upstream: ChannelLayout) -> BusLayout {
+        // 单声道插件跟随上游：立体声时每个声道一个实例
+        if (self.metadata.num_inputs, self.metadata.num_outputs) == (1, 1) {
+            return BusLayout::uniform(upstream);
+        }
+        BusLayout::from_metadata(&self.metadata, upstream)
+    }
+
+    fn process(&mut self, buffer: &mut AudioBuffer) {
+        if !self.activated {
+            return;
+        }
+        let frames = buffer.frames();
+        for start in (0..frames).step_by(self.max_block_size) {
+            self.process_block(&mut buffer.slice(start, start + self.max_block_size));
+        }
+    }
+
+    fn get_info(&self) -> &PluginMetadata {
+        &self.metadata
+    }
+
+    fn set_parameter(&mut self, id: u32, value: f64) {
+        // 可能在音频线程调用，未知的端口直接忽略，不写日志
+        if let Some(port) = self.control_input(id) {
+            let plain = port.to_plain(value, self.sample_rate);
+            self.control_values[id as usize] = plain;
+        }
+    }
+
+    fn get_parameter(&self, id: u32) -> Option<f64> {
+        let port = self.control_input(id)?;
+        Some(port.to_normalized(self.control_values[id as usize], self.sample_rate))
+    }
+
+    fn parameter_descriptors(&self) -> Vec<ParameterDescriptor> {
+        self.ports
+            .iter()
+            .filter(|p| !p.is_audio && p.is_input)
+            .map(|port| port.descriptor(self.sample_rate))
+            .collect()
+    }
+
+    fn get_all_parameters(&self) -> Vec<PluginParameter> {
+        self.ports
+            .iter()
+            .filter(|p| !p.is_audio && p.is_input)
+            .map(|port| {
+                let plain = self.control_values[port.index as usize];
+                PluginParameter {
+                    id: port.index,
+                    name: port.name.clone(),
+                    value: port.to_normalized(plain, self.sample_rate),
+                    display: port.display_value(plain),
+                }
+            })
+            .collect()
+    }
+
+    fn save_state(&self) -> PluginState {
+        // LADSPA 没有内部状态，只保存控制端口
+        PluginState {
+            plugin_id: self.metadata.id.clone(),
+            parameters: self.get_all_parameters(),
+            state_data: String::new(),
+        }
+    }
+
+    fn load_state(&mut self, state: &PluginState) {
+        for param in &state.parameters {
+            self.set_parameter(param.id, param.value);
+        }
+    }
+}
+
+impl Drop for LadspaPlugin {
+    fn drop(&mut self) {
+        self.destroy_instances();
+        debug!("LADSPA 插件已销毁: {}", self.metadata.name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::with_interleaved;
+    use std::ptr;
+
+    // 一个进程内的最小 LADSPA 单声道增益插件，用于测试宿主逻辑
+
+    struct TestGain {
+        gain: *const f32,
+        bypass: *const f32,
+        input: *const f32,
+        output: *mut f32,
+    }
+
+    struct PortNames([*const c_char; 4]);
+
+    // 静态描述符中的裸指针只指向常量数据
+    unsafe impl Sync for LadspaDescriptor {}
+    unsafe impl Sync for PortNames {}
+
+    static PORT_DESCRIPTORS: [c_int; 4] = [
+        PORT_INPUT | PORT_CONTROL,
+        PORT_INPUT | PORT_CONTROL,
+        PORT_INPUT | PORT_AUDIO,
+        PORT_OUTPUT | PORT_AUDIO,
+    ];
+
+    static PORT_NAMES: PortNames = PortNames([
+        c"Gain".as_ptr(),
+        c"Bypass".as_ptr(),
+        c"Input".as_ptr(),
+        c"Output".as_ptr(),
+    ]);
+
+    static PORT_HINTS: [LadspaPortRangeHint; 4] = [
+        LadspaPortRangeHint {
+            hint_descriptor: HINT_BOUNDED_BELOW | HINT_BOUNDED_ABOVE | HINT_LOGARITHMIC | HINT_DEFAULT_1,
+            lower_bound: 0.1,
+            upper_bound: 10.0,
+        },
+        LadspaPortRangeHint { hint_descriptor: HINT_TOGGLED | HINT_DEFAULT_0, lower_bound: 0.0, upper_bound: 0.0 },
+        LadspaPortRangeHint { hint_descriptor: 0, lower_bound: 0.0, upper_bound: 0.0 },
+        LadspaPortRangeHint { hint_descriptor: 0, lower_bound: 0.0, upper_bound: 0.0 },
+    ];
+
+    static DESCRIPTOR: LadspaDescriptor = LadspaDescriptor {
+        unique_id: 4242,
+        label: c"test_gain".as_ptr(),
+        properties: 0,
+        name: c"Test Gain".as_ptr(),
+        maker: c"Test".as_ptr(),
+        copyright: c"None".as_ptr(),
+        port_count: 4,
+        port_descriptors: PORT_DESCRIPTORS.as_ptr(),
+        port_names: PORT_NAMES.0.as_ptr(),
+        port_range_hints: PORT_HINTS.as_ptr(),
+        implementation_data: ptr::null_mut(),
+        instantiate: Some(test_instantiate),
+        connect_port: Some(test_connect_port),
+        activate: None,
+        run: Some(test_run),
+        run_adding: None,
+        set_run_adding_gain: None,
+        deactivate: None,
+        cleanup: Some(test_cleanup),
+    };
+
+    unsafe extern "C" fn test_descriptor(index: c_ulong) -> *const LadspaDescriptor {
+        if index == 0 { &DESCRIPTOR } else { ptr::null() }
+    }
+
+    unsafe extern "C" fn test_instantiate(_desc: *const LadspaDescriptor, _rate: c_ulong) -> LadspaHandle {
+        Box::into_raw(Box::new(TestGain {
+            gain: ptr::null(),
+            bypass: ptr::null(),
+            input: ptr::null(),
+            output: ptr::null_mut(),
+        })) as LadspaHandle
+    }
+
+    unsafe extern "C" fn test_connect_port(instance: LadspaHandle, port: c_ulong, data: *mut LadspaData) {
+        let plugin = &mut *(instance as *mut TestGain);
+        match port {
+            0 => plugin.gain = data,
+            1 => plugin.bypass = data,
+            2 => plugin.input = data,
+            3 => plugin.output = data,
+            _ => {}
+        }
+    }
+
+    unsafe extern "C" fn test_run(instance: LadspaHandle, sample_count: c_ulong) {
+        let plugin = &*(instance as *const TestGain);
+        let gain = if *plugin.bypass > 0.0 { 1.0 } else { *plugin.gain };
+        for i in 0..sample_count as usize {
+            *plugin.output.add(i) = *plugin.input.add(i) * gain;
+        }
+    }
+
+    unsafe extern "C" fn test_cleanup(instance: LadspaHandle) {
+        drop(Box::from_raw(instance as *mut TestGain));
+    }
+
+    fn test_plugin() -> LadspaPlugin {
+        LadspaPlugin::instantiate(test_descriptor, Path::new("/test/gain.so"), None, None).unwrap()
+    }
+
+    #[test]
+    fn test_metadata_and_defaults() {
+        let plugin = test_plugin();
+        let info = plugin.get_info();
+        assert_eq!(info.id, "ladspa:4242");
+        assert_eq!(info.name, "Test Gain");
+        assert_eq!(info.format, PluginFormat::Ladspa);
+        assert_eq!((info.num_inputs, info.num_outputs), (1, 1));
+
+        let params = plugin.get_all_parameters();
+        assert_eq!(params.len(), 2);
+        assert_eq!(params[0].name, "Gain");
+        // 对数刻度 0.1..10 上的 1.0 正好在中间
+        assert!((params[0].value - 0.5).abs() < 1e-6);
+        assert_eq!(params[1].display, "Off");
+
+        let descriptors = plugin.parameter_descriptors();
+        assert!(descriptors[0].logarithmic);
+        assert_eq!((descriptors[0].min, descriptors[0].max), (0.1f32 as f64, 10.0));
+        assert!((descriptors[0].default_normalized() - params[0].value).abs() < 1e-6);
+        assert!(descriptors[1].is_toggle());
+        assert_eq!(plugin.text_to_value(1, "On"), Some(1.0));
+    }
+
+    #[test]
+    fn test_hint_ranges() {
+        let port = LadspaPortInfo {
+            index: 0,
+            name: "Cutoff".to_string(),
+            is_input: true,
+            is_audio: false,
+            hint: HINT_BOUNDED_BELOW | HINT_BOUNDED_ABOVE | HINT_SAMPLE_RATE | HINT_DEFAULT_MAXIMUM,
+            lower: 0.0,
+            upper: 0.5,
+        };
+        assert_eq!(port.range(48000.0), (0.0, 24000.0));
+        assert_eq!(port.default_value(48000.0), 24000.0);
+
+        let unbounded = LadspaPortInfo { hint: HINT_INTEGER | HINT_DEFAULT_MIDDLE, ..port };
+        assert_eq!(unbounded.range(48000.0), (0.0, 1.0));
+        assert_eq!(unbounded.to_plain(0.4, 48000.0), 0.0);
+    }
+
+    #[test]
+    fn test_dual_mono_process() {
+        let mut plugin = test_plugin();
+        assert_eq!(plugin.instances.len(), 2);
+
+        plugin.set_parameter(0, 1.0);
+        let mut buffer = vec![0.1, -0.2, 0.3, -0.4];
+        with_interleaved(&mut buffer, 2, |buffer| plugin.process(buffer));
+        let expected = [1.0, -2.0, 3.0, -4.0];
+        for (out, exp) in buffer.iter().zip(expected) {
+            assert!((out - exp).abs() < 1e-5);
+        }
+
+        plugin.set_parameter(1, 1.0);
+        let mut buffer = vec![0.5, 0.25];
+        with_interleaved(&mut buffer, 2, |buffer| plugin.process(buffer));
+        assert_eq!(buffer, vec![0.5, 0.25]);
+    }
+
+    #[test]
+    fn test_prepare_recreates_instances() {
+        let mut plugin = test_plugin();
+        plugin.set_parameter(0, 1.0);
+
+        plugin.prepare(44100.0, 16, ChannelLayout::Stereo);
+        assert_eq!(plugin.instances.len(), 2);
+        assert_eq!(plugin.bus_layout(ChannelLayout::Mono), BusLayout::uniform(ChannelLayout::Mono));
+        assert_eq!(plugin.instances[0].inputs[0].len(), 16);
+        assert!((plugin.get_parameter(0).unwrap() - 1.0).abs() < 1e-6);
+
+        // 超过块大小的缓冲区被分块处理
+        let mut buffer = vec![0.1; 40 * 2];
+        with_interleaved(&mut buffer, 2, |buffer| plugin.process(buffer));
+        assert!(buffer.iter().all(|&s| (s - 1.0).abs() < 1e-5));
+
+        plugin.release();
+        let mut buffer = vec![0.1; 4];
+        with_interleaved(&mut buffer, 2, |buffer| plugin.process(buffer));
+        assert_eq!(buffer, vec![0.1; 4]);
+    }
+
+    #[test]
+    fn test_state_roundtrip() {
+        let mut plugin = test_plugin();
+        plugin.set_parameter(0, 0.75);
+        let state = plugin.save_state();
+
+        let mut restored = test_plugin();
+        restored.load_state(&state);
+        assert!((restored.get_parameter(0).unwrap() - 0.75).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_missing_file() {
+        assert!(LadspaPlugin::load(Path::new("/nonexistent/missing.so"), None).is_err());
+    }
+}
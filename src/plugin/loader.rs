@@ -2,11 +2,25 @@ use anyhow::Result;
 use log::info;
 use std::path::Path;
 
-use super::types::{PluginMetadata, AudioProcessor};
+use crate::audio::PluginFactory;
+#[cfg(test)]
+use crate::audio::{AudioBuffer, DelayLine};
+
+use super::types::{PluginMetadata, AudioProcessor, PluginFormat};
+#[cfg(test)]
+use super::types::ChannelLayout;
 use super::au_wrapper::AudioUnitPlugin;
+use super::clap_wrapper::ClapPlugin;
+use super::lv2_wrapper::Lv2Plugin;
+use super::vst3_wrapper::Vst3Plugin;
+use super::ladspa_wrapper::LadspaPlugin;
+use super::native_wrapper::{self, NativePlugin};
+use super::wasm_wrapper::WasmPlugin;
+use super::builtin::{BuiltinKind, BuiltinPlugin};
+use super::routing::{ParallelRack, RACK_ID};
 use super::scanner::PluginInfo;
 
-/// Audio Unit 插件加载器
+/// 插件加载器（按格式分派到对应的宿主实现）
 pub struct PluginLoader {
     // 缓存已加载的插件信息
     loaded_plugins: Vec<String>,
@@ -19,17 +33,29 @@ impl PluginLoader {
         }
     }
     
-    /// 从路径加载插件
+    /// 从路径加载插件（根据扩展名判断格式）
     pub fn load_plugin(&mut self, path: &Path) -> Result<Box<dyn AudioProcessor>> {
         info!("加载插件: {:?}", path);
         
-        // 使用 AudioUnitPlugin 包装器加载
-        let plugin = AudioUnitPlugin::load(path)?;
+        // .so 可能是 LADSPA 也可能是原生插件，按导出符号区分
+        let format = match detect_format(path) {
+            Some(PluginFormat::Ladspa) if native_wrapper::is_native_library(path) => Some(PluginFormat::Native),
+            format => format,
+        };
         
-        // 记录已加载
-        self.loaded_plugins.push(plugin.get_info().id.clone());
+        let plugin: Box<dyn AudioProcessor> = match format {
+            Some(PluginFormat::Clap) => Box::new(ClapPlugin::load(path, None)?),
+            Some(PluginFormat::Lv2) => Box::new(Lv2Plugin::load(path, None)?),
+            Some(PluginFormat::Vst3) => Box::new(Vst3Plugin::load(path, None)?),
+            Some(PluginFormat::Ladspa) => Box::new(LadspaPlugin::load(path, None)?),
+            Some(PluginFormat::Native) => Box::new(NativePlugin::load(path, None)?),
+            Some(PluginFormat::Wasm) => Box::new(WasmPlugin::load(path)?),
+            Some(PluginFormat::AudioUnit) => Box::new(AudioUnitPlugin::load(path)?),
+            Some(PluginFormat::Builtin) => return Err(anyhow::anyhow!("内置效果器不能从文件加载: {:?}", path)),
+            None => return Err(anyhow::anyhow!("无法识别的插件格式: {:?}", path)),
+        };
         
-        Ok(Box::new(plugin))
+        self.register(plugin)
     }
     
     /// 从插件信息加载
@@ -38,15 +64,50 @@ impl PluginLoader {
             return Err(anyhow::anyhow!("插件无效: {}", info.error.as_ref().unwrap_or(&"未知错误".to_string())));
         }
         
-        self.load_plugin(&info.metadata.path)
+        self.load_from_metadata(&info.metadata)
     }
     
-    /// 从元数据加载
+    /// 从元数据加载（同一文件可能包含多个插件，按 ID 选择）
     pub fn load_from_metadata(&mut self, metadata: &PluginMetadata) -> Result<Box<dyn AudioProcessor>> {
-        self.load_plugin(&metadata.path)
+        info!("加载插件: {} ({:?})", metadata.name, metadata.format);
+        
+        let plugin: Box<dyn AudioProcessor> = match metadata.format {
+            PluginFormat::Clap => Box::new(ClapPlugin::from_metadata(metadata)?),
+            PluginFormat::Lv2 => Box::new(Lv2Plugin::from_metadata(metadata)?),
+            PluginFormat::Vst3 => Box::new(Vst3Plugin::from_metadata(metadata)?),
+            PluginFormat::Ladspa => Box::new(LadspaPlugin::from_metadata(metadata)?),
+            PluginFormat::Native => Box::new(NativePlugin::from_metadata(metadata)?),
+            PluginFormat::Wasm => Box::new(WasmPlugin::from_metadata(metadata)?),
+            PluginFormat::AudioUnit => Box::new(AudioUnitPlugin::from_metadata(metadata.clone())?),
+            PluginFormat::Builtin if metadata.id == RACK_ID => Box::new(ParallelRack::new()),
+            PluginFormat::Builtin => Box::new(BuiltinPlugin::from_metadata(metadata)?),
+        };
+        
+        self.register(plugin)
+    }
+    
+    /// 按插件 ID 加载（恢复工程和预设时使用）：内置效果器和并联机架直接创建，其余在扫描结果中查找
+    pub fn load_by_id(&mut self, id: &str, plugins: &[PluginInfo]) -> Result<Box<dyn AudioProcessor>> {
+        if id == RACK_ID {
+            return self.register(Box::new(ParallelRack::new()));
+        }
+        if let Some(kind) = BuiltinKind::from_id(id) {
+            return self.register(Box::new(BuiltinPlugin::new(kind)));
+        }
+        match plugins.iter().find(|info| info.metadata.id == id) {
+            Some(info) => self.load_from_info(info),
+            None => Err(anyhow::anyhow!("找不到插件: {}", id)),
+        }
     }
     
-    /// 卸载插件
+    /// 记录已加载的插件
+    fn register(&mut self, plugin: Box<dyn AudioProcessor>) -> Result<Box<dyn AudioProcessor>> {
+        self.loaded_plugins.push(plugin.get_info().id.clone());
+        Ok(plugin)
+    }
+    
+    /// 卸载插件（插件链中的插件由音频引擎释放，这里供直接持有插件的调用方使用）
+    #[allow(dead_code)]
     pub fn unload_plugin(&mut self, plugin: Box<dyn AudioProcessor>) {
         let id = plugin.get_info().id.clone();
         info!("卸载插件: {}", id);
@@ -61,11 +122,33 @@ impl PluginLoader {
     }
     
     /// 获取已加载插件列表
+    #[allow(dead_code)]
     pub fn get_loaded_plugins(&self) -> &[String] {
         &self.loaded_plugins
     }
 }
 
+/// 按插件 ID 创建插件的工厂（打开工程和切换预设时使用），plugins 是扫描结果
+pub fn plugin_factory(plugins: Vec<PluginInfo>) -> PluginFactory {
+    let mut loader = PluginLoader::new();
+    Box::new(move |state: &super::types::PluginState| loader.load_by_id(&state.plugin_id, &plugins))
+}
+
+/// 根据文件扩展名判断插件格式
+pub fn detect_format(path: &Path) -> Option<PluginFormat> {
+    match path.extension().and_then(|s| s.to_str()) {
+        Some("clap") => Some(PluginFormat::Clap),
+        Some("lv2") => Some(PluginFormat::Lv2),
+        Some("vst3") => Some(PluginFormat::Vst3),
+        // 单独的 .so 文件按 LADSPA 处理（LV2/VST3 的二进制都在 bundle 内）
+        Some("so") => Some(PluginFormat::Ladspa),
+        Some("dylib") | Some("dll") => Some(PluginFormat::Native),
+        Some("wasm") => Some(PluginFormat::Wasm),
+        Some("component") => Some(PluginFormat::AudioUnit),
+        _ => None,
+    }
+}
+
 impl Default for PluginLoader {
     fn default() -> Self {
         Self::new()
@@ -73,19 +156,57 @@ impl Default for PluginLoader {
 }
 
 /// 模拟插件（用于测试）
+#[cfg(test)]
 pub struct DummyPlugin {
     metadata: PluginMetadata,
+    /// 模拟的处理延迟（默认为 0，即直通）
+    delay: DelayLine,
+    /// 是否声明 MIDI 输入
+    midi: bool,
 }
 
+#[cfg(test)]
 impl DummyPlugin {
     pub fn new(metadata: PluginMetadata) -> Self {
-        Self { metadata }
+        Self { metadata, delay: DelayLine::new(0), midi: false }
+    }
+    
+    /// 把信号推迟 latency 帧并如实报告延迟的模拟插件
+    pub fn with_latency(metadata: PluginMetadata, latency: u32) -> Self {
+        Self { metadata, delay: DelayLine::new(latency as usize), midi: false }
+    }
+    
+    /// 声明 MIDI 输入的模拟插件
+    pub fn with_midi(metadata: PluginMetadata) -> Self {
+        Self { midi: true, ..Self::new(metadata) }
     }
 }
 
+#[cfg(test)]
 impl AudioProcessor for DummyPlugin {
-    fn process(&mut self, _buffer: &mut [f32]) {
-        // Bypass - 不做任何处理
+    fn prepare(&mut self, _sample_rate: f64, _max_block_size: usize, _layout: ChannelLayout) {
+        // 直通，不需要按配置分配资源
+    }
+    
+    fn reset(&mut self) {
+        self.delay.clear();
+    }
+    
+    fn release(&mut self) {
+        // 没有需要释放的资源
+    }
+    
+    fn latency_samples(&self) -> u32 {
+        self.delay.latency() as u32
+    }
+    
+    fn process(&mut self, buffer: &mut AudioBuffer) {
+        // 直通，只有设置了延迟时推迟信号
+        self.delay.process(buffer);
+    }
+    
+    fn accepts_midi(&self) -> bool {
+        self.midi
     }
     
     fn get_info(&self) -> &PluginMetadata {
@@ -104,6 +225,10 @@ impl AudioProcessor for DummyPlugin {
         Vec::new()
     }
     
+    fn parameter_descriptors(&self) -> Vec<super::types::ParameterDescriptor> {
+        Vec::new()
+    }
+    
     fn save_state(&self) -> super::types::PluginState {
         super::types::PluginState {
             plugin_id: self.metadata.id.clone(),
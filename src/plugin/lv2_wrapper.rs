@@ -0,0 +1,1323 @@
+
+// LV2 插件宿主
+// 解析 bundle 中的 manifest.ttl / 插件 TTL 获取端口信息，
+// 加载共享库并连接音频/控制端口，以 AudioProcessor 的形式接入插件链
+
+use anyhow::{Result, Context};
+use base64::Engine as _;
+use libloading::Library;
+use log::{info, warn, debug};
+use serde::{Deserialize, Serialize};
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::sync::{Arc, Mutex};
+
+use crate::audio::AudioBuffer;
+
+use super::ttl::{self, Graph, Node, RDFS_LABEL, RDFS_SEE_ALSO, RDF_TYPE, RDF_VALUE};
+use super::types::{PluginMetadata, AudioProcessor, PluginParameter, PluginState, PluginFormat, ChannelLayout, ParameterDescriptor, ParameterFlags};
+
+const LV2_CORE: &str = "http://lv2plug.in/ns/lv2core#";
+const LV2_ATOM: &str = "http://lv2plug.in/ns/ext/atom#";
+const LV2_UNITS: &str = "http://lv2plug.in/ns/extensions/units#";
+const LV2_PORT_PROPS: &str = "http://lv2plug.in/ns/ext/port-props#";
+const DOAP_NAME: &str = "http://usefulinc.com/ns/doap#name";
+const DOAP_MAINTAINER: &str = "http://usefulinc.com/ns/doap#maintainer";
+const FOAF_NAME: &str = "http://xmlns.com/foaf/0.1/name";
+
+const URID_MAP: &CStr = c"http://lv2plug.in/ns/ext/urid#map";
+const URID_UNMAP: &CStr = c"http://lv2plug.in/ns/ext/urid#unmap";
+const BOUNDED_BLOCK_LENGTH: &CStr = c"http://lv2plug.in/ns/ext/buf-size#boundedBlockLength";
+const STATE_INTERFACE: &CStr = c"http://lv2plug.in/ns/ext/state#interface";
+
+/// 宿主提供的 feature（插件声明的 requiredFeature 必须在此列表中）
+const SUPPORTED_FEATURES: &[&str] = &[
+    "http://lv2plug.in/ns/ext/urid#map",
+    "http://lv2plug.in/ns/ext/urid#unmap",
+    "http://lv2plug.in/ns/ext/buf-size#boundedBlockLength",
+    "http://lv2plug.in/ns/lv2core#isLive",
+];
+
+/// 实例化插件时使用的采样率
+const DEFAULT_SAMPLE_RATE: f64 = 48000.0;
+
+/// 单次调用 run 的最大帧数
+const MAX_BLOCK_SIZE: usize = 4096;
+
+/// Atom 端口缓冲区大小（字节）
+const ATOM_BUFFER_SIZE: usize = 8192;
+
+// ===== LV2 C ABI =====
+
+type Lv2Handle = *mut c_void;
+
+#[repr(C)]
+struct Lv2Feature {
+    uri: *const c_char,
+    data: *mut c_void,
+}
+
+#[repr(C)]
+struct Lv2Descriptor {
+    uri: *const c_char,
+    instantiate: Option<unsafe extern "C" fn(
+        descriptor: *const Lv2Descriptor,
+        sample_rate: f64,
+        bundle_path: *const c_char,
+        features: *const *const Lv2Feature,
+    ) -> Lv2Handle>,
+    connect_port: Option<unsafe extern "C" fn(instance: Lv2Handle, port: u32, data: *mut c_void)>,
+    activate: Option<unsafe extern "C" fn(instance: Lv2Handle)>,
+    run: Option<unsafe extern "C" fn(instance: Lv2Handle, sample_count: u32)>,
+    deactivate: Option<unsafe extern "C" fn(instance: Lv2Handle)>,
+    cleanup: Option<unsafe extern "C" fn(instance: Lv2Handle)>,
+    extension_data: Option<unsafe extern "C" fn(uri: *const c_char) -> *const c_void>,
+}
+
+type Lv2DescriptorFunction = unsafe extern "C" fn(index: u32) -> *const Lv2Descriptor;
+
+#[repr(C)]
+struct UridMapFeature {
+    handle: *mut c_void,
+    map: unsafe extern "C" fn(handle: *mut c_void, uri: *const c_char) -> u32,
+}
+
+#[repr(C)]
+struct UridUnmapFeature {
+    handle: *mut c_void,
+    unmap: unsafe extern "C" fn(handle: *mut c_void, urid: u32) -> *const c_char,
+}
+
+type StateStoreFunction = unsafe extern "C" fn(
+    handle: *mut c_void,
+    key: u32,
+    value: *const c_void,
+    size: usize,
+    value_type: u32,
+    flags: u32,
+) -> u32;
+
+type StateRetrieveFunction = unsafe extern "C" fn(
+    handle: *mut c_void,
+    key: u32,
+    size: *mut usize,
+    value_type: *mut u32,
+    flags: *mut u32,
+) -> *const c_void;
+
+#[repr(C)]
+struct StateInterface {
+    save: Option<unsafe extern "C" fn(
+        instance: Lv2Handle,
+        store: StateStoreFunction,
+        handle: *mut c_void,
+        flags: u32,
+        features: *const *const Lv2Feature,
+    ) -> u32>,
+    restore: Option<unsafe extern "C" fn(
+        instance: Lv2Handle,
+        retrieve: StateRetrieveFunction,
+        handle: *mut c_void,
+        flags: u32,
+        features: *const *const Lv2Feature,
+    ) -> u32>,
+}
+
+const LV2_STATE_SUCCESS: u32 = 0;
+const LV2_STATE_ERR_UNKNOWN: u32 = 1;
+const LV2_STATE_IS_POD: u32 = 1;
+const LV2_STATE_IS_PORTABLE: u32 = 2;
+
+// ===== TTL 元数据 =====
+
+/// 端口类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lv2PortKind {
+    Audio,
+    Control,
+    Cv,
+    Atom,
+    Other,
+}
+
+/// 端口信息（来自 TTL）
+#[derive(Debug, Clone)]
+pub struct Lv2PortInfo {
+    pub index: u32,
+    pub symbol: String,
+    pub name: String,
+    pub kind: Lv2PortKind,
+    pub is_input: bool,
+    pub default: f32,
+    pub minimum: f32,
+    pub maximum: f32,
+    pub toggled: bool,
+    pub integer: bool,
+    pub optional: bool,
+    /// 枚举值 (标签, 值)
+    pub scale_points: Vec<(String, f32)>,
+    /// 单位符号（units:unit），没有时为空
+    pub unit: String,
+    /// pprops:logarithmic
+    pub logarithmic: bool,
+    /// pprops:notAutomatic
+    pub not_automatic: bool,
+    /// pprops:notOnGUI
+    pub hidden: bool,
+}
+
+impl Lv2PortInfo {
+    /// 对数刻度要求范围都是正数
+    fn is_logarithmic(&self) -> bool {
+        self.logarithmic && self.minimum > 0.0 && self.maximum > self.minimum
+    }
+
+    /// 控制端口的参数描述
+    pub fn descriptor(&self) -> ParameterDescriptor {
+        let flags = ParameterFlags {
+            automatable: self.is_input && !self.not_automatic,
+            read_only: !self.is_input,
+            bypass: self.symbol == "bypass" || self.symbol == "enabled",
+            hidden: self.hidden,
+        };
+        let (min, max) = (self.minimum as f64, self.maximum as f64);
+        let mut descriptor = ParameterDescriptor::new(self.index, self.name.clone(), min, max, self.default as f64)
+            .with_unit(self.unit.clone())
+            .with_logarithmic(self.is_logarithmic())
+            .with_flags(flags);
+
+        if self.toggled {
+            descriptor = descriptor.with_steps(1);
+        } else if self.integer {
+            descriptor = descriptor.with_steps((max - min).round().max(1.0) as u32);
+        }
+        if !self.scale_points.is_empty() {
+            let mut names: Vec<(f64, String)> = self.scale_points
+                .iter()
+                .map(|(label, value)| (*value as f64, label.clone()))
+                .collect();
+            names.sort_by(|a, b| a.0.total_cmp(&b.0));
+            descriptor = descriptor.with_value_names(names);
+        }
+        descriptor
+    }
+}
+
+/// 插件信息（来自 TTL）
+#[derive(Debug, Clone)]
+pub struct Lv2PluginInfo {
+    pub uri: String,
+    pub name: String,
+    pub vendor: String,
+    pub version: String,
+    pub bundle: PathBuf,
+    pub binary: PathBuf,
+    pub ports: Vec<Lv2PortInfo>,
+    pub required_features: Vec<String>,
+}
+
+impl Lv2PluginInfo {
+    fn audio_ports(&self, is_input: bool) -> Vec<u32> {
+        self.ports
+            .iter()
+            .filter(|p| p.kind == Lv2PortKind::Audio && p.is_input == is_input)
+            .map(|p| p.index)
+            .collect()
+    }
+
+    fn to_metadata(&self) -> PluginMetadata {
+        PluginMetadata {
+            id: format!("lv2:{}", self.uri),
+            name: self.name.clone(),
+            vendor: self.vendor.clone(),
+            version: self.version.clone(),
+            path: self.bundle.clone(),
+            format: PluginFormat::Lv2,
+            num_inputs: self.audio_ports(true).len() as u32,
+            num_outputs: self.audio_ports(false).len() as u32,
+        }
+    }
+}
+
+fn lv2(term: &str) -> String {
+    format!("{}{}", LV2_CORE, term)
+}
+
+/// 解析 bundle 的 manifest.ttl 及其引用的 TTL 文件
+pub fn parse_bundle(bundle: &Path) -> Result<Vec<Lv2PluginInfo>> {
+    let manifest = bundle.join("manifest.ttl");
+    if !manifest.exists() {
+        return Err(anyhow::anyhow!("找不到 manifest.ttl"));
+    }
+
+    let mut graph = Graph::new();
+    graph.load_file(&manifest)?;
+
+    // 加载 rdfs:seeAlso 指向的文件（通常是插件自己的 TTL）
+    let mut loaded = vec![manifest];
+    let see_also: Vec<PathBuf> = graph.triples()
+        .iter()
+        .filter(|t| t.predicate == RDFS_SEE_ALSO)
+        .filter_map(|t| t.object.as_iri().and_then(ttl::uri_to_path))
+        .collect();
+
+    for file in see_also {
+        if loaded.contains(&file) || !file.exists() {
+            continue;
+        }
+        if let Err(e) = graph.load_file(&file) {
+            warn!("{}", e);
+        }
+        loaded.push(file);
+    }
+
+    plugins_from_graph(&graph, bundle)
+}
+
+/// 从 RDF 图中提取所有 lv2:Plugin
+fn plugins_from_graph(graph: &Graph, bundle: &Path) -> Result<Vec<Lv2PluginInfo>> {
+    let mut plugins = Vec::new();
+
+    for subject in graph.subjects_of_type(&lv2("Plugin")) {
+        let Some(uri) = subject.as_iri() else {
+            continue;
+        };
+
+        let binary = graph.object(&subject, &lv2("binary"))
+            .and_then(|n| n.as_iri())
+            .and_then(ttl::uri_to_path)
+            .ok_or_else(|| anyhow::anyhow!("插件缺少 lv2:binary: {}", uri))?;
+
+        let name = graph.object(&subject, DOAP_NAME)
+            .and_then(|n| n.as_str())
+            .unwrap_or(uri)
+            .to_string();
+
+        let vendor = graph.object(&subject, DOAP_MAINTAINER)
+            .and_then(|m| graph.object(m, FOAF_NAME))
+            .and_then(|n| n.as_str())
+            .unwrap_or_default()
+            .to_string();
+
+        let minor = graph.object(&subject, &lv2("minorVersion")).and_then(|n| n.as_f64());
+        let micro = graph.object(&subject, &lv2("microVersion")).and_then(|n| n.as_f64());
+        let version = match (minor, micro) {
+            (Some(minor), Some(micro)) => format!("0.{}.{}", minor, micro),
+            _ => String::new(),
+        };
+
+        let mut ports: Vec<Lv2PortInfo> = graph.objects(&subject, &lv2("port"))
+            .filter_map(|port| parse_port(graph, port))
+            .collect();
+        ports.sort_by_key(|p| p.index);
+
+        let required_features = graph.objects(&subject, &lv2("requiredFeature"))
+            .filter_map(|n| n.as_iri().map(str::to_string))
+            .collect();
+
+        plugins.push(Lv2PluginInfo {
+            uri: uri.to_string(),
+            name,
+            vendor,
+            version,
+            bundle: bundle.to_path_buf(),
+            binary,
+            ports,
+            required_features,
+        });
+    }
+
+    Ok(plugins)
+}
+
+/// units:unit 的符号：内置单位按 URI 映射，自定义单位读取 units:symbol
+fn unit_symbol(graph: &Graph, port: &Node) -> String {
+    let Some(unit) = graph.object(port, &format!("{}unit", LV2_UNITS)) else {
+        return String::new();
+    };
+    if let Some(symbol) = graph.object(unit, &format!("{}symbol", LV2_UNITS)).and_then(|n| n.as_str()) {
+        return symbol.to_string();
+    }
+
+    let name = unit.as_iri().and_then(|iri| iri.strip_prefix(LV2_UNITS)).unwrap_or_default();
+    match name {
+        "db" => "dB",
+        "hz" => "Hz",
+        "khz" => "kHz",
+        "mhz" => "MHz",
+        "ms" => "ms",
+        "s" => "s",
+        "min" => "min",
+        "pc" => "%",
+        "bpm" => "BPM",
+        "cent" => "ct",
+        "semitone12TET" => "semi",
+        "oct" => "oct",
+        "degree" => "°",
+        "bar" => "bars",
+        "beat" => "beats",
+        "frame" => "frames",
+        "m" => "m",
+        "cm" => "cm",
+        "mm" => "mm",
+        _ => "",
+    }
+    .to_string()
+}
+
+fn parse_port(graph: &Graph, port: &Node) -> Option<Lv2PortInfo> {
+    let index = graph.object(port, &lv2("index"))?.as_f64()? as u32;
+    let is_type = |t: &str| graph.has(port, RDF_TYPE, t);
+    let has_property = |p: &str| graph.has(port, &lv2("portProperty"), &lv2(p));
+    let has_pprop = |p: &str| graph.has(port, &lv2("portProperty"), &format!("{}{}", LV2_PORT_PROPS, p));
+    let number = |p: &str| graph.object(port, &lv2(p)).and_then(|n| n.as_f64()).map(|v| v as f32);
+
+    let kind = if is_type(&lv2("AudioPort")) {
+        Lv2PortKind::Audio
+    } else if is_type(&lv2("ControlPort")) {
+        Lv2PortKind::Control
+    } else if is_type(&lv2("CVPort")) {
+        Lv2PortKind::Cv
+    } else if is_type(&format!("{}AtomPort", LV2_ATOM)) {
+        Lv2PortKind::Atom
+    } else {
+        Lv2PortKind::Other
+    };
+
+    let symbol = graph.object(port, &lv2("symbol"))
+        .and_then(|n| n.as_str())
+        .unwrap_or_default()
+        .to_string();
+    let name = graph.object(port, &lv2("name"))
+        .and_then(|n| n.as_str())
+        .map(str::to_string)
+        .unwrap_or_else(|| symbol.clone());
+
+    let minimum = number("minimum").unwrap_or(0.0);
+    let maximum = number("maximum").unwrap_or(1.0);
+    let default = number("default").unwrap_or(minimum);
+
+    let scale_points = graph.objects(port, &lv2("scalePoint"))
+        .filter_map(|point| {
+            let label = graph.object(point, RDFS_LABEL)?.as_str()?.to_string();
+            let value = graph.object(point, RDF_VALUE)?.as_f64()? as f32;
+            Some((label, value))
+        })
+        .collect();
+
+    Some(Lv2PortInfo {
+        index,
+        symbol,
+        name,
+        kind,
+        is_input: is_type(&lv2("InputPort")),
+        default,
+        minimum,
+        maximum,
+        toggled: has_property("toggled"),
+        integer: has_property("integer") || has_property("enumeration"),
+        optional: has_property("connectionOptional"),
+        scale_points,
+        unit: unit_symbol(graph, port),
+        logarithmic: has_pprop("logarithmic"),
+        not_automatic: has_pprop("notAutomatic"),
+        hidden: has_pprop("notOnGUI"),
+    })
+}
+
+/// 列出 bundle 中的所有插件
+pub fn scan_lv2_bundle(bundle: &Path) -> Result<Vec<PluginMetadata>> {
+    Ok(parse_bundle(bundle)?
+        .iter()
+        .map(Lv2PluginInfo::to_metadata)
+        .collect())
+}
+
+// ===== 宿主 feature =====
+
+/// URID 映射表（urid = 下标 + 1）
+struct UridTable {
+    uris: Mutex<Vec<CString>>,
+}
+
+impl UridTable {
+    fn map(&self, uri: &CStr) -> u32 {
+        let mut uris = self.uris.lock().unwrap();
+        if let Some(pos) = uris.iter().position(|u| u.as_c_str() == uri) {
+            return pos as u32 + 1;
+        }
+        uris.push(uri.to_owned());
+        uris.len() as u32
+    }
+
+    fn unmap(&self, urid: u32) -> *const c_char {
+        let uris = self.uris.lock().unwrap();
+        match urid.checked_sub(1).and_then(|i| uris.get(i as usize)) {
+            // CString 的堆内存不会随 Vec 扩容移动
+            Some(uri) => uri.as_ptr(),
+            None => ptr::null(),
+        }
+    }
+
+    fn uri(&self, urid: u32) -> Option<String> {
+        let uris = self.uris.lock().unwrap();
+        let uri = uris.get(urid.checked_sub(1)? as usize)?;
+        Some(uri.to_string_lossy().into_owned())
+    }
+}
+
+unsafe extern "C" fn urid_map(handle: *mut c_void, uri: *const c_char) -> u32 {
+    if uri.is_null() {
+        return 0;
+    }
+    (*(handle as *const UridTable)).map(CStr::from_ptr(uri))
+}
+
+unsafe extern "C" fn urid_unmap(handle: *mut c_void, urid: u32) -> *const c_char {
+    (*(handle as *const UridTable)).unmap(urid)
+}
+
+/// 传给插件的 feature 列表（地址必须在插件生命周期内保持稳定）
+struct HostFeatures {
+    urids: Box<UridTable>,
+    _map: Box<UridMapFeature>,
+    _unmap: Box<UridUnmapFeature>,
+    _features: Vec<Lv2Feature>,
+    feature_ptrs: Vec<*const Lv2Feature>,
+}
+
+impl HostFeatures {
+    fn new() -> Box<Self> {
+        let urids = Box::new(UridTable { uris: Mutex::new(Vec::new()) });
+        let handle = &*urids as *const UridTable as *mut c_void;
+
+        let mut map = Box::new(UridMapFeature { handle, map: urid_map });
+        let mut unmap = Box::new(UridUnmapFeature { handle, unmap: urid_unmap });
+
+        let features = vec![
+            Lv2Feature { uri: URID_MAP.as_ptr(), data: &mut *map as *mut UridMapFeature as *mut c_void },
+            Lv2Feature { uri: URID_UNMAP.as_ptr(), data: &mut *unmap as *mut UridUnmapFeature as *mut c_void },
+            Lv2Feature { uri: BOUNDED_BLOCK_LENGTH.as_ptr(), data: ptr::null_mut() },
+        ];
+        let mut feature_ptrs: Vec<*const Lv2Feature> = features.iter().map(|f| f as *const Lv2Feature).collect();
+        feature_ptrs.push(ptr::null());
+
+        Box::new(Self {
+            urids,
+            _map: map,
+            _unmap: unmap,
+            _features: features,
+            feature_ptrs,
+        })
+    }
+
+    fn as_ptr(&self) -> *const *const Lv2Feature {
+        self.feature_ptrs.as_ptr()
+    }
+
+    fn map(&self, uri: &str) -> u32 {
+        let uri = CString::new(uri).unwrap_or_default();
+        self.urids.map(&uri)
+    }
+}
+
+// ===== 状态保存 =====
+
+/// 一条 LV2 状态记录（key 和类型以 URI 保存，便于跨会话恢复）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Lv2StateEntry {
+    key: String,
+    value_type: String,
+    flags: u32,
+    value: String,
+}
+
+/// save/restore 回调的上下文
+struct StateContext<'a> {
+    features: &'a HostFeatures,
+    entries: Vec<(u32, u32, u32, Vec<u8>)>,
+}
+
+unsafe extern "C" fn state_store(
+    handle: *mut c_void,
+    key: u32,
+    value: *const c_void,
+    size: usize,
+    value_type: u32,
+    flags: u32,
+) -> u32 {
+    let context = &mut *(handle as *mut StateContext);
+    if flags & LV2_STATE_IS_POD == 0 {
+        // 非 POD 数据无法可靠地序列化
+        return LV2_STATE_ERR_UNKNOWN;
+    }
+    let data = std::slice::from_raw_parts(value as *const u8, size).to_vec();
+    context.entries.retain(|e| e.0 != key);
+    context.entries.push((key, value_type, flags, data));
+    LV2_STATE_SUCCESS
+}
+
+unsafe extern "C" fn state_retrieve(
+    handle: *mut c_void,
+    key: u32,
+    size: *mut usize,
+    value_type: *mut u32,
+    flags: *mut u32,
+) -> *const c_void {
+    let context = &*(handle as *const StateContext);
+    match context.entries.iter().find(|e| e.0 == key) {
+        Some((_, t, f, data)) => {
+            *size = data.len();
+            *value_type = *t;
+            *flags = *f;
+            data.as_ptr() as *const c_void
+        }
+        None => ptr::null(),
+    }
+}
+
+// ===== 插件实例 =====
+
+/// 端口缓冲区
+enum PortBuffer {
+    None,
+    Audio(Vec<f32>),
+    Atom(Vec<u64>),
+}
+
+/// LV2 插件包装器
+pub struct Lv2Plugin {
+    metadata: PluginMetadata,
+    info: Lv2PluginInfo,
+    descriptor: *const Lv2Descriptor,
+    handle: Lv2Handle,
+    state_interface: *const StateInterface,
+
+    /// 控制端口的值（按端口下标，连接后不再改变大小）
+    control_values: Vec<f32>,
+    port_buffers: Vec<PortBuffer>,
+    audio_inputs: Vec<u32>,
+    audio_outputs: Vec<u32>,
+
+    atom_sequence_urid: u32,
+    atom_chunk_urid: u32,
+
+    /// 实例化时的采样率和音频端口缓冲区大小
+    sample_rate: f64,
+    max_block_size: usize,
+    activated: bool,
+
+    // 以下字段必须比插件实例活得更久
+    features: Box<HostFeatures>,
+    _library: Option<Arc<Library>>,
+}
+
+// 插件实例只会被持有者独占访问
+unsafe impl Send for Lv2Plugin {}
+
+impl Lv2Plugin {
+    /// 从 bundle 加载插件
+    /// plugin_uri 为 None 时加载 bundle 中的第一个插件
+    pub fn load(bundle: &Path, plugin_uri: Option<&str>) -> Result<Self> {
+        info!("加载 LV2 插件: {:?}", bundle);
+
+        let plugins = parse_bundle(bundle)?;
+        let info = match plugin_uri {
+            Some(uri) => plugins.into_iter()
+                .find(|p| p.uri == uri)
+                .ok_or_else(|| anyhow::anyhow!("找不到插件: {}", uri))?,
+            None => plugins.into_iter()
+                .next()
+                .ok_or_else(|| anyhow::anyhow!("bundle 中没有插件"))?,
+        };
+
+        let library = unsafe { Library::new(&info.binary) }
+            .context(format!("加载动态库失败: {:?}", info.binary))?;
+        let descriptor_fn = unsafe {
+            *library.get::<Lv2DescriptorFunction>(b"lv2_descriptor\0")
+                .context("找不到 lv2_descriptor 符号")?
+        };
+
+        Self::instantiate(info, descriptor_fn, Some(Arc::new(library)))
+    }
+
+    /// 从扫描信息加载插件
+    pub fn from_metadata(metadata: &PluginMetadata) -> Result<Self> {
+        let uri = metadata.id.strip_prefix("lv2:").unwrap_or(&metadata.id);
+        Self::load(&metadata.path, Some(uri))
+    }
+
+    fn instantiate(
+        info: Lv2PluginInfo,
+        descriptor_fn: Lv2DescriptorFunction,
+        library: Option<Arc<Library>>,
+    ) -> Result<Self> {
+        if let Some(missing) = info.required_features.iter().find(|f| !SUPPORTED_FEATURES.contains(&f.as_str())) {
+            return Err(anyhow::anyhow!("插件需要不支持的 feature: {}", missing));
+        }
+
+        // 在共享库中查找与 URI 对应的描述符
+        let mut descriptor = ptr::null();
+        for index in 0.. {
+            let desc = unsafe { descriptor_fn(index) };
+            if desc.is_null() {
+                break;
+            }
+            let uri = unsafe { CStr::from_ptr((*desc).uri) };
+            if uri.to_string_lossy() == info.uri {
+                descriptor = desc;
+                break;
+            }
+        }
+        if descriptor.is_null() {
+            return Err(anyhow::anyhow!("共享库中找不到插件: {}", info.uri));
+        }
+
+        let desc = unsafe { &*descriptor };
+        if desc.connect_port.is_none() {
+            return Err(anyhow::anyhow!("插件缺少 connect_port"));
+        }
+        if desc.run.is_none() {
+            return Err(anyhow::anyhow!("插件缺少 run"));
+        }
+
+        let features = HostFeatures::new();
+        let atom_sequence_urid = features.map(&format!("{}Sequence", LV2_ATOM));
+        let atom_chunk_urid = features.map(&format!("{}Chunk", LV2_ATOM));
+
+        let handle = Self::create_handle(descriptor, &info, DEFAULT_SAMPLE_RATE, &features)?;
+
+        let state_interface = match desc.extension_data {
+            Some(extension_data) => unsafe { extension_data(STATE_INTERFACE.as_ptr()) as *const StateInterface },
+            None => ptr::null(),
+        };
+
+        let port_count = info.ports.iter().map(|p| p.index + 1).max().unwrap_or(0) as usize;
+        let mut control_values = vec![0.0f32; port_count];
+        let mut port_buffers: Vec<PortBuffer> = (0..port_count).map(|_| PortBuffer::None).collect();
+
+        for port in &info.ports {
+            let idx = port.index as usize;
+            match port.kind {
+                Lv2PortKind::Control => control_values[idx] = port.default,
+                Lv2PortKind::Audio | Lv2PortKind::Cv => port_buffers[idx] = PortBuffer::Audio(vec![0.0; MAX_BLOCK_SIZE]),
+                Lv2PortKind::Atom => port_buffers[idx] = PortBuffer::Atom(vec![0; ATOM_BUFFER_SIZE / 8]),
+                Lv2PortKind::Other if port.optional => {}
+                Lv2PortKind::Other => port_buffers[idx] = PortBuffer::Audio(vec![0.0; MAX_BLOCK_SIZE]),
+            }
+        }
+
+        let audio_inputs = info.audio_ports(true);
+        let audio_outputs = info.audio_ports(false);
+
+        let mut instance = Self {
+            metadata: info.to_metadata(),
+            info,
+            descriptor,
+            handle,
+            state_interface,
+            control_values,
+            port_buffers,
+            audio_inputs,
+            audio_outputs,
+            atom_sequence_urid,
+            atom_chunk_urid,
+            sample_rate: DEFAULT_SAMPLE_RATE,
+            max_block_size: MAX_BLOCK_SIZE,
+            activated: false,
+            features,
+            _library: library,
+        };
+
+        instance.connect_ports();
+        instance.activate();
+
+        info!("LV2 插件已加载: {} ({} 个端口)", instance.metadata.name, instance.info.ports.len());
+        Ok(instance)
+    }
+
+    /// 以指定采样率创建插件实例
+    fn create_handle(
+        descriptor: *const Lv2Descriptor,
+        info: &Lv2PluginInfo,
+        sample_rate: f64,
+        features: &HostFeatures,
+    ) -> Result<Lv2Handle> {
+        let instantiate = unsafe { (*descriptor).instantiate }
+            .ok_or_else(|| anyhow::anyhow!("插件缺少 instantiate"))?;
+
+        // bundle 路径必须以 '/' 结尾
+        let mut bundle_path = info.bundle.to_string_lossy().into_owned();
+        if !bundle_path.ends_with('/') {
+            bundle_path.push('/');
+        }
+        let bundle_c = CString::new(bundle_path).context("bundle 路径包含非法字符")?;
+
+        let handle = unsafe { instantiate(descriptor, sample_rate, bundle_c.as_ptr(), features.as_ptr()) };
+        if handle.is_null() {
+            return Err(anyhow::anyhow!("插件实例化失败: {}", info.name));
+        }
+        Ok(handle)
+    }
+
+    /// 连接所有端口（缓冲区地址在下次调整大小之前保持不变）
+    fn connect_ports(&mut self) {
+        let Some(connect_port) = (unsafe { (*self.descriptor).connect_port }) else {
+            return;
+        };
+        for port in &self.info.ports {
+            let idx = port.index as usize;
+            let data: *mut c_void = match (&mut self.port_buffers[idx], port.kind) {
+                (_, Lv2PortKind::Control) => &mut self.control_values[idx] as *mut f32 as *mut c_void,
+                (PortBuffer::Audio(buffer), _) => buffer.as_mut_ptr() as *mut c_void,
+                (PortBuffer::Atom(buffer), _) => buffer.as_mut_ptr() as *mut c_void,
+                (PortBuffer::None, _) => ptr::null_mut(),
+            };
+            unsafe { connect_port(self.handle, port.index, data) };
+        }
+    }
+
+    fn activate(&mut self) {
+        if self.activated {
+            return;
+        }
+        if let Some(activate) = unsafe { (*self.descriptor).activate } {
+            unsafe { activate(self.handle) };
+        }
+        self.activated = true;
+    }
+
+    fn deactivate(&mut self) {
+        if !self.activated {
+            return;
+        }
+        if let Some(deactivate) = unsafe { (*self.descriptor).deactivate } {
+            unsafe { deactivate(self.handle) };
+        }
+        self.activated = false;
+    }
+
+    fn port(&self, index: u32) -> Option<&Lv2PortInfo> {
+        self.info.ports.iter().find(|p| p.index == index)
+    }
+
+    fn control_inputs(&self) -> impl Iterator<Item = &Lv2PortInfo> {
+        self.info.ports
+            .iter()
+            .filter(|p| p.kind == Lv2PortKind::Control && p.is_input)
+    }
+
+    fn to_normalized(port: &Lv2PortInfo, plain: f32) -> f64 {
+        if port.is_logarithmic() && !port.toggled && !port.integer {
+            let plain = plain.clamp(port.minimum, port.maximum);
+            return ((plain / port.minimum).ln() / (port.maximum / port.minimum).ln()) as f64;
+        }
+        if port.maximum > port.minimum {
+            ((plain - port.minimum) / (port.maximum - port.minimum)).clamp(0.0, 1.0) as f64
+        } else {
+            0.0
+        }
+    }
+
+    fn to_plain(port: &Lv2PortInfo, normalized: f64) -> f32 {
+        let normalized = normalized.clamp(0.0, 1.0) as f32;
+        if port.toggled {
+            return if normalized >= 0.5 { port.maximum } else { port.minimum };
+        }
+        if port.is_logarithmic() && !port.integer {
+            return port.minimum * (port.maximum / port.minimum).powf(normalized);
+        }
+        let value = port.minimum + normalized * (port.maximum - port.minimum);
+        if port.integer { value.round() } else { value }
+    }
+
+    fn display_value(port: &Lv2PortInfo, plain: f32) -> String {
+        if let Some((label, _)) = port.scale_points.iter().find(|(_, v)| (*v - plain).abs() < 1e-6) {
+            return label.clone();
+        }
+        if port.toggled {
+            return if plain > 0.0 { "On" } else { "Off" }.to_string();
+        }
+        let text = if port.integer {
+            format!("{}", plain as i64)
+        } else {
+            format!("{:.2}", plain)
+        };
+        if port.unit.is_empty() {
+            text
+        } else {
+            format!("{} {}", text, port.unit)
+        }
+    }
+
+    /// 在每次 run 之前重置 atom 端口头部
+    fn prepare_atom_ports(&mut self) {
+        for port in &self.info.ports {
+            if let PortBuffer::Atom(buffer) = &mut self.port_buffers[port.index as usize] {
+                // LV2_Atom { size, type } 占第一个 u64，LV2_Atom_Sequence_Body 占第二个
+                let (size, urid) = if port.is_input {
+                    (8u32, self.atom_sequence_urid)
+                } else {
+                    ((ATOM_BUFFER_SIZE - 8) as u32, self.atom_chunk_urid)
+                };
+                let header = unsafe { &mut *(buffer.as_mut_ptr() as *mut [u32; 4]) };
+                *header = [size, urid, 0, 0];
+            }
+        }
+    }
+
+    /// 处理一个不超过 max_block_size 帧的块
+    fn process_block(&mut self, block: &mut AudioBuffer) {
+        let frames = block.frames();
+
+        // 缓冲区声道 -> 输入端口
+        let inputs = self.audio_inputs.len();
+        for (channel, &port) in self.audio_inputs.iter().enumerate() {
+            if let PortBuffer::Audio(buffer) = &mut self.port_buffers[port as usize] {
+                block.read_mapped(channel, inputs, &mut buffer[..frames]);
+            }
+        }
+
+        self.prepare_atom_ports();
+
+        if let Some(run) = unsafe { (*self.descriptor).run } {
+            unsafe { run(self.handle, frames as u32) };
+        }
+
+        // 输出端口 -> 缓冲区声道（最多取前两个输出）
+        let left = self.audio_outputs.first().copied();
+        let right = self.audio_outputs.get(1).copied().or(left);
+        if let (Some(left), Some(right)) = (left, right) {
+            let (PortBuffer::Audio(out_left), PortBuffer::Audio(out_right)) =
+                (&self.port_buffers[left as usize], &self.port_buffers[right as usize])
+            else {
+                return;
+            };
+            let outputs = self.audio_outputs.len().min(2);
+            block.write_mapped(&[&out_left[..frames], &out_right[..frames]][..outputs]);
+        }
+    }
+
+    /// 通过 state:interface 保存插件内部状态
+    fn save_extension_state(&self) -> Vec<Lv2StateEntry> {
+        if self.state_interface.is_null() {
+            return Vec::new();
+        }
+        let Some(save) = (unsafe { (*self.state_interface).save }) else {
+            return Vec::new();
+        };
+
+        let mut context = StateContext {
+            features: &self.features,
+            entries: Vec::new(),
+        };
+        let status = unsafe {
+            save(
+                self.handle,
+                state_store,
+                &mut context as *mut StateContext as *mut c_void,
+                LV2_STATE_IS_POD | LV2_STATE_IS_PORTABLE,
+                self.features.as_ptr(),
+            )
+        };
+        if status != LV2_STATE_SUCCESS {
+            warn!("LV2 插件保存状态失败: {}", self.metadata.name);
+            return Vec::new();
+        }
+
+        context.entries
+            .iter()
+            .filter_map(|(key, value_type, flags, data)| {
+                Some(Lv2StateEntry {
+                    key: context.features.urids.uri(*key)?,
+                    value_type: context.features.urids.uri(*value_type)?,
+                    flags: *flags,
+                    value: base64::engine::general_purpose::STANDARD.encode(data),
+                })
+            })
+            .collect()
+    }
+
+    /// 通过 state:interface 恢复插件内部状态
+    fn restore_extension_state(&mut self, entries: &[Lv2StateEntry]) {
+        if self.state_interface.is_null() || entries.is_empty() {
+            return;
+        }
+        let Some(restore) = (unsafe { (*self.state_interface).restore }) else {
+            return;
+        };
+
+        let mut context = StateContext {
+            features: &self.features,
+            entries: Vec::new(),
+        };
+        for entry in entries {
+            let Ok(data) = base64::engine::general_purpose::STANDARD.decode(&entry.value) else {
+                continue;
+            };
+            let key = context.features.map(&entry.key);
+            let value_type = context.features.map(&entry.value_type);
+            context.entries.push((key, value_type, entry.flags, data));
+        }
+
+        let status = unsafe {
+            restore(
+                self.handle,
+                state_retrieve,
+                &mut context as *mut StateContext as *mut c_void,
+                0,
+                self.features.as_ptr(),
+            )
+        };
+        if status != LV2_STATE_SUCCESS {
+            warn!("LV2 插件恢复状态失败: {}", self.metadata.name);
+        }
+    }
+}
+
+impl AudioProcessor for Lv2Plugin {
+    fn prepare(&mut self, sample_rate: f64, max_block_size: usize, _layout: ChannelLayout) {
+        self.deactivate();
+
+        // LV2 只在实例化时接收采样率，变化时重新创建实例并恢复内部状态
+        let mut restore = None;
+        if sample_rate != self.sample_rate {
+            let entries = self.save_extension_state();
+            match Self::create_handle(self.descriptor, &self.info, sample_rate, &self.features) {
+                Ok(handle) => {
+                    if let Some(cleanup) = unsafe { (*self.descriptor).cleanup } {
+                        unsafe { cleanup(self.handle) };
+                    }
+                    self.handle = handle;
+                    self.sample_rate = sample_rate;
+                    restore = Some(entries);
+                }
+                Err(e) => warn!("LV2 插件无法切换到 {} Hz: {}", sample_rate, e),
+            }
+        }
+
+        self.max_block_size = max_block_size.max(1);
+        for buffer in &mut self.port_buffers {
+            if let PortBuffer::Audio(buffer) = buffer {
+                buffer.resize(self.max_block_size, 0.0);
+            }
+        }
+        self.connect_ports();
+
+        if let Some(entries) = restore {
+            self.restore_extension_state(&entries);
+        }
+        self.activate();
+    }
+
+    fn reset(&mut self) {
+        // LV2 规定 deactivate + activate 会清空插件的处理状态
+        if self.activated {
+            self.deactivate();
+            self.activate();
+        }
+    }
+
+    fn release(&mut self) {
+        self.deactivate();
+    }
+
+    fn process(&mut self, buffer: &mut AudioBuffer) {
+        if !self.activated {
+            return;
+        }
+        let frames = buffer.frames();
+        for start in (0..frames).step_by(self.max_block_size) {
+            self.process_block(&mut buffer.slice(start, start + self.max_block_size));
+        }
+    }
+
+    fn get_info(&self) -> &PluginMetadata {
+        &self.metadata
+    }
+
+    fn set_parameter(&mut self, id: u32, value: f64) {
+        match self.port(id) {
+            Some(port) if port.kind == Lv2PortKind::Control && port.is_input => {
+                self.control_values[id as usize] = Self::to_plain(port, value);
+            }
+            // 可能在音频线程调用，未知的端口直接忽略，不写日志
+            _ => {}
+        }
+    }
+
+    fn get_parameter(&self, id: u32) -> Option<f64> {
+        let port = self.port(id).filter(|p| p.kind == Lv2PortKind::Control)?;
+        Some(Self::to_normalized(port, self.control_values[id as usize]))
+    }
+
+    fn parameter_descriptors(&self) -> Vec<ParameterDescriptor> {
+        self.control_inputs().map(Lv2PortInfo::descriptor).collect()
+    }
+
+    fn get_all_parameters(&self) -> Vec<PluginParameter> {
+        self.control_inputs()
+            .map(|port| {
+                let plain = self.control_values[port.index as usize];
+                PluginParameter {
+                    id: port.index,
+                    name: port.name.clone(),
+                    value: Self::to_normalized(port, plain),
+                    display: Self::display_value(port, plain),
+                }
+            })
+            .collect()
+    }
+
+    fn save_state(&self) -> PluginState {
+        let entries = self.save_extension_state();
+        let state_data = if entries.is_empty() {
+            String::new()
+        } else {
+            let json = serde_json::to_vec(&entries).unwrap_or_default();
+            base64::engine::general_purpose::STANDARD.encode(json)
+        };
+
+        PluginState {
+            plugin_id: self.metadata.id.clone(),
+            parameters: self.get_all_parameters(),
+            state_data,
+        }
+    }
+
+    fn load_state(&mut self, state: &PluginState) {
+        for param in &state.parameters {
+            self.set_parameter(param.id, param.value);
+        }
+
+        if state.state_data.is_empty() {
+            return;
+        }
+        let entries: Vec<Lv2StateEntry> = base64::engine::general_purpose::STANDARD
+            .decode(&state.state_data)
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .unwrap_or_else(|| {
+                warn!("LV2 状态数据解析失败: {}", self.metadata.name);
+                Vec::new()
+            });
+        self.restore_extension_state(&entries);
+    }
+}
+
+impl Drop for Lv2Plugin {
+    fn drop(&mut self) {
+        self.deactivate();
+        if let Some(cleanup) = unsafe { (*self.descriptor).cleanup } {
+            unsafe { cleanup(self.handle) };
+        }
+        debug!("LV2 插件已销毁: {}", self.metadata.name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::with_interleaved;
+
+    // 一个进程内的最小 LV2 增益插件，用于测试宿主逻辑
+
+    const TEST_TTL: &str = r#"
+        @prefix lv2:  <http://lv2plug.in/ns/lv2core#> .
+        @prefix doap: <http://usefulinc.com/ns/doap#> .
+        @prefix foaf: <http://xmlns.com/foaf/0.1/> .
+        @prefix rdfs: <http://www.w3.org/2000/01/rdf-schema#> .
+        @prefix units: <http://lv2plug.in/ns/extensions/units#> .
+        @prefix pprops: <http://lv2plug.in/ns/ext/port-props#> .
+
+        <urn:test:gain>
+            a lv2:Plugin ;
+            lv2:binary <gain.so> ;
+            doap:name "Test Gain" ;
+            doap:maintainer [ foaf:name "Test" ] ;
+            lv2:minorVersion 1 ;
+            lv2:microVersion 2 ;
+            lv2:port [
+                a lv2:InputPort, lv2:ControlPort ;
+                lv2:index 0 ; lv2:symbol "gain" ; lv2:name "Gain" ;
+                lv2:default 1.0 ; lv2:minimum 0.0 ; lv2:maximum 2.0 ;
+                units:unit units:coef
+            ] , [
+                a lv2:InputPort, lv2:ControlPort ;
+                lv2:index 1 ; lv2:symbol "mode" ; lv2:name "Mode" ;
+                lv2:portProperty lv2:enumeration, pprops:notAutomatic ;
+                lv2:default 0 ; lv2:minimum 0 ; lv2:maximum 1 ;
+                lv2:scalePoint [ rdfs:label "Clean" ; rdf:value 0 ] ,
+                               [ rdfs:label "Drive" ; rdf:value 1 ]
+            ] , [
+                a lv2:InputPort, lv2:AudioPort ; lv2:index 2 ; lv2:symbol "in"
+            ] , [
+                a lv2:OutputPort, lv2:AudioPort ; lv2:index 3 ; lv2:symbol "out"
+            ] .
+    "#;
+
+    struct TestGain {
+        rate: f64,
+        gain: *const f32,
+        input: *const f32,
+        output: *mut f32,
+    }
+
+    static DESCRIPTOR: Lv2Descriptor = Lv2Descriptor {
+        uri: c"urn:test:gain".as_ptr(),
+        instantiate: Some(test_instantiate),
+        connect_port: Some(test_connect_port),
+        activate: None,
+        run: Some(test_run),
+        deactivate: None,
+        cleanup: Some(test_cleanup),
+        extension_data: None,
+    };
+
+    // 静态描述符中的裸指针只指向常量字符串
+    unsafe impl Sync for Lv2Descriptor {}
+
+    unsafe extern "C" fn test_descriptor(index: u32) -> *const Lv2Descriptor {
+        if index == 0 { &DESCRIPTOR } else { ptr::null() }
+    }
+
+    unsafe extern "C" fn test_instantiate(
+        _desc: *const Lv2Descriptor,
+        rate: f64,
+        _bundle: *const c_char,
+        _features: *const *const Lv2Feature,
+    ) -> Lv2Handle {
+        Box::into_raw(Box::new(TestGain {
+            rate,
+            gain: ptr::null(),
+            input: ptr::null(),
+            output: ptr::null_mut(),
+        })) as Lv2Handle
+    }
+
+    unsafe extern "C" fn test_connect_port(instance: Lv2Handle, port: u32, data: *mut c_void) {
+        let plugin = &mut *(instance as *mut TestGain);
+        match port {
+            0 => plugin.gain = data as *const f32,
+            2 => plugin.input = data as *const f32,
+            3 => plugin.output = data as *mut f32,
+            _ => {}
+        }
+    }
+
+    unsafe extern "C" fn test_run(instance: Lv2Handle, sample_count: u32) {
+        let plugin = &*(instance as *const TestGain);
+        for i in 0..sample_count as usize {
+            *plugin.output.add(i) = *plugin.input.add(i) * *plugin.gain;
+        }
+    }
+
+    unsafe extern "C" fn test_cleanup(instance: Lv2Handle) {
+        drop(Box::from_raw(instance as *mut TestGain));
+    }
+
+    fn test_info() -> Lv2PluginInfo {
+        let ttl = format!("@prefix rdf: <{}> .\n{}", "http://www.w3.org/1999/02/22-rdf-syntax-ns#", TEST_TTL);
+        let mut graph = Graph::new();
+        graph.parse(&ttl, "file:///test/gain.lv2/gain.ttl").unwrap();
+        plugins_from_graph(&graph, Path::new("/test/gain.lv2")).unwrap().remove(0)
+    }
+
+    #[test]
+    fn test_plugin_info_from_ttl() {
+        let info = test_info();
+        assert_eq!(info.uri, "urn:test:gain");
+        assert_eq!(info.name, "Test Gain");
+        assert_eq!(info.vendor, "Test");
+        assert_eq!(info.version, "0.1.2");
+        assert_eq!(info.binary, PathBuf::from("/test/gain.lv2/gain.so"));
+        assert_eq!(info.ports.len(), 4);
+        assert_eq!(info.ports[1].scale_points.len(), 2);
+
+        let metadata = info.to_metadata();
+        assert_eq!(metadata.id, "lv2:urn:test:gain");
+        assert_eq!(metadata.format, PluginFormat::Lv2);
+        assert_eq!((metadata.num_inputs, metadata.num_outputs), (1, 1));
+    }
+
+    #[test]
+    fn test_lv2_process_and_parameters() {
+        let mut plugin = Lv2Plugin::instantiate(test_info(), test_descriptor, None).unwrap();
+
+        let params = plugin.get_all_parameters();
+        assert_eq!(params.len(), 2);
+        assert!((params[0].value - 0.5).abs() < 1e-6);
+        assert_eq!(params[1].display, "Clean");
+
+        // 单声道插件：输入求和，输出复制到两个声道
+        plugin.set_parameter(0, 0.25);
+        let mut buffer = vec![1.0, 0.0, 1.0, 0.0];
+        with_interleaved(&mut buffer, 2, |buffer| plugin.process(buffer));
+        assert!(buffer.iter().all(|&s| (s - 0.25).abs() < 1e-6));
+
+        // 单声道吉他输入直接处理，不经过立体声
+        let mut mono = vec![1.0; 8];
+        plugin.process(&mut AudioBuffer::from_mono(&mut mono));
+        assert!(mono.iter().all(|&s| (s - 0.5).abs() < 1e-6));
+
+        plugin.set_parameter(1, 0.9);
+        assert_eq!(plugin.get_all_parameters()[1].display, "Drive");
+    }
+
+    #[test]
+    fn test_lv2_parameter_descriptors() {
+        let plugin = Lv2Plugin::instantiate(test_info(), test_descriptor, None).unwrap();
+        let descriptors = plugin.parameter_descriptors();
+        assert_eq!(descriptors.len(), 2);
+
+        let gain = &descriptors[0];
+        assert_eq!((gain.min, gain.max, gain.default), (0.0, 2.0, 1.0));
+        assert_eq!(gain.steps, 0);
+        assert!(gain.unit.is_empty());
+        assert!(gain.flags.automatable);
+
+        let mode = &descriptors[1];
+        assert!(mode.is_enum());
+        assert_eq!(mode.steps, 1);
+        assert!(!mode.flags.automatable);
+        assert_eq!(mode.value_names[1], (1.0, "Drive".to_string()));
+        assert_eq!(plugin.text_to_value(1, "drive"), Some(1.0));
+        assert_eq!(plugin.value_to_text(1, 0.0).unwrap(), "Clean");
+    }
+
+    #[test]
+    fn test_lv2_state_roundtrip() {
+        let mut plugin = Lv2Plugin::instantiate(test_info(), test_descriptor, None).unwrap();
+        plugin.set_parameter(0, 0.75);
+        let state = plugin.save_state();
+
+        let mut restored = Lv2Plugin::instantiate(test_info(), test_descriptor, None).unwrap();
+        restored.load_state(&state);
+        assert!((restored.get_parameter(0).unwrap() - 0.75).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_lv2_prepare_reinstantiates() {
+        let mut plugin = Lv2Plugin::instantiate(test_info(), test_descriptor, None).unwrap();
+        plugin.set_parameter(0, 0.25);
+
+        plugin.prepare(96000.0, 64, ChannelLayout::Stereo);
+        assert_eq!(unsafe { (*(plugin.handle as *const TestGain)).rate }, 96000.0);
+
+        // 参数保留，新的端口缓冲区已连接
+        let mut buffer = vec![1.0; 200 * 2];
+        with_interleaved(&mut buffer, 2, |buffer| plugin.process(buffer));
+        assert!(buffer.iter().all(|&s| (s - 0.5).abs() < 1e-6));
+
+        plugin.release();
+        let mut buffer = vec![1.0; 4];
+        with_interleaved(&mut buffer, 2, |buffer| plugin.process(buffer));
+        assert!(buffer.iter().all(|&s| s == 1.0));
+    }
+
+    #[test]
+    fn test_missing_bundle() {
+        assert!(Lv2Plugin::load(Path::new("/nonexistent/missing.lv2"), None).is_err());
+    }
+}
@@ -1,21 +1,51 @@
 mod scanner;
 mod loader;
 mod chain;
+mod routing;
 mod types;
+mod events;
 mod au_wrapper;
+mod clap_wrapper;
+mod lv2_wrapper;
+mod vst3_wrapper;
+mod ladspa_wrapper;
+mod native_wrapper;
+mod wasm_wrapper;
+mod builtin;
+mod ttl;
 mod project;
 
 pub use scanner::PluginScanner;
 #[allow(unused_imports)]
 pub use scanner::PluginInfo;
 #[allow(unused_imports)]
-pub use loader::PluginLoader;
+pub use loader::{plugin_factory, PluginLoader};
+#[cfg(test)]
+pub use loader::DummyPlugin;
 #[allow(unused_imports)]
-pub use chain::PluginChain;
+pub use chain::{PluginChain, ChainSlot, ChainLayouts, MAX_PLUGINS};
+#[allow(unused_imports)]
+pub use routing::{ParallelRack, Lane, RackState, LaneState, restore_chain, RACK_ID, MAX_LANES};
 #[allow(unused_imports)]
 pub use types::*;
 #[allow(unused_imports)]
+pub use events::{EventKind, EventList, ProcessEvent, MAX_EVENTS};
+#[allow(unused_imports)]
 pub use au_wrapper::AudioUnitPlugin;
 #[allow(unused_imports)]
-pub use project::{Project, ProjectManager, AudioConfig};
+pub use clap_wrapper::ClapPlugin;
+#[allow(unused_imports)]
+pub use lv2_wrapper::Lv2Plugin;
+#[allow(unused_imports)]
+pub use vst3_wrapper::Vst3Plugin;
+#[allow(unused_imports)]
+pub use ladspa_wrapper::LadspaPlugin;
+#[allow(unused_imports)]
+pub use native_wrapper::NativePlugin;
+#[allow(unused_imports)]
+pub use wasm_wrapper::{WasmPlugin, WasmLimits};
+#[allow(unused_imports)]
+pub use builtin::{BuiltinPlugin, BuiltinKind};
+#[allow(unused_imports)]
+pub use project::{Project, ProjectManager, AudioConfig, Preset};
 
@@ -0,0 +1,553 @@
+// 原生 Rust 插件宿主
+// 加载用 plugin-sdk 编写的 cdylib，通过版本化的 C ABI 描述符 + 虚表调用插件
+
+use anyhow::{Result, Context};
+use base64::Engine as _;
+use libloading::Library;
+use log::{info, warn, debug};
+use plugin_sdk::{
+    EntryFunction, ParameterInfo, ParameterRangeInfo, PluginDescriptor, ABI_VERSION, ENTRY_SYMBOL, PARAM_AUTOMATABLE,
+    PARAM_BYPASS, PARAM_HIDDEN, PARAM_LOGARITHMIC, PARAM_READ_ONLY, TEXT_SIZE,
+};
+use std::ffi::{c_char, c_void, CStr};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::audio::AudioBuffer;
+
+use super::types::{PluginMetadata, AudioProcessor, PluginParameter, PluginState, PluginFormat, ChannelLayout, ProcessConfig, ParameterDescriptor, ParameterFlags};
+
+/// 实例化插件时使用的采样率
+const DEFAULT_SAMPLE_RATE: f64 = 48000.0;
+
+fn c_str_to_string(ptr: *const c_char) -> String {
+    if ptr.is_null() {
+        String::new()
+    } else {
+        unsafe { CStr::from_ptr(ptr) }.to_string_lossy().into_owned()
+    }
+}
+
+/// 库中所有 ABI 版本匹配的描述符
+fn descriptors(entry: EntryFunction) -> Vec<*const PluginDescriptor> {
+    (0..)
+        .map(|index| unsafe { entry(index) })
+        .take_while(|desc| !desc.is_null())
+        .filter(|&desc| {
+            // 只读取第一个字段，版本不匹配时其余布局不可信
+            let version = unsafe { *(desc as *const u32) };
+            if version != ABI_VERSION {
+                warn!("跳过 ABI 版本不匹配的插件: 插件 v{}，宿主 v{}", version, ABI_VERSION);
+            }
+            version == ABI_VERSION
+        })
+        .collect()
+}
+
+fn descriptor_to_metadata(desc: &PluginDescriptor, path: &Path) -> PluginMetadata {
+    PluginMetadata {
+        id: format!("native:{}", c_str_to_string(desc.id)),
+        name: c_str_to_string(desc.name),
+        vendor: c_str_to_string(desc.vendor),
+        version: c_str_to_string(desc.version),
+        path: path.to_path_buf(),
+        format: PluginFormat::Native,
+        num_inputs: desc.num_inputs,
+        num_outputs: desc.num_outputs,
+    }
+}
+
+fn open_library(path: &Path) -> Result<(Library, EntryFunction)> {
+    let library = unsafe { Library::new(path) }
+        .context(format!("加载动态库失败: {:?}", path))?;
+    let entry = unsafe {
+        *library.get::<EntryFunction>(ENTRY_SYMBOL)
+            .context("找不到 plugin_loader_entry 符号")?
+    };
+    Ok((library, entry))
+}
+
+/// 动态库是否导出原生插件入口
+pub fn is_native_library(path: &Path) -> bool {
+    open_library(path).is_ok()
+}
+
+/// 列出动态库中的所有原生插件
+pub fn scan_native_library(path: &Path) -> Result<Vec<PluginMetadata>> {
+    let (_library, entry) = open_library(path)?;
+    let found: Vec<PluginMetadata> = descriptors(entry)
+        .into_iter()
+        .map(|desc| descriptor_to_metadata(unsafe { &*desc }, path))
+        .collect();
+
+    if found.is_empty() {
+        return Err(anyhow::anyhow!("没有 ABI v{} 兼容的插件", ABI_VERSION));
+    }
+    Ok(found)
+}
+
+/// 原生 Rust 插件包装器
+pub struct NativePlugin {
+    metadata: PluginMetadata,
+    descriptor: *const PluginDescriptor,
+    instance: *mut c_void,
+    sample_rate: f64,
+    /// release 之后到下次 prepare 之前直通
+    active: bool,
+    /// ABI 使用交错立体声，平面缓冲区在这里转换（max_block_size * 2）
+    scratch: Vec<f32>,
+    _library: Option<Arc<Library>>,
+}
+
+// 插件实例只会被持有者独占访问（Plugin trait 要求 Send）
+unsafe impl Send for NativePlugin {}
+
+impl NativePlugin {
+    /// 从动态库加载插件
+    /// plugin_id 为 None 时加载库中的第一个插件
+    pub fn load(path: &Path, plugin_id: Option<&str>) -> Result<Self> {
+        info!("加载原生插件: {:?}", path);
+
+        let (library, entry) = open_library(path)?;
+        Self::instantiate(entry, path, plugin_id, Some(Arc::new(library)))
+    }
+
+    /// 从扫描信息加载插件
+    pub fn from_metadata(metadata: &PluginMetadata) -> Result<Self> {
+        let id = metadata.id.strip_prefix("native:").unwrap_or(&metadata.id);
+        Self::load(&metadata.path, Some(id))
+    }
+
+    fn instantiate(
+        entry: EntryFunction,
+        path: &Path,
+        plugin_id: Option<&str>,
+        library: Option<Arc<Library>>,
+    ) -> Result<Self> {
+        let all = descriptors(entry);
+        let descriptor = match plugin_id {
+            Some(id) => all.into_iter()
+                .find(|&d| c_str_to_string(unsafe { (*d).id }) == id)
+                .ok_or_else(|| anyhow::anyhow!("找不到插件: {}", id))?,
+            None => all.into_iter()
+                .next()
+                .ok_or_else(|| anyhow::anyhow!("库中没有 ABI v{} 兼容的插件", ABI_VERSION))?,
+        };
+
+        let desc = unsafe { &*descriptor };
+        let metadata = descriptor_to_metadata(desc, path);
+        let instance = unsafe { (desc.vtable.create)(DEFAULT_SAMPLE_RATE) };
+        if instance.is_null() {
+            return Err(anyhow::anyhow!("插件实例化失败: {}", metadata.name));
+        }
+
+        info!("原生插件已加载: {} v{}", metadata.name, metadata.version);
+        Ok(Self {
+            metadata,
+            descriptor,
+            instance,
+            sample_rate: DEFAULT_SAMPLE_RATE,
+            active: true,
+            scratch: vec![0.0; ProcessConfig::default().max_block_size * 2],
+            _library: library,
+        })
+    }
+
+    fn descriptor(&self) -> &PluginDescriptor {
+        unsafe { &*self.descriptor }
+    }
+
+    /// 读取插件提供的范围信息，没有时按 0.0 - 1.0 描述
+    fn param_descriptor(&self, param: &PluginParameter) -> ParameterDescriptor {
+        let vtable = &self.descriptor().vtable;
+        let mut range = ParameterRangeInfo::empty();
+        if !unsafe { (vtable.parameter_range)(self.instance, param.id, &mut range) } {
+            return ParameterDescriptor::normalized(param.id, param.name.clone(), 0.0);
+        }
+
+        let names = (0..range.value_count)
+            .filter_map(|index| {
+                let mut name = [0 as c_char; TEXT_SIZE];
+                unsafe { (vtable.parameter_value_name)(self.instance, param.id, index, name.as_mut_ptr()) }
+                    .then(|| (range.min + index as f64, plugin_sdk::read_text(&name)))
+            })
+            .collect();
+
+        ParameterDescriptor::new(param.id, param.name.clone(), range.min, range.max, range.default)
+            .with_unit(plugin_sdk::read_text(&range.unit))
+            .with_steps(range.steps)
+            .with_logarithmic(range.flags & PARAM_LOGARITHMIC != 0)
+            .with_value_names(names)
+            .with_flags(ParameterFlags {
+                automatable: range.flags & PARAM_AUTOMATABLE != 0,
+                read_only: range.flags & PARAM_READ_ONLY != 0,
+                bypass: range.flags & PARAM_BYPASS != 0,
+                hidden: range.flags & PARAM_HIDDEN != 0,
+            })
+    }
+
+    /// 以新采样率重新创建实例，并恢复参数和内部状态
+    /// 只在插件没有提供 prepare / reset 时使用
+    fn recreate(&mut self, sample_rate: f64) {
+        let state = self.save_state();
+        let instance = unsafe { (self.descriptor().vtable.create)(sample_rate) };
+        if instance.is_null() {
+            warn!("原生插件重新实例化失败: {}", self.metadata.name);
+            return;
+        }
+
+        unsafe { (self.descriptor().vtable.destroy)(self.instance) };
+        self.instance = instance;
+        self.sample_rate = sample_rate;
+        self.load_state(&state);
+    }
+}
+
+unsafe extern "C" fn collect_state(context: *mut c_void, data: *const u8, len: usize) {
+    if !data.is_null() {
+        let out = &mut *(context as *mut Vec<u8>);
+        out.extend_from_slice(std::slice::from_raw_parts(data, len));
+    }
+}
+
+impl AudioProcessor for NativePlugin {
+    fn prepare(&mut self, sample_rate: f64, max_block_size: usize, _layout: ChannelLayout) {
+        let max_block_size = max_block_size.max(1);
+        let prepared = match self.descriptor().vtable.prepare {
+            Some(prepare) => unsafe { prepare(self.instance, sample_rate, max_block_size as u32) },
+            None => false,
+        };
+        if prepared {
+            self.sample_rate = sample_rate;
+        } else if sample_rate != self.sample_rate {
+            // 不支持 prepare 的插件接受任意长度的缓冲区，只有采样率变化需要重建
+            self.recreate(sample_rate);
+        }
+        self.scratch.resize(max_block_size * 2, 0.0);
+        self.active = true;
+    }
+
+    fn reset(&mut self) {
+        let reset = match self.descriptor().vtable.reset {
+            Some(reset) => unsafe { reset(self.instance) },
+            None => false,
+        };
+        if !reset {
+            // 不支持 reset 时重建实例来清空延迟线等处理状态
+            self.recreate(self.sample_rate);
+        }
+    }
+
+    fn latency_samples(&self) -> u32 {
+        match self.descriptor().vtable.latency {
+            Some(latency) => unsafe { latency(self.instance) },
+            None => 0,
+        }
+    }
+
+    fn release(&mut self) {
+        self.active = false;
+    }
+
+    fn process(&mut self, buffer: &mut AudioBuffer) {
+        if !self.active {
+            return;
+        }
+        let process = self.descriptor().vtable.process;
+        let block_size = self.scratch.len() / 2;
+        for start in (0..buffer.frames()).step_by(block_size) {
+            let mut block = buffer.slice(start, start + block_size);
+            let scratch = &mut self.scratch[..block.frames() * 2];
+            block.copy_to_interleaved(scratch, 2);
+            unsafe { process(self.instance, scratch.as_mut_ptr(), scratch.len()) };
+            block.copy_from_interleaved(scratch, 2);
+        }
+    }
+
+    fn get_info(&self) -> &PluginMetadata {
+        &self.metadata
+    }
+
+    fn set_parameter(&mut self, id: u32, value: f64) {
+        unsafe { (self.descriptor().vtable.set_parameter)(self.instance, id, value.clamp(0.0, 1.0)) };
+    }
+
+    fn get_parameter(&self, id: u32) -> Option<f64> {
+        let mut value = 0.0;
+        let found = unsafe { (self.descriptor().vtable.get_parameter)(self.instance, id, &mut value) };
+        found.then_some(value)
+    }
+
+    fn get_all_parameters(&self) -> Vec<PluginParameter> {
+        let vtable = &self.descriptor().vtable;
+        let count = unsafe { (vtable.parameter_count)(self.instance) };
+
+        (0..count)
+            .filter_map(|index| {
+                let mut param = ParameterInfo::empty();
+                unsafe { (vtable.parameter_info)(self.instance, index, &mut param) }.then(|| PluginParameter {
+                    id: param.id,
+                    name: plugin_sdk::read_text(&param.name),
+                    value: param.value,
+                    display: plugin_sdk::read_text(&param.display),
+                })
+            })
+            .collect()
+    }
+
+    fn parameter_descriptors(&self) -> Vec<ParameterDescriptor> {
+        self.get_all_parameters()
+            .iter()
+            .map(|param| self.param_descriptor(param))
+            .collect()
+    }
+
+    fn save_state(&self) -> PluginState {
+        let mut data = Vec::new();
+        unsafe {
+            (self.descriptor().vtable.save_state)(
+                self.instance,
+                &mut data as *mut Vec<u8> as *mut c_void,
+                collect_state,
+            )
+        };
+
+        PluginState {
+            plugin_id: self.metadata.id.clone(),
+            parameters: self.get_all_parameters(),
+            state_data: base64::engine::general_purpose::STANDARD.encode(&data),
+        }
+    }
+
+    fn load_state(&mut self, state: &PluginState) {
+        // 先恢复参数，再让插件用内部状态覆盖
+        for param in &state.parameters {
+            self.set_parameter(param.id, param.value);
+        }
+
+        let Ok(data) = base64::engine::general_purpose::STANDARD.decode(&state.state_data) else {
+            warn!("原生插件状态数据无效: {}", self.metadata.name);
+            return;
+        };
+        if !data.is_empty() {
+            let ok = unsafe { (self.descriptor().vtable.load_state)(self.instance, data.as_ptr(), data.len()) };
+            if !ok {
+                warn!("原生插件加载状态失败: {}", self.metadata.name);
+            }
+        }
+    }
+}
+
+impl Drop for NativePlugin {
+    fn drop(&mut self) {
+        unsafe { (self.descriptor().vtable.destroy)(self.instance) };
+        debug!("原生插件已销毁: {}", self.metadata.name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::with_interleaved;
+    use plugin_sdk::{export_plugins, Parameter, ParameterRange, Plugin};
+
+    // 一个进程内的原生增益插件，通过 SDK 宏导出，用于测试宿主逻辑
+
+    struct TestGain {
+        gain: f64,
+    }
+
+    impl Plugin for TestGain {
+        const ID: &'static CStr = c"test.gain";
+        const NAME: &'static CStr = c"Test Gain";
+        const VENDOR: &'static CStr = c"Test";
+        const VERSION: &'static CStr = c"1.0.0";
+
+        fn new(_sample_rate: f64) -> Self {
+            Self { gain: 0.5 }
+        }
+
+        fn process(&mut self, buffer: &mut [f32]) {
+            for sample in buffer {
+                *sample *= (self.gain * 2.0) as f32;
+            }
+        }
+
+        fn parameters(&self) -> Vec<Parameter> {
+            vec![Parameter {
+                id: 0,
+                name: "Gain".to_string(),
+                value: self.gain,
+                display: format!("{:.2}", self.gain * 2.0),
+            }]
+        }
+
+        fn parameter_range(&self, id: u32) -> Option<ParameterRange> {
+            (id == 0).then(|| ParameterRange::new(0.0, 2.0, 1.0).with_unit("x"))
+        }
+
+        fn set_parameter(&mut self, id: u32, value: f64) {
+            if id == 0 {
+                self.gain = value;
+            }
+        }
+
+        fn save_state(&self) -> Vec<u8> {
+            self.gain.to_le_bytes().to_vec()
+        }
+
+        fn load_state(&mut self, data: &[u8]) -> bool {
+            match data.try_into() {
+                Ok(bytes) => {
+                    self.gain = f64::from_le_bytes(bytes);
+                    true
+                }
+                Err(_) => false,
+            }
+        }
+    }
+
+    /// 实现了 prepare / reset / latency 的插件：按采样率预读 1 ms
+    struct TestLookahead {
+        latency: u32,
+        history: f32,
+    }
+
+    impl Plugin for TestLookahead {
+        const ID: &'static CStr = c"test.lookahead";
+        const NAME: &'static CStr = c"Test Lookahead";
+        const VENDOR: &'static CStr = c"Test";
+        const VERSION: &'static CStr = c"1.0.0";
+
+        fn new(sample_rate: f64) -> Self {
+            Self { latency: (sample_rate / 1000.0) as u32, history: 0.0 }
+        }
+
+        fn process(&mut self, buffer: &mut [f32]) {
+            self.history += buffer.iter().sum::<f32>();
+        }
+
+        fn prepare(&mut self, sample_rate: f64, _max_block_size: usize) -> bool {
+            self.latency = (sample_rate / 1000.0) as u32;
+            true
+        }
+
+        fn reset(&mut self) -> bool {
+            self.history = 0.0;
+            true
+        }
+
+        fn latency(&self) -> u32 {
+            self.latency
+        }
+
+        fn parameters(&self) -> Vec<Parameter> {
+            vec![Parameter { id: 0, name: "History".to_string(), value: self.history as f64, display: String::new() }]
+        }
+    }
+
+    export_plugins!(TestGain, TestLookahead);
+
+    fn test_plugin() -> NativePlugin {
+        NativePlugin::instantiate(plugin_loader_entry, Path::new("/test/libgain.so"), None, None).unwrap()
+    }
+
+    #[test]
+    fn test_native_metadata() {
+        let plugin = test_plugin();
+        let info = plugin.get_info();
+        assert_eq!(info.id, "native:test.gain");
+        assert_eq!(info.name, "Test Gain");
+        assert_eq!(info.version, "1.0.0");
+        assert_eq!(info.format, PluginFormat::Native);
+    }
+
+    #[test]
+    fn test_native_process_and_parameters() {
+        let mut plugin = test_plugin();
+        plugin.set_parameter(0, 1.0);
+
+        let mut buffer = vec![0.25, -0.5];
+        with_interleaved(&mut buffer, 2, |buffer| plugin.process(buffer));
+        assert_eq!(buffer, vec![0.5, -1.0]);
+
+        let params = plugin.get_all_parameters();
+        assert_eq!(params.len(), 1);
+        assert_eq!(params[0].name, "Gain");
+        assert_eq!(params[0].display, "2.00");
+        assert_eq!(plugin.get_parameter(0), Some(1.0));
+        assert_eq!(plugin.get_parameter(9), None);
+
+        let descriptors = plugin.parameter_descriptors();
+        assert_eq!((descriptors[0].min, descriptors[0].max, descriptors[0].default), (0.0, 2.0, 1.0));
+        assert_eq!(descriptors[0].unit, "x");
+        assert_eq!(plugin.value_to_text(0, 0.5).unwrap(), "1.00 x");
+    }
+
+    #[test]
+    fn test_native_state_roundtrip() {
+        let mut plugin = test_plugin();
+        plugin.set_parameter(0, 0.75);
+        let state = plugin.save_state();
+
+        let mut restored = test_plugin();
+        restored.load_state(&state);
+        assert_eq!(restored.get_parameter(0), Some(0.75));
+    }
+
+    #[test]
+    fn test_native_prepare_keeps_state() {
+        let mut plugin = test_plugin();
+        plugin.set_parameter(0, 0.75);
+
+        plugin.prepare(96000.0, 256, ChannelLayout::Stereo);
+        assert_eq!(plugin.sample_rate, 96000.0);
+        assert_eq!(plugin.get_parameter(0), Some(0.75));
+
+        plugin.release();
+        let mut buffer = vec![0.25, -0.5];
+        with_interleaved(&mut buffer, 2, |buffer| plugin.process(buffer));
+        assert_eq!(buffer, vec![0.25, -0.5]);
+    }
+
+    #[test]
+    fn test_native_lifecycle_entries() {
+        let mut plugin = NativePlugin::instantiate(
+            plugin_loader_entry,
+            Path::new("/test/libgain.so"),
+            Some("test.lookahead"),
+            None,
+        )
+        .unwrap();
+        let instance = plugin.instance;
+        assert_eq!(plugin.latency_samples(), 48);
+
+        // prepare 和 reset 直接调用插件，不重建实例
+        plugin.prepare(96000.0, 256, ChannelLayout::Stereo);
+        assert_eq!(plugin.instance, instance);
+        assert_eq!(plugin.sample_rate, 96000.0);
+        assert_eq!(plugin.latency_samples(), 96);
+
+        let mut buffer = vec![0.25, 0.5];
+        with_interleaved(&mut buffer, 2, |buffer| plugin.process(buffer));
+        assert_eq!(plugin.get_parameter(0), Some(0.75));
+        plugin.reset();
+        assert_eq!(plugin.instance, instance);
+        assert_eq!(plugin.get_parameter(0), Some(0.0));
+
+        // 没有实现 prepare 的插件在采样率变化时重建
+        let mut gain = test_plugin();
+        let instance = gain.instance;
+        gain.prepare(48000.0, 256, ChannelLayout::Stereo);
+        assert_eq!(gain.instance, instance);
+        gain.prepare(96000.0, 256, ChannelLayout::Stereo);
+        assert_ne!(gain.instance, instance);
+        assert_eq!(gain.latency_samples(), 0);
+    }
+
+    #[test]
+    fn test_unknown_plugin_id() {
+        let result = NativePlugin::instantiate(plugin_loader_entry, Path::new("/test/libgain.so"), Some("other"), None);
+        assert!(result.is_err());
+    }
+}
@@ -7,7 +7,7 @@ use std::path::{Path, PathBuf};
 use std::fs;
 use log::info;
 
-use super::types::PluginState;
+use super::types::{PluginState, SlotSettings};
 
 /// 工程文件结构
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -29,10 +29,38 @@ pub struct Project {
     
     /// 插件链状态
     pub plugin_chain: Vec<PluginState>,
+    
+    /// 插件链各位置的旁通、静音、独奏和干湿比
+    #[serde(default)]
+    pub slot_settings: Vec<SlotSettings>,
+    
+    /// 预设列表，按 MIDI 程序号切换（第 0 个对应程序 0）
+    #[serde(default)]
+    pub presets: Vec<Preset>,
 }
 
-/// 音频配置
+/// 预设：一条完整的插件链
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Preset {
+    /// 预设名称
+    pub name: String,
+    
+    /// 插件链状态
+    pub plugin_chain: Vec<PluginState>,
+    
+    /// 插件链各位置的设置
+    #[serde(default)]
+    pub slot_settings: Vec<SlotSettings>,
+}
+
+impl Preset {
+    pub fn new(name: String, plugin_chain: Vec<PluginState>, slot_settings: Vec<SlotSettings>) -> Self {
+        Self { name, plugin_chain, slot_settings }
+    }
+}
+
+/// 音频配置（音频引擎按它打开设备）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AudioConfig {
     /// 采样率
     pub sample_rate: u32,
@@ -42,6 +70,22 @@ pub struct AudioConfig {
     
     /// 通道数
     pub channels: u16,
+    
+    /// 输入设备名称（为空时使用系统默认设备）
+    #[serde(default)]
+    pub input_device: Option<String>,
+    
+    /// 输出设备名称（为空时使用系统默认设备）
+    #[serde(default)]
+    pub output_device: Option<String>,
+    
+    /// 整数输出格式量化前加 TPDF 抖动
+    #[serde(default)]
+    pub dither: bool,
+    
+    /// 原始 MIDI 输入设备（例如 /dev/snd/midiC1D0），程序切换消息选择预设
+    #[serde(default)]
+    pub midi_input: Option<String>,
 }
 
 impl Default for AudioConfig {
@@ -50,6 +94,10 @@ impl Default for AudioConfig {
             sample_rate: 48000,
             buffer_size: 256,
             channels: 2,
+            input_device: None,
+            output_device: None,
+            dither: false,
+            midi_input: None,
         }
     }
 }
@@ -66,6 +114,8 @@ impl Project {
             modified: now,
             audio_config: AudioConfig::default(),
             plugin_chain: Vec::new(),
+            slot_settings: Vec::new(),
+            presets: Vec::new(),
         }
     }
     
@@ -109,6 +159,23 @@ impl Project {
     pub fn get_plugin_chain(&self) -> &[PluginState] {
         &self.plugin_chain
     }
+    
+    /// 更新插件链各位置的设置
+    pub fn update_slot_settings(&mut self, slot_settings: Vec<SlotSettings>) {
+        self.slot_settings = slot_settings;
+    }
+    
+    /// 获取插件链各位置的设置
+    pub fn get_slot_settings(&self) -> &[SlotSettings] {
+        &self.slot_settings
+    }
+    
+    /// 把当前插件链另存为预设，返回预设的程序号
+    pub fn add_preset(&mut self, name: String) -> usize {
+        let preset = Preset::new(name, self.plugin_chain.clone(), self.slot_settings.clone());
+        self.presets.push(preset);
+        self.presets.len() - 1
+    }
 }
 
 /// 工程管理器
@@ -218,6 +285,43 @@ mod tests {
         let _ = fs::remove_file(test_path);
     }
     
+    #[test]
+    fn test_slot_settings_roundtrip() {
+        let mut project = Project::new("Slots".to_string());
+        let bypassed = SlotSettings { bypass: true, mix: 0.4, ..SlotSettings::default() };
+        project.update_slot_settings(vec![bypassed, SlotSettings::default()]);
+        
+        let json = serde_json::to_string(&project).unwrap();
+        let loaded: Project = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.get_slot_settings(), project.get_slot_settings());
+        
+        // 旧工程文件没有 slot_settings 字段
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        value.as_object_mut().unwrap().remove("slot_settings");
+        let old: Project = serde_json::from_value(value).unwrap();
+        assert!(old.get_slot_settings().is_empty());
+    }
+    
+    #[test]
+    fn test_presets_roundtrip() {
+        let mut project = Project::new("Presets".to_string());
+        project.update_slot_settings(vec![SlotSettings { mute: true, ..SlotSettings::default() }]);
+        assert_eq!(project.add_preset("Clean".to_string()), 0);
+        assert_eq!(project.add_preset("Lead".to_string()), 1);
+        
+        let json = serde_json::to_string(&project).unwrap();
+        let loaded: Project = serde_json::from_str(&json).unwrap();
+        let names: Vec<_> = loaded.presets.iter().map(|preset| preset.name.as_str()).collect();
+        assert_eq!(names, vec!["Clean", "Lead"]);
+        assert!(loaded.presets[1].slot_settings[0].mute);
+        
+        // 旧工程文件没有 presets 字段
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        value.as_object_mut().unwrap().remove("presets");
+        let old: Project = serde_json::from_value(value).unwrap();
+        assert!(old.presets.is_empty());
+    }
+    
     #[test]
     fn test_project_manager() {
         let mut manager = ProjectManager::new();
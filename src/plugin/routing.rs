@@ -0,0 +1,711 @@
+// 并联路由
+// 分离器 -> 若干并联支路（每条支路是一条插件链）-> 带增益和声像的混合器
+
+use anyhow::Result;
+use base64::Engine;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::path::PathBuf;
+
+use crate::audio::{AudioBuffer, DelayLine, PlanarBuffer, Smoother};
+use super::builtin::ParamSpec;
+use super::chain::PluginChain;
+use super::types::{
+    AudioProcessor, BusLayout, ChannelLayout, ParameterDescriptor, PluginFormat, PluginMetadata,
+    PluginParameter, PluginState, ProcessConfig, SlotSettings,
+};
+
+/// 并联机架的插件 ID
+pub const RACK_ID: &str = "route:parallel";
+
+/// 每个机架最多的支路数
+pub const MAX_LANES: usize = 4;
+
+/// 支路增益，最小值表示静音
+const LANE_GAIN: ParamSpec = ParamSpec::linear("Gain", -60.0, 12.0, 0.0, "dB");
+/// 支路声像（-1 = 左，1 = 右）
+const LANE_PAN: ParamSpec = ParamSpec::linear("Pan", -1.0, 1.0, 0.0, "");
+
+/// 支路增益和声像的平滑时间
+const SMOOTHING_MS: f32 = 20.0;
+
+/// 单条支路的保存状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LaneState {
+    pub gain_db: f32,
+    pub pan: f32,
+    pub plugins: Vec<PluginState>,
+    pub slot_settings: Vec<SlotSettings>,
+}
+
+impl Default for LaneState {
+    fn default() -> Self {
+        Self {
+            gain_db: LANE_GAIN.default,
+            pan: LANE_PAN.default,
+            plugins: Vec::new(),
+            slot_settings: Vec::new(),
+        }
+    }
+}
+
+/// 并联机架的保存状态（以 base64 编码的 JSON 放在 PluginState::state_data 中，嵌套的机架同理）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RackState {
+    pub lanes: Vec<LaneState>,
+}
+
+impl RackState {
+    fn decode(state: &PluginState) -> Option<Self> {
+        let data = base64::engine::general_purpose::STANDARD.decode(&state.state_data).ok()?;
+        serde_json::from_slice(&data).ok()
+    }
+
+    /// 编码成机架的插件状态，parameters 是各支路的增益和声像
+    pub fn to_plugin_state(&self, parameters: Vec<PluginParameter>) -> PluginState {
+        let json = serde_json::to_vec(self).unwrap_or_default();
+        PluginState {
+            plugin_id: RACK_ID.to_string(),
+            parameters,
+            state_data: base64::engine::general_purpose::STANDARD.encode(json),
+        }
+    }
+}
+
+/// 并联机架的一条支路
+///
+/// 运行中添加支路时在控制线程用 prepare 准备好，音频线程放进机架时不分配内存；
+/// 被移除的支路也以这种形式交回控制线程，由 release 释放其中的插件。
+pub struct Lane {
+    /// 放在堆上，支路随命令和回复传递时只移动指针
+    chain: Box<PluginChain>,
+    gain_db: f32,
+    pan: f32,
+    gain: Smoother,
+    pan_smoother: Smoother,
+    /// 支路的工作缓冲区（两个声道 x max_block_size 帧）
+    buffer: PlanarBuffer,
+    /// 补偿延迟，把支路推迟到与最慢的支路对齐
+    compensation: DelayLine,
+}
+
+impl Lane {
+    /// 按机架的处理配置准备一条空支路，并为延迟补偿预留 reserved 帧（不在音频线程调用）
+    pub fn prepare(config: ProcessConfig, reserved: usize) -> Self {
+        let smoother = |value| Smoother::linear(SMOOTHING_MS, config.sample_rate as f32).with_value(value);
+        let mut chain = Box::new(PluginChain::new());
+        chain.prepare(config.sample_rate, config.max_block_size, config.layout);
+        chain.reserve_latency(reserved);
+        let mut compensation = DelayLine::new(0);
+        compensation.reserve(reserved);
+        Self {
+            chain,
+            gain_db: LANE_GAIN.default,
+            pan: LANE_PAN.default,
+            gain: smoother(1.0),
+            pan_smoother: smoother(0.0),
+            buffer: PlanarBuffer::new(2, config.max_block_size),
+            compensation,
+        }
+    }
+
+    /// 支路离开音频线程之前在音频线程中调用
+    pub fn suspend(&mut self) {
+        self.chain.suspend();
+    }
+
+    /// 释放支路中所有插件的处理资源并取出插件链（不在音频线程调用）
+    pub fn release(mut self) -> PluginChain {
+        self.chain.release();
+        *self.chain
+    }
+
+    fn set_gain_db(&mut self, db: f32) {
+        self.gain_db = db.clamp(LANE_GAIN.min, LANE_GAIN.max);
+        let linear = if self.gain_db <= LANE_GAIN.min { 0.0 } else { 10f32.powf(self.gain_db / 20.0) };
+        self.gain.set_target(linear);
+    }
+
+    fn set_pan(&mut self, pan: f32) {
+        self.pan = pan.clamp(LANE_PAN.min, LANE_PAN.max);
+        self.pan_smoother.set_target(self.pan);
+    }
+
+    fn save(&self) -> LaneState {
+        LaneState {
+            gain_db: self.gain_db,
+            pan: self.pan,
+            plugins: self.chain.save_chain_state(),
+            slot_settings: self.chain.save_slot_settings(),
+        }
+    }
+}
+
+/// 并联机架
+///
+/// 输入复制到每条支路，支路各自按顺序处理后按增益和声像（平衡方式，居中为单位增益）混合成立体声。
+/// 机架本身实现 AudioProcessor，可以放进插件链的任意位置，支路中也可以再放机架。
+/// 没有支路时直通；空支路就是干信号，可以用来做干湿分离和并联压缩。
+/// 支路增益和声像作为参数暴露（支路 i 的增益为 2i，声像为 2i + 1），可以自动化。
+/// 各支路延迟不同时，较快的支路被推迟到与最慢的支路对齐，机架报告最慢支路的延迟。
+/// 运行中的支路编辑由宿主在控制线程准备好（支路、插件位置和布局），音频线程用 insert_lane / take_lane / lane_mut 放进机架。
+pub struct ParallelRack {
+    metadata: PluginMetadata,
+    lanes: Vec<Lane>,
+    config: ProcessConfig,
+    /// 最慢支路的延迟（帧）
+    latency: u32,
+    /// 为支路的延迟补偿预留的帧数
+    reserved: usize,
+    /// 混合器累加缓冲区
+    mix: PlanarBuffer,
+    /// 每帧的左右声道系数
+    left_ramp: Vec<f32>,
+    right_ramp: Vec<f32>,
+}
+
+impl ParallelRack {
+    pub fn new() -> Self {
+        let config = ProcessConfig::default();
+        Self {
+            metadata: Self::metadata(),
+            lanes: Vec::with_capacity(MAX_LANES),
+            config,
+            latency: 0,
+            reserved: 0,
+            mix: PlanarBuffer::new(2, config.max_block_size),
+            left_ramp: vec![0.0; config.max_block_size],
+            right_ramp: vec![0.0; config.max_block_size],
+        }
+    }
+
+    pub fn metadata() -> PluginMetadata {
+        PluginMetadata {
+            id: RACK_ID.to_string(),
+            name: "Parallel".to_string(),
+            vendor: "Builtin".to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            path: PathBuf::new(),
+            format: PluginFormat::Builtin,
+            num_inputs: 2,
+            num_outputs: 2,
+        }
+    }
+
+    /// 插件是并联机架时取得机架
+    pub fn downcast(plugin: &dyn AudioProcessor) -> Option<&Self> {
+        let plugin: &dyn Any = plugin;
+        plugin.downcast_ref()
+    }
+
+    pub fn downcast_mut(plugin: &mut dyn AudioProcessor) -> Option<&mut Self> {
+        let plugin: &mut dyn Any = plugin;
+        plugin.downcast_mut()
+    }
+
+    /// lanes 条支路的参数描述（支路 i 的增益为 2i，声像为 2i + 1）
+    pub fn lane_descriptors(lanes: usize) -> Vec<ParameterDescriptor> {
+        (0..lanes as u32 * 2)
+            .map(|id| {
+                let spec = if id.is_multiple_of(2) { LANE_GAIN } else { LANE_PAN };
+                let mut descriptor = spec.descriptor(id);
+                descriptor.name = format!("Lane {} {}", id / 2 + 1, spec.name);
+                descriptor
+            })
+            .collect()
+    }
+
+    /// 按保存的状态重建机架，支路中的插件由 load 创建
+    pub fn from_state(state: &PluginState, load: &mut dyn FnMut(&PluginState) -> Result<Box<dyn AudioProcessor>>) -> Result<Self> {
+        let rack_state = RackState::decode(state).ok_or_else(|| anyhow::anyhow!("并联机架状态无效"))?;
+        let mut rack = Self::new();
+        for lane_state in &rack_state.lanes {
+            let index = rack.add_lane()?;
+            let lane = &mut rack.lanes[index];
+            restore_chain(&mut lane.chain, &lane_state.plugins, load)?;
+            if !lane_state.slot_settings.is_empty() {
+                lane.chain.load_slot_settings(&lane_state.slot_settings);
+            }
+            lane.set_gain_db(lane_state.gain_db);
+            lane.set_pan(lane_state.pan);
+            lane.gain.finish();
+            lane.pan_smoother.finish();
+        }
+        rack.refresh_latency();
+        Ok(rack)
+    }
+
+    /// 添加一条空支路，返回支路编号
+    pub fn add_lane(&mut self) -> Result<usize> {
+        if self.lanes.len() >= MAX_LANES {
+            return Err(anyhow::anyhow!("并联支路已满（最多 {} 条）", MAX_LANES));
+        }
+        self.lanes.push(Lane::prepare(self.config, self.reserved));
+        self.refresh_latency();
+        info!("添加并联支路 {}", self.lanes.len());
+        Ok(self.lanes.len() - 1)
+    }
+
+    /// 放进控制线程准备好的支路（实时安全），支路已满时原样返回
+    pub fn insert_lane(&mut self, lane: Lane) -> Option<Lane> {
+        if self.lanes.len() >= MAX_LANES {
+            return Some(lane);
+        }
+        self.lanes.push(lane);
+        self.update_latency();
+        None
+    }
+
+    /// 取出支路（实时安全），交回控制线程释放
+    pub fn take_lane(&mut self, index: usize) -> Option<Lane> {
+        if index >= self.lanes.len() {
+            return None;
+        }
+        let lane = self.lanes.remove(index);
+        self.update_latency();
+        Some(lane)
+    }
+
+    pub fn lane_count(&self) -> usize {
+        self.lanes.len()
+    }
+
+    /// 机架的处理配置（支路中的插件按它 prepare）
+    pub fn config(&self) -> ProcessConfig {
+        self.config
+    }
+
+    /// 支路的插件链（只读）
+    pub fn lane(&self, index: usize) -> Option<&PluginChain> {
+        self.lanes.get(index).map(|lane| &*lane.chain)
+    }
+
+    /// 支路的插件链（添加到支路的插件按机架的配置 prepare）
+    /// 修改支路中的插件后调用 refresh_latency 重新对齐各支路；
+    /// 在音频线程用 insert_slot 等实时编辑时改为调用 update_latency
+    pub fn lane_mut(&mut self, index: usize) -> Option<&mut PluginChain> {
+        self.lanes.get_mut(index).map(|lane| &mut *lane.chain)
+    }
+
+    /// 设置支路增益（dB，-60 dB 为静音），平滑过渡
+    #[cfg(test)]
+    pub fn set_lane_gain_db(&mut self, index: usize, db: f32) -> Result<()> {
+        self.lane_state_mut(index)?.set_gain_db(db);
+        Ok(())
+    }
+
+    /// 设置支路声像（-1.0 = 左，1.0 = 右），平滑过渡
+    #[cfg(test)]
+    pub fn set_lane_pan(&mut self, index: usize, pan: f32) -> Result<()> {
+        self.lane_state_mut(index)?.set_pan(pan);
+        Ok(())
+    }
+
+    #[cfg(test)]
+    pub fn lane_gain_db(&self, index: usize) -> Option<f32> {
+        self.lanes.get(index).map(|lane| lane.gain_db)
+    }
+
+    #[cfg(test)]
+    pub fn lane_pan(&self, index: usize) -> Option<f32> {
+        self.lanes.get(index).map(|lane| lane.pan)
+    }
+
+    /// 支路的延迟（帧），不含补偿
+    #[cfg(test)]
+    pub fn lane_latency(&self, index: usize) -> Option<u32> {
+        self.lanes.get(index).map(|lane| lane.chain.latency())
+    }
+
+    /// 重新读取各支路的延迟并调整补偿延迟（不在音频线程调用）
+    pub fn refresh_latency(&mut self) {
+        for lane in &mut self.lanes {
+            lane.chain.refresh_latency();
+        }
+        self.update_latency();
+    }
+
+    /// 按支路中各位置已知的延迟重新对齐各支路（实时编辑后调用），不超过预留容量时不分配内存
+    pub fn update_latency(&mut self) {
+        self.latency = self.lanes.iter().map(|lane| lane.chain.latency()).max().unwrap_or(0);
+        for lane in &mut self.lanes {
+            lane.compensation.set_latency((self.latency - lane.chain.latency()) as usize);
+        }
+    }
+
+    /// 为支路的延迟补偿预留容量，之后添加的支路也按它预留（不在音频线程调用）
+    pub fn reserve_latency(&mut self, frames: usize) {
+        self.reserved = frames;
+        for lane in &mut self.lanes {
+            lane.chain.reserve_latency(frames);
+            lane.compensation.reserve(frames);
+        }
+    }
+
+    #[cfg(test)]
+    fn lane_state_mut(&mut self, index: usize) -> Result<&mut Lane> {
+        self.lanes.get_mut(index).ok_or_else(|| anyhow::anyhow!("索引超出范围"))
+    }
+
+    /// 保存机架结构和所有支路的状态
+    pub fn save_rack_state(&self) -> RackState {
+        RackState { lanes: self.lanes.iter().map(Lane::save).collect() }
+    }
+
+    /// 参数编号 -> (支路, 参数表)
+    fn lane_param(&self, id: u32) -> Option<(usize, ParamSpec)> {
+        let index = id as usize / 2;
+        if index >= self.lanes.len() {
+            return None;
+        }
+        Some((index, if id.is_multiple_of(2) { LANE_GAIN } else { LANE_PAN }))
+    }
+
+    fn plain_value(&self, id: u32) -> Option<(ParamSpec, f32)> {
+        let (index, spec) = self.lane_param(id)?;
+        let lane = &self.lanes[index];
+        Some((spec, if id.is_multiple_of(2) { lane.gain_db } else { lane.pan }))
+    }
+
+    /// 处理不超过 max_block_size 帧的一块
+    fn process_block(&mut self, block: &mut AudioBuffer) {
+        let frames = block.frames();
+        let mut mix = self.mix.as_buffer_channels(2, frames);
+        mix.clear();
+
+        for lane in &mut self.lanes {
+            // 分离器：输入复制到支路
+            let mut input = lane.buffer.as_buffer_channels(2, frames);
+            for (index, channel) in input.channels_mut().enumerate() {
+                block.read_mapped(index, 2, channel);
+            }
+            lane.chain.process(&mut input);
+            lane.compensation.process(&mut input);
+
+            // 混合器：平衡声像，居中时两边都是单位增益
+            let ramps = self.left_ramp[..frames].iter_mut().zip(&mut self.right_ramp[..frames]);
+            for (left, right) in ramps {
+                let gain = lane.gain.next_value();
+                let pan = lane.pan_smoother.next_value();
+                *left = gain * (1.0 - pan).min(1.0);
+                *right = gain * (1.0 + pan).min(1.0);
+            }
+            let ramps = [&self.left_ramp[..frames], &self.right_ramp[..frames]];
+            for ((sum, lane), ramp) in mix.channels_mut().zip(input.channels()).zip(ramps) {
+                for ((sum, &sample), &gain) in sum.iter_mut().zip(lane).zip(ramp) {
+                    *sum += sample * gain;
+                }
+            }
+        }
+
+        block.write_mapped(&[mix.channel(0), mix.channel(1)]);
+    }
+}
+
+impl Default for ParallelRack {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AudioProcessor for ParallelRack {
+    fn prepare(&mut self, sample_rate: f64, max_block_size: usize, layout: ChannelLayout) {
+        self.config = ProcessConfig { sample_rate, max_block_size: max_block_size.max(1), layout };
+        self.mix.resize(2, self.config.max_block_size);
+        self.left_ramp.resize(self.config.max_block_size, 0.0);
+        self.right_ramp.resize(self.config.max_block_size, 0.0);
+        for lane in &mut self.lanes {
+            lane.chain.prepare(sample_rate, self.config.max_block_size, layout);
+            lane.buffer.resize(2, self.config.max_block_size);
+            lane.gain.set_sample_rate(sample_rate as f32);
+            lane.pan_smoother.set_sample_rate(sample_rate as f32);
+        }
+        self.refresh_latency();
+    }
+
+    fn reset(&mut self) {
+        for lane in &mut self.lanes {
+            lane.chain.reset();
+            lane.gain.finish();
+            lane.pan_smoother.finish();
+            lane.compensation.clear();
+        }
+    }
+
+    fn release(&mut self) {
+        for lane in &mut self.lanes {
+            lane.chain.release();
+        }
+    }
+
+    fn suspend(&mut self) {
+        for lane in &mut self.lanes {
+            lane.chain.suspend();
+        }
+    }
+
+    /// 支路跟随上游布局，混合器总是输出立体声
+    fn bus_layout(&self, upstream: ChannelLayout) -> BusLayout {
+        BusLayout::new(upstream, ChannelLayout::Stereo)
+    }
+
+    fn latency_samples(&self) -> u32 {
+        self.latency
+    }
+
+    fn process(&mut self, buffer: &mut AudioBuffer) {
+        if self.lanes.is_empty() {
+            return;
+        }
+        let block_size = self.config.max_block_size;
+        let frames = buffer.frames();
+        for start in (0..frames).step_by(block_size) {
+            let end = (start + block_size).min(frames);
+            self.process_block(&mut buffer.slice(start, end));
+        }
+    }
+
+    fn get_info(&self) -> &PluginMetadata {
+        &self.metadata
+    }
+
+    fn set_parameter(&mut self, id: u32, value: f64) {
+        let Some((index, spec)) = self.lane_param(id) else {
+            return;
+        };
+        let plain = spec.to_plain(value);
+        if id.is_multiple_of(2) {
+            self.lanes[index].set_gain_db(plain);
+        } else {
+            self.lanes[index].set_pan(plain);
+        }
+    }
+
+    fn get_parameter(&self, id: u32) -> Option<f64> {
+        let (spec, plain) = self.plain_value(id)?;
+        Some(spec.to_normalized(plain))
+    }
+
+    fn get_all_parameters(&self) -> Vec<PluginParameter> {
+        (0..self.lanes.len() as u32 * 2)
+            .filter_map(|id| {
+                let (spec, plain) = self.plain_value(id)?;
+                Some(PluginParameter {
+                    id,
+                    name: format!("Lane {} {}", id / 2 + 1, spec.name),
+                    value: spec.to_normalized(plain),
+                    display: spec.display(plain),
+                })
+            })
+            .collect()
+    }
+
+    fn parameter_descriptors(&self) -> Vec<ParameterDescriptor> {
+        Self::lane_descriptors(self.lanes.len())
+    }
+
+    fn save_state(&self) -> PluginState {
+        self.save_rack_state().to_plugin_state(self.get_all_parameters())
+    }
+
+    /// 只恢复参数和支路中插件的状态，结构不同时忽略（结构由 from_state 重建）
+    fn load_state(&mut self, state: &PluginState) {
+        let Some(rack_state) = RackState::decode(state) else {
+            warn!("并联机架状态无效");
+            return;
+        };
+        if rack_state.lanes.len() != self.lanes.len() {
+            warn!("并联支路数量不匹配");
+            return;
+        }
+        for (lane, lane_state) in self.lanes.iter_mut().zip(&rack_state.lanes) {
+            lane.set_gain_db(lane_state.gain_db);
+            lane.set_pan(lane_state.pan);
+            lane.chain.load_chain_state(&lane_state.plugins);
+            if !lane_state.slot_settings.is_empty() {
+                lane.chain.load_slot_settings(&lane_state.slot_settings);
+            }
+        }
+    }
+}
+
+/// 按保存的插件状态重建插件链（工程加载时使用）
+/// 并联机架按其中的结构递归重建，其它插件由 load 创建，随后恢复各自的状态。
+/// 状态中的参数值优先于状态数据：运行中保存的状态来自引擎的镜像，只有参数是最新的
+pub fn restore_chain(
+    chain: &mut PluginChain,
+    states: &[PluginState],
+    load: &mut dyn FnMut(&PluginState) -> Result<Box<dyn AudioProcessor>>,
+) -> Result<()> {
+    for state in states {
+        let mut plugin: Box<dyn AudioProcessor> = if state.plugin_id == RACK_ID {
+            Box::new(ParallelRack::from_state(state, load)?)
+        } else {
+            let mut plugin = load(state)?;
+            plugin.load_state(state);
+            plugin
+        };
+        for parameter in &state.parameters {
+            if plugin.get_parameter(parameter.id) != Some(parameter.value) {
+                plugin.set_parameter(parameter.id, parameter.value);
+            }
+        }
+        chain.add_plugin(plugin)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::with_interleaved;
+    use crate::plugin::{BuiltinKind, BuiltinPlugin, Project};
+
+    fn gain_plugin(db: f64) -> Box<dyn AudioProcessor> {
+        let mut plugin = BuiltinPlugin::new(BuiltinKind::Gain);
+        plugin.set_parameter(0, (db + 24.0) / 48.0);
+        plugin.reset();
+        Box::new(plugin)
+    }
+
+    fn load_builtin(state: &PluginState) -> Result<Box<dyn AudioProcessor>> {
+        let kind = BuiltinKind::from_id(&state.plugin_id).ok_or_else(|| anyhow::anyhow!("未知插件"))?;
+        Ok(Box::new(BuiltinPlugin::new(kind)))
+    }
+
+    fn db(db: f32) -> f32 {
+        10f32.powf(db / 20.0)
+    }
+
+    /// 干信号支路 + -6 dB 支路
+    fn dry_wet_rack() -> ParallelRack {
+        let mut rack = ParallelRack::new();
+        rack.prepare(48000.0, 64, ChannelLayout::Stereo);
+        rack.add_lane().unwrap();
+        let wet = rack.add_lane().unwrap();
+        rack.lane_mut(wet).unwrap().add_plugin(gain_plugin(-6.0)).unwrap();
+        rack
+    }
+
+    #[test]
+    fn test_lanes_are_summed() {
+        let mut rack = dry_wet_rack();
+        let mut buffer = vec![0.5; 200 * 2];
+        with_interleaved(&mut buffer, 2, |buffer| rack.process(buffer));
+        assert!(buffer.iter().all(|&s| (s - 0.5 * (1.0 + db(-6.0))).abs() < 1e-4));
+
+        // -60 dB 的支路静音
+        rack.set_lane_gain_db(0, -60.0).unwrap();
+        rack.reset();
+        let mut buffer = vec![0.5; 200 * 2];
+        with_interleaved(&mut buffer, 2, |buffer| rack.process(buffer));
+        assert!((buffer[199 * 2] - 0.5 * db(-6.0)).abs() < 1e-4);
+        assert!(rack.set_lane_gain_db(2, 0.0).is_err());
+    }
+
+    #[test]
+    fn test_lane_pan_and_parameters() {
+        let mut rack = dry_wet_rack();
+        // 参数 3 = 支路 2 的声像，归一化 0.0 = 最左
+        rack.set_parameter(3, 0.0);
+        assert_eq!(rack.lane_pan(1), Some(-1.0));
+        assert_eq!(rack.parameter_descriptors().len(), 4);
+        assert_eq!(rack.get_all_parameters()[1].name, "Lane 1 Pan");
+        rack.reset();
+
+        let mut buffer = vec![1.0; 100 * 2];
+        with_interleaved(&mut buffer, 2, |buffer| rack.process(buffer));
+        assert!((buffer[99 * 2] - (1.0 + db(-6.0))).abs() < 1e-4);
+        assert!((buffer[99 * 2 + 1] - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_nested_rack_in_chain() {
+        let mut chain = PluginChain::new();
+        chain.prepare(48000.0, 64, ChannelLayout::Mono);
+
+        // 内层机架：两条 -6 dB 支路
+        let mut inner = ParallelRack::new();
+        for _ in 0..2 {
+            let lane = inner.add_lane().unwrap();
+            inner.lane_mut(lane).unwrap().add_plugin(gain_plugin(-6.0)).unwrap();
+        }
+        let mut outer = ParallelRack::new();
+        outer.add_lane().unwrap();
+        let lane = outer.add_lane().unwrap();
+        outer.lane_mut(lane).unwrap().add_plugin(Box::new(inner)).unwrap();
+        outer.set_lane_gain_db(0, -60.0).unwrap();
+        outer.reset();
+
+        chain.add_plugin(gain_plugin(-6.0)).unwrap();
+        chain.add_plugin(Box::new(outer)).unwrap();
+        // 机架把单声道输入混成立体声
+        assert_eq!(chain.output_layout(), ChannelLayout::Stereo);
+
+        let mut buffer = vec![1.0; 300 * 2];
+        with_interleaved(&mut buffer, 2, |buffer| chain.process(buffer));
+        let expected = db(-6.0) * 2.0 * db(-6.0);
+        assert!(buffer.iter().all(|&s| (s - expected).abs() < 1e-4));
+    }
+
+    #[test]
+    fn test_rack_state_roundtrip_through_project() {
+        let mut chain = PluginChain::new();
+        chain.prepare(48000.0, 64, ChannelLayout::Stereo);
+        let mut rack = dry_wet_rack();
+        rack.set_lane_pan(1, 0.5).unwrap();
+        rack.lane_mut(1).unwrap().set_slot_mix(0, 0.25).unwrap();
+        chain.add_plugin(gain_plugin(-3.0)).unwrap();
+        chain.add_plugin(Box::new(rack)).unwrap();
+
+        let mut project = Project::new("Rack".to_string());
+        project.update_plugin_chain(chain.save_chain_state());
+        let json = serde_json::to_string(&project).unwrap();
+        let loaded: Project = serde_json::from_str(&json).unwrap();
+
+        let mut restored = PluginChain::new();
+        restored.prepare(48000.0, 64, ChannelLayout::Stereo);
+        restore_chain(&mut restored, loaded.get_plugin_chain(), &mut load_builtin).unwrap();
+        assert_eq!(restored.get_plugin_names(), vec!["Gain", "Parallel"]);
+
+        let mut expected = vec![0.5; 256 * 2];
+        let mut actual = expected.clone();
+        chain.reset();
+        with_interleaved(&mut expected, 2, |buffer| chain.process(buffer));
+        with_interleaved(&mut actual, 2, |buffer| restored.process(buffer));
+        assert!(expected.iter().zip(&actual).all(|(a, b)| (a - b).abs() < 1e-5));
+
+        let state = restored.save_chain_state();
+        let rack_state = RackState::decode(&state[1]).unwrap();
+        assert_eq!(rack_state.lanes[1].pan, 0.5);
+        assert_eq!(rack_state.lanes[1].slot_settings[0].mix, 0.25);
+    }
+
+    #[test]
+    fn test_lanes_are_latency_aligned() {
+        use crate::plugin::loader::DummyPlugin;
+
+        let mut rack = dry_wet_rack();
+        let metadata = BuiltinKind::Gain.metadata();
+        rack.lane_mut(1).unwrap().add_plugin(Box::new(DummyPlugin::with_latency(metadata, 50))).unwrap();
+        rack.refresh_latency();
+        assert_eq!(rack.latency_samples(), 50);
+        assert_eq!(rack.lane_latency(0), Some(0));
+
+        // 干信号支路被推迟 50 帧，两条支路的脉冲重合
+        let mut buffer = vec![0.0; 128 * 2];
+        buffer[0] = 1.0;
+        with_interleaved(&mut buffer, 2, |buffer| rack.process(buffer));
+        let peaks: Vec<usize> = (0..128).filter(|&i| buffer[i * 2] != 0.0).collect();
+        assert_eq!(peaks, vec![50]);
+        assert!((buffer[100] - (1.0 + db(-6.0))).abs() < 1e-4);
+
+        // 机架放进插件链后，链的延迟包含最慢的支路
+        let mut chain = PluginChain::new();
+        chain.prepare(48000.0, 64, ChannelLayout::Stereo);
+        chain.add_plugin(Box::new(rack)).unwrap();
+        assert_eq!(chain.latency(), 50);
+    }
+}
@@ -5,6 +5,14 @@ use std::fs;
 use serde::{Deserialize, Serialize};
 
 use super::types::{PluginMetadata, PluginFormat};
+use super::clap_wrapper::scan_clap_file;
+use super::lv2_wrapper::scan_lv2_bundle;
+use super::vst3_wrapper::scan_vst3_bundle;
+use super::ladspa_wrapper::scan_ladspa_file;
+use super::native_wrapper::scan_native_library;
+use super::wasm_wrapper::scan_wasm_file;
+use super::builtin;
+use super::routing::ParallelRack;
 
 /// 插件信息（用于扫描结果）
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -14,7 +22,30 @@ pub struct PluginInfo {
     pub error: Option<String>,
 }
 
-/// Audio Unit 插件扫描器
+impl PluginInfo {
+    /// 扫描失败的插件条目
+    fn invalid(path: &Path, format: PluginFormat, error: &anyhow::Error) -> Self {
+        Self {
+            metadata: PluginMetadata {
+                id: String::new(),
+                name: path.file_stem()
+                    .and_then(|s| s.to_str())
+                    .unwrap_or("Unknown")
+                    .to_string(),
+                vendor: String::new(),
+                version: String::new(),
+                path: path.to_path_buf(),
+                format,
+                num_inputs: 2,
+                num_outputs: 2,
+            },
+            valid: false,
+            error: Some(error.to_string()),
+        }
+    }
+}
+
+/// 插件扫描器
 pub struct PluginScanner {
     cache_file: PathBuf,
 }
@@ -27,23 +58,32 @@ impl PluginScanner {
         }
     }
     
-    /// 扫描所有 Audio Unit 插件
+    /// 扫描所有格式的插件
     pub fn scan_all(&self) -> Result<Vec<PluginInfo>> {
-        info!("开始扫描 Audio Unit 插件...");
-        
-        let mut all_plugins = Vec::new();
+        info!("开始扫描插件...");
         
-        // macOS Audio Unit 标准目录
-        let search_paths = vec![
-            PathBuf::from("/Library/Audio/Plug-Ins/Components"),
-            PathBuf::from(format!("{}/Library/Audio/Plug-Ins/Components", 
-                std::env::var("HOME").unwrap_or_default())),
-        ];
+        // 内置效果器和并联机架总是可用
+        let mut all_plugins: Vec<PluginInfo> = builtin::builtin_plugins()
+            .into_iter()
+            .chain([ParallelRack::metadata()])
+            .map(|metadata| PluginInfo { metadata, valid: true, error: None })
+            .collect();
         
-        for path in search_paths {
+        for (path, format) in self.search_paths() {
             if path.exists() {
-                info!("扫描目录: {:?}", path);
-                match self.scan_directory(&path) {
+                info!("扫描目录: {:?} ({:?})", path, format);
+                let result = match format {
+                    PluginFormat::AudioUnit => scan_tree(&path, |p| has_extension(p, "component"), false, format, scan_au_bundle),
+                    PluginFormat::Clap => scan_tree(&path, |p| has_extension(p, "clap"), true, format, scan_clap_file),
+                    PluginFormat::Lv2 => scan_tree(&path, |p| p.is_dir() && has_extension(p, "lv2"), false, format, scan_lv2_bundle),
+                    PluginFormat::Vst3 => scan_tree(&path, |p| has_extension(p, "vst3"), true, format, scan_vst3_bundle),
+                    PluginFormat::Ladspa => scan_tree(&path, |p| p.is_file() && has_extension(p, "so"), false, format, scan_ladspa_file),
+                    PluginFormat::Native => scan_tree(&path, |p| p.is_file() && has_extension(p, std::env::consts::DLL_EXTENSION), false, format, scan_native_library),
+                    PluginFormat::Wasm => scan_tree(&path, |p| p.is_file() && has_extension(p, "wasm"), false, format, scan_wasm_file),
+                    // 内置效果器没有搜索目录
+                    PluginFormat::Builtin => continue,
+                };
+                match result {
                     Ok(plugins) => {
                         info!("  找到 {} 个插件", plugins.len());
                         all_plugins.extend(plugins);
@@ -65,116 +105,67 @@ impl PluginScanner {
         Ok(all_plugins)
     }
     
-    /// 扫描指定目录
-    fn scan_directory(&self, path: &Path) -> Result<Vec<PluginInfo>> {
-        let mut plugins = Vec::new();
+    /// 各格式的标准搜索目录
+    fn search_paths(&self) -> Vec<(PathBuf, PluginFormat)> {
+        let home = std::env::var("HOME").unwrap_or_default();
         
-        let entries = fs::read_dir(path)
-            .context(format!("读取目录失败: {:?}", path))?;
+        // macOS Audio Unit 标准目录
+        let mut paths = vec![
+            (PathBuf::from("/Library/Audio/Plug-Ins/Components"), PluginFormat::AudioUnit),
+            (PathBuf::from(format!("{}/Library/Audio/Plug-Ins/Components", home)), PluginFormat::AudioUnit),
+        ];
         
-        for entry in entries {
-            let entry = entry?;
-            let path = entry.path();
-            
-            // Audio Unit 插件是 .component bundle（macOS 上是目录）
-            if path.extension().and_then(|s| s.to_str()) == Some("component") {
-                debug!("发现插件: {:?}", path);
-                
-                match self.scan_plugin(&path) {
-                    Ok(info) => plugins.push(info),
-                    Err(e) => {
-                        warn!("扫描插件失败 {:?}: {}", path, e);
-                        plugins.push(PluginInfo {
-                            metadata: PluginMetadata {
-                                id: String::new(),
-                                name: path.file_stem()
-                                    .and_then(|s| s.to_str())
-                                    .unwrap_or("Unknown")
-                                    .to_string(),
-                                vendor: String::new(),
-                                version: String::new(),
-                                path: path.clone(),
-                                format: PluginFormat::AudioUnit,
-                                num_inputs: 2,
-                                num_outputs: 2,
-                            },
-                            valid: false,
-                            error: Some(e.to_string()),
-                        });
-                    }
-                }
-            }
+        // CLAP: 先搜索 CLAP_PATH，再搜索标准目录
+        if let Ok(clap_path) = std::env::var("CLAP_PATH") {
+            paths.extend(std::env::split_paths(&clap_path).map(|p| (p, PluginFormat::Clap)));
         }
+        paths.push((PathBuf::from(format!("{}/.clap", home)), PluginFormat::Clap));
+        paths.push((PathBuf::from("/usr/lib/clap"), PluginFormat::Clap));
+        paths.push((PathBuf::from("/Library/Audio/Plug-Ins/CLAP"), PluginFormat::Clap));
+        paths.push((PathBuf::from(format!("{}/Library/Audio/Plug-Ins/CLAP", home)), PluginFormat::Clap));
         
-        Ok(plugins)
-    }
-    
-    /// 扫描单个插件
-    fn scan_plugin(&self, path: &Path) -> Result<PluginInfo> {
-        // 在 macOS 上，Audio Unit 是一个 bundle（目录结构）
-        // 结构: PluginName.component/Contents/MacOS/PluginName
-        
-        let plugin_name = path.file_stem()
-            .and_then(|s| s.to_str())
-            .ok_or_else(|| anyhow::anyhow!("无效的插件名称"))?
-            .to_string();
+        // LV2: LV2_PATH 优先，否则使用标准目录
+        match std::env::var("LV2_PATH") {
+            Ok(lv2_path) => {
+                paths.extend(std::env::split_paths(&lv2_path).map(|p| (p, PluginFormat::Lv2)));
+            }
+            Err(_) => {
+                paths.push((PathBuf::from(format!("{}/.lv2", home)), PluginFormat::Lv2));
+                paths.push((PathBuf::from("/usr/local/lib/lv2"), PluginFormat::Lv2));
+                paths.push((PathBuf::from("/usr/lib/lv2"), PluginFormat::Lv2));
+            }
+        }
         
-        // 检查 bundle 结构
-        let macos_dir = path.join("Contents/MacOS");
+        // VST3: Linux 标准目录
+        paths.push((PathBuf::from(format!("{}/.vst3", home)), PluginFormat::Vst3));
+        paths.push((PathBuf::from("/usr/local/lib/vst3"), PluginFormat::Vst3));
+        paths.push((PathBuf::from("/usr/lib/vst3"), PluginFormat::Vst3));
         
-        if !macos_dir.exists() {
-            return Err(anyhow::anyhow!("找不到 Contents/MacOS 目录"));
+        // LADSPA: LADSPA_PATH 优先，否则使用标准目录
+        match std::env::var("LADSPA_PATH") {
+            Ok(ladspa_path) => {
+                paths.extend(std::env::split_paths(&ladspa_path).map(|p| (p, PluginFormat::Ladspa)));
+            }
+            Err(_) => {
+                paths.push((PathBuf::from(format!("{}/.ladspa", home)), PluginFormat::Ladspa));
+                paths.push((PathBuf::from("/usr/local/lib/ladspa"), PluginFormat::Ladspa));
+                paths.push((PathBuf::from("/usr/lib/ladspa"), PluginFormat::Ladspa));
+            }
         }
         
-        // 先尝试标准路径（二进制文件名 = bundle 名）
-        let standard_binary = macos_dir.join(&plugin_name);
-        let binary_exists = if standard_binary.exists() {
-            true
-        } else {
-            // 如果标准路径不存在，尝试查找任何可执行文件
-            match fs::read_dir(&macos_dir) {
-                Ok(entries) => {
-                    let mut found = false;
-                    for entry in entries.flatten() {
-                        let entry_path = entry.path();
-                        if entry_path.is_file() {
-                            // 在 macOS 上，可执行文件通常没有扩展名
-                            if entry_path.extension().is_none() {
-                                debug!("找到可执行文件: {:?}", entry_path);
-                                found = true;
-                                break;
-                            }
-                        }
-                    }
-                    found
-                }
-                Err(_) => false,
-            }
-        };
+        // 原生插件: PLUGIN_LOADER_PATH、默认目录
+        if let Ok(native_path) = std::env::var("PLUGIN_LOADER_PATH") {
+            paths.extend(std::env::split_paths(&native_path).map(|p| (p, PluginFormat::Native)));
+        }
+        paths.push((PathBuf::from(format!("{}/.plugin-loader/plugins", home)), PluginFormat::Native));
         
-        if !binary_exists {
-            return Err(anyhow::anyhow!("找不到插件二进制文件"));
+        // Wasm 沙盒插件: PLUGIN_LOADER_WASM_PATH、默认目录
+        if let Ok(wasm_path) = std::env::var("PLUGIN_LOADER_WASM_PATH") {
+            paths.extend(std::env::split_paths(&wasm_path).map(|p| (p, PluginFormat::Wasm)));
         }
+        paths.push((PathBuf::from(format!("{}/.plugin-loader/wasm", home)), PluginFormat::Wasm));
         
-        // TODO: 在 Phase 2 后期，我们会实际加载插件并读取其元数据
-        // 现在只是创建基础信息
-        
-        let metadata = PluginMetadata {
-            id: format!("au:{}", plugin_name),
-            name: plugin_name.clone(),
-            vendor: "Unknown".to_string(), // 需要从插件读取
-            version: "0.0.0".to_string(),   // 需要从插件读取
-            path: path.to_path_buf(),
-            format: PluginFormat::AudioUnit,
-            num_inputs: 2,  // 默认立体声
-            num_outputs: 2, // 默认立体声
-        };
-        
-        Ok(PluginInfo {
-            metadata,
-            valid: true,
-            error: None,
-        })
+        paths
     }
     
     /// 保存插件缓存
@@ -206,6 +197,113 @@ impl PluginScanner {
     }
 }
 
+/// 扫描目录：对 matches 命中的条目调用 scan（一个文件或 bundle 可以包含多个插件），
+/// recurse 为 true 时进入未命中的子目录
+fn scan_tree(
+    path: &Path,
+    matches: fn(&Path) -> bool,
+    recurse: bool,
+    format: PluginFormat,
+    scan: fn(&Path) -> Result<Vec<PluginMetadata>>,
+) -> Result<Vec<PluginInfo>> {
+    let mut plugins = Vec::new();
+    
+    let entries = fs::read_dir(path)
+        .context(format!("读取目录失败: {:?}", path))?;
+    
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        
+        if matches(&path) {
+            debug!("发现插件: {:?}", path);
+            
+            match scan(&path) {
+                Ok(found) => plugins.extend(found.into_iter().map(|metadata| PluginInfo {
+                    metadata,
+                    valid: true,
+                    error: None,
+                })),
+                Err(e) => {
+                    warn!("扫描插件失败 {:?}: {}", path, e);
+                    plugins.push(PluginInfo::invalid(&path, format, &e));
+                }
+            }
+        } else if recurse && path.is_dir() {
+            plugins.extend(scan_tree(&path, matches, recurse, format, scan)?);
+        }
+    }
+    
+    Ok(plugins)
+}
+
+/// 文件扩展名是否为 extension
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension().and_then(|s| s.to_str()) == Some(extension)
+}
+
+/// 扫描 Audio Unit bundle
+fn scan_au_bundle(path: &Path) -> Result<Vec<PluginMetadata>> {
+    // 在 macOS 上，Audio Unit 是一个 bundle（目录结构）
+    // 结构: PluginName.component/Contents/MacOS/PluginName
+    
+    let plugin_name = path.file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or_else(|| anyhow::anyhow!("无效的插件名称"))?
+        .to_string();
+    
+    // 检查 bundle 结构
+    let macos_dir = path.join("Contents/MacOS");
+    
+    if !macos_dir.exists() {
+        return Err(anyhow::anyhow!("找不到 Contents/MacOS 目录"));
+    }
+    
+    // 先尝试标准路径（二进制文件名 = bundle 名）
+    let standard_binary = macos_dir.join(&plugin_name);
+    let binary_exists = if standard_binary.exists() {
+        true
+    } else {
+        // 如果标准路径不存在，尝试查找任何可执行文件
+        match fs::read_dir(&macos_dir) {
+            Ok(entries) => {
+                let mut found = false;
+                for entry in entries.flatten() {
+                    let entry_path = entry.path();
+                    if entry_path.is_file() {
+                        // 在 macOS 上，可执行文件通常没有扩展名
+                        if entry_path.extension().is_none() {
+                            debug!("找到可执行文件: {:?}", entry_path);
+                            found = true;
+                            break;
+                        }
+                    }
+                }
+                found
+            }
+            Err(_) => false,
+        }
+    };
+    
+    if !binary_exists {
+        return Err(anyhow::anyhow!("找不到插件二进制文件"));
+    }
+    
+    // TODO: 在 Phase 2 后期，我们会实际加载插件并读取其元数据
+    // 现在只是创建基础信息
+    
+    Ok(vec![PluginMetadata {
+        id: format!("au:{}", plugin_name),
+        name: plugin_name.clone(),
+        vendor: "Unknown".to_string(), // 需要从插件读取
+        version: "0.0.0".to_string(),   // 需要从插件读取
+        path: path.to_path_buf(),
+        format: PluginFormat::AudioUnit,
+        num_inputs: 2,  // 默认立体声
+        num_outputs: 2, // 默认立体声
+    }])
+}
+
 impl Default for PluginScanner {
     fn default() -> Self {
         Self::new()
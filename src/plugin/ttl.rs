@@ -0,0 +1,681 @@
+
+// Turtle (TTL) 解析器
+// 只实现 LV2 元数据需要的子集：前缀、IRI、空白节点、集合、字面量
+
+use anyhow::{Result, Context};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+pub const RDF_FIRST: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#first";
+pub const RDF_REST: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#rest";
+pub const RDF_NIL: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#nil";
+pub const RDF_VALUE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#value";
+pub const RDFS_LABEL: &str = "http://www.w3.org/2000/01/rdf-schema#label";
+pub const RDFS_SEE_ALSO: &str = "http://www.w3.org/2000/01/rdf-schema#seeAlso";
+
+const XSD: &str = "http://www.w3.org/2001/XMLSchema#";
+
+/// RDF 节点
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Iri(String),
+    Blank(u32),
+    Literal {
+        value: String,
+        datatype: Option<String>,
+    },
+}
+
+impl Node {
+    pub fn as_iri(&self) -> Option<&str> {
+        match self {
+            Node::Iri(iri) => Some(iri),
+            _ => None,
+        }
+    }
+
+    /// 字面量的文本值
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Node::Literal { value, .. } => Some(value),
+            _ => None,
+        }
+    }
+
+    /// 数值字面量
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Node::Literal { value, .. } => value.parse().ok(),
+            _ => None,
+        }
+    }
+}
+
+/// 三元组
+#[derive(Debug, Clone)]
+pub struct Triple {
+    pub subject: Node,
+    pub predicate: String,
+    pub object: Node,
+}
+
+/// 三元组集合（可以合并多个文件）
+#[derive(Debug, Default)]
+pub struct Graph {
+    triples: Vec<Triple>,
+    next_blank: u32,
+}
+
+impl Graph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 解析并合并一个 TTL 文件
+    pub fn load_file(&mut self, path: &Path) -> Result<()> {
+        let text = fs::read_to_string(path)
+            .context(format!("读取 TTL 文件失败: {:?}", path))?;
+
+        self.parse(&text, &path_to_uri(path))
+            .context(format!("解析 TTL 文件失败: {:?}", path))
+    }
+
+    /// 解析 TTL 文本，base 用于解析相对 IRI
+    pub fn parse(&mut self, text: &str, base: &str) -> Result<()> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            base: base.to_string(),
+            prefixes: HashMap::new(),
+            blank_labels: HashMap::new(),
+            graph: self,
+        };
+        parser.parse_document()
+    }
+
+    pub fn triples(&self) -> &[Triple] {
+        &self.triples
+    }
+
+    /// 给定主语和谓语的所有宾语
+    pub fn objects(&self, subject: &Node, predicate: &str) -> impl Iterator<Item = &Node> + '_ {
+        let subject = subject.clone();
+        let predicate = predicate.to_string();
+        self.triples
+            .iter()
+            .filter(move |t| t.subject == subject && t.predicate == predicate)
+            .map(|t| &t.object)
+    }
+
+    /// 给定主语和谓语的第一个宾语
+    pub fn object(&self, subject: &Node, predicate: &str) -> Option<&Node> {
+        self.objects(subject, predicate).next()
+    }
+
+    /// 判断三元组 (subject, predicate, <object>) 是否存在
+    pub fn has(&self, subject: &Node, predicate: &str, object: &str) -> bool {
+        self.objects(subject, predicate)
+            .any(|o| o.as_iri() == Some(object))
+    }
+
+    /// 所有 rdf:type 为指定类型的主语
+    pub fn subjects_of_type(&self, type_iri: &str) -> Vec<Node> {
+        let mut subjects: Vec<Node> = Vec::new();
+        for t in &self.triples {
+            if t.predicate == RDF_TYPE
+                && t.object.as_iri() == Some(type_iri)
+                && !subjects.contains(&t.subject)
+            {
+                subjects.push(t.subject.clone());
+            }
+        }
+        subjects
+    }
+
+    fn add(&mut self, subject: Node, predicate: String, object: Node) {
+        self.triples.push(Triple { subject, predicate, object });
+    }
+
+    fn new_blank(&mut self) -> Node {
+        self.next_blank += 1;
+        Node::Blank(self.next_blank)
+    }
+}
+
+/// 文件路径转换为 file:// URI
+pub fn path_to_uri(path: &Path) -> String {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir().unwrap_or_default().join(path)
+    };
+
+    let mut uri = String::from("file://");
+    for byte in absolute.to_string_lossy().bytes() {
+        match byte {
+            b' ' => uri.push_str("%20"),
+            b'%' => uri.push_str("%25"),
+            _ => uri.push(byte as char),
+        }
+    }
+    uri
+}
+
+/// file:// URI 转换为文件路径
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?;
+
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or_default();
+            if let Ok(byte) = u8::from_str_radix(hex, 16) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    Some(PathBuf::from(String::from_utf8_lossy(&decoded).into_owned()))
+}
+
+// ===== 词法分析 =====
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Iri(String),
+    PrefixedName(String, String),
+    BlankLabel(String),
+    String(String),
+    Number(String),
+    Boolean(bool),
+    LangTag(String),
+    Directive(String),
+    A,
+    Dot,
+    Semicolon,
+    Comma,
+    OpenBracket,
+    CloseBracket,
+    OpenParen,
+    CloseParen,
+    DataType,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            _ if c.is_whitespace() => i += 1,
+            '#' => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '<' => {
+                let start = i + 1;
+                i = start;
+                while i < chars.len() && chars[i] != '>' {
+                    i += 1;
+                }
+                if i >= chars.len() {
+                    return Err(anyhow::anyhow!("IRI 未闭合"));
+                }
+                tokens.push(Token::Iri(chars[start..i].iter().collect()));
+                i += 1;
+            }
+            '"' | '\'' => {
+                let (value, next) = read_string(&chars, i)?;
+                tokens.push(Token::String(value));
+                i = next;
+            }
+            '@' => {
+                let start = i + 1;
+                i = start;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '-') {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                if word == "prefix" || word == "base" {
+                    tokens.push(Token::Directive(word));
+                } else {
+                    tokens.push(Token::LangTag(word));
+                }
+            }
+            '^' if chars.get(i + 1) == Some(&'^') => {
+                tokens.push(Token::DataType);
+                i += 2;
+            }
+            '.' if !chars.get(i + 1).is_some_and(|c| c.is_ascii_digit()) => {
+                tokens.push(Token::Dot);
+                i += 1;
+            }
+            ';' => { tokens.push(Token::Semicolon); i += 1; }
+            ',' => { tokens.push(Token::Comma); i += 1; }
+            '[' => { tokens.push(Token::OpenBracket); i += 1; }
+            ']' => { tokens.push(Token::CloseBracket); i += 1; }
+            '(' => { tokens.push(Token::OpenParen); i += 1; }
+            ')' => { tokens.push(Token::CloseParen); i += 1; }
+            _ if c.is_ascii_digit() || c == '+' || c == '-' || c == '.' => {
+                let start = i;
+                i += 1;
+                while i < chars.len() {
+                    let d = chars[i];
+                    let is_number_char = d.is_ascii_digit()
+                        || d == 'e' || d == 'E'
+                        || ((d == '+' || d == '-') && matches!(chars[i - 1], 'e' | 'E'))
+                        || (d == '.' && chars.get(i + 1).is_some_and(|c| c.is_ascii_digit()));
+                    if !is_number_char {
+                        break;
+                    }
+                    i += 1;
+                }
+                tokens.push(Token::Number(chars[start..i].iter().collect()));
+            }
+            '_' if chars.get(i + 1) == Some(&':') => {
+                let start = i + 2;
+                i = start;
+                while i < chars.len() && is_name_char(chars[i]) {
+                    i += 1;
+                }
+                tokens.push(Token::BlankLabel(chars[start..i].iter().collect()));
+            }
+            _ if is_name_char(c) || c == ':' => {
+                let start = i;
+                while i < chars.len() && (is_name_char(chars[i]) || chars[i] == ':' || chars[i] == '.') {
+                    i += 1;
+                }
+                // 名称不能以 '.' 结尾（那是语句结束符）
+                while i > start && chars[i - 1] == '.' {
+                    i -= 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                tokens.push(match word.as_str() {
+                    "a" => Token::A,
+                    "true" => Token::Boolean(true),
+                    "false" => Token::Boolean(false),
+                    _ if word.eq_ignore_ascii_case("prefix") => Token::Directive("prefix".to_string()),
+                    _ if word.eq_ignore_ascii_case("base") => Token::Directive("base".to_string()),
+                    _ => match word.split_once(':') {
+                        Some((prefix, local)) => Token::PrefixedName(prefix.to_string(), local.to_string()),
+                        None => return Err(anyhow::anyhow!("无法识别的名称: {}", word)),
+                    },
+                });
+            }
+            _ => return Err(anyhow::anyhow!("无法识别的字符: {:?}", c)),
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-' || c == '%'
+}
+
+/// 读取字符串字面量（支持单引号、双引号和三引号）
+fn read_string(chars: &[char], start: usize) -> Result<(String, usize)> {
+    let quote = chars[start];
+    let long = chars.get(start + 1) == Some(&quote) && chars.get(start + 2) == Some(&quote);
+    let mut i = if long { start + 3 } else { start + 1 };
+    let mut value = String::new();
+
+    loop {
+        let Some(&c) = chars.get(i) else {
+            return Err(anyhow::anyhow!("字符串未闭合"));
+        };
+
+        if c == '\\' {
+            let escaped = chars.get(i + 1).copied().unwrap_or('\\');
+            match escaped {
+                'n' => value.push('\n'),
+                't' => value.push('\t'),
+                'r' => value.push('\r'),
+                'u' | 'U' => {
+                    let len = if escaped == 'u' { 4 } else { 8 };
+                    let hex: String = chars.iter().skip(i + 2).take(len).collect();
+                    if let Some(ch) = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                        value.push(ch);
+                    }
+                    i += len;
+                }
+                other => value.push(other),
+            }
+            i += 2;
+            continue;
+        }
+
+        if c == quote {
+            if !long {
+                return Ok((value, i + 1));
+            }
+            if chars.get(i + 1) == Some(&quote) && chars.get(i + 2) == Some(&quote) {
+                return Ok((value, i + 3));
+            }
+        }
+
+        value.push(c);
+        i += 1;
+    }
+}
+
+// ===== 语法分析 =====
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    base: String,
+    prefixes: HashMap<String, String>,
+    blank_labels: HashMap<String, Node>,
+    graph: &'a mut Graph,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Result<Token> {
+        let token = self.tokens.get(self.pos)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("意外的文件结尾"))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<()> {
+        let token = self.next()?;
+        if token != expected {
+            return Err(anyhow::anyhow!("期望 {:?}，实际为 {:?}", expected, token));
+        }
+        Ok(())
+    }
+
+    fn parse_document(&mut self) -> Result<()> {
+        while let Some(token) = self.peek().cloned() {
+            match token {
+                Token::Directive(directive) => {
+                    self.pos += 1;
+                    self.parse_directive(&directive)?;
+                }
+                Token::OpenBracket => {
+                    self.pos += 1;
+                    let subject = self.parse_blank_property_list()?;
+                    if self.peek() != Some(&Token::Dot) {
+                        self.parse_predicate_object_list(&subject)?;
+                    }
+                    self.expect(Token::Dot)?;
+                }
+                _ => {
+                    let subject = self.parse_subject()?;
+                    self.parse_predicate_object_list(&subject)?;
+                    self.expect(Token::Dot)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_directive(&mut self, directive: &str) -> Result<()> {
+        if directive == "prefix" {
+            let Token::PrefixedName(prefix, local) = self.next()? else {
+                return Err(anyhow::anyhow!("@prefix 语法错误"));
+            };
+            if !local.is_empty() {
+                return Err(anyhow::anyhow!("@prefix 语法错误"));
+            }
+            let Token::Iri(iri) = self.next()? else {
+                return Err(anyhow::anyhow!("@prefix 缺少 IRI"));
+            };
+            let iri = self.resolve(&iri);
+            self.prefixes.insert(prefix, iri);
+        } else {
+            let Token::Iri(iri) = self.next()? else {
+                return Err(anyhow::anyhow!("@base 缺少 IRI"));
+            };
+            self.base = self.resolve(&iri);
+        }
+
+        // SPARQL 风格的 PREFIX/BASE 没有结尾的 '.'
+        if self.peek() == Some(&Token::Dot) {
+            self.pos += 1;
+        }
+        Ok(())
+    }
+
+    fn parse_subject(&mut self) -> Result<Node> {
+        match self.next()? {
+            Token::Iri(iri) => Ok(Node::Iri(self.resolve(&iri))),
+            Token::PrefixedName(prefix, local) => Ok(Node::Iri(self.expand(&prefix, &local)?)),
+            Token::BlankLabel(label) => Ok(self.labelled_blank(&label)),
+            Token::OpenParen => self.parse_collection(),
+            token => Err(anyhow::anyhow!("无效的主语: {:?}", token)),
+        }
+    }
+
+    fn parse_predicate_object_list(&mut self, subject: &Node) -> Result<()> {
+        loop {
+            let predicate = match self.next()? {
+                Token::A => RDF_TYPE.to_string(),
+                Token::Iri(iri) => self.resolve(&iri),
+                Token::PrefixedName(prefix, local) => self.expand(&prefix, &local)?,
+                token => return Err(anyhow::anyhow!("无效的谓语: {:?}", token)),
+            };
+
+            loop {
+                let object = self.parse_object()?;
+                self.graph.add(subject.clone(), predicate.clone(), object);
+                if self.peek() == Some(&Token::Comma) {
+                    self.pos += 1;
+                } else {
+                    break;
+                }
+            }
+
+            // 允许多个连续的 ';'
+            let mut saw_semicolon = false;
+            while self.peek() == Some(&Token::Semicolon) {
+                self.pos += 1;
+                saw_semicolon = true;
+            }
+            if !saw_semicolon || matches!(self.peek(), Some(Token::Dot) | Some(Token::CloseBracket) | None) {
+                return Ok(());
+            }
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<Node> {
+        match self.next()? {
+            Token::Iri(iri) => Ok(Node::Iri(self.resolve(&iri))),
+            Token::PrefixedName(prefix, local) => Ok(Node::Iri(self.expand(&prefix, &local)?)),
+            Token::BlankLabel(label) => Ok(self.labelled_blank(&label)),
+            Token::OpenBracket => self.parse_blank_property_list(),
+            Token::OpenParen => self.parse_collection(),
+            Token::Boolean(value) => Ok(Node::Literal {
+                value: value.to_string(),
+                datatype: Some(format!("{}boolean", XSD)),
+            }),
+            Token::Number(value) => {
+                let datatype = if value.contains(['e', 'E']) {
+                    "double"
+                } else if value.contains('.') {
+                    "decimal"
+                } else {
+                    "integer"
+                };
+                Ok(Node::Literal { value, datatype: Some(format!("{}{}", XSD, datatype)) })
+            }
+            Token::String(value) => {
+                let datatype = match self.peek() {
+                    Some(Token::LangTag(_)) => {
+                        self.pos += 1;
+                        None
+                    }
+                    Some(Token::DataType) => {
+                        self.pos += 1;
+                        match self.next()? {
+                            Token::Iri(iri) => Some(self.resolve(&iri)),
+                            Token::PrefixedName(prefix, local) => Some(self.expand(&prefix, &local)?),
+                            token => return Err(anyhow::anyhow!("无效的数据类型: {:?}", token)),
+                        }
+                    }
+                    _ => None,
+                };
+                Ok(Node::Literal { value, datatype })
+            }
+            token => Err(anyhow::anyhow!("无效的宾语: {:?}", token)),
+        }
+    }
+
+    /// '[' 已被读取
+    fn parse_blank_property_list(&mut self) -> Result<Node> {
+        let node = self.graph.new_blank();
+        if self.peek() != Some(&Token::CloseBracket) {
+            self.parse_predicate_object_list(&node)?;
+        }
+        self.expect(Token::CloseBracket)?;
+        Ok(node)
+    }
+
+    /// '(' 已被读取，展开为 rdf:first/rdf:rest 链表
+    fn parse_collection(&mut self) -> Result<Node> {
+        let mut items = Vec::new();
+        while self.peek() != Some(&Token::CloseParen) {
+            items.push(self.parse_object()?);
+        }
+        self.expect(Token::CloseParen)?;
+
+        let mut head = Node::Iri(RDF_NIL.to_string());
+        for item in items.into_iter().rev() {
+            let cell = self.graph.new_blank();
+            self.graph.add(cell.clone(), RDF_FIRST.to_string(), item);
+            self.graph.add(cell.clone(), RDF_REST.to_string(), head);
+            head = cell;
+        }
+        Ok(head)
+    }
+
+    fn labelled_blank(&mut self, label: &str) -> Node {
+        // 同一文件内的同名标签指向同一节点
+        if let Some(node) = self.blank_labels.get(label) {
+            return node.clone();
+        }
+        let node = self.graph.new_blank();
+        self.blank_labels.insert(label.to_string(), node.clone());
+        node
+    }
+
+    fn expand(&self, prefix: &str, local: &str) -> Result<String> {
+        let namespace = self.prefixes.get(prefix)
+            .ok_or_else(|| anyhow::anyhow!("未定义的前缀: {}:", prefix))?;
+        Ok(format!("{}{}", namespace, local))
+    }
+
+    fn resolve(&self, iri: &str) -> String {
+        if iri.contains(':') {
+            return iri.to_string();
+        }
+        if iri.is_empty() {
+            return self.base.clone();
+        }
+        if iri.starts_with('#') {
+            let base = self.base.split('#').next().unwrap_or_default();
+            return format!("{}{}", base, iri);
+        }
+        if iri.starts_with('/') {
+            return format!("file://{}", iri);
+        }
+
+        // 相对于 base 所在目录
+        match self.base.rfind('/') {
+            Some(idx) => format!("{}{}", &self.base[..=idx], iri),
+            None => iri.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LV2: &str = "http://lv2plug.in/ns/lv2core#";
+
+    #[test]
+    fn test_parse_manifest() {
+        let text = r#"
+            @prefix lv2:  <http://lv2plug.in/ns/lv2core#> .
+            @prefix rdfs: <http://www.w3.org/2000/01/rdf-schema#> .
+
+            <http://example.org/amp>
+                a lv2:Plugin ;
+                lv2:binary <amp.so> ;
+                rdfs:seeAlso <amp.ttl> .
+        "#;
+
+        let mut graph = Graph::new();
+        graph.parse(text, "file:///usr/lib/lv2/amp.lv2/manifest.ttl").unwrap();
+
+        let plugins = graph.subjects_of_type(&format!("{}Plugin", LV2));
+        assert_eq!(plugins, vec![Node::Iri("http://example.org/amp".to_string())]);
+
+        let binary = graph.object(&plugins[0], &format!("{}binary", LV2)).unwrap();
+        assert_eq!(binary.as_iri(), Some("file:///usr/lib/lv2/amp.lv2/amp.so"));
+        assert_eq!(
+            uri_to_path(binary.as_iri().unwrap()),
+            Some(PathBuf::from("/usr/lib/lv2/amp.lv2/amp.so"))
+        );
+    }
+
+    #[test]
+    fn test_parse_ports() {
+        let text = r#"
+            @prefix lv2: <http://lv2plug.in/ns/lv2core#> .
+            @prefix doap: <http://usefulinc.com/ns/doap#> .
+
+            <urn:gain> a lv2:Plugin, lv2:AmplifierPlugin ;
+                doap:name "Gain"@en , """Verstärker""" ;
+                lv2:port [
+                    a lv2:InputPort , lv2:ControlPort ;
+                    lv2:index 0 ;
+                    lv2:symbol "gain" ;
+                    lv2:default -6.0 ;
+                    lv2:minimum -90 ;
+                    lv2:maximum 2.4E1 ;
+                ] , [
+                    a lv2:AudioPort, lv2:InputPort ;
+                    lv2:index 1
+                ] ;
+                lv2:extensionData ( <urn:a> <urn:b> ) .
+        "#;
+
+        let mut graph = Graph::new();
+        graph.parse(text, "file:///tmp/gain.ttl").unwrap();
+
+        let plugin = Node::Iri("urn:gain".to_string());
+        assert_eq!(graph.object(&plugin, "http://usefulinc.com/ns/doap#name").unwrap().as_str(), Some("Gain"));
+
+        let ports: Vec<&Node> = graph.objects(&plugin, &format!("{}port", LV2)).collect();
+        assert_eq!(ports.len(), 2);
+        assert!(graph.has(ports[0], RDF_TYPE, &format!("{}ControlPort", LV2)));
+        assert_eq!(graph.object(ports[0], &format!("{}default", LV2)).unwrap().as_f64(), Some(-6.0));
+        assert_eq!(graph.object(ports[0], &format!("{}minimum", LV2)).unwrap().as_f64(), Some(-90.0));
+        assert_eq!(graph.object(ports[0], &format!("{}maximum", LV2)).unwrap().as_f64(), Some(24.0));
+        assert_eq!(graph.object(ports[1], &format!("{}index", LV2)).unwrap().as_f64(), Some(1.0));
+
+        // 集合展开为链表
+        let list = graph.object(&plugin, &format!("{}extensionData", LV2)).unwrap();
+        assert_eq!(graph.object(list, RDF_FIRST).unwrap().as_iri(), Some("urn:a"));
+    }
+}
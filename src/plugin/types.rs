@@ -1,11 +1,157 @@
+use std::any::Any;
 use std::path::PathBuf;
 use serde::{Deserialize, Serialize};
 
+use crate::audio::AudioBuffer;
+
+use super::events::{EventKind, EventList};
+
 /// 插件类型
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PluginFormat {
     AudioUnit, // macOS 原生格式
-    // 未来可以扩展: VST3, VST2, etc.
+    Clap,      // CLAP（.clap 动态库）
+    Lv2,       // LV2（.lv2 bundle + TTL 元数据）
+    Vst3,      // VST3（.vst3 bundle，Linux）
+    Ladspa,    // LADSPA（.so 动态库）
+    Native,    // 原生 Rust 插件（plugin-sdk 构建的 cdylib）
+    Wasm,      // WebAssembly 沙盒插件（.wasm）
+    Builtin,   // 内置效果器（无需外部文件）
+    // 未来可以扩展: VST2, etc.
+}
+
+/// 声道布局
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ChannelLayout {
+    Mono,
+    #[default]
+    Stereo,
+}
+
+impl ChannelLayout {
+    /// 声道数
+    pub fn channels(&self) -> usize {
+        match self {
+            ChannelLayout::Mono => 1,
+            ChannelLayout::Stereo => 2,
+        }
+    }
+    
+    /// 由声道数推出布局，0 个声道（没有该总线）时返回 None
+    pub fn from_channels(channels: u32) -> Option<Self> {
+        match channels {
+            0 => None,
+            1 => Some(ChannelLayout::Mono),
+            _ => Some(ChannelLayout::Stereo),
+        }
+    }
+}
+
+/// 插件主总线的输入 / 输出布局
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BusLayout {
+    pub input: ChannelLayout,
+    pub output: ChannelLayout,
+}
+
+impl BusLayout {
+    pub fn new(input: ChannelLayout, output: ChannelLayout) -> Self {
+        Self { input, output }
+    }
+    
+    /// 输入输出相同
+    pub fn uniform(layout: ChannelLayout) -> Self {
+        Self::new(layout, layout)
+    }
+    
+    /// 由插件的输入 / 输出声道数推出布局，没有输入（或输出）总线时沿用上游布局
+    pub fn from_metadata(info: &PluginMetadata, upstream: ChannelLayout) -> Self {
+        let input = ChannelLayout::from_channels(info.num_inputs).unwrap_or(upstream);
+        let output = ChannelLayout::from_channels(info.num_outputs).unwrap_or(input);
+        Self::new(input, output)
+    }
+    
+    /// 处理时缓冲区需要的声道数（输入和输出中较多的一个）
+    pub fn channels(&self) -> usize {
+        self.input.channels().max(self.output.channels())
+    }
+}
+
+/// 插件对单声道和立体声上游分别协商出的总线布局
+///
+/// 插件在音频线程时控制线程不能调用 bus_layout，实时编辑后按它重新协商整条链的布局
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BusTable {
+    pub mono: BusLayout,
+    pub stereo: BusLayout,
+}
+
+impl BusTable {
+    /// 询问插件两种上游布局下的总线布局（插件不在音频线程时调用）
+    pub fn of(plugin: &dyn AudioProcessor) -> Self {
+        Self {
+            mono: plugin.bus_layout(ChannelLayout::Mono),
+            stereo: plugin.bus_layout(ChannelLayout::Stereo),
+        }
+    }
+    
+    /// 上一级送来 upstream 时的布局
+    pub fn negotiate(&self, upstream: ChannelLayout) -> SlotLayout {
+        let bus = match upstream {
+            ChannelLayout::Mono => self.mono,
+            ChannelLayout::Stereo => self.stereo,
+        };
+        SlotLayout { upstream, bus }
+    }
+}
+
+/// 进入插件前对上一级信号做的转换
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutConversion {
+    /// 布局相同，直接传递
+    None,
+    /// 单声道复制到两个声道
+    Upmix,
+    /// 两个声道取平均
+    Downmix,
+}
+
+/// 插件链中某个位置协商后的布局
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlotLayout {
+    /// 上一级（链的输入或前一个插件）送来的布局
+    pub upstream: ChannelLayout,
+    /// 插件实际使用的布局
+    pub bus: BusLayout,
+}
+
+impl SlotLayout {
+    pub fn conversion(&self) -> LayoutConversion {
+        match (self.upstream, self.bus.input) {
+            (ChannelLayout::Mono, ChannelLayout::Stereo) => LayoutConversion::Upmix,
+            (ChannelLayout::Stereo, ChannelLayout::Mono) => LayoutConversion::Downmix,
+            _ => LayoutConversion::None,
+        }
+    }
+}
+
+/// 处理配置（AudioProcessor::prepare 的参数）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProcessConfig {
+    pub sample_rate: f64,
+    /// 每次 process 的最大帧数
+    pub max_block_size: usize,
+    pub layout: ChannelLayout,
+}
+
+impl Default for ProcessConfig {
+    fn default() -> Self {
+        Self {
+            sample_rate: 48000.0,
+            max_block_size: 4096,
+            layout: ChannelLayout::Stereo,
+        }
+    }
 }
 
 /// 插件信息
@@ -52,6 +198,204 @@ pub struct PluginParameter {
     pub display: String,
 }
 
+/// 参数标志
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ParameterFlags {
+    /// 可以被自动化 / MIDI 控制
+    pub automatable: bool,
+    /// 只读（插件输出的电平、增益衰减量等）
+    pub read_only: bool,
+    /// 插件自带的旁通开关
+    pub bypass: bool,
+    /// 不应显示在通用界面中
+    pub hidden: bool,
+}
+
+impl ParameterFlags {
+    /// 普通的可自动化参数
+    pub const AUTOMATABLE: Self = Self {
+        automatable: true,
+        read_only: false,
+        bypass: false,
+        hidden: false,
+    };
+}
+
+/// 参数描述
+///
+/// min/max/default 是插件自己单位下的实际值（plain，例如 dB、Hz），
+/// 插件接口上传递的仍然是 0.0 - 1.0 的归一化值，两者通过 to_plain / to_normalized 换算。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParameterDescriptor {
+    pub id: u32,
+    pub name: String,
+    pub min: f64,
+    pub max: f64,
+    pub default: f64,
+    /// 单位（"dB"、"Hz"、"%"，没有单位时为空）
+    pub unit: String,
+    /// 离散步数，0 表示连续；开关为 1，n 个枚举值为 n - 1
+    pub steps: u32,
+    /// 枚举值 (实际值, 名称)
+    pub value_names: Vec<(f64, String)>,
+    /// 归一化值按对数映射（要求 min > 0）
+    pub logarithmic: bool,
+    pub flags: ParameterFlags,
+}
+
+impl ParameterDescriptor {
+    /// 连续的线性参数
+    pub fn new(id: u32, name: impl Into<String>, min: f64, max: f64, default: f64) -> Self {
+        Self {
+            id,
+            name: name.into(),
+            min,
+            max,
+            default: default.clamp(min.min(max), max.max(min)),
+            unit: String::new(),
+            steps: 0,
+            value_names: Vec::new(),
+            logarithmic: false,
+            flags: ParameterFlags::AUTOMATABLE,
+        }
+    }
+
+    /// 开关参数（0 = Off，1 = On）
+    pub fn toggle(id: u32, name: impl Into<String>, default: bool) -> Self {
+        Self::new(id, name, 0.0, 1.0, if default { 1.0 } else { 0.0 }).with_steps(1)
+    }
+
+    /// 只用归一化值描述的参数（插件没有提供范围信息时使用）
+    pub fn normalized(id: u32, name: impl Into<String>, default: f64) -> Self {
+        Self::new(id, name, 0.0, 1.0, default)
+    }
+
+    pub fn with_unit(mut self, unit: impl Into<String>) -> Self {
+        self.unit = unit.into();
+        self
+    }
+
+    pub fn with_steps(mut self, steps: u32) -> Self {
+        self.steps = steps;
+        self
+    }
+
+    /// 设置枚举值；步数未设置时按枚举值个数计算
+    pub fn with_value_names(mut self, names: Vec<(f64, String)>) -> Self {
+        if self.steps == 0 && names.len() > 1 {
+            self.steps = names.len() as u32 - 1;
+        }
+        self.value_names = names;
+        self
+    }
+
+    pub fn with_logarithmic(mut self, logarithmic: bool) -> Self {
+        // 对数映射要求范围都是正数
+        self.logarithmic = logarithmic && self.min > 0.0 && self.max > self.min;
+        self
+    }
+
+    pub fn with_flags(mut self, flags: ParameterFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    pub fn is_toggle(&self) -> bool {
+        self.steps == 1 && self.value_names.is_empty()
+    }
+
+    pub fn is_enum(&self) -> bool {
+        !self.value_names.is_empty()
+    }
+
+    /// 归一化值 -> 实际值（离散参数对齐到最近的一步）
+    pub fn to_plain(&self, normalized: f64) -> f64 {
+        let normalized = normalized.clamp(0.0, 1.0);
+        if self.steps > 0 {
+            let step = (normalized * self.steps as f64).round() / self.steps as f64;
+            return self.min + step * (self.max - self.min);
+        }
+        if self.logarithmic {
+            self.min * (self.max / self.min).powf(normalized)
+        } else {
+            self.min + normalized * (self.max - self.min)
+        }
+    }
+
+    /// 实际值 -> 归一化值
+    pub fn to_normalized(&self, plain: f64) -> f64 {
+        if self.max == self.min {
+            return 0.0;
+        }
+        let plain = plain.clamp(self.min.min(self.max), self.max.max(self.min));
+        let normalized = if self.logarithmic && self.steps == 0 {
+            (plain / self.min).ln() / (self.max / self.min).ln()
+        } else {
+            (plain - self.min) / (self.max - self.min)
+        };
+        if self.steps > 0 {
+            (normalized * self.steps as f64).round() / self.steps as f64
+        } else {
+            normalized.clamp(0.0, 1.0)
+        }
+    }
+
+    /// 默认值（归一化）
+    pub fn default_normalized(&self) -> f64 {
+        self.to_normalized(self.default)
+    }
+
+    /// 归一化值 -> 显示文本，例如 "-6.0 dB"、"On"、"Sine"
+    pub fn value_to_text(&self, normalized: f64) -> String {
+        let plain = self.to_plain(normalized);
+
+        if let Some((_, name)) = self.value_names.iter().min_by(|a, b| {
+            (a.0 - plain).abs().total_cmp(&(b.0 - plain).abs())
+        }) {
+            return name.clone();
+        }
+        if self.is_toggle() {
+            return if plain >= (self.min + self.max) / 2.0 { "On" } else { "Off" }.to_string();
+        }
+
+        // 整数步长的参数不显示小数
+        let integral = self.steps > 0 && ((self.max - self.min) / self.steps as f64).fract() == 0.0;
+        let number = if integral || plain.abs() >= 100.0 {
+            format!("{:.0}", plain)
+        } else if plain.abs() >= 10.0 {
+            format!("{:.1}", plain)
+        } else {
+            format!("{:.2}", plain)
+        };
+
+        if self.unit.is_empty() {
+            number
+        } else {
+            format!("{} {}", number, self.unit)
+        }
+    }
+
+    /// 显示文本 -> 归一化值，接受枚举名、On/Off 和带或不带单位的数字
+    pub fn text_to_value(&self, text: &str) -> Option<f64> {
+        let text = text.trim();
+
+        if let Some((plain, _)) = self.value_names.iter().find(|(_, name)| name.eq_ignore_ascii_case(text)) {
+            return Some(self.to_normalized(*plain));
+        }
+        if self.is_toggle() {
+            match text.to_ascii_lowercase().as_str() {
+                "on" | "true" | "yes" => return Some(1.0),
+                "off" | "false" | "no" => return Some(0.0),
+                _ => {}
+            }
+        }
+
+        let number = text.strip_suffix(self.unit.as_str()).unwrap_or(text).trim();
+        let plain: f64 = number.parse().ok()?;
+        Some(self.to_normalized(plain))
+    }
+}
+
 /// 插件状态（用于保存/加载）
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PluginState {
@@ -66,16 +410,115 @@ pub struct PluginState {
     pub state_data: String,
 }
 
+/// 插件链中单个位置的宿主侧设置（与插件自身状态分开保存）
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SlotSettings {
+    /// 旁通：插件不处理，保留内部状态
+    pub bypass: bool,
+    
+    /// 静音：该位置输出静音
+    pub mute: bool,
+    
+    /// 独奏：有位置独奏时，其余位置都被旁通
+    pub solo: bool,
+    
+    /// 干湿比（0.0 = 全干，1.0 = 全湿）
+    pub mix: f32,
+}
+
+impl SlotSettings {
+    pub fn new() -> Self {
+        Self {
+            bypass: false,
+            mute: false,
+            solo: false,
+            mix: 1.0,
+        }
+    }
+}
+
+impl Default for SlotSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// 音频处理的 Trait
-pub trait AudioProcessor: Send {
+///
+/// 生命周期：prepare -> process（可多次）-> suspend -> release，release 之后可以再次 prepare。
+/// prepare/reset/release 不在音频线程中调用，可以分配内存；process 和 suspend 在音频线程中调用。
+/// 宿主自己的处理器（并联机架）通过 Any 识别，插件格式不需要为它们实现额外的方法。
+pub trait AudioProcessor: Send + Any {
+    /// 准备处理：告知采样率、每次 process 的最大帧数和声道布局
+    /// 加入插件链时和音频配置变化时调用
+    fn prepare(&mut self, sample_rate: f64, max_block_size: usize, layout: ChannelLayout);
+    
+    /// 清空内部状态（延迟线、滤波器历史、包络等），参数保持不变
+    fn reset(&mut self);
+    
+    /// 释放 prepare 分配的处理资源，之后在再次 prepare 之前 process 为直通
+    fn release(&mut self);
+    
+    /// 音频线程即将不再调用 process（插件链或位置交回控制线程之前），在音频线程中调用，不分配内存
+    ///
+    /// 要求在音频线程结束处理的格式（CLAP 的 stop_processing）覆盖此方法；之后再次 process 时重新开始处理
+    fn suspend(&mut self) {}
+    
+    /// 协商主总线布局：upstream 是上一级送来的布局，返回插件实际使用的输入 / 输出布局
+    ///
+    /// 默认由 num_inputs / num_outputs 决定；没有输入（或输出）总线时沿用上游布局。
+    /// 能适应多种布局的插件（例如逐声道处理的效果器）覆盖此方法跟随上游。
+    /// 协商结果的输入布局随后通过 prepare 传给插件。
+    fn bus_layout(&self, upstream: ChannelLayout) -> BusLayout {
+        BusLayout::from_metadata(self.get_info(), upstream)
+    }
+    
+    /// 插件报告的处理延迟（帧），在 prepare 之后有效
+    /// 插件链据此对齐旁通和干湿比的干信号
+    fn latency_samples(&self) -> u32 {
+        0
+    }
+    
     /// 处理音频缓冲区
-    /// buffer: 交错的立体声音频数据 [L, R, L, R, ...]
-    fn process(&mut self, buffer: &mut [f32]);
+    /// buffer: 平面（非交错）缓冲区，声道数由调用方决定（单声道吉他输入、立体声、多声道接口），
+    /// 插件按 AudioBuffer 的映射规则读写自己支持的声道
+    fn process(&mut self, buffer: &mut AudioBuffer);
+    
+    /// 带事件的处理：events 的偏移相对 buffer 开头（单位为帧），已按偏移排序
+    ///
+    /// 默认实现在每个事件的位置把缓冲区切开，先调用 set_parameter 再处理后面的部分，
+    /// 没有原生事件机制的格式（LV2、LADSPA、内置效果器等）由此获得采样级精度。
+    /// MIDI 事件默认忽略。
+    fn process_with_events(&mut self, buffer: &mut AudioBuffer, events: &EventList) {
+        let frames = buffer.frames();
+        let mut start = 0;
+        for event in events.iter() {
+            let offset = (event.offset as usize).min(frames);
+            if offset > start {
+                self.process(&mut buffer.slice(start, offset));
+                start = offset;
+            }
+            if let EventKind::Parameter { id, value } = event.kind {
+                self.set_parameter(id, value);
+            }
+        }
+        if start < frames {
+            self.process(&mut buffer.slice(start, frames));
+        }
+    }
+    
+    /// 插件是否有接受 MIDI 的输入端口，没有的插件收不到转发的 MIDI 消息
+    fn accepts_midi(&self) -> bool {
+        false
+    }
     
     /// 获取插件信息
     fn get_info(&self) -> &PluginMetadata;
     
-    /// 设置参数
+    /// 设置参数（归一化值）
+    ///
+    /// 旁通的插件和默认的 process_with_events 在音频线程调用它，实现不能阻塞、分配内存或写日志
     fn set_parameter(&mut self, id: u32, value: f64);
     
     /// 获取参数
@@ -84,9 +527,65 @@ pub trait AudioProcessor: Send {
     /// 获取所有参数
     fn get_all_parameters(&self) -> Vec<PluginParameter>;
     
+    /// 参数描述（范围、单位、步数、枚举值、标志），顺序与 get_all_parameters 一致
+    fn parameter_descriptors(&self) -> Vec<ParameterDescriptor>;
+    
+    /// 归一化值 -> 显示文本
+    /// 默认按参数描述计算，插件自己提供格式化时覆盖
+    fn value_to_text(&self, id: u32, value: f64) -> Option<String> {
+        self.parameter_descriptors()
+            .into_iter()
+            .find(|d| d.id == id)
+            .map(|d| d.value_to_text(value))
+    }
+    
+    /// 显示文本 -> 归一化值
+    fn text_to_value(&self, id: u32, text: &str) -> Option<f64> {
+        self.parameter_descriptors()
+            .into_iter()
+            .find(|d| d.id == id)
+            .and_then(|d| d.text_to_value(text))
+    }
+    
     /// 保存状态
     fn save_state(&self) -> PluginState;
     
     /// 加载状态
     fn load_state(&mut self, state: &PluginState);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_descriptor_log_and_steps() {
+        let freq = ParameterDescriptor::new(0, "Freq", 20.0, 20000.0, 1000.0)
+            .with_unit("Hz")
+            .with_logarithmic(true);
+        assert!((freq.to_plain(0.5) - 632.455).abs() < 0.01);
+        assert!((freq.to_normalized(freq.to_plain(0.3)) - 0.3).abs() < 1e-9);
+
+        let mode = ParameterDescriptor::new(1, "Mode", 0.0, 2.0, 0.0).with_value_names(vec![
+            (0.0, "Sine".to_string()),
+            (1.0, "Square".to_string()),
+            (2.0, "Saw".to_string()),
+        ]);
+        assert_eq!(mode.steps, 2);
+        assert_eq!(mode.to_plain(0.4), 1.0);
+        assert_eq!(mode.value_to_text(1.0), "Saw");
+        assert_eq!(mode.text_to_value("square"), Some(0.5));
+    }
+
+    #[test]
+    fn test_descriptor_text_roundtrip() {
+        let gain = ParameterDescriptor::new(0, "Gain", -24.0, 24.0, 0.0).with_unit("dB");
+        assert_eq!(gain.value_to_text(0.375), "-6.00 dB");
+        assert_eq!(gain.text_to_value("-6 dB"), Some(0.375));
+        assert_eq!(gain.text_to_value("abc"), None);
+
+        let bypass = ParameterDescriptor::toggle(1, "Bypass", false);
+        assert_eq!(bypass.value_to_text(1.0), "On");
+        assert_eq!(bypass.text_to_value("off"), Some(0.0));
+    }
+}
@@ -0,0 +1,1850 @@
+// VST3 插件宿主 (Linux)
+// 读取 bundle 的 moduleinfo.json，加载 Contents/<arch>-linux/*.so，
+// 通过 VST3 COM ABI 创建 IComponent / IAudioProcessor / IEditController
+
+use anyhow::{Result, Context};
+use base64::Engine as _;
+use libloading::os::unix::Library;
+use log::{info, warn, debug};
+use serde::Deserialize;
+use std::cell::Cell;
+use std::ffi::{c_char, c_void};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::audio::AudioBuffer;
+
+use super::events::{EventKind, EventList};
+use super::types::{PluginMetadata, AudioProcessor, PluginParameter, PluginState, PluginFormat, ChannelLayout, ParameterDescriptor, ParameterFlags};
+
+/// 激活插件时使用的采样率
+const DEFAULT_SAMPLE_RATE: f64 = 48000.0;
+
+/// 单次调用 process 的最大帧数
+const MAX_BLOCK_SIZE: usize = 4096;
+
+/// 每个处理块最多携带的参数队列数 / 每个队列的点数
+const MAX_PARAM_QUEUES: usize = 64;
+const MAX_QUEUE_POINTS: usize = 16;
+
+// ===== VST3 基础类型 =====
+
+type Tuid = [u8; 16];
+type TResult = i32;
+type TBool = u8;
+type String128 = [u16; 128];
+
+const K_RESULT_OK: TResult = 0;
+const K_RESULT_FALSE: TResult = 1;
+const K_INVALID_ARGUMENT: TResult = 2;
+const K_NOT_IMPLEMENTED: TResult = 3;
+const K_NO_INTERFACE: TResult = -1;
+
+const K_AUDIO: i32 = 0;
+const K_INPUT: i32 = 0;
+const K_OUTPUT: i32 = 1;
+const K_REALTIME: i32 = 0;
+const K_SAMPLE32: i32 = 0;
+
+const K_SPEAKER_MONO: u64 = 1 << 19;
+const K_SPEAKER_STEREO: u64 = 0b11;
+
+const K_PARAM_CAN_AUTOMATE: i32 = 1 << 0;
+const K_PARAM_IS_READ_ONLY: i32 = 1 << 1;
+const K_PARAM_IS_LIST: i32 = 1 << 3;
+const K_PARAM_IS_HIDDEN: i32 = 1 << 4;
+const K_PARAM_IS_BYPASS: i32 = 1 << 16;
+
+/// 列表参数最多列出多少个取值名称
+const MAX_LIST_VALUES: i32 = 128;
+
+const AUDIO_MODULE_CLASS: &str = "Audio Module Class";
+
+/// 非 Windows 平台上 (COM_COMPATIBLE = 0) 的 INLINE_UID 字节序
+const fn inline_uid(l1: u32, l2: u32, l3: u32, l4: u32) -> Tuid {
+    let (a, b, c, d) = (l1.to_be_bytes(), l2.to_be_bytes(), l3.to_be_bytes(), l4.to_be_bytes());
+    [
+        a[0], a[1], a[2], a[3], b[0], b[1], b[2], b[3],
+        c[0], c[1], c[2], c[3], d[0], d[1], d[2], d[3],
+    ]
+}
+
+const FUNKNOWN_IID: Tuid = inline_uid(0x00000000, 0x00000000, 0xC0000000, 0x00000046);
+const IPLUGIN_FACTORY_IID: Tuid = inline_uid(0x7A4D811C, 0x52114A1F, 0xAED9D2EE, 0x0B43BF9F);
+const ICOMPONENT_IID: Tuid = inline_uid(0xE831FF31, 0xF2D54301, 0x928EBBEE, 0x25697802);
+const IAUDIO_PROCESSOR_IID: Tuid = inline_uid(0x42043F99, 0xB7DA453C, 0xA569E79D, 0x9AAEC33D);
+const IEDIT_CONTROLLER_IID: Tuid = inline_uid(0xDCD7BBE3, 0x7742448D, 0xA874AACC, 0x979C759E);
+const ICONNECTION_POINT_IID: Tuid = inline_uid(0x70A4156F, 0x6E6E4026, 0x989148BF, 0xAA60D8D1);
+const IBSTREAM_IID: Tuid = inline_uid(0xC3BF6EA2, 0x30994752, 0x9B6BF990, 0x1EE33E9B);
+const IHOST_APPLICATION_IID: Tuid = inline_uid(0x58E595CC, 0xDB2D4969, 0x8B6AAF8C, 0x36A664E5);
+const ICOMPONENT_HANDLER_IID: Tuid = inline_uid(0x93A0BEA3, 0x0BD045DB, 0x8E890B0C, 0xC1E46AC6);
+const IPARAMETER_CHANGES_IID: Tuid = inline_uid(0xA4779663, 0x0BB64A56, 0xB44384A8, 0x466FEB9D);
+const IPARAM_VALUE_QUEUE_IID: Tuid = inline_uid(0x01263A18, 0xED074F6F, 0x98C9D356, 0x4686F9BA);
+
+#[repr(C)]
+struct PFactoryInfo {
+    vendor: [c_char; 64],
+    url: [c_char; 256],
+    email: [c_char; 128],
+    flags: i32,
+}
+
+#[repr(C)]
+struct PClassInfo {
+    cid: Tuid,
+    cardinality: i32,
+    category: [c_char; 32],
+    name: [c_char; 64],
+}
+
+#[repr(C)]
+struct BusInfo {
+    media_type: i32,
+    direction: i32,
+    channel_count: i32,
+    name: String128,
+    bus_type: i32,
+    flags: u32,
+}
+
+#[repr(C)]
+struct ParameterInfo {
+    id: u32,
+    title: String128,
+    short_title: String128,
+    units: String128,
+    step_count: i32,
+    default_normalized_value: f64,
+    unit_id: i32,
+    flags: i32,
+}
+
+#[repr(C)]
+struct ProcessSetup {
+    process_mode: i32,
+    symbolic_sample_size: i32,
+    max_samples_per_block: i32,
+    sample_rate: f64,
+}
+
+#[repr(C)]
+struct AudioBusBuffers {
+    num_channels: i32,
+    silence_flags: u64,
+    channel_buffers_32: *mut *mut f32,
+}
+
+#[repr(C)]
+struct ProcessData {
+    process_mode: i32,
+    symbolic_sample_size: i32,
+    num_samples: i32,
+    num_inputs: i32,
+    num_outputs: i32,
+    inputs: *mut AudioBusBuffers,
+    outputs: *mut AudioBusBuffers,
+    input_parameter_changes: *mut c_void,
+    output_parameter_changes: *mut c_void,
+    input_events: *mut c_void,
+    output_events: *mut c_void,
+    process_context: *mut c_void,
+}
+
+// ===== 接口虚表 =====
+
+#[repr(C)]
+struct FUnknownVtbl {
+    query_interface: unsafe extern "system" fn(this: *mut c_void, iid: *const Tuid, obj: *mut *mut c_void) -> TResult,
+    add_ref: unsafe extern "system" fn(this: *mut c_void) -> u32,
+    release: unsafe extern "system" fn(this: *mut c_void) -> u32,
+}
+
+#[repr(C)]
+struct IPluginFactoryVtbl {
+    unknown: FUnknownVtbl,
+    get_factory_info: unsafe extern "system" fn(this: *mut c_void, info: *mut PFactoryInfo) -> TResult,
+    count_classes: unsafe extern "system" fn(this: *mut c_void) -> i32,
+    get_class_info: unsafe extern "system" fn(this: *mut c_void, index: i32, info: *mut PClassInfo) -> TResult,
+    create_instance: unsafe extern "system" fn(this: *mut c_void, cid: *const Tuid, iid: *const Tuid, obj: *mut *mut c_void) -> TResult,
+}
+
+#[repr(C)]
+struct IComponentVtbl {
+    unknown: FUnknownVtbl,
+    initialize: unsafe extern "system" fn(this: *mut c_void, context: *mut c_void) -> TResult,
+    terminate: unsafe extern "system" fn(this: *mut c_void) -> TResult,
+    get_controller_class_id: unsafe extern "system" fn(this: *mut c_void, cid: *mut Tuid) -> TResult,
+    set_io_mode: unsafe extern "system" fn(this: *mut c_void, mode: i32) -> TResult,
+    get_bus_count: unsafe extern "system" fn(this: *mut c_void, media_type: i32, dir: i32) -> i32,
+    get_bus_info: unsafe extern "system" fn(this: *mut c_void, media_type: i32, dir: i32, index: i32, bus: *mut BusInfo) -> TResult,
+    get_routing_info: unsafe extern "system" fn(this: *mut c_void, in_info: *mut c_void, out_info: *mut c_void) -> TResult,
+    activate_bus: unsafe extern "system" fn(this: *mut c_void, media_type: i32, dir: i32, index: i32, state: TBool) -> TResult,
+    set_active: unsafe extern "system" fn(this: *mut c_void, state: TBool) -> TResult,
+    set_state: unsafe extern "system" fn(this: *mut c_void, state: *mut c_void) -> TResult,
+    get_state: unsafe extern "system" fn(this: *mut c_void, state: *mut c_void) -> TResult,
+}
+
+#[repr(C)]
+struct IAudioProcessorVtbl {
+    unknown: FUnknownVtbl,
+    set_bus_arrangements: unsafe extern "system" fn(this: *mut c_void, inputs: *mut u64, num_ins: i32, outputs: *mut u64, num_outs: i32) -> TResult,
+    get_bus_arrangement: unsafe extern "system" fn(this: *mut c_void, dir: i32, index: i32, arr: *mut u64) -> TResult,
+    can_process_sample_size: unsafe extern "system" fn(this: *mut c_void, symbolic_sample_size: i32) -> TResult,
+    get_latency_samples: unsafe extern "system" fn(this: *mut c_void) -> u32,
+    setup_processing: unsafe extern "system" fn(this: *mut c_void, setup: *mut ProcessSetup) -> TResult,
+    set_processing: unsafe extern "system" fn(this: *mut c_void, state: TBool) -> TResult,
+    process: unsafe extern "system" fn(this: *mut c_void, data: *mut ProcessData) -> TResult,
+    get_tail_samples: unsafe extern "system" fn(this: *mut c_void) -> u32,
+}
+
+#[repr(C)]
+struct IEditControllerVtbl {
+    unknown: FUnknownVtbl,
+    initialize: unsafe extern "system" fn(this: *mut c_void, context: *mut c_void) -> TResult,
+    terminate: unsafe extern "system" fn(this: *mut c_void) -> TResult,
+    set_component_state: unsafe extern "system" fn(this: *mut c_void, state: *mut c_void) -> TResult,
+    set_state: unsafe extern "system" fn(this: *mut c_void, state: *mut c_void) -> TResult,
+    get_state: unsafe extern "system" fn(this: *mut c_void, state: *mut c_void) -> TResult,
+    get_parameter_count: unsafe extern "system" fn(this: *mut c_void) -> i32,
+    get_parameter_info: unsafe extern "system" fn(this: *mut c_void, index: i32, info: *mut ParameterInfo) -> TResult,
+    get_param_string_by_value: unsafe extern "system" fn(this: *mut c_void, id: u32, value: f64, string: *mut String128) -> TResult,
+    get_param_value_by_string: unsafe extern "system" fn(this: *mut c_void, id: u32, string: *const u16, value: *mut f64) -> TResult,
+    normalized_param_to_plain: unsafe extern "system" fn(this: *mut c_void, id: u32, value: f64) -> f64,
+    plain_param_to_normalized: unsafe extern "system" fn(this: *mut c_void, id: u32, value: f64) -> f64,
+    get_param_normalized: unsafe extern "system" fn(this: *mut c_void, id: u32) -> f64,
+    set_param_normalized: unsafe extern "system" fn(this: *mut c_void, id: u32, value: f64) -> TResult,
+    set_component_handler: unsafe extern "system" fn(this: *mut c_void, handler: *mut c_void) -> TResult,
+    create_view: unsafe extern "system" fn(this: *mut c_void, name: *const c_char) -> *mut c_void,
+}
+
+#[repr(C)]
+struct IConnectionPointVtbl {
+    unknown: FUnknownVtbl,
+    connect: unsafe extern "system" fn(this: *mut c_void, other: *mut c_void) -> TResult,
+    disconnect: unsafe extern "system" fn(this: *mut c_void, other: *mut c_void) -> TResult,
+    notify: unsafe extern "system" fn(this: *mut c_void, message: *mut c_void) -> TResult,
+}
+
+#[repr(C)]
+struct IBStreamVtbl {
+    unknown: FUnknownVtbl,
+    read: unsafe extern "system" fn(this: *mut c_void, buffer: *mut c_void, num_bytes: i32, num_read: *mut i32) -> TResult,
+    write: unsafe extern "system" fn(this: *mut c_void, buffer: *mut c_void, num_bytes: i32, num_written: *mut i32) -> TResult,
+    seek: unsafe extern "system" fn(this: *mut c_void, pos: i64, mode: i32, result: *mut i64) -> TResult,
+    tell: unsafe extern "system" fn(this: *mut c_void, pos: *mut i64) -> TResult,
+}
+
+#[repr(C)]
+struct IHostApplicationVtbl {
+    unknown: FUnknownVtbl,
+    get_name: unsafe extern "system" fn(this: *mut c_void, name: *mut String128) -> TResult,
+    create_instance: unsafe extern "system" fn(this: *mut c_void, cid: *mut Tuid, iid: *mut Tuid, obj: *mut *mut c_void) -> TResult,
+}
+
+#[repr(C)]
+struct IComponentHandlerVtbl {
+    unknown: FUnknownVtbl,
+    begin_edit: unsafe extern "system" fn(this: *mut c_void, id: u32) -> TResult,
+    perform_edit: unsafe extern "system" fn(this: *mut c_void, id: u32, value: f64) -> TResult,
+    end_edit: unsafe extern "system" fn(this: *mut c_void, id: u32) -> TResult,
+    restart_component: unsafe extern "system" fn(this: *mut c_void, flags: i32) -> TResult,
+}
+
+#[repr(C)]
+struct IParameterChangesVtbl {
+    unknown: FUnknownVtbl,
+    get_parameter_count: unsafe extern "system" fn(this: *mut c_void) -> i32,
+    get_parameter_data: unsafe extern "system" fn(this: *mut c_void, index: i32) -> *mut c_void,
+    add_parameter_data: unsafe extern "system" fn(this: *mut c_void, id: *const u32, index: *mut i32) -> *mut c_void,
+}
+
+#[repr(C)]
+struct IParamValueQueueVtbl {
+    unknown: FUnknownVtbl,
+    get_parameter_id: unsafe extern "system" fn(this: *mut c_void) -> u32,
+    get_point_count: unsafe extern "system" fn(this: *mut c_void) -> i32,
+    get_point: unsafe extern "system" fn(this: *mut c_void, index: i32, offset: *mut i32, value: *mut f64) -> TResult,
+    add_point: unsafe extern "system" fn(this: *mut c_void, offset: i32, value: f64, index: *mut i32) -> TResult,
+}
+
+/// 具有固定 IID 的 COM 接口
+trait Interface {
+    const IID: Tuid;
+}
+
+impl Interface for IPluginFactoryVtbl { const IID: Tuid = IPLUGIN_FACTORY_IID; }
+impl Interface for IComponentVtbl { const IID: Tuid = ICOMPONENT_IID; }
+impl Interface for IAudioProcessorVtbl { const IID: Tuid = IAUDIO_PROCESSOR_IID; }
+impl Interface for IEditControllerVtbl { const IID: Tuid = IEDIT_CONTROLLER_IID; }
+impl Interface for IConnectionPointVtbl { const IID: Tuid = ICONNECTION_POINT_IID; }
+
+/// 插件侧 COM 对象的引用（drop 时调用 release）
+struct ComPtr<V> {
+    ptr: *mut *const V,
+}
+
+impl<V: Interface> ComPtr<V> {
+    /// 接管一个已经 addRef 过的指针
+    fn from_raw(ptr: *mut c_void) -> Option<Self> {
+        if ptr.is_null() {
+            None
+        } else {
+            Some(Self { ptr: ptr as *mut *const V })
+        }
+    }
+
+    fn vtbl(&self) -> &V {
+        unsafe { &**self.ptr }
+    }
+
+    fn as_ptr(&self) -> *mut c_void {
+        self.ptr as *mut c_void
+    }
+
+    fn unknown(&self) -> &FUnknownVtbl {
+        // 所有虚表的第一部分都是 FUnknown
+        unsafe { &*(*self.ptr as *const FUnknownVtbl) }
+    }
+
+    fn query<U: Interface>(&self) -> Option<ComPtr<U>> {
+        let mut obj = ptr::null_mut();
+        let result = unsafe { (self.unknown().query_interface)(self.as_ptr(), &U::IID, &mut obj) };
+        if result == K_RESULT_OK {
+            ComPtr::from_raw(obj)
+        } else {
+            None
+        }
+    }
+}
+
+impl<V> Drop for ComPtr<V> {
+    fn drop(&mut self) {
+        unsafe {
+            let unknown = &*(*self.ptr as *const FUnknownVtbl);
+            (unknown.release)(self.ptr as *mut c_void);
+        }
+    }
+}
+
+// ===== 宿主侧 COM 对象 =====
+// 这些对象由宿主持有，生命周期覆盖插件实例，引用计数只作记录
+
+unsafe extern "system" fn host_add_ref(this: *mut c_void) -> u32 {
+    let refs = &*((this as *const *const c_void).add(1) as *const AtomicU32);
+    refs.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+unsafe extern "system" fn host_release(this: *mut c_void) -> u32 {
+    let refs = &*((this as *const *const c_void).add(1) as *const AtomicU32);
+    refs.fetch_sub(1, Ordering::Relaxed).saturating_sub(1)
+}
+
+unsafe fn host_query(this: *mut c_void, iid: *const Tuid, own_iid: &Tuid, obj: *mut *mut c_void) -> TResult {
+    if *iid == FUNKNOWN_IID || *iid == *own_iid {
+        host_add_ref(this);
+        *obj = this;
+        K_RESULT_OK
+    } else {
+        *obj = ptr::null_mut();
+        K_NO_INTERFACE
+    }
+}
+
+/// 内存中的 IBStream
+#[repr(C)]
+struct MemoryStream {
+    vtbl: *const IBStreamVtbl,
+    refs: AtomicU32,
+    data: Vec<u8>,
+    pos: usize,
+}
+
+static MEMORY_STREAM_VTBL: IBStreamVtbl = IBStreamVtbl {
+    unknown: FUnknownVtbl {
+        query_interface: memory_stream_query,
+        add_ref: host_add_ref,
+        release: host_release,
+    },
+    read: memory_stream_read,
+    write: memory_stream_write,
+    seek: memory_stream_seek,
+    tell: memory_stream_tell,
+};
+
+impl MemoryStream {
+    fn new(data: Vec<u8>) -> Box<Self> {
+        Box::new(Self {
+            vtbl: &MEMORY_STREAM_VTBL,
+            refs: AtomicU32::new(1),
+            data,
+            pos: 0,
+        })
+    }
+
+    fn as_ptr(&mut self) -> *mut c_void {
+        self as *mut Self as *mut c_void
+    }
+}
+
+unsafe extern "system" fn memory_stream_query(this: *mut c_void, iid: *const Tuid, obj: *mut *mut c_void) -> TResult {
+    host_query(this, iid, &IBSTREAM_IID, obj)
+}
+
+unsafe extern "system" fn memory_stream_read(this: *mut c_void, buffer: *mut c_void, num_bytes: i32, num_read: *mut i32) -> TResult {
+    let stream = &mut *(this as *mut MemoryStream);
+    let available = stream.data.len().saturating_sub(stream.pos);
+    let len = available.min(num_bytes.max(0) as usize);
+    ptr::copy_nonoverlapping(stream.data.as_ptr().add(stream.pos), buffer as *mut u8, len);
+    stream.pos += len;
+    if !num_read.is_null() {
+        *num_read = len as i32;
+    }
+    if len == 0 && num_bytes > 0 { K_RESULT_FALSE } else { K_RESULT_OK }
+}
+
+unsafe extern "system" fn memory_stream_write(this: *mut c_void, buffer: *mut c_void, num_bytes: i32, num_written: *mut i32) -> TResult {
+    let stream = &mut *(this as *mut MemoryStream);
+    let len = num_bytes.max(0) as usize;
+    let bytes = std::slice::from_raw_parts(buffer as *const u8, len);
+    let end = stream.pos + len;
+    if stream.data.len() < end {
+        stream.data.resize(end, 0);
+    }
+    stream.data[stream.pos..end].copy_from_slice(bytes);
+    stream.pos = end;
+    if !num_written.is_null() {
+        *num_written = len as i32;
+    }
+    K_RESULT_OK
+}
+
+unsafe extern "system" fn memory_stream_seek(this: *mut c_void, pos: i64, mode: i32, result: *mut i64) -> TResult {
+    let stream = &mut *(this as *mut MemoryStream);
+    let base = match mode {
+        0 => 0,
+        1 => stream.pos as i64,
+        2 => stream.data.len() as i64,
+        _ => return K_INVALID_ARGUMENT,
+    };
+    let new_pos = base + pos;
+    if new_pos < 0 {
+        return K_INVALID_ARGUMENT;
+    }
+    stream.pos = new_pos as usize;
+    if !result.is_null() {
+        *result = new_pos;
+    }
+    K_RESULT_OK
+}
+
+unsafe extern "system" fn memory_stream_tell(this: *mut c_void, pos: *mut i64) -> TResult {
+    let stream = &*(this as *const MemoryStream);
+    if pos.is_null() {
+        return K_INVALID_ARGUMENT;
+    }
+    *pos = stream.pos as i64;
+    K_RESULT_OK
+}
+
+/// IHostApplication
+#[repr(C)]
+struct HostApplication {
+    vtbl: *const IHostApplicationVtbl,
+    refs: AtomicU32,
+}
+
+static HOST_APPLICATION_VTBL: IHostApplicationVtbl = IHostApplicationVtbl {
+    unknown: FUnknownVtbl {
+        query_interface: host_application_query,
+        add_ref: host_add_ref,
+        release: host_release,
+    },
+    get_name: host_application_get_name,
+    create_instance: host_application_create_instance,
+};
+
+unsafe extern "system" fn host_application_query(this: *mut c_void, iid: *const Tuid, obj: *mut *mut c_void) -> TResult {
+    host_query(this, iid, &IHOST_APPLICATION_IID, obj)
+}
+
+unsafe extern "system" fn host_application_get_name(_this: *mut c_void, name: *mut String128) -> TResult {
+    let name = &mut *name;
+    name.fill(0);
+    for (dst, src) in name.iter_mut().zip("Plugin Loader".encode_utf16()) {
+        *dst = src;
+    }
+    K_RESULT_OK
+}
+
+unsafe extern "system" fn host_application_create_instance(
+    _this: *mut c_void,
+    _cid: *mut Tuid,
+    _iid: *mut Tuid,
+    obj: *mut *mut c_void,
+) -> TResult {
+    // 暂不提供 IMessage / IAttributeList
+    *obj = ptr::null_mut();
+    K_NOT_IMPLEMENTED
+}
+
+/// IComponentHandler：收集控制器（插件界面）发出的参数修改
+#[repr(C)]
+struct ComponentHandler {
+    vtbl: *const IComponentHandlerVtbl,
+    refs: AtomicU32,
+    edits: Mutex<Vec<(u32, f64)>>,
+}
+
+static COMPONENT_HANDLER_VTBL: IComponentHandlerVtbl = IComponentHandlerVtbl {
+    unknown: FUnknownVtbl {
+        query_interface: component_handler_query,
+        add_ref: host_add_ref,
+        release: host_release,
+    },
+    begin_edit: component_handler_begin_edit,
+    perform_edit: component_handler_perform_edit,
+    end_edit: component_handler_begin_edit,
+    restart_component: component_handler_restart,
+};
+
+unsafe extern "system" fn component_handler_query(this: *mut c_void, iid: *const Tuid, obj: *mut *mut c_void) -> TResult {
+    host_query(this, iid, &ICOMPONENT_HANDLER_IID, obj)
+}
+
+unsafe extern "system" fn component_handler_begin_edit(_this: *mut c_void, _id: u32) -> TResult {
+    K_RESULT_OK
+}
+
+unsafe extern "system" fn component_handler_perform_edit(this: *mut c_void, id: u32, value: f64) -> TResult {
+    let handler = &*(this as *const ComponentHandler);
+    if let Ok(mut edits) = handler.edits.lock() {
+        edits.push((id, value));
+    }
+    K_RESULT_OK
+}
+
+unsafe extern "system" fn component_handler_restart(_this: *mut c_void, flags: i32) -> TResult {
+    debug!("VST3 插件请求重启: flags = {:#x}", flags);
+    K_RESULT_OK
+}
+
+/// IParamValueQueue（预分配，不在音频线程中分配内存）
+#[repr(C)]
+struct ParamValueQueue {
+    vtbl: *const IParamValueQueueVtbl,
+    refs: AtomicU32,
+    id: u32,
+    points: Vec<(i32, f64)>,
+}
+
+static PARAM_VALUE_QUEUE_VTBL: IParamValueQueueVtbl = IParamValueQueueVtbl {
+    unknown: FUnknownVtbl {
+        query_interface: param_value_queue_query,
+        add_ref: host_add_ref,
+        release: host_release,
+    },
+    get_parameter_id: param_value_queue_get_id,
+    get_point_count: param_value_queue_get_point_count,
+    get_point: param_value_queue_get_point,
+    add_point: param_value_queue_add_point,
+};
+
+impl ParamValueQueue {
+    fn add(&mut self, offset: i32, value: f64) -> usize {
+        if self.points.len() < MAX_QUEUE_POINTS {
+            self.points.push((offset, value));
+        } else if let Some(last) = self.points.last_mut() {
+            // 队列已满时覆盖最后一个点
+            *last = (offset, value);
+        }
+        self.points.len() - 1
+    }
+}
+
+unsafe extern "system" fn param_value_queue_query(this: *mut c_void, iid: *const Tuid, obj: *mut *mut c_void) -> TResult {
+    host_query(this, iid, &IPARAM_VALUE_QUEUE_IID, obj)
+}
+
+unsafe extern "system" fn param_value_queue_get_id(this: *mut c_void) -> u32 {
+    (*(this as *const ParamValueQueue)).id
+}
+
+unsafe extern "system" fn param_value_queue_get_point_count(this: *mut c_void) -> i32 {
+    (*(this as *const ParamValueQueue)).points.len() as i32
+}
+
+unsafe extern "system" fn param_value_queue_get_point(this: *mut c_void, index: i32, offset: *mut i32, value: *mut f64) -> TResult {
+    let queue = &*(this as *const ParamValueQueue);
+    match queue.points.get(index as usize) {
+        Some(&(o, v)) => {
+            *offset = o;
+            *value = v;
+            K_RESULT_OK
+        }
+        None => K_INVALID_ARGUMENT,
+    }
+}
+
+unsafe extern "system" fn param_value_queue_add_point(this: *mut c_void, offset: i32, value: f64, index: *mut i32) -> TResult {
+    let queue = &mut *(this as *mut ParamValueQueue);
+    let added = queue.add(offset, value);
+    if !index.is_null() {
+        *index = added as i32;
+    }
+    K_RESULT_OK
+}
+
+/// IParameterChanges（预分配固定数量的队列）
+#[repr(C)]
+struct ParameterChanges {
+    vtbl: *const IParameterChangesVtbl,
+    refs: AtomicU32,
+    queues: Vec<ParamValueQueue>,
+    used: usize,
+}
+
+static PARAMETER_CHANGES_VTBL: IParameterChangesVtbl = IParameterChangesVtbl {
+    unknown: FUnknownVtbl {
+        query_interface: parameter_changes_query,
+        add_ref: host_add_ref,
+        release: host_release,
+    },
+    get_parameter_count: parameter_changes_get_count,
+    get_parameter_data: parameter_changes_get_data,
+    add_parameter_data: parameter_changes_add_data,
+};
+
+impl ParameterChanges {
+    fn new() -> Box<Self> {
+        let queues = (0..MAX_PARAM_QUEUES)
+            .map(|_| ParamValueQueue {
+                vtbl: &PARAM_VALUE_QUEUE_VTBL,
+                refs: AtomicU32::new(1),
+                id: 0,
+                points: Vec::with_capacity(MAX_QUEUE_POINTS),
+            })
+            .collect();
+
+        Box::new(Self {
+            vtbl: &PARAMETER_CHANGES_VTBL,
+            refs: AtomicU32::new(1),
+            queues,
+            used: 0,
+        })
+    }
+
+    /// 查找或分配参数对应的队列
+    fn queue_for(&mut self, id: u32) -> Option<(usize, &mut ParamValueQueue)> {
+        if let Some(index) = self.queues[..self.used].iter().position(|q| q.id == id) {
+            return Some((index, &mut self.queues[index]));
+        }
+        if self.used == self.queues.len() {
+            return None;
+        }
+        let index = self.used;
+        self.used += 1;
+        let queue = &mut self.queues[index];
+        queue.id = id;
+        queue.points.clear();
+        Some((index, queue))
+    }
+
+    /// 在音频线程调用，队列已满时丢弃参数（不写日志）
+    fn add(&mut self, id: u32, offset: i32, value: f64) {
+        if let Some((_, queue)) = self.queue_for(id) {
+            queue.add(offset, value);
+        }
+    }
+
+    /// 加入 [start, end) 内的参数事件，偏移改为相对 start 并限制在块内
+    /// VST3 的音符/MIDI 走 IEventList，这里只翻译参数事件
+    fn add_events(&mut self, events: &EventList, start: u32, end: u32, last_frame: u32) {
+        for event in events.range(start, end) {
+            if let EventKind::Parameter { id, value } = event.kind {
+                let offset = (event.offset - start).min(last_frame);
+                self.add(id, offset as i32, value.clamp(0.0, 1.0));
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.used = 0;
+    }
+
+    fn as_ptr(&mut self) -> *mut c_void {
+        self as *mut Self as *mut c_void
+    }
+}
+
+unsafe extern "system" fn parameter_changes_query(this: *mut c_void, iid: *const Tuid, obj: *mut *mut c_void) -> TResult {
+    host_query(this, iid, &IPARAMETER_CHANGES_IID, obj)
+}
+
+unsafe extern "system" fn parameter_changes_get_count(this: *mut c_void) -> i32 {
+    (*(this as *const ParameterChanges)).used as i32
+}
+
+unsafe extern "system" fn parameter_changes_get_data(this: *mut c_void, index: i32) -> *mut c_void {
+    let changes = &mut *(this as *mut ParameterChanges);
+    if index < 0 || index as usize >= changes.used {
+        return ptr::null_mut();
+    }
+    &mut changes.queues[index as usize] as *mut ParamValueQueue as *mut c_void
+}
+
+unsafe extern "system" fn parameter_changes_add_data(this: *mut c_void, id: *const u32, index: *mut i32) -> *mut c_void {
+    let changes = &mut *(this as *mut ParameterChanges);
+    match changes.queue_for(*id) {
+        Some((i, queue)) => {
+            if !index.is_null() {
+                *index = i as i32;
+            }
+            queue as *mut ParamValueQueue as *mut c_void
+        }
+        None => ptr::null_mut(),
+    }
+}
+
+// ===== moduleinfo.json =====
+
+/// moduleinfo.json 中的类描述
+#[derive(Debug, Deserialize)]
+struct ModuleInfoClass {
+    #[serde(rename = "CID")]
+    cid: String,
+    #[serde(rename = "Category", default)]
+    category: String,
+    #[serde(rename = "Name", default)]
+    name: String,
+    #[serde(rename = "Vendor", default)]
+    vendor: String,
+    #[serde(rename = "Version", default)]
+    version: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModuleInfoFactory {
+    #[serde(rename = "Vendor", default)]
+    vendor: String,
+}
+
+/// moduleinfo.json（SDK 3.7.5+ 生成）
+#[derive(Debug, Deserialize)]
+struct ModuleInfo {
+    #[serde(rename = "Factory Info")]
+    factory_info: Option<ModuleInfoFactory>,
+    #[serde(rename = "Classes", default)]
+    classes: Vec<ModuleInfoClass>,
+}
+
+/// moduleinfo.json 允许注释和尾随逗号，转换为标准 JSON
+fn strip_json5(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    let mut in_string = false;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if in_string {
+            out.push(c);
+            if c == '\\' {
+                if let Some(&next) = chars.get(i + 1) {
+                    out.push(next);
+                    i += 1;
+                }
+            } else if c == '"' {
+                in_string = false;
+            }
+            i += 1;
+            continue;
+        }
+
+        match c {
+            '"' => {
+                in_string = true;
+                out.push(c);
+            }
+            '/' if chars.get(i + 1) == Some(&'/') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                i += 2;
+                while i + 1 < chars.len() && !(chars[i] == '*' && chars[i + 1] == '/') {
+                    i += 1;
+                }
+                i += 2;
+                continue;
+            }
+            ',' => {
+                // 尾随逗号：下一个非空白字符是 } 或 ]
+                let next = chars[i + 1..].iter().find(|c| !c.is_whitespace());
+                if !matches!(next, Some('}') | Some(']')) {
+                    out.push(c);
+                }
+            }
+            _ => out.push(c),
+        }
+        i += 1;
+    }
+
+    out
+}
+
+fn parse_module_info(text: &str) -> Result<ModuleInfo> {
+    serde_json::from_str(&strip_json5(text)).context("解析 moduleinfo.json 失败")
+}
+
+fn cid_to_hex(cid: &Tuid) -> String {
+    cid.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+fn hex_to_cid(hex: &str) -> Option<Tuid> {
+    if hex.len() != 32 {
+        return None;
+    }
+    let mut cid = [0u8; 16];
+    for (i, byte) in cid.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    Some(cid)
+}
+
+fn c_chars_to_string(chars: &[c_char]) -> String {
+    let bytes: Vec<u8> = chars.iter()
+        .take_while(|&&c| c != 0)
+        .map(|&c| c as u8)
+        .collect();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+fn string128_to_string(chars: &String128) -> String {
+    let len = chars.iter().position(|&c| c == 0).unwrap_or(chars.len());
+    String::from_utf16_lossy(&chars[..len])
+}
+
+/// bundle 中当前平台的共享库路径
+fn bundle_binary(bundle: &Path) -> Result<PathBuf> {
+    // 旧版 VST3 在 Linux 上可能直接是一个 .so 文件
+    if bundle.is_file() {
+        return Ok(bundle.to_path_buf());
+    }
+
+    let arch_dir = bundle.join("Contents").join(format!("{}-linux", std::env::consts::ARCH));
+    let stem = bundle.file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or_else(|| anyhow::anyhow!("无效的插件名称"))?;
+
+    let standard = arch_dir.join(format!("{}.so", stem));
+    if standard.exists() {
+        return Ok(standard);
+    }
+
+    // 二进制文件名与 bundle 名不一致时取第一个 .so
+    fs::read_dir(&arch_dir)
+        .context(format!("找不到 {:?}", arch_dir))?
+        .flatten()
+        .map(|e| e.path())
+        .find(|p| p.extension().and_then(|s| s.to_str()) == Some("so"))
+        .ok_or_else(|| anyhow::anyhow!("找不到插件二进制文件"))
+}
+
+fn class_metadata(bundle: &Path, cid: &str, name: &str, vendor: &str, version: &str) -> PluginMetadata {
+    PluginMetadata {
+        id: format!("vst3:{}", cid),
+        name: name.to_string(),
+        vendor: vendor.to_string(),
+        version: version.to_string(),
+        path: bundle.to_path_buf(),
+        format: PluginFormat::Vst3,
+        num_inputs: 2,
+        num_outputs: 2,
+    }
+}
+
+/// 列出 bundle 中的所有音频效果类
+/// 优先读取 moduleinfo.json，避免加载插件代码
+pub fn scan_vst3_bundle(bundle: &Path) -> Result<Vec<PluginMetadata>> {
+    let module_info = bundle.join("Contents/Resources/moduleinfo.json");
+    if module_info.exists() {
+        let text = fs::read_to_string(&module_info)
+            .context(format!("读取文件失败: {:?}", module_info))?;
+        let info = parse_module_info(&text)?;
+        let factory_vendor = info.factory_info.map(|f| f.vendor).unwrap_or_default();
+
+        return Ok(info.classes
+            .iter()
+            .filter(|c| c.category == AUDIO_MODULE_CLASS)
+            .map(|c| {
+                let vendor = if c.vendor.is_empty() { &factory_vendor } else { &c.vendor };
+                class_metadata(bundle, &c.cid.to_uppercase(), &c.name, vendor, &c.version)
+            })
+            .collect());
+    }
+
+    let module = Vst3Module::open(bundle)?;
+    Ok(module.audio_classes()
+        .iter()
+        .map(|(cid, name)| class_metadata(bundle, &cid_to_hex(cid), name, &module.vendor, ""))
+        .collect())
+}
+
+// ===== 模块 =====
+
+type ModuleEntryFn = unsafe extern "C" fn(handle: *mut c_void) -> bool;
+type ModuleExitFn = unsafe extern "C" fn() -> bool;
+type GetPluginFactoryFn = unsafe extern "system" fn() -> *mut c_void;
+
+/// 已加载的 VST3 模块（同一 bundle 的多个实例共享）
+struct Vst3Module {
+    factory: Option<ComPtr<IPluginFactoryVtbl>>,
+    vendor: String,
+    library: Option<Library>,
+}
+
+// 工厂接口要求线程安全
+unsafe impl Send for Vst3Module {}
+unsafe impl Sync for Vst3Module {}
+
+impl Vst3Module {
+    fn open(bundle: &Path) -> Result<Arc<Self>> {
+        let binary = bundle_binary(bundle)?;
+        debug!("加载 VST3 模块: {:?}", binary);
+
+        let library = unsafe { Library::new(&binary) }
+            .context(format!("加载动态库失败: {:?}", binary))?;
+
+        unsafe {
+            let get_factory = *library.get::<GetPluginFactoryFn>(b"GetPluginFactory\0")
+                .context("找不到 GetPluginFactory 符号")?;
+
+            // Linux 上必须在获取工厂前调用 ModuleEntry
+            let raw = library.into_raw();
+            let library = Library::from_raw(raw);
+            if let Ok(entry) = library.get::<ModuleEntryFn>(b"ModuleEntry\0") {
+                if !entry(raw) {
+                    return Err(anyhow::anyhow!("ModuleEntry 失败"));
+                }
+            }
+
+            let factory = ComPtr::<IPluginFactoryVtbl>::from_raw(get_factory())
+                .ok_or_else(|| anyhow::anyhow!("GetPluginFactory 返回空指针"))?;
+
+            let mut factory_info: PFactoryInfo = std::mem::zeroed();
+            let vendor = if (factory.vtbl().get_factory_info)(factory.as_ptr(), &mut factory_info) == K_RESULT_OK {
+                c_chars_to_string(&factory_info.vendor)
+            } else {
+                String::new()
+            };
+
+            Ok(Arc::new(Self {
+                factory: Some(factory),
+                vendor,
+                library: Some(library),
+            }))
+        }
+    }
+
+    fn factory(&self) -> &ComPtr<IPluginFactoryVtbl> {
+        self.factory.as_ref().expect("工厂在模块卸载前有效")
+    }
+
+    /// 所有 "Audio Module Class" 类 (CID, 名称)
+    fn audio_classes(&self) -> Vec<(Tuid, String)> {
+        let factory = self.factory();
+        let count = unsafe { (factory.vtbl().count_classes)(factory.as_ptr()) };
+
+        (0..count)
+            .filter_map(|index| {
+                let mut class_info: PClassInfo = unsafe { std::mem::zeroed() };
+                let result = unsafe { (factory.vtbl().get_class_info)(factory.as_ptr(), index, &mut class_info) };
+                (result == K_RESULT_OK && c_chars_to_string(&class_info.category) == AUDIO_MODULE_CLASS)
+                    .then(|| (class_info.cid, c_chars_to_string(&class_info.name)))
+            })
+            .collect()
+    }
+
+    fn create<V: Interface>(&self, cid: &Tuid) -> Option<ComPtr<V>> {
+        let factory = self.factory();
+        let mut obj = ptr::null_mut();
+        let result = unsafe { (factory.vtbl().create_instance)(factory.as_ptr(), cid, &V::IID, &mut obj) };
+        if result == K_RESULT_OK {
+            ComPtr::from_raw(obj)
+        } else {
+            None
+        }
+    }
+}
+
+impl Drop for Vst3Module {
+    fn drop(&mut self) {
+        // 先释放工厂，再调用 ModuleExit，最后卸载动态库
+        self.factory = None;
+        if let Some(library) = self.library.take() {
+            unsafe {
+                if let Ok(exit) = library.get::<ModuleExitFn>(b"ModuleExit\0") {
+                    exit();
+                }
+            }
+            drop(library);
+        }
+    }
+}
+
+// ===== 插件实例 =====
+
+/// 参数信息缓存
+struct Vst3ParamInfo {
+    id: u32,
+    name: String,
+    units: String,
+    step_count: i32,
+    default_normalized: f64,
+    flags: i32,
+}
+
+/// VST3 插件包装器
+pub struct Vst3Plugin {
+    metadata: PluginMetadata,
+    component: ComPtr<IComponentVtbl>,
+    processor: ComPtr<IAudioProcessorVtbl>,
+    controller: Option<ComPtr<IEditControllerVtbl>>,
+
+    /// 控制器是否是独立对象（需要单独 terminate）
+    separate_controller: bool,
+    connections: Option<(ComPtr<IConnectionPointVtbl>, ComPtr<IConnectionPointVtbl>)>,
+
+    params: Vec<Vst3ParamInfo>,
+    param_changes: Box<ParameterChanges>,
+    /// 参数的当前值（归一化，与 params 一一对应）
+    /// 处理路径只改这里和 param_changes，控制器在控制线程按它同步（sync_controller）
+    values: Vec<f64>,
+    /// values 有控制器还不知道的修改
+    controller_stale: Cell<bool>,
+
+    input_channels: u32,
+    output_channels: u32,
+    input_buffers: [Vec<f32>; 2],
+    output_buffers: [Vec<f32>; 2],
+
+    sample_rate: f64,
+    max_block_size: usize,
+    active: bool,
+    processing: bool,
+
+    // 以下字段必须比插件实例活得更久
+    handler: Box<ComponentHandler>,
+    host: Box<HostApplication>,
+    _module: Arc<Vst3Module>,
+}
+
+// 插件实例只会被持有者独占访问
+unsafe impl Send for Vst3Plugin {}
+
+impl Vst3Plugin {
+    /// 从 bundle 加载插件
+    /// class_id 为 None 时加载 bundle 中的第一个音频效果类
+    pub fn load(bundle: &Path, class_id: Option<&str>) -> Result<Self> {
+        info!("加载 VST3 插件: {:?}", bundle);
+
+        let module = Vst3Module::open(bundle)?;
+        let classes = module.audio_classes();
+        let (cid, name) = match class_id {
+            Some(hex) => {
+                let cid = hex_to_cid(hex).ok_or_else(|| anyhow::anyhow!("无效的类 ID: {}", hex))?;
+                classes.into_iter()
+                    .find(|(c, _)| *c == cid)
+                    .ok_or_else(|| anyhow::anyhow!("找不到插件类: {}", hex))?
+            }
+            None => classes.into_iter()
+                .next()
+                .ok_or_else(|| anyhow::anyhow!("bundle 中没有音频效果类"))?,
+        };
+
+        let metadata = class_metadata(bundle, &cid_to_hex(&cid), &name, &module.vendor, "");
+        Self::instantiate(module, &cid, metadata)
+    }
+
+    /// 从扫描信息加载插件
+    pub fn from_metadata(metadata: &PluginMetadata) -> Result<Self> {
+        let cid = metadata.id.strip_prefix("vst3:").unwrap_or(&metadata.id);
+        let mut plugin = Self::load(&metadata.path, Some(cid))?;
+        plugin.metadata.version = metadata.version.clone();
+        Ok(plugin)
+    }
+
+    fn instantiate(module: Arc<Vst3Module>, cid: &Tuid, metadata: PluginMetadata) -> Result<Self> {
+        let mut host = Box::new(HostApplication {
+            vtbl: &HOST_APPLICATION_VTBL,
+            refs: AtomicU32::new(1),
+        });
+        let host_ptr = &mut *host as *mut HostApplication as *mut c_void;
+
+        let component = module.create::<IComponentVtbl>(cid)
+            .ok_or_else(|| anyhow::anyhow!("创建 IComponent 失败: {}", metadata.name))?;
+        if unsafe { (component.vtbl().initialize)(component.as_ptr(), host_ptr) } != K_RESULT_OK {
+            return Err(anyhow::anyhow!("IComponent 初始化失败: {}", metadata.name));
+        }
+
+        let Some(processor) = component.query::<IAudioProcessorVtbl>() else {
+            unsafe { (component.vtbl().terminate)(component.as_ptr()) };
+            return Err(anyhow::anyhow!("插件未实现 IAudioProcessor: {}", metadata.name));
+        };
+
+        // 控制器可能与组件是同一个对象，也可能是独立的类
+        let mut separate_controller = false;
+        let controller = match component.query::<IEditControllerVtbl>() {
+            Some(controller) => Some(controller),
+            None => {
+                let mut controller_cid = [0u8; 16];
+                let has_cid = unsafe { (component.vtbl().get_controller_class_id)(component.as_ptr(), &mut controller_cid) } == K_RESULT_OK;
+                let controller = has_cid.then(|| module.create::<IEditControllerVtbl>(&controller_cid)).flatten();
+                match controller {
+                    Some(controller) if unsafe { (controller.vtbl().initialize)(controller.as_ptr(), host_ptr) } == K_RESULT_OK => {
+                        separate_controller = true;
+                        Some(controller)
+                    }
+                    _ => {
+                        warn!("VST3 插件没有可用的 IEditController: {}", metadata.name);
+                        None
+                    }
+                }
+            }
+        };
+
+        let mut instance = Self {
+            metadata,
+            component,
+            processor,
+            controller,
+            separate_controller,
+            connections: None,
+            params: Vec::new(),
+            param_changes: ParameterChanges::new(),
+            values: Vec::new(),
+            controller_stale: Cell::new(false),
+            input_channels: 2,
+            output_channels: 2,
+            input_buffers: [vec![0.0; MAX_BLOCK_SIZE], vec![0.0; MAX_BLOCK_SIZE]],
+            output_buffers: [vec![0.0; MAX_BLOCK_SIZE], vec![0.0; MAX_BLOCK_SIZE]],
+            sample_rate: DEFAULT_SAMPLE_RATE,
+            max_block_size: MAX_BLOCK_SIZE,
+            active: false,
+            processing: false,
+            handler: Box::new(ComponentHandler {
+                vtbl: &COMPONENT_HANDLER_VTBL,
+                refs: AtomicU32::new(1),
+                edits: Mutex::new(Vec::new()),
+            }),
+            host,
+            _module: module,
+        };
+
+        instance.connect_controller();
+        instance.setup_buses();
+        instance.query_parameters();
+        instance.activate()?;
+
+        info!("VST3 插件已加载: {} ({} 个参数)", instance.metadata.name, instance.params.len());
+        Ok(instance)
+    }
+
+    /// 按当前采样率和块大小调用 setupProcessing，然后激活组件
+    fn activate(&mut self) -> Result<()> {
+        if self.active {
+            return Ok(());
+        }
+
+        let mut setup = ProcessSetup {
+            process_mode: K_REALTIME,
+            symbolic_sample_size: K_SAMPLE32,
+            max_samples_per_block: self.max_block_size as i32,
+            sample_rate: self.sample_rate,
+        };
+        if unsafe { (self.processor.vtbl().setup_processing)(self.processor.as_ptr(), &mut setup) } != K_RESULT_OK {
+            return Err(anyhow::anyhow!("setupProcessing 失败: {}", self.metadata.name));
+        }
+
+        if unsafe { (self.component.vtbl().set_active)(self.component.as_ptr(), 1) } != K_RESULT_OK {
+            return Err(anyhow::anyhow!("插件激活失败: {}", self.metadata.name));
+        }
+        self.active = true;
+        Ok(())
+    }
+
+    /// 停止处理并停用组件（setupProcessing 只能在停用状态下调用）
+    fn deactivate(&mut self) {
+        unsafe {
+            if self.processing {
+                (self.processor.vtbl().set_processing)(self.processor.as_ptr(), 0);
+                self.processing = false;
+            }
+            if self.active {
+                (self.component.vtbl().set_active)(self.component.as_ptr(), 0);
+                self.active = false;
+            }
+        }
+    }
+
+    /// 连接组件和控制器，并同步组件状态
+    fn connect_controller(&mut self) {
+        let Some(controller) = &self.controller else {
+            return;
+        };
+
+        let handler_ptr = &mut *self.handler as *mut ComponentHandler as *mut c_void;
+        unsafe { (controller.vtbl().set_component_handler)(controller.as_ptr(), handler_ptr) };
+
+        if self.separate_controller {
+            if let (Some(a), Some(b)) = (
+                self.component.query::<IConnectionPointVtbl>(),
+                controller.query::<IConnectionPointVtbl>(),
+            ) {
+                unsafe {
+                    (a.vtbl().connect)(a.as_ptr(), b.as_ptr());
+                    (b.vtbl().connect)(b.as_ptr(), a.as_ptr());
+                }
+                self.connections = Some((a, b));
+            }
+        }
+
+        // 让控制器的参数显示与组件一致
+        let state = self.component_state();
+        if !state.is_empty() {
+            let mut stream = MemoryStream::new(state);
+            unsafe { (controller.vtbl().set_component_state)(controller.as_ptr(), stream.as_ptr()) };
+        }
+    }
+
+    /// 协商主总线的声道布局并激活
+    fn setup_buses(&mut self) {
+        let component = &self.component;
+        let main_bus_channels = |dir: i32| -> Option<u32> {
+            let count = unsafe { (component.vtbl().get_bus_count)(component.as_ptr(), K_AUDIO, dir) };
+            if count <= 0 {
+                return None;
+            }
+            let mut bus: BusInfo = unsafe { std::mem::zeroed() };
+            if unsafe { (component.vtbl().get_bus_info)(component.as_ptr(), K_AUDIO, dir, 0, &mut bus) } == K_RESULT_OK {
+                Some(bus.channel_count.clamp(1, 2) as u32)
+            } else {
+                Some(2)
+            }
+        };
+
+        let input = main_bus_channels(K_INPUT);
+        let output = main_bus_channels(K_OUTPUT);
+
+        // 尝试立体声，插件拒绝时保持插件默认布局
+        let arrangement = |channels: u32| if channels == 1 { K_SPEAKER_MONO } else { K_SPEAKER_STEREO };
+        let mut inputs: Vec<u64> = input.map(|_| K_SPEAKER_STEREO).into_iter().collect();
+        let mut outputs: Vec<u64> = output.map(|_| K_SPEAKER_STEREO).into_iter().collect();
+        let accepted = unsafe {
+            (self.processor.vtbl().set_bus_arrangements)(
+                self.processor.as_ptr(),
+                inputs.as_mut_ptr(),
+                inputs.len() as i32,
+                outputs.as_mut_ptr(),
+                outputs.len() as i32,
+            )
+        } == K_RESULT_OK;
+
+        if accepted {
+            self.input_channels = input.map_or(0, |_| 2);
+            self.output_channels = output.map_or(0, |_| 2);
+        } else {
+            debug!("插件不接受立体声布局，使用默认布局 ({:?} -> {:?})",
+                input.map(arrangement), output.map(arrangement));
+            self.input_channels = input.unwrap_or(0);
+            self.output_channels = output.unwrap_or(0);
+        }
+
+        unsafe {
+            if input.is_some() {
+                (self.component.vtbl().activate_bus)(self.component.as_ptr(), K_AUDIO, K_INPUT, 0, 1);
+            }
+            if output.is_some() {
+                (self.component.vtbl().activate_bus)(self.component.as_ptr(), K_AUDIO, K_OUTPUT, 0, 1);
+            }
+        }
+
+        self.metadata.num_inputs = self.input_channels;
+        self.metadata.num_outputs = self.output_channels;
+    }
+
+    fn query_parameters(&mut self) {
+        let Some(controller) = &self.controller else {
+            return;
+        };
+
+        let count = unsafe { (controller.vtbl().get_parameter_count)(controller.as_ptr()) };
+        for index in 0..count {
+            let mut param_info: ParameterInfo = unsafe { std::mem::zeroed() };
+            if unsafe { (controller.vtbl().get_parameter_info)(controller.as_ptr(), index, &mut param_info) } == K_RESULT_OK {
+                self.params.push(Vst3ParamInfo {
+                    id: param_info.id,
+                    name: string128_to_string(&param_info.title),
+                    units: string128_to_string(&param_info.units),
+                    step_count: param_info.step_count,
+                    default_normalized: param_info.default_normalized_value,
+                    flags: param_info.flags,
+                });
+            }
+        }
+        self.read_controller_values();
+    }
+
+    /// 从控制器读取所有参数的当前值（控制线程）
+    fn read_controller_values(&mut self) {
+        let Some(controller) = &self.controller else {
+            return;
+        };
+        self.values = self.params
+            .iter()
+            .map(|param| unsafe { (controller.vtbl().get_param_normalized)(controller.as_ptr(), param.id) })
+            .collect();
+        self.controller_stale.set(false);
+    }
+
+    /// 把处理路径记下的参数值同步给控制器（控制线程，音频线程不碰控制器）
+    fn sync_controller(&self) {
+        let Some(controller) = &self.controller else {
+            return;
+        };
+        if !self.controller_stale.replace(false) {
+            return;
+        }
+        for (param, &value) in self.params.iter().zip(&self.values) {
+            unsafe { (controller.vtbl().set_param_normalized)(controller.as_ptr(), param.id, value) };
+        }
+    }
+
+    fn param_index(&self, id: u32) -> Option<usize> {
+        self.params.iter().position(|param| param.id == id)
+    }
+
+    /// 记下参数的新值（可能在音频线程调用）
+    fn record_value(&mut self, id: u32, value: f64) {
+        if let Some(index) = self.param_index(id) {
+            self.values[index] = value;
+            self.controller_stale.set(true);
+        }
+    }
+
+    fn component_state(&self) -> Vec<u8> {
+        let mut stream = MemoryStream::new(Vec::new());
+        let result = unsafe { (self.component.vtbl().get_state)(self.component.as_ptr(), stream.as_ptr()) };
+        if result == K_RESULT_OK {
+            std::mem::take(&mut stream.data)
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn controller_state(&self) -> Vec<u8> {
+        let Some(controller) = &self.controller else {
+            return Vec::new();
+        };
+        let mut stream = MemoryStream::new(Vec::new());
+        let result = unsafe { (controller.vtbl().get_state)(controller.as_ptr(), stream.as_ptr()) };
+        if result == K_RESULT_OK {
+            std::mem::take(&mut stream.data)
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// 归一化值 -> 插件单位下的实际值（没有控制器时就是归一化值）
+    fn param_plain(&self, id: u32, value: f64) -> f64 {
+        match &self.controller {
+            Some(controller) => unsafe { (controller.vtbl().normalized_param_to_plain)(controller.as_ptr(), id, value) },
+            None => value,
+        }
+    }
+
+    fn descriptor(&self, param: &Vst3ParamInfo) -> ParameterDescriptor {
+        let flags = ParameterFlags {
+            automatable: param.flags & K_PARAM_CAN_AUTOMATE != 0,
+            read_only: param.flags & K_PARAM_IS_READ_ONLY != 0,
+            bypass: param.flags & K_PARAM_IS_BYPASS != 0,
+            hidden: param.flags & K_PARAM_IS_HIDDEN != 0,
+        };
+        let min = self.param_plain(param.id, 0.0);
+        let max = self.param_plain(param.id, 1.0);
+        let default = self.param_plain(param.id, param.default_normalized);
+        let mut descriptor = ParameterDescriptor::new(param.id, param.name.clone(), min, max, default)
+            .with_unit(param.units.clone())
+            .with_flags(flags);
+
+        if param.step_count > 0 {
+            descriptor = descriptor.with_steps(param.step_count as u32);
+            if param.flags & K_PARAM_IS_LIST != 0 && param.step_count < MAX_LIST_VALUES {
+                let names = (0..=param.step_count)
+                    .map(|step| {
+                        let normalized = step as f64 / param.step_count as f64;
+                        (self.param_plain(param.id, normalized), self.param_display(param.id, normalized))
+                    })
+                    .collect();
+                descriptor = descriptor.with_value_names(names);
+            }
+        }
+        descriptor
+    }
+
+    /// 通用界面中显示的参数（不含隐藏和只读参数）
+    fn visible_params(&self) -> impl Iterator<Item = &Vst3ParamInfo> {
+        self.params
+            .iter()
+            .filter(|p| p.flags & (K_PARAM_IS_HIDDEN | K_PARAM_IS_READ_ONLY) == 0)
+    }
+
+    fn param_display(&self, id: u32, value: f64) -> String {
+        if let Some(controller) = &self.controller {
+            let mut text: String128 = [0; 128];
+            if unsafe { (controller.vtbl().get_param_string_by_value)(controller.as_ptr(), id, value, &mut text) } == K_RESULT_OK {
+                return string128_to_string(&text);
+            }
+        }
+        format!("{:.2}", value)
+    }
+
+    /// 处理一个不超过 max_block_size 帧的块
+    fn process_block(&mut self, block: &mut AudioBuffer) {
+        let frames = block.frames();
+
+        // 把控制器（插件界面）上的修改同步给处理器
+        if let Ok(mut edits) = self.handler.edits.try_lock() {
+            for (id, value) in edits.drain(..) {
+                self.param_changes.add(id, 0, value);
+                if let Some(index) = self.params.iter().position(|param| param.id == id) {
+                    self.values[index] = value;
+                }
+            }
+        }
+
+        // 缓冲区声道 -> 插件输入声道
+        let inputs = self.input_channels as usize;
+        for (channel, buffer) in self.input_buffers.iter_mut().take(inputs).enumerate() {
+            block.read_mapped(channel, inputs, &mut buffer[..frames]);
+        }
+
+        let mut input_ptrs = [self.input_buffers[0].as_mut_ptr(), self.input_buffers[1].as_mut_ptr()];
+        let mut output_ptrs = [self.output_buffers[0].as_mut_ptr(), self.output_buffers[1].as_mut_ptr()];
+
+        let mut input = AudioBusBuffers {
+            num_channels: self.input_channels as i32,
+            silence_flags: 0,
+            channel_buffers_32: input_ptrs.as_mut_ptr(),
+        };
+        let mut output = AudioBusBuffers {
+            num_channels: self.output_channels as i32,
+            silence_flags: 0,
+            channel_buffers_32: output_ptrs.as_mut_ptr(),
+        };
+
+        let mut data = ProcessData {
+            process_mode: K_REALTIME,
+            symbolic_sample_size: K_SAMPLE32,
+            num_samples: frames as i32,
+            num_inputs: i32::from(self.input_channels > 0),
+            num_outputs: i32::from(self.output_channels > 0),
+            inputs: &mut input,
+            outputs: &mut output,
+            input_parameter_changes: self.param_changes.as_ptr(),
+            output_parameter_changes: ptr::null_mut(),
+            input_events: ptr::null_mut(),
+            output_events: ptr::null_mut(),
+            process_context: ptr::null_mut(),
+        };
+
+        let result = unsafe { (self.processor.vtbl().process)(self.processor.as_ptr(), &mut data) };
+        self.param_changes.clear();
+
+        if result != K_RESULT_OK || self.output_channels == 0 {
+            // 出错时保持原始音频（直通）
+            return;
+        }
+
+        // 插件输出声道 -> 缓冲区声道
+        let [out_left, out_right] = &self.output_buffers;
+        let outputs = (self.output_channels as usize).min(2);
+        block.write_mapped(&[&out_left[..frames], &out_right[..frames]][..outputs]);
+    }
+}
+
+impl AudioProcessor for Vst3Plugin {
+    fn prepare(&mut self, sample_rate: f64, max_block_size: usize, _layout: ChannelLayout) {
+        self.sync_controller();
+        self.deactivate();
+        self.sample_rate = sample_rate;
+        self.max_block_size = max_block_size.max(1);
+        for buffer in self.input_buffers.iter_mut().chain(&mut self.output_buffers) {
+            buffer.resize(self.max_block_size, 0.0);
+        }
+        if let Err(e) = self.activate() {
+            warn!("VST3 插件准备失败: {}", e);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.sync_controller();
+        // VST3 规定 setActive(false) + setActive(true) 会清空处理状态
+        if self.active {
+            self.deactivate();
+            if let Err(e) = self.activate() {
+                warn!("VST3 插件重置失败: {}", e);
+            }
+        }
+    }
+
+    fn release(&mut self) {
+        self.sync_controller();
+        self.deactivate();
+    }
+
+    fn latency_samples(&self) -> u32 {
+        unsafe { (self.processor.vtbl().get_latency_samples)(self.processor.as_ptr()) }
+    }
+
+    fn process(&mut self, buffer: &mut AudioBuffer) {
+        if !self.active {
+            return;
+        }
+        if !self.processing {
+            // setProcessing 应在音频线程中调用
+            unsafe { (self.processor.vtbl().set_processing)(self.processor.as_ptr(), 1) };
+            self.processing = true;
+        }
+
+        let block_size = self.max_block_size;
+        for start in (0..buffer.frames()).step_by(block_size) {
+            self.process_block(&mut buffer.slice(start, start + block_size));
+        }
+    }
+
+    fn process_with_events(&mut self, buffer: &mut AudioBuffer, events: &EventList) {
+        // 记下参数的新值（get_parameter 从这里读），控制器之后在控制线程同步
+        for event in events.iter() {
+            if let EventKind::Parameter { id, value } = event.kind {
+                self.record_value(id, value.clamp(0.0, 1.0));
+            }
+        }
+        if !self.active {
+            return;
+        }
+        if !self.processing {
+            unsafe { (self.processor.vtbl().set_processing)(self.processor.as_ptr(), 1) };
+            self.processing = true;
+        }
+
+        // 参数事件通过 IParameterChanges 带着采样偏移交给处理器
+        let block_size = self.max_block_size;
+        let frames = buffer.frames();
+        for block_start in (0..frames).step_by(block_size) {
+            let mut block = buffer.slice(block_start, block_start + block_size);
+            let start = block_start as u32;
+            let end = if block_start + block.frames() == frames { u32::MAX } else { start + block_size as u32 };
+            let last_frame = block.frames().saturating_sub(1) as u32;
+            self.param_changes.add_events(events, start, end, last_frame);
+            self.process_block(&mut block);
+        }
+    }
+
+    fn get_info(&self) -> &PluginMetadata {
+        &self.metadata
+    }
+
+    fn set_parameter(&mut self, id: u32, value: f64) {
+        // 旁通时在音频线程调用，不碰控制器
+        let value = value.clamp(0.0, 1.0);
+        self.record_value(id, value);
+        // 下一个处理块通知处理器
+        self.param_changes.add(id, 0, value);
+    }
+
+    fn get_parameter(&self, id: u32) -> Option<f64> {
+        self.param_index(id).map(|index| self.values[index])
+    }
+
+    fn parameter_descriptors(&self) -> Vec<ParameterDescriptor> {
+        self.visible_params().map(|param| self.descriptor(param)).collect()
+    }
+
+    fn value_to_text(&self, id: u32, value: f64) -> Option<String> {
+        self.params.iter().any(|p| p.id == id).then(|| self.param_display(id, value))
+    }
+
+    fn text_to_value(&self, id: u32, text: &str) -> Option<f64> {
+        let controller = self.controller.as_ref()?;
+        let text: Vec<u16> = text.encode_utf16().chain(std::iter::once(0)).collect();
+        let mut value = 0.0;
+        let result = unsafe { (controller.vtbl().get_param_value_by_string)(controller.as_ptr(), id, text.as_ptr(), &mut value) };
+        (result == K_RESULT_OK).then_some(value.clamp(0.0, 1.0))
+    }
+
+    fn get_all_parameters(&self) -> Vec<PluginParameter> {
+        self.visible_params()
+            .map(|param| {
+                let value = self.get_parameter(param.id).unwrap_or(0.0);
+                PluginParameter {
+                    id: param.id,
+                    name: param.name.clone(),
+                    value,
+                    display: self.param_display(param.id, value),
+                }
+            })
+            .collect()
+    }
+
+    fn save_state(&self) -> PluginState {
+        self.sync_controller();
+        // state_data 布局: [组件状态长度 u64][组件状态][控制器状态长度 u64][控制器状态]
+        let component = self.component_state();
+        let controller = self.controller_state();
+
+        let mut data = Vec::with_capacity(16 + component.len() + controller.len());
+        data.extend_from_slice(&(component.len() as u64).to_le_bytes());
+        data.extend_from_slice(&component);
+        data.extend_from_slice(&(controller.len() as u64).to_le_bytes());
+        data.extend_from_slice(&controller);
+
+        PluginState {
+            plugin_id: self.metadata.id.clone(),
+            parameters: self.get_all_parameters(),
+            state_data: base64::engine::general_purpose::STANDARD.encode(&data),
+        }
+    }
+
+    fn load_state(&mut self, state: &PluginState) {
+        let data = base64::engine::general_purpose::STANDARD
+            .decode(&state.state_data)
+            .unwrap_or_default();
+
+        let Some((component, controller)) = split_state_chunks(&data) else {
+            // 没有二进制状态时逐个恢复参数
+            for param in &state.parameters {
+                self.set_parameter(param.id, param.value);
+            }
+            return;
+        };
+
+        if !component.is_empty() {
+            let mut stream = MemoryStream::new(component.to_vec());
+            if unsafe { (self.component.vtbl().set_state)(self.component.as_ptr(), stream.as_ptr()) } != K_RESULT_OK {
+                warn!("VST3 组件加载状态失败: {}", self.metadata.name);
+            }
+        }
+
+        if let Some(controller_ptr) = &self.controller {
+            if !component.is_empty() {
+                let mut stream = MemoryStream::new(component.to_vec());
+                unsafe { (controller_ptr.vtbl().set_component_state)(controller_ptr.as_ptr(), stream.as_ptr()) };
+            }
+            if !controller.is_empty() {
+                let mut stream = MemoryStream::new(controller.to_vec());
+                unsafe { (controller_ptr.vtbl().set_state)(controller_ptr.as_ptr(), stream.as_ptr()) };
+            }
+        }
+        self.read_controller_values();
+    }
+}
+
+/// 拆分 save_state 写入的两段状态
+fn split_state_chunks(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    let read_len = |bytes: &[u8]| -> Option<usize> {
+        Some(u64::from_le_bytes(bytes.get(..8)?.try_into().ok()?) as usize)
+    };
+
+    let component_len = read_len(data)?;
+    let component = data.get(8..8 + component_len)?;
+    let rest = &data[8 + component_len..];
+    let controller_len = read_len(rest)?;
+    let controller = rest.get(8..8 + controller_len)?;
+    Some((component, controller))
+}
+
+impl Drop for Vst3Plugin {
+    fn drop(&mut self) {
+        self.deactivate();
+        unsafe {
+            if let Some((a, b)) = self.connections.take() {
+                (a.vtbl().disconnect)(a.as_ptr(), b.as_ptr());
+                (b.vtbl().disconnect)(b.as_ptr(), a.as_ptr());
+            }
+            if let Some(controller) = &self.controller {
+                (controller.vtbl().set_component_handler)(controller.as_ptr(), ptr::null_mut());
+                if self.separate_controller {
+                    (controller.vtbl().terminate)(controller.as_ptr());
+                }
+            }
+            (self.component.vtbl().terminate)(self.component.as_ptr());
+        }
+        // 接口引用在字段 drop 时释放，模块最后卸载
+        debug!("VST3 插件已销毁: {} (宿主引用 {})",
+            self.metadata.name, self.host.refs.load(Ordering::Relaxed));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugin::events::ProcessEvent;
+    use std::env;
+
+    const MODULE_INFO: &str = r#"{
+        // 由 moduleinfotool 生成
+        "Name": "Test Gain",
+        "Version": "1.2.0",
+        "Factory Info": {
+            "Vendor": "Test Vendor",
+            "URL": "https://example.com",
+        },
+        "Classes": [
+            {
+                "CID": "0123456789abcdef0123456789ABCDEF",
+                "Category": "Audio Module Class",
+                "Name": "Test Gain",
+                "Vendor": "",
+                "Version": "1.2.0",
+                "Sub Categories": ["Fx", "Dynamics",],
+            },
+            {
+                "CID": "FEDCBA9876543210FEDCBA9876543210",
+                "Category": "Component Controller Class",
+                "Name": "Test Gain Controller",
+            },
+        ],
+    }"#;
+
+    #[test]
+    fn test_scan_module_info() {
+        let bundle = env::temp_dir().join("test_scan_module_info.vst3");
+        let resources = bundle.join("Contents/Resources");
+        fs::create_dir_all(&resources).unwrap();
+        fs::write(resources.join("moduleinfo.json"), MODULE_INFO).unwrap();
+
+        let plugins = scan_vst3_bundle(&bundle).unwrap();
+        fs::remove_dir_all(&bundle).unwrap();
+
+        // 只列出音频效果类，控制器类被忽略
+        assert_eq!(plugins.len(), 1);
+        assert_eq!(plugins[0].id, "vst3:0123456789ABCDEF0123456789ABCDEF");
+        assert_eq!(plugins[0].name, "Test Gain");
+        assert_eq!(plugins[0].vendor, "Test Vendor");
+        assert_eq!(plugins[0].version, "1.2.0");
+        assert_eq!(plugins[0].format, PluginFormat::Vst3);
+    }
+
+    #[test]
+    fn test_cid_hex_roundtrip() {
+        let hex = cid_to_hex(&IEDIT_CONTROLLER_IID);
+        assert_eq!(hex, "DCD7BBE37742448DA874AACC979C759E");
+        assert_eq!(hex_to_cid(&hex), Some(IEDIT_CONTROLLER_IID));
+        assert_eq!(hex_to_cid("1234"), None);
+    }
+
+    #[test]
+    fn test_memory_stream() {
+        let mut stream = MemoryStream::new(Vec::new());
+        let this = stream.as_ptr();
+        let vtbl = &MEMORY_STREAM_VTBL;
+
+        unsafe {
+            let mut written = 0;
+            let mut bytes = *b"hello";
+            (vtbl.write)(this, bytes.as_mut_ptr() as *mut c_void, 5, &mut written);
+            assert_eq!(written, 5);
+
+            let mut pos = 0;
+            (vtbl.seek)(this, 1, 0, &mut pos);
+            assert_eq!(pos, 1);
+
+            let mut buffer = [0u8; 8];
+            let mut read = 0;
+            (vtbl.read)(this, buffer.as_mut_ptr() as *mut c_void, 8, &mut read);
+            assert_eq!(&buffer[..read as usize], b"ello");
+
+            // 流末尾读取返回 kResultFalse
+            assert_eq!((vtbl.read)(this, buffer.as_mut_ptr() as *mut c_void, 8, &mut read), K_RESULT_FALSE);
+        }
+    }
+
+    #[test]
+    fn test_parameter_changes() {
+        let mut changes = ParameterChanges::new();
+        changes.add(7, 0, 0.25);
+        changes.add(7, 16, 0.5);
+        changes.add(9, 0, 1.0);
+
+        unsafe {
+            let this = changes.as_ptr();
+            assert_eq!(parameter_changes_get_count(this), 2);
+
+            let queue = parameter_changes_get_data(this, 0);
+            assert_eq!(param_value_queue_get_id(queue), 7);
+            assert_eq!(param_value_queue_get_point_count(queue), 2);
+
+            let (mut offset, mut value) = (0, 0.0);
+            param_value_queue_get_point(queue, 1, &mut offset, &mut value);
+            assert_eq!((offset, value), (16, 0.5));
+        }
+
+        changes.clear();
+        assert_eq!(unsafe { parameter_changes_get_count(changes.as_ptr()) }, 0);
+    }
+
+    #[test]
+    fn test_parameter_changes_from_events() {
+        let mut events = EventList::new();
+        events.push(ProcessEvent::parameter(3, 7, 0.5));
+        events.push(ProcessEvent::midi(4, [0x90, 60, 100]));
+        events.push(ProcessEvent::parameter(70, 7, 1.5));
+        events.push(ProcessEvent::parameter(200, 9, 0.25));
+
+        // 第二个块 [64, 128)：偏移相对块开头
+        let mut changes = ParameterChanges::new();
+        changes.add_events(&events, 64, 128, 63);
+        unsafe {
+            let this = changes.as_ptr();
+            assert_eq!(parameter_changes_get_count(this), 1);
+            let queue = parameter_changes_get_data(this, 0);
+            let (mut offset, mut value) = (0, 0.0);
+            param_value_queue_get_point(queue, 0, &mut offset, &mut value);
+            assert_eq!((offset, value), (6, 1.0));
+        }
+
+        // 最后一块包含超出缓冲区的事件，偏移限制在块内
+        changes.clear();
+        changes.add_events(&events, 128, u32::MAX, 31);
+        unsafe {
+            let queue = parameter_changes_get_data(changes.as_ptr(), 0);
+            assert_eq!(param_value_queue_get_id(queue), 9);
+            let (mut offset, mut value) = (0, 0.0);
+            param_value_queue_get_point(queue, 0, &mut offset, &mut value);
+            assert_eq!(offset, 31);
+        }
+    }
+
+    #[test]
+    fn test_split_state_chunks() {
+        let mut data = Vec::new();
+        data.extend_from_slice(&3u64.to_le_bytes());
+        data.extend_from_slice(b"abc");
+        data.extend_from_slice(&2u64.to_le_bytes());
+        data.extend_from_slice(b"xy");
+
+        assert_eq!(split_state_chunks(&data), Some((&b"abc"[..], &b"xy"[..])));
+        assert_eq!(split_state_chunks(&data[..5]), None);
+    }
+
+    #[test]
+    fn test_missing_bundle() {
+        assert!(Vst3Plugin::load(Path::new("/nonexistent/missing.vst3"), None).is_err());
+    }
+}
@@ -0,0 +1,730 @@
+// WebAssembly 沙盒插件
+// 用内嵌的 wasmi 解释器运行 .wasm 效果器：不提供任何宿主导入，
+// 内存有上限，每个处理块有 fuel（指令数）和时间预算，出错时插件停用并直通音频
+//
+// 插件需要导出:
+//   memory                                  线性内存
+//   describe() -> i64                       (ptr << 32) | len，指向 UTF-8 JSON 描述
+//   init(sample_rate: f64, max_frames: i32) -> i32
+//                                           返回交错立体声 f32 缓冲区的地址（max_frames * 2 个采样）
+//   process(frames: i32)                    原地处理缓冲区
+// 可选导出:
+//   set_parameter(id: i32, value: f64)      value 为 0.0 - 1.0
+//   get_parameter(id: i32) -> f64
+//   save_state() -> i64                     (ptr << 32) | len
+//   alloc(len: i32) -> i32                  为 load_state 分配内存
+//   load_state(ptr: i32, len: i32) -> i32   成功返回非 0
+//
+// describe 的 JSON 格式:
+//   {"id": "...", "name": "...", "vendor": "...", "version": "...",
+//    "parameters": [{"id": 0, "name": "Gain", "default": 0.5}]}
+// 参数的可选字段（用于显示，插件接口上仍然是 0.0 - 1.0）:
+//   "min" / "max"（实际值范围，默认 0 / 1）、"unit"、"steps"、"logarithmic"、
+//   "values"（枚举名，依次对应 min, min + 1, ...）、
+//   "automatable"（默认 true）、"read_only"、"bypass"、"hidden"
+
+use anyhow::{Result, Context};
+use base64::Engine as _;
+use log::{info, warn, debug};
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use wasmi::{Config, Engine, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, TypedFunc};
+
+use crate::audio::AudioBuffer;
+
+use super::types::{PluginMetadata, AudioProcessor, PluginParameter, PluginState, PluginFormat, ChannelLayout, ParameterDescriptor, ParameterFlags};
+
+/// 实例化插件时使用的采样率
+const DEFAULT_SAMPLE_RATE: f64 = 48000.0;
+
+/// 默认的单次调用 process 最大帧数
+const MAX_BLOCK_SIZE: usize = 4096;
+
+/// 非音频调用（describe / 参数 / 状态）的 fuel 预算
+const CONTROL_FUEL: u64 = 10_000_000;
+
+/// 沙盒资源限制
+#[derive(Debug, Clone)]
+pub struct WasmLimits {
+    /// 线性内存上限（字节）
+    pub max_memory_bytes: usize,
+    /// 每帧可用的 fuel（约等于 Wasm 指令数）
+    pub fuel_per_frame: u64,
+    /// 单个处理块允许占用的实时时间比例（1.0 = 整个块的时长）
+    pub max_cpu_ratio: f64,
+    /// 连续超时多少个块后停用插件
+    pub max_overruns: u32,
+}
+
+impl Default for WasmLimits {
+    fn default() -> Self {
+        Self {
+            max_memory_bytes: 16 * 1024 * 1024,
+            fuel_per_frame: 20_000,
+            max_cpu_ratio: 0.5,
+            max_overruns: 3,
+        }
+    }
+}
+
+/// describe() 返回的插件描述
+#[derive(Debug, Deserialize)]
+struct WasmDescription {
+    id: String,
+    name: String,
+    #[serde(default)]
+    vendor: String,
+    #[serde(default)]
+    version: String,
+    #[serde(default)]
+    parameters: Vec<WasmParamInfo>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct WasmParamInfo {
+    id: u32,
+    name: String,
+    /// 归一化默认值
+    #[serde(default)]
+    default: f64,
+    #[serde(default)]
+    min: Option<f64>,
+    #[serde(default)]
+    max: Option<f64>,
+    #[serde(default)]
+    unit: String,
+    #[serde(default)]
+    steps: u32,
+    #[serde(default)]
+    logarithmic: bool,
+    #[serde(default)]
+    values: Vec<String>,
+    #[serde(default = "default_automatable")]
+    automatable: bool,
+    #[serde(default)]
+    read_only: bool,
+    #[serde(default)]
+    bypass: bool,
+    #[serde(default)]
+    hidden: bool,
+}
+
+fn default_automatable() -> bool {
+    true
+}
+
+impl WasmParamInfo {
+    fn descriptor(&self) -> ParameterDescriptor {
+        let min = self.min.unwrap_or(0.0);
+        let max = self.max.unwrap_or(1.0);
+        let names = self.values
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (min + i as f64, name.clone()))
+            .collect();
+        let mut descriptor = ParameterDescriptor::new(self.id, self.name.clone(), min, max, min)
+            .with_unit(self.unit.clone())
+            .with_steps(self.steps)
+            .with_logarithmic(self.logarithmic)
+            .with_value_names(names)
+            .with_flags(ParameterFlags {
+                automatable: self.automatable,
+                read_only: self.read_only,
+                bypass: self.bypass,
+                hidden: self.hidden,
+            });
+        descriptor.default = descriptor.to_plain(self.default);
+        descriptor
+    }
+}
+
+/// Store 中的宿主数据
+struct HostState {
+    limits: StoreLimits,
+}
+
+/// 沙盒中的插件实例
+struct WasmRuntime {
+    store: Store<HostState>,
+    memory: Memory,
+    buffer_ptr: usize,
+    max_frames: usize,
+    process: TypedFunc<i32, ()>,
+    set_parameter: Option<TypedFunc<(i32, f64), ()>>,
+    get_parameter: Option<TypedFunc<i32, f64>>,
+    save_state: Option<TypedFunc<(), i64>>,
+    alloc: Option<TypedFunc<i32, i32>>,
+    load_state: Option<TypedFunc<(i32, i32), i32>>,
+}
+
+/// 拆分 (ptr << 32) | len
+fn unpack(packed: i64) -> (usize, usize) {
+    (((packed as u64) >> 32) as usize, (packed as u64 & 0xFFFF_FFFF) as usize)
+}
+
+impl WasmRuntime {
+    fn instantiate(wasm: &[u8], limits: &WasmLimits, sample_rate: f64, max_frames: usize) -> Result<(Self, WasmDescription)> {
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+
+        let module = Module::new(&engine, wasm)
+            .map_err(|e| anyhow::anyhow!("Wasm 模块无效: {}", e))?;
+
+        // 沙盒不提供任何宿主函数
+        if let Some(import) = module.imports().next() {
+            return Err(anyhow::anyhow!("Wasm 插件不能导入宿主函数: {}::{}", import.module(), import.name()));
+        }
+
+        let host = HostState {
+            limits: StoreLimitsBuilder::new()
+                .memory_size(limits.max_memory_bytes)
+                .memories(1)
+                .instances(1)
+                .build(),
+        };
+        let mut store = Store::new(&engine, host);
+        store.limiter(|host| &mut host.limits);
+        store.set_fuel(CONTROL_FUEL).map_err(|e| anyhow::anyhow!("{}", e))?;
+
+        let linker = Linker::<HostState>::new(&engine);
+        let instance = linker.instantiate(&mut store, &module)
+            .and_then(|pre| pre.start(&mut store))
+            .map_err(|e| anyhow::anyhow!("Wasm 插件实例化失败: {}", e))?;
+
+        let memory = instance.get_memory(&store, "memory")
+            .ok_or_else(|| anyhow::anyhow!("Wasm 插件没有导出 memory"))?;
+
+        macro_rules! required {
+            ($name:literal) => {
+                instance.get_typed_func(&store, $name)
+                    .map_err(|e| anyhow::anyhow!("Wasm 插件缺少导出 {}: {}", $name, e))?
+            };
+        }
+        let describe: TypedFunc<(), i64> = required!("describe");
+        let init: TypedFunc<(f64, i32), i32> = required!("init");
+        let process: TypedFunc<i32, ()> = required!("process");
+
+        let mut runtime = Self {
+            memory,
+            buffer_ptr: 0,
+            max_frames,
+            process,
+            set_parameter: instance.get_typed_func(&store, "set_parameter").ok(),
+            get_parameter: instance.get_typed_func(&store, "get_parameter").ok(),
+            save_state: instance.get_typed_func(&store, "save_state").ok(),
+            alloc: instance.get_typed_func(&store, "alloc").ok(),
+            load_state: instance.get_typed_func(&store, "load_state").ok(),
+            store,
+        };
+
+        let packed = runtime.call_control(|store| describe.call(store, ()))?;
+        let json = runtime.read_memory(packed)?;
+        let description: WasmDescription = serde_json::from_slice(&json)
+            .context("解析插件描述失败")?;
+
+        let buffer_ptr = runtime.call_control(|store| init.call(store, (sample_rate, max_frames as i32)))?;
+        runtime.buffer_ptr = buffer_ptr as u32 as usize;
+        if runtime.buffer_ptr + max_frames * 2 * 4 > runtime.memory.data(&runtime.store).len() {
+            return Err(anyhow::anyhow!("Wasm 插件的音频缓冲区超出内存范围"));
+        }
+
+        Ok((runtime, description))
+    }
+
+    /// 以控制调用的 fuel 预算调用插件
+    fn call_control<R>(&mut self, call: impl FnOnce(&mut Store<HostState>) -> Result<R, wasmi::Error>) -> Result<R> {
+        self.store.set_fuel(CONTROL_FUEL).map_err(|e| anyhow::anyhow!("{}", e))?;
+        call(&mut self.store).map_err(|e| anyhow::anyhow!("Wasm 插件执行出错: {}", e))
+    }
+
+    fn read_memory(&self, packed: i64) -> Result<Vec<u8>> {
+        let (ptr, len) = unpack(packed);
+        self.memory.data(&self.store)
+            .get(ptr..ptr + len)
+            .map(|bytes| bytes.to_vec())
+            .ok_or_else(|| anyhow::anyhow!("Wasm 插件返回的内存范围无效"))
+    }
+
+    /// 处理一个不超过 max_frames 帧的块
+    /// 插件看到的是交错立体声：单声道输入复制到两个声道，输出再取平均写回
+    fn process_block(&mut self, block: &mut AudioBuffer, fuel: u64) -> Result<()> {
+        if block.is_empty() {
+            return Ok(());
+        }
+        let frames = block.frames();
+        let channels = block.num_channels();
+        let start = self.buffer_ptr;
+        let end = start + frames * 2 * 4;
+
+        let memory = self.memory.data_mut(&mut self.store);
+        let Some(guest) = memory.get_mut(start..end) else {
+            return Err(anyhow::anyhow!("音频缓冲区超出内存范围"));
+        };
+        let (left, right) = (block.channel(0), block.channel(1.min(channels - 1)));
+        for ((bytes, l), r) in guest.chunks_exact_mut(8).zip(left).zip(right) {
+            bytes[..4].copy_from_slice(&l.to_le_bytes());
+            bytes[4..].copy_from_slice(&r.to_le_bytes());
+        }
+
+        self.store.set_fuel(fuel).map_err(|e| anyhow::anyhow!("{}", e))?;
+        self.process.call(&mut self.store, frames as i32)
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+
+        // 插件可能 grow 了内存，重新获取
+        let memory = self.memory.data(&self.store);
+        let Some(guest) = memory.get(start..end) else {
+            return Err(anyhow::anyhow!("音频缓冲区超出内存范围"));
+        };
+        let sample = |bytes: &[u8]| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        if channels == 1 {
+            for (out, frame) in block.channel_mut(0).iter_mut().zip(guest.chunks_exact(8)) {
+                *out = (sample(&frame[..4]) + sample(&frame[4..])) * 0.5;
+            }
+        } else {
+            for (index, channel) in block.channels_mut().take(2).enumerate() {
+                for (out, frame) in channel.iter_mut().zip(guest.chunks_exact(8)) {
+                    *out = sample(&frame[index * 4..]);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn describe_to_metadata(description: &WasmDescription, path: &Path) -> PluginMetadata {
+    PluginMetadata {
+        id: format!("wasm:{}", description.id),
+        name: description.name.clone(),
+        vendor: description.vendor.clone(),
+        version: description.version.clone(),
+        path: path.to_path_buf(),
+        format: PluginFormat::Wasm,
+        num_inputs: 2,
+        num_outputs: 2,
+    }
+}
+
+/// 读取 .wasm 文件的插件信息（在沙盒中调用 describe）
+pub fn scan_wasm_file(path: &Path) -> Result<Vec<PluginMetadata>> {
+    let wasm = fs::read(path).context(format!("读取文件失败: {:?}", path))?;
+    let (_, description) = WasmRuntime::instantiate(&wasm, &WasmLimits::default(), DEFAULT_SAMPLE_RATE, MAX_BLOCK_SIZE)?;
+    Ok(vec![describe_to_metadata(&description, path)])
+}
+
+/// Wasm 插件包装器
+pub struct WasmPlugin {
+    metadata: PluginMetadata,
+    params: Vec<WasmParamInfo>,
+
+    /// 参数值缓存（get_parameter 只有 &self，不调用插件）
+    param_values: Vec<f64>,
+
+    runtime: RefCell<WasmRuntime>,
+    /// 模块字节码，采样率或块大小变化时用来重新实例化
+    wasm: Vec<u8>,
+    limits: WasmLimits,
+    sample_rate: f64,
+    /// release 之后到下次 prepare 之前直通
+    active: bool,
+
+    /// 连续超时的块数
+    overruns: u32,
+    /// 插件出错或超限后停用，之后直通音频
+    faulted: bool,
+}
+
+impl WasmPlugin {
+    /// 使用默认限制加载插件
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::load_with_limits(path, WasmLimits::default())
+    }
+
+    /// 使用指定限制加载插件
+    pub fn load_with_limits(path: &Path, limits: WasmLimits) -> Result<Self> {
+        info!("加载 Wasm 插件: {:?}", path);
+
+        let wasm = fs::read(path).context(format!("读取文件失败: {:?}", path))?;
+        Self::from_bytes(&wasm, path, limits)
+    }
+
+    /// 从扫描信息加载插件
+    pub fn from_metadata(metadata: &PluginMetadata) -> Result<Self> {
+        Self::load(&metadata.path)
+    }
+
+    fn from_bytes(wasm: &[u8], path: &Path, limits: WasmLimits) -> Result<Self> {
+        let sample_rate = DEFAULT_SAMPLE_RATE;
+        let (runtime, description) = WasmRuntime::instantiate(wasm, &limits, sample_rate, MAX_BLOCK_SIZE)?;
+
+        let mut plugin = Self {
+            metadata: describe_to_metadata(&description, path),
+            param_values: description.parameters.iter().map(|p| p.default.clamp(0.0, 1.0)).collect(),
+            params: description.parameters,
+            runtime: RefCell::new(runtime),
+            wasm: wasm.to_vec(),
+            limits,
+            sample_rate,
+            active: true,
+            overruns: 0,
+            faulted: false,
+        };
+
+        // 以插件报告的当前值为准
+        for index in 0..plugin.params.len() {
+            if let Some(value) = plugin.query_parameter(plugin.params[index].id) {
+                plugin.param_values[index] = value;
+            }
+        }
+
+        info!("Wasm 插件已加载: {} ({} 个参数)", plugin.metadata.name, plugin.params.len());
+        Ok(plugin)
+    }
+
+    /// 插件是否因出错或超限被停用（停用时写日志，这里供测试检查）
+    #[cfg(test)]
+    pub fn is_faulted(&self) -> bool {
+        self.faulted
+    }
+
+    fn fault(&mut self, reason: &str) {
+        if !self.faulted {
+            warn!("Wasm 插件已停用 ({}): {}", self.metadata.name, reason);
+            self.faulted = true;
+        }
+    }
+
+    fn query_parameter(&self, id: u32) -> Option<f64> {
+        let mut runtime = self.runtime.borrow_mut();
+        let get = runtime.get_parameter?;
+        runtime.call_control(|store| get.call(store, id as i32)).ok()
+    }
+
+    fn param_index(&self, id: u32) -> Option<usize> {
+        self.params.iter().position(|p| p.id == id)
+    }
+
+    /// 以新的采样率和块大小重新实例化，并恢复参数和内部状态
+    /// 成功后清除停用标记（新实例有新的资源预算）
+    fn reinstantiate(&mut self, sample_rate: f64, max_frames: usize) {
+        let state = self.save_state();
+        match WasmRuntime::instantiate(&self.wasm, &self.limits, sample_rate, max_frames) {
+            Ok((runtime, _)) => {
+                self.runtime = RefCell::new(runtime);
+                self.sample_rate = sample_rate;
+                self.overruns = 0;
+                self.faulted = false;
+                self.load_state(&state);
+            }
+            Err(e) => self.fault(&e.to_string()),
+        }
+    }
+}
+
+impl AudioProcessor for WasmPlugin {
+    fn prepare(&mut self, sample_rate: f64, max_block_size: usize, _layout: ChannelLayout) {
+        let max_frames = max_block_size.max(1);
+        if sample_rate != self.sample_rate || max_frames != self.runtime.get_mut().max_frames {
+            self.reinstantiate(sample_rate, max_frames);
+        }
+        self.active = true;
+    }
+
+    fn reset(&mut self) {
+        // 没有约定 reset 导出，重新实例化来清空插件内部的处理状态
+        let max_frames = self.runtime.get_mut().max_frames;
+        self.reinstantiate(self.sample_rate, max_frames);
+    }
+
+    fn release(&mut self) {
+        self.active = false;
+    }
+
+    fn process(&mut self, buffer: &mut AudioBuffer) {
+        if self.faulted || !self.active {
+            return;
+        }
+
+        let max_frames = self.runtime.get_mut().max_frames;
+        for block_start in (0..buffer.frames()).step_by(max_frames) {
+            let mut block = buffer.slice(block_start, block_start + max_frames);
+            let frames = block.frames();
+            let fuel = self.limits.fuel_per_frame * frames as u64;
+            let budget = Duration::from_secs_f64(frames as f64 / self.sample_rate * self.limits.max_cpu_ratio);
+
+            let started = Instant::now();
+            let result = self.runtime.get_mut().process_block(&mut block, fuel);
+            let elapsed = started.elapsed();
+
+            if let Err(e) = result {
+                // 出错时 block 保持原样，之后的块也直通
+                self.fault(&e.to_string());
+                return;
+            }
+
+            if elapsed > budget {
+                self.overruns += 1;
+                debug!("Wasm 插件处理超时: {:?} > {:?}", elapsed, budget);
+                if self.overruns >= self.limits.max_overruns {
+                    self.fault("处理时间超出预算");
+                    return;
+                }
+            } else {
+                self.overruns = 0;
+            }
+        }
+    }
+
+    fn get_info(&self) -> &PluginMetadata {
+        &self.metadata
+    }
+
+    fn set_parameter(&mut self, id: u32, value: f64) {
+        // 可能在音频线程调用，未知的参数直接忽略，不写日志
+        let Some(index) = self.param_index(id) else {
+            return;
+        };
+        let value = value.clamp(0.0, 1.0);
+        self.param_values[index] = value;
+
+        if self.faulted {
+            return;
+        }
+        let runtime = self.runtime.get_mut();
+        if let Some(set) = runtime.set_parameter {
+            if let Err(e) = runtime.call_control(|store| set.call(store, (id as i32, value))) {
+                self.fault(&e.to_string());
+            }
+        }
+    }
+
+    fn get_parameter(&self, id: u32) -> Option<f64> {
+        self.param_index(id).map(|index| self.param_values[index])
+    }
+
+    fn parameter_descriptors(&self) -> Vec<ParameterDescriptor> {
+        self.params.iter().map(WasmParamInfo::descriptor).collect()
+    }
+
+    fn get_all_parameters(&self) -> Vec<PluginParameter> {
+        self.params
+            .iter()
+            .zip(&self.param_values)
+            .map(|(param, &value)| PluginParameter {
+                id: param.id,
+                name: param.name.clone(),
+                value,
+                display: param.descriptor().value_to_text(value),
+            })
+            .collect()
+    }
+
+    fn save_state(&self) -> PluginState {
+        let mut data = Vec::new();
+        if !self.faulted {
+            let mut runtime = self.runtime.borrow_mut();
+            if let Some(save) = runtime.save_state {
+                match runtime.call_control(|store| save.call(store, ())).and_then(|packed| runtime.read_memory(packed)) {
+                    Ok(bytes) => data = bytes,
+                    Err(e) => warn!("Wasm 插件保存状态失败: {}", e),
+                }
+            }
+        }
+
+        PluginState {
+            plugin_id: self.metadata.id.clone(),
+            parameters: self.get_all_parameters(),
+            state_data: base64::engine::general_purpose::STANDARD.encode(&data),
+        }
+    }
+
+    fn load_state(&mut self, state: &PluginState) {
+        for param in &state.parameters {
+            self.set_parameter(param.id, param.value);
+        }
+
+        let data = base64::engine::general_purpose::STANDARD
+            .decode(&state.state_data)
+            .unwrap_or_default();
+        if data.is_empty() || self.faulted {
+            return;
+        }
+
+        let runtime = self.runtime.get_mut();
+        let (Some(alloc), Some(load)) = (runtime.alloc, runtime.load_state) else {
+            return;
+        };
+
+        let result = runtime.call_control(|store| alloc.call(store, data.len() as i32))
+            .and_then(|ptr| {
+                runtime.memory.write(&mut runtime.store, ptr as u32 as usize, &data)
+                    .map_err(|e| anyhow::anyhow!("写入状态失败: {}", e))?;
+                runtime.call_control(|store| load.call(store, (ptr, data.len() as i32)))
+            });
+
+        match result {
+            Ok(0) => warn!("Wasm 插件拒绝了保存的状态: {}", self.metadata.name),
+            Ok(_) => {
+                // 状态可能改变了参数
+                for index in 0..self.params.len() {
+                    if let Some(value) = self.query_parameter(self.params[index].id) {
+                        self.param_values[index] = value;
+                    }
+                }
+            }
+            Err(e) => self.fault(&e.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::with_interleaved;
+
+    const DESCRIPTION: &str = r#"{"id": "test.gain", "name": "Test Gain", "vendor": "Test", "version": "1.0.0", "parameters": [{"id": 0, "name": "Gain", "default": 0.5}]}"#;
+
+    /// 一个 Wasm 增益插件：输出 = 输入 * value * 2
+    fn gain_module(process_body: &str) -> Vec<u8> {
+        let wat = r#"
+            (module
+              (memory (export "memory") 1)
+              (data (i32.const 0) "{JSON}")
+              (global $value (mut f64) (f64.const 0.5))
+              (func (export "describe") (result i64)
+                (i64.const {LEN}))
+              (func (export "init") (param f64 i32) (result i32)
+                (i32.const 1024))
+              (func (export "process") (param $frames i32)
+                (local $i i32) (local $end i32)
+                {PROCESS})
+              (func (export "set_parameter") (param i32 f64)
+                (global.set $value (local.get 1)))
+              (func (export "get_parameter") (param i32) (result f64)
+                (global.get $value))
+              (func (export "save_state") (result i64)
+                (f64.store (i32.const 512) (global.get $value))
+                (i64.or (i64.shl (i64.const 512) (i64.const 32)) (i64.const 8)))
+              (func (export "alloc") (param i32) (result i32)
+                (i32.const 768))
+              (func (export "load_state") (param $ptr i32) (param $len i32) (result i32)
+                (global.set $value (f64.load (local.get $ptr)))
+                (i32.const 1)))
+        "#
+        .replace("{JSON}", &DESCRIPTION.replace('"', "\\\""))
+        .replace("{LEN}", &DESCRIPTION.len().to_string())
+        .replace("{PROCESS}", process_body);
+
+        wat::parse_str(wat).unwrap()
+    }
+
+    const GAIN_PROCESS: &str = r#"
+        (local.set $i (i32.const 1024))
+        (local.set $end (i32.add (i32.const 1024) (i32.shl (local.get $frames) (i32.const 3))))
+        (block $done
+          (loop $next
+            (br_if $done (i32.ge_u (local.get $i) (local.get $end)))
+            (f32.store (local.get $i)
+              (f32.mul (f32.load (local.get $i))
+                       (f32.demote_f64 (f64.mul (global.get $value) (f64.const 2)))))
+            (local.set $i (i32.add (local.get $i) (i32.const 4)))
+            (br $next)))
+    "#;
+
+    fn test_plugin(process_body: &str) -> WasmPlugin {
+        WasmPlugin::from_bytes(&gain_module(process_body), Path::new("/test/gain.wasm"), WasmLimits::default()).unwrap()
+    }
+
+    #[test]
+    fn test_wasm_metadata() {
+        let plugin = test_plugin(GAIN_PROCESS);
+        let info = plugin.get_info();
+        assert_eq!(info.id, "wasm:test.gain");
+        assert_eq!(info.name, "Test Gain");
+        assert_eq!(info.format, PluginFormat::Wasm);
+        assert_eq!(plugin.get_parameter(0), Some(0.5));
+
+        // 描述里没有范围信息时按 0 - 1 显示
+        let descriptor = &plugin.parameter_descriptors()[0];
+        assert_eq!((descriptor.min, descriptor.max, descriptor.default), (0.0, 1.0, 0.5));
+        assert_eq!(plugin.get_all_parameters()[0].display, "0.50");
+    }
+
+    #[test]
+    fn test_wasm_parameter_descriptor_fields() {
+        let json = r#"{"id": 1, "name": "Mode", "default": 0.5, "min": 0, "max": 2,
+                       "values": ["Clean", "Crunch", "Lead"], "automatable": false}"#;
+        let info: WasmParamInfo = serde_json::from_str(json).unwrap();
+        let descriptor = info.descriptor();
+        assert_eq!(descriptor.steps, 2);
+        assert_eq!(descriptor.default, 1.0);
+        assert_eq!(descriptor.value_to_text(1.0), "Lead");
+        assert_eq!(descriptor.text_to_value("crunch"), Some(0.5));
+        assert!(!descriptor.flags.automatable);
+    }
+
+    #[test]
+    fn test_wasm_process_and_state() {
+        let mut plugin = test_plugin(GAIN_PROCESS);
+        plugin.set_parameter(0, 1.0);
+
+        let mut buffer = vec![0.25, -0.5, 0.125, 1.0];
+        with_interleaved(&mut buffer, 2, |buffer| plugin.process(buffer));
+        assert_eq!(buffer, vec![0.5, -1.0, 0.25, 2.0]);
+
+        let state = plugin.save_state();
+        let mut restored = test_plugin(GAIN_PROCESS);
+        restored.load_state(&state);
+        assert_eq!(restored.get_parameter(0), Some(1.0));
+    }
+
+    #[test]
+    fn test_fuel_limit_stops_runaway_plugin() {
+        // 死循环的插件在 fuel 用尽后被停用，音频保持直通
+        let mut plugin = test_plugin("(loop $forever (br $forever))");
+        let mut buffer = vec![0.5; 64];
+        with_interleaved(&mut buffer, 2, |buffer| plugin.process(buffer));
+        assert!(plugin.is_faulted());
+
+        let mut buffer = vec![0.25; 64];
+        with_interleaved(&mut buffer, 2, |buffer| plugin.process(buffer));
+        assert_eq!(buffer, vec![0.25; 64]);
+    }
+
+    #[test]
+    fn test_wasm_prepare_reinstantiates() {
+        let mut plugin = test_plugin(GAIN_PROCESS);
+        plugin.set_parameter(0, 1.0);
+
+        plugin.prepare(96000.0, 8, ChannelLayout::Stereo);
+        assert_eq!(plugin.runtime.get_mut().max_frames, 8);
+        assert_eq!(plugin.get_parameter(0), Some(1.0));
+
+        // 参数通过状态恢复到了新实例，超过块大小时分块处理
+        let mut buffer = vec![0.25; 20 * 2];
+        with_interleaved(&mut buffer, 2, |buffer| plugin.process(buffer));
+        assert_eq!(buffer, vec![0.5; 20 * 2]);
+        assert!(!plugin.is_faulted());
+    }
+
+    #[test]
+    fn test_memory_limit() {
+        let limits = WasmLimits {
+            max_memory_bytes: 32 * 1024,
+            ..WasmLimits::default()
+        };
+        // 插件声明了 64 KiB 初始内存，超出限制
+        let result = WasmPlugin::from_bytes(&gain_module(GAIN_PROCESS), Path::new("/test/gain.wasm"), limits);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_imports_rejected() {
+        let wasm = wat::parse_str(r#"(module (import "env" "abort" (func)))"#).unwrap();
+        let result = WasmPlugin::from_bytes(&wasm, Path::new("/test/bad.wasm"), WasmLimits::default());
+        assert!(result.is_err());
+    }
+}
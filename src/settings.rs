@@ -0,0 +1,92 @@
+// 用户设置
+// 与工程无关、跟随用户的设置（音频设备和配置），保存在 ~/.config/plugin-loader/settings.json
+
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::plugin::AudioConfig;
+
+/// 用户设置
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UserSettings {
+    /// 上次选择的音频设备和配置
+    #[serde(default)]
+    pub audio: AudioConfig,
+}
+
+impl UserSettings {
+    /// 默认的设置文件路径
+    pub fn default_path() -> PathBuf {
+        let home = std::env::var("HOME").unwrap_or_default();
+        PathBuf::from(home).join(".config/plugin-loader/settings.json")
+    }
+    
+    /// 从文件加载设置，文件不存在时返回默认设置
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        
+        let content = fs::read_to_string(path)
+            .context(format!("读取设置文件失败: {:?}", path))?;
+        serde_json::from_str(&content).context("解析设置文件失败")
+    }
+    
+    /// 从默认路径加载设置，失败时记录警告并使用默认设置
+    pub fn load_or_default() -> Self {
+        let path = Self::default_path();
+        Self::load(&path).unwrap_or_else(|e| {
+            warn!("无法加载用户设置 {:?}: {:#}", path, e);
+            Self::default()
+        })
+    }
+    
+    /// 保存设置到文件（目录不存在时创建）
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .context(format!("创建设置目录失败: {:?}", dir))?;
+        }
+        
+        let content = serde_json::to_string_pretty(self)
+            .context("序列化设置失败")?;
+        fs::write(path, content)
+            .context(format!("写入设置文件失败: {:?}", path))?;
+        
+        info!("用户设置已保存到: {:?}", path);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_settings_roundtrip() {
+        let dir = std::env::temp_dir().join("plugin_loader_settings_test");
+        let path = dir.join("settings.json");
+        let _ = fs::remove_dir_all(&dir);
+        
+        // 没有设置文件时使用默认设置
+        assert_eq!(UserSettings::load(&path).unwrap(), UserSettings::default());
+        
+        let mut settings = UserSettings::default();
+        settings.audio.sample_rate = 96000;
+        settings.audio.buffer_size = 64;
+        settings.audio.input_device = Some("USB Audio".to_string());
+        settings.save(&path).unwrap();
+        assert_eq!(UserSettings::load(&path).unwrap(), settings);
+        
+        // 旧格式的音频配置没有设备字段
+        fs::write(&path, r#"{"audio": {"sample_rate": 44100, "buffer_size": 128, "channels": 2}}"#).unwrap();
+        let old = UserSettings::load(&path).unwrap();
+        assert_eq!(old.audio.sample_rate, 44100);
+        assert_eq!(old.audio.output_device, None);
+        
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1,9 +1,34 @@
+use anyhow::Result;
 use eframe::egui;
-use std::sync::{Arc, Mutex};
+use std::path::Path;
+use std::time::Duration;
 use log::{info, error};
 
-use crate::plugin::{PluginScanner, PluginInfo};
-use crate::audio::AudioProcessorEngine;
+use crate::plugin::{
+    plugin_factory, AudioConfig, LayoutConversion, PluginLoader, PluginScanner, PluginInfo,
+    ProjectManager, SlotSettings,
+};
+use crate::audio::{input_device_names, midi_device_paths, output_device_names, AudioEngine, AudioProcessorEngine};
+use crate::settings::UserSettings;
+
+use super::widgets::{channel_meters, LevelMeter, TunerDisplay};
+
+/// 音频设置窗口中可选的采样率
+const SAMPLE_RATES: [u32; 4] = [44100, 48000, 88200, 96000];
+
+/// 音频设置窗口中可选的缓冲区大小（帧）
+const BUFFER_SIZES: [u32; 6] = [32, 64, 128, 256, 512, 1024];
+
+/// 插件链面板中的操作（渲染时记录，释放锁后执行）
+enum ChainAction {
+    Add,
+    LoadFile,
+    Replace(usize),
+    Move(usize, usize),
+    Remove(usize),
+    Settings(usize, SlotSettings),
+    Clear,
+}
 
 /// Plugin Loader 主应用
 pub struct PluginLoaderApp {
@@ -13,13 +38,21 @@ pub struct PluginLoaderApp {
     /// 已扫描的插件列表
     plugins: Vec<PluginInfo>,
     
-    /// 音频处理引擎
-    audio_engine: Arc<Mutex<Option<AudioProcessorEngine>>>,
+    /// 音频引擎（与命令行入口相同）
+    engine: AudioEngine,
+    
+    /// 插件加载器
+    loader: PluginLoader,
+    
+    /// 从文件加载插件时的路径，以及插件链编辑的状态消息
+    plugin_path: String,
+    chain_status: String,
     
-    /// 已加载的插件（用于显示）
-    loaded_plugins: Vec<String>,
+    /// 打开参数窗口 / 并联机架窗口的位置
+    editing_slot: Option<usize>,
+    editing_rack: Option<usize>,
     
-    /// 选中的插件索引
+    /// 选中的插件索引（过滤后的列表中）
     selected_plugin: Option<usize>,
     
     /// 搜索过滤文本
@@ -30,6 +63,33 @@ pub struct PluginLoaderApp {
     
     /// 扫描状态消息
     scan_status: String,
+    
+    /// 用户设置（音频设备和配置）
+    settings: UserSettings,
+    
+    /// 音频设置窗口中正在编辑的配置（窗口关闭时为 None）
+    audio_draft: Option<AudioConfig>,
+    
+    /// 打开音频设置窗口时枚举的输入、输出和 MIDI 设备
+    input_devices: Vec<String>,
+    output_devices: Vec<String>,
+    midi_devices: Vec<String>,
+    
+    /// 当前工程，以及工程窗口中的文件路径和状态消息
+    projects: ProjectManager,
+    project_path: String,
+    show_project_window: bool,
+    project_status: String,
+    
+    /// 新预设的名称
+    preset_name: String,
+    
+    /// 输入输出电平表（每个声道一个）
+    input_levels: Vec<LevelMeter>,
+    output_levels: Vec<LevelMeter>,
+    
+    /// 调音器读数
+    tuner: TunerDisplay,
 }
 
 impl Default for PluginLoaderApp {
@@ -48,15 +108,329 @@ impl PluginLoaderApp {
         let plugins = scanner.load_cache().unwrap_or_default();
         info!("从缓存加载了 {} 个插件", plugins.len());
         
+        let settings = UserSettings::load_or_default();
+        let mut engine = AudioEngine::new();
+        engine.set_audio_config(settings.audio.clone());
+        let tuner = TunerDisplay::new(engine.tuner_readout());
+        
         Self {
             scanner,
             plugins,
-            audio_engine: Arc::new(Mutex::new(None)),
-            loaded_plugins: Vec::new(),
+            engine,
+            loader: PluginLoader::new(),
+            plugin_path: String::new(),
+            chain_status: String::new(),
+            editing_slot: None,
+            editing_rack: None,
             selected_plugin: None,
             search_filter: String::new(),
             show_scan_window: false,
             scan_status: String::new(),
+            settings,
+            audio_draft: None,
+            input_devices: Vec::new(),
+            output_devices: Vec::new(),
+            midi_devices: Vec::new(),
+            projects: ProjectManager::new(),
+            project_path: "project.json".to_string(),
+            show_project_window: false,
+            project_status: String::new(),
+            preset_name: String::new(),
+            input_levels: Vec::new(),
+            output_levels: Vec::new(),
+            tuner,
+        }
+    }
+    
+    /// 启动音频引擎，失败时记录在日志中
+    fn start_audio(&mut self) {
+        info!("启动音频引擎");
+        if let Err(e) = self.engine.start() {
+            error!("音频引擎启动失败: {}", e);
+        }
+    }
+    
+    /// 重新打开音频设备
+    fn restart_audio(&mut self) {
+        info!("重启音频引擎");
+        if let Err(e) = self.engine.restart() {
+            error!("音频引擎重启失败: {}", e);
+        }
+    }
+    
+    /// 打开音频设置窗口（重新枚举设备）
+    fn open_audio_settings(&mut self) {
+        self.input_devices = input_device_names();
+        self.output_devices = output_device_names();
+        self.midi_devices = midi_device_paths().iter().map(|path| path.display().to_string()).collect();
+        self.audio_draft = Some(self.engine.audio_config().clone());
+    }
+    
+    /// 应用音频设置：保存到用户设置，运行中时重新打开设备
+    fn apply_audio_settings(&mut self, config: AudioConfig) {
+        info!("应用音频设置: {:?}", config);
+        self.engine.set_audio_config(config.clone());
+        self.settings.audio = config;
+        if let Err(e) = self.settings.save(&UserSettings::default_path()) {
+            error!("保存用户设置失败: {:#}", e);
+        }
+        if self.engine.is_running() {
+            self.restart_audio();
+        }
+    }
+    
+    /// 新工程的名称（工程文件名）
+    fn project_name(&self) -> String {
+        Path::new(&self.project_path).file_stem().and_then(|s| s.to_str()).unwrap_or("Untitled").to_string()
+    }
+    
+    /// 新建工程：清空插件链和预设
+    fn new_project(&mut self) {
+        self.projects.new_project(self.project_name());
+        let cleared = self.edit_chain(|processor| {
+            processor.set_presets(Vec::new());
+            processor.clear()
+        });
+        if cleared {
+            self.project_status = "已新建工程".to_string();
+        }
+    }
+    
+    /// 打开工程：插件链和预设交给音频引擎（插件按当前的扫描结果创建）
+    fn open_project(&mut self) {
+        let result = self
+            .projects
+            .open_project(Path::new(&self.project_path))
+            .and_then(|project| self.engine.load_project(project, plugin_factory(self.plugins.clone())).map(|_| project.name.clone()));
+        match result {
+            Ok(name) => self.project_status = format!("已打开工程: {}", name),
+            Err(e) => {
+                self.projects.close_project();
+                self.project_status = format!("打开工程失败: {:#}", e);
+                error!("{}", self.project_status);
+            }
+        }
+    }
+    
+    /// 保存工程：插件链和预设从音频引擎读取（运行中不停止音频流）
+    /// save_as 为 false 时保存到工程原来的文件
+    fn save_project(&mut self, save_as: bool) {
+        let path = Path::new(&self.project_path).to_path_buf();
+        let result = self.read_project().and_then(|_| match save_as {
+            true => self.projects.save_as(&path),
+            false => self.projects.save_current(),
+        });
+        self.project_status = match result {
+            Ok(()) => "已保存工程".to_string(),
+            Err(e) => {
+                error!("保存工程失败: {:#}", e);
+                format!("保存工程失败: {:#}", e)
+            }
+        };
+    }
+    
+    /// 把音频引擎的插件链和预设写入当前工程（没有工程时新建一个）
+    fn read_project(&mut self) -> Result<()> {
+        if self.projects.get_current_project().is_none() {
+            self.projects.new_project(self.project_name());
+        }
+        let project = self.projects.get_current_project_mut().ok_or_else(|| anyhow::anyhow!("没有打开的工程"))?;
+        self.engine.save_project(project)
+    }
+    
+    /// 把当前插件链另存为预设（写入当前工程，再交给音频引擎）
+    fn save_preset(&mut self) {
+        let name = self.preset_name.trim().to_string();
+        if name.is_empty() {
+            self.chain_status = "请输入预设名称".to_string();
+            return;
+        }
+        if let Err(e) = self.read_project() {
+            self.chain_status = format!("保存预设失败: {:#}", e);
+            error!("{}", self.chain_status);
+            return;
+        }
+        if let Some(project) = self.projects.get_current_project_mut() {
+            let program = project.add_preset(name);
+            let presets = project.presets.clone();
+            self.edit_chain(|processor| {
+                processor.set_presets(presets);
+                Ok(())
+            });
+            self.chain_status = format!("已保存预设 {}（保存工程后写入文件）", program);
+            self.preset_name.clear();
+        }
+    }
+    
+    /// 在插件链上执行一次编辑，失败时记录在状态消息中，返回是否成功
+    fn edit_chain(&mut self, edit: impl FnOnce(&mut AudioProcessorEngine) -> Result<()>) -> bool {
+        let result = match self.engine.processor().lock() {
+            Ok(mut processor) => edit(&mut processor),
+            Err(_) => Err(anyhow::anyhow!("插件链被锁定")),
+        };
+        match result {
+            Ok(()) => {
+                self.chain_status.clear();
+                true
+            }
+            Err(e) => {
+                self.chain_status = format!("{:#}", e);
+                error!("编辑插件链失败: {}", self.chain_status);
+                false
+            }
+        }
+    }
+    
+    /// 插件库中选中的插件
+    fn selected_info(&self) -> Option<PluginInfo> {
+        self.filtered_plugins().get(self.selected_plugin?).map(|info| (*info).clone())
+    }
+    
+    /// 执行插件链面板中的操作
+    fn apply_chain_action(&mut self, action: ChainAction) {
+        match action {
+            ChainAction::Add | ChainAction::Replace(_) => {
+                let Some(info) = self.selected_info() else {
+                    self.chain_status = "请先在插件库中选择插件".to_string();
+                    return;
+                };
+                let plugin = match self.loader.load_from_info(&info) {
+                    Ok(plugin) => plugin,
+                    Err(e) => {
+                        self.chain_status = format!("加载插件失败: {:#}", e);
+                        return;
+                    }
+                };
+                match action {
+                    ChainAction::Replace(index) => self.edit_chain(|processor| processor.replace_plugin(index, plugin)),
+                    _ => self.edit_chain(|processor| processor.add_plugin(plugin)),
+                };
+            }
+            ChainAction::LoadFile => {
+                let plugin = match self.loader.load_plugin(Path::new(self.plugin_path.trim())) {
+                    Ok(plugin) => plugin,
+                    Err(e) => {
+                        self.chain_status = format!("加载插件失败: {:#}", e);
+                        return;
+                    }
+                };
+                self.edit_chain(|processor| processor.add_plugin(plugin));
+            }
+            ChainAction::Move(from, to) => {
+                self.edit_chain(|processor| processor.move_plugin(from, to));
+            }
+            ChainAction::Remove(index) => {
+                self.edit_chain(|processor| processor.remove_plugin(index));
+                self.editing_slot = None;
+                self.editing_rack = None;
+            }
+            ChainAction::Settings(index, settings) => {
+                self.edit_chain(|processor| processor.set_slot_settings(index, settings));
+            }
+            ChainAction::Clear => {
+                self.edit_chain(|processor| processor.clear());
+                self.editing_slot = None;
+                self.editing_rack = None;
+            }
+        }
+    }
+    
+    /// 参数窗口：按插件的参数描述显示滑块、开关和枚举值，双击滑块恢复默认值
+    fn parameter_window(&mut self, ctx: &egui::Context) {
+        let Some(slot) = self.editing_slot else {
+            return;
+        };
+        let Ok(mut processor) = self.engine.processor().lock() else {
+            return;
+        };
+        let Some(name) = processor.plugin_names().get(slot).cloned() else {
+            self.editing_slot = None;
+            return;
+        };
+        
+        let mut open = true;
+        egui::Window::new(format!("参数 - {}", name))
+            .id(egui::Id::new("parameters"))
+            .open(&mut open)
+            .default_width(400.0)
+            .show(ctx, |ui| {
+                let descriptors = processor.parameters(slot).to_vec();
+                if descriptors.is_empty() {
+                    ui.label("没有可调节的参数");
+                }
+                egui::Grid::new("parameter_grid").num_columns(2).show(ui, |ui| {
+                    for descriptor in descriptors.iter().filter(|descriptor| !descriptor.flags.hidden) {
+                        let id = descriptor.id;
+                        let mut value = processor.parameter_value(slot, id).unwrap_or_else(|| descriptor.default_normalized());
+                        ui.label(&descriptor.name);
+                        let changed = if descriptor.is_enum() {
+                            let text = processor.parameter_text(slot, id, value).unwrap_or_default();
+                            let mut changed = false;
+                            egui::ComboBox::from_id_source(("parameter", id)).selected_text(text).show_ui(ui, |ui| {
+                                for (plain, label) in &descriptor.value_names {
+                                    changed |= ui.selectable_value(&mut value, descriptor.to_normalized(*plain), label).changed();
+                                }
+                            });
+                            changed
+                        } else if descriptor.is_toggle() {
+                            let mut on = value >= 0.5;
+                            let changed = ui.add_enabled(!descriptor.flags.read_only, egui::Checkbox::without_text(&mut on)).changed();
+                            value = if on { 1.0 } else { 0.0 };
+                            changed
+                        } else {
+                            let slider = egui::Slider::new(&mut value, 0.0..=1.0)
+                                .custom_formatter(|normalized, _| processor.parameter_text(slot, id, normalized).unwrap_or_default())
+                                .custom_parser(|text| processor.parse_parameter(slot, id, text));
+                            let response = ui.add_enabled(!descriptor.flags.read_only, slider);
+                            if response.double_clicked() {
+                                value = descriptor.default_normalized();
+                            }
+                            response.changed() || response.double_clicked()
+                        };
+                        ui.end_row();
+                        if changed {
+                            processor.set_parameter(slot, id, value);
+                        }
+                    }
+                });
+            });
+        if !open {
+            self.editing_slot = None;
+        }
+    }
+    
+    /// 并联机架窗口：添加和移除支路、调节支路增益和声像、编辑支路中的插件
+    /// 编辑经命令队列交给音频线程，运行中也可以进行
+    fn rack_window(&mut self, ctx: &egui::Context) {
+        let Some(index) = self.editing_rack else {
+            return;
+        };
+        let selected = self.selected_info();
+        let loader = &mut self.loader;
+        let Ok(mut processor) = self.engine.processor().lock() else {
+            return;
+        };
+        if !processor.is_rack(index) {
+            self.editing_rack = None;
+            return;
+        }
+        
+        let mut open = true;
+        let mut result = Ok(());
+        egui::Window::new(format!("并联机架 - 位置 {}", index + 1))
+            .id(egui::Id::new("rack"))
+            .open(&mut open)
+            .default_width(450.0)
+            .show(ctx, |ui| {
+                result = rack_editor(ui, &mut processor, index, loader, selected.as_ref());
+            });
+        drop(processor);
+        if let Err(e) = result {
+            self.chain_status = format!("{:#}", e);
+            error!("编辑并联机架失败: {}", self.chain_status);
+        }
+        if !open {
+            self.editing_rack = None;
         }
     }
     
@@ -97,6 +471,12 @@ impl PluginLoaderApp {
 
 impl eframe::App for PluginLoaderApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // 释放音频线程退回的插件；运行时定期刷新电平表
+        self.engine.poll();
+        if self.engine.is_running() {
+            ctx.request_repaint_after(Duration::from_millis(50));
+        }
+        
         // 顶部菜单栏
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
@@ -106,12 +486,8 @@ impl eframe::App for PluginLoaderApp {
                         ui.close_menu();
                     }
                     ui.separator();
-                    if ui.button("📂 打开工程").clicked() {
-                        // TODO: 实现打开工程
-                        ui.close_menu();
-                    }
-                    if ui.button("💾 保存工程").clicked() {
-                        // TODO: 实现保存工程
+                    if ui.button("📂 打开 / 保存工程").clicked() {
+                        self.show_project_window = true;
                         ui.close_menu();
                     }
                     ui.separator();
@@ -122,12 +498,11 @@ impl eframe::App for PluginLoaderApp {
                 
                 ui.menu_button("音频", |ui| {
                     if ui.button("⚙️ 音频设置").clicked() {
-                        // TODO: 打开音频设置
+                        self.open_audio_settings();
                         ui.close_menu();
                     }
                     if ui.button("🎸 启动音频引擎").clicked() {
-                        // TODO: 启动音频引擎
-                        info!("启动音频引擎");
+                        self.start_audio();
                         ui.close_menu();
                     }
                 });
@@ -173,7 +548,8 @@ impl eframe::App for PluginLoaderApp {
                 
                 // 插件列表
                 egui::ScrollArea::vertical().show(ui, |ui| {
-                    let filtered = self.filtered_plugins();
+                    // 复制一份，点击时要修改 selected_plugin
+                    let filtered: Vec<PluginInfo> = self.filtered_plugins().into_iter().cloned().collect();
                     
                     for (idx, plugin) in filtered.iter().enumerate() {
                         let is_selected = self.selected_plugin == Some(idx);
@@ -208,34 +584,110 @@ impl eframe::App for PluginLoaderApp {
             });
         
         // 中央面板 - 插件链
+        let mut action = None;
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.heading("🔗 插件链");
             ui.separator();
             
-            if self.loaded_plugins.is_empty() {
+            ui.horizontal(|ui| {
+                if ui.button("➕ 添加选中插件").clicked() {
+                    action = Some(ChainAction::Add);
+                }
+                ui.text_edit_singleline(&mut self.plugin_path);
+                if ui.button("📂 从文件加载").clicked() {
+                    action = Some(ChainAction::LoadFile);
+                }
+                if ui.button("🗑️ 清空").clicked() {
+                    action = Some(ChainAction::Clear);
+                }
+            });
+            if !self.chain_status.is_empty() {
+                ui.colored_label(egui::Color32::from_rgb(255, 100, 100), &self.chain_status);
+            }
+            
+            let Ok(mut processor) = self.engine.processor().lock() else {
+                return;
+            };
+            
+            // 整条链的旁通、输出增益和干湿比
+            ui.horizontal(|ui| {
+                let mut bypass = processor.is_bypassed();
+                if ui.checkbox(&mut bypass, "旁通").changed() {
+                    processor.set_bypass(bypass);
+                }
+                let mut gain_db = processor.gain_db();
+                if ui.add(egui::Slider::new(&mut gain_db, -24.0..=24.0).text("增益 dB")).changed() {
+                    processor.set_gain_db(gain_db);
+                }
+                let mut mix = processor.mix();
+                if ui.add(egui::Slider::new(&mut mix, 0.0..=1.0).text("干湿")).changed() {
+                    processor.set_mix(mix);
+                }
+            });
+            ui.separator();
+            
+            if processor.is_empty() {
                 ui.vertical_centered(|ui| {
                     ui.add_space(100.0);
                     ui.label("暂无插件");
-                    ui.label("从左侧插件库拖拽插件到此处");
+                    ui.label("在左侧插件库选择插件后点击“添加选中插件”");
                 });
-            } else {
-                for (idx, plugin_name) in self.loaded_plugins.iter().enumerate() {
+                return;
+            }
+            
+            let count = processor.len();
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for (idx, plugin_name) in processor.plugin_names().iter().enumerate() {
                     ui.horizontal(|ui| {
                         ui.label(format!("{}.", idx + 1));
                         ui.label(plugin_name);
+                        match processor.slot_layout(idx).map(|layout| layout.conversion()) {
+                            Some(LayoutConversion::Upmix) => ui.small("单声道 → 立体声"),
+                            Some(LayoutConversion::Downmix) => ui.small("立体声 → 单声道"),
+                            _ => ui.small(""),
+                        };
+                        if let Some(latency) = processor.slot_latency(idx).filter(|&latency| latency > 0) {
+                            ui.small(format!("延迟 {} 帧", latency));
+                        }
                         ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                            if ui.button("❌").clicked() {
-                                // TODO: 移除插件
+                            if ui.button("❌").on_hover_text("移除").clicked() {
+                                action = Some(ChainAction::Remove(idx));
+                            }
+                            if processor.is_rack(idx) && ui.button("🔀").on_hover_text("编辑并联支路").clicked() {
+                                self.editing_rack = Some(idx);
+                            }
+                            if ui.button("⚙️").on_hover_text("参数").clicked() {
+                                self.editing_slot = Some(idx);
+                            }
+                            if ui.button("🔁").on_hover_text("替换为选中的插件").clicked() {
+                                action = Some(ChainAction::Replace(idx));
                             }
-                            if ui.button("⚙️").clicked() {
-                                // TODO: 打开插件 UI
+                            if idx + 1 < count && ui.button("⬇").clicked() {
+                                action = Some(ChainAction::Move(idx, idx + 1));
+                            }
+                            if idx > 0 && ui.button("⬆").clicked() {
+                                action = Some(ChainAction::Move(idx, idx - 1));
                             }
                         });
                     });
+                    if let Some(mut settings) = processor.slot_settings(idx) {
+                        ui.horizontal(|ui| {
+                            let mut changed = ui.checkbox(&mut settings.bypass, "旁通").changed();
+                            changed |= ui.checkbox(&mut settings.mute, "静音").changed();
+                            changed |= ui.checkbox(&mut settings.solo, "独奏").changed();
+                            changed |= ui.add(egui::Slider::new(&mut settings.mix, 0.0..=1.0).text("干湿")).changed();
+                            if changed {
+                                action = Some(ChainAction::Settings(idx, settings));
+                            }
+                        });
+                    }
                     ui.separator();
                 }
-            }
+            });
         });
+        if let Some(action) = action {
+            self.apply_chain_action(action);
+        }
         
         // 右侧面板 - 电平表和控制
         egui::SidePanel::right("meters")
@@ -245,27 +697,62 @@ impl eframe::App for PluginLoaderApp {
                 ui.heading("🎚️ 电平表");
                 ui.separator();
                 
-                // TODO: 实现实时电平表
                 ui.label("输入:");
-                ui.add(egui::ProgressBar::new(0.5).show_percentage());
+                channel_meters(ui, &mut self.input_levels, &self.engine.input_meter().get_channel_peaks_db());
                 
                 ui.add_space(10.0);
                 
                 ui.label("输出:");
-                ui.add(egui::ProgressBar::new(0.3).show_percentage());
+                channel_meters(ui, &mut self.output_levels, &self.engine.output_meter().get_channel_peaks_db());
+                
+                ui.separator();
+                
+                ui.heading("🎵 调音器");
+                self.tuner.ui(ui);
+                
+                ui.separator();
+                
+                // 预设：点击或 MIDI 程序切换后交叉淡化到新的插件链
+                ui.heading("🎛️ 预设");
+                if let Ok(mut processor) = self.engine.processor().lock() {
+                    if processor.presets().is_empty() {
+                        ui.label("暂无预设");
+                    }
+                    let current = processor.current_preset();
+                    let names: Vec<String> = processor.presets().iter().map(|preset| preset.name.clone()).collect();
+                    for (program, name) in names.iter().enumerate() {
+                        let label = format!("{}. {}", program, name);
+                        if ui.selectable_label(current == Some(program), label).clicked() {
+                            processor.set_plugin_factory(plugin_factory(self.plugins.clone()));
+                            if let Err(e) = processor.select_preset(program) {
+                                error!("切换预设失败: {}", e);
+                            }
+                        }
+                    }
+                    let mut options = processor.swap_options();
+                    if ui.checkbox(&mut options.keep_tail, "切换时保留尾音").changed() {
+                        processor.set_swap_options(options);
+                    }
+                }
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(&mut self.preset_name);
+                    if ui.button("💾 存为预设").clicked() {
+                        self.save_preset();
+                    }
+                });
                 
                 ui.separator();
                 
                 // 控制按钮
                 ui.vertical_centered(|ui| {
-                    if ui.button("▶️ 播放").clicked() {
-                        // TODO: 启动音频
+                    if ui.button("▶️ 启动").clicked() {
+                        self.start_audio();
                     }
-                    if ui.button("⏸️ 暂停").clicked() {
-                        // TODO: 暂停音频
+                    if ui.button("🔄 重启").clicked() {
+                        self.restart_audio();
                     }
                     if ui.button("⏹️ 停止").clicked() {
-                        // TODO: 停止音频
+                        self.engine.stop();
                     }
                 });
             });
@@ -273,16 +760,156 @@ impl eframe::App for PluginLoaderApp {
         // 底部状态栏
         egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
             ui.horizontal(|ui| {
-                ui.label("就绪");
+                ui.label(if self.engine.is_running() { "运行中" } else { "就绪" });
                 ui.separator();
-                ui.label(format!("插件: {}", self.loaded_plugins.len()));
+                let (plugins, config, latency) = match self.engine.processor().lock() {
+                    Ok(processor) => (processor.len(), Some(processor.config()), Some(processor.latency_ms())),
+                    Err(_) => (0, None, None),
+                };
+                ui.label(format!("插件: {}", plugins));
                 ui.separator();
-                ui.label("采样率: 48000 Hz");
+                match self.engine.sample_rate() {
+                    Some(rate) => ui.label(format!("采样率: {} Hz", rate)),
+                    None => ui.label("采样率: --"),
+                };
                 ui.separator();
-                ui.label("延迟: 5.3 ms");
+                match latency.filter(|_| self.engine.sample_rate().is_some()) {
+                    Some(ms) => ui.label(format!("延迟: {:.1} ms", ms)),
+                    None => ui.label("延迟: --"),
+                };
+                if let Some(config) = config {
+                    ui.label(format!("（块 {} 帧）", config.max_block_size));
+                }
+                if let Some(stats) = self.engine.transport_stats() {
+                    ui.separator();
+                    ui.label(format!("缓冲: {}/{} 帧 ({:+.1})", stats.fill_frames(), stats.target_frames(), stats.fill_error()));
+                    ui.label(format!("欠载: {} 溢出: {} 重新对齐: {}", stats.underruns(), stats.overruns(), stats.recenters()));
+                    ui.label(format!("漂移: {:+.0} ppm", stats.ratio_ppm()));
+                }
+                if self.engine.is_running() && self.engine.fallback_reason().is_some() {
+                    ui.separator();
+                    ui.colored_label(egui::Color32::from_rgb(255, 180, 80), "⚠️ 使用默认设备");
+                }
             });
         });
         
+        // 音频设置窗口
+        if let Some(mut draft) = self.audio_draft.take() {
+            let mut open = true;
+            let mut apply = false;
+            egui::Window::new("音频设置")
+                .open(&mut open)
+                .default_width(400.0)
+                .show(ctx, |ui| {
+                    egui::Grid::new("audio_settings").num_columns(2).show(ui, |ui| {
+                        ui.label("输入设备:");
+                        device_combo(ui, "input_device", "系统默认", &mut draft.input_device, &self.input_devices);
+                        ui.end_row();
+                        
+                        ui.label("输出设备:");
+                        device_combo(ui, "output_device", "系统默认", &mut draft.output_device, &self.output_devices);
+                        ui.end_row();
+                        
+                        ui.label("MIDI 输入:");
+                        device_combo(ui, "midi_input", "无", &mut draft.midi_input, &self.midi_devices);
+                        ui.end_row();
+                        
+                        ui.label("采样率:");
+                        egui::ComboBox::from_id_source("sample_rate")
+                            .selected_text(format!("{} Hz", draft.sample_rate))
+                            .show_ui(ui, |ui| {
+                                for rate in SAMPLE_RATES {
+                                    ui.selectable_value(&mut draft.sample_rate, rate, format!("{} Hz", rate));
+                                }
+                            });
+                        ui.end_row();
+                        
+                        ui.label("缓冲区:");
+                        egui::ComboBox::from_id_source("buffer_size")
+                            .selected_text(format!("{} 帧", draft.buffer_size))
+                            .show_ui(ui, |ui| {
+                                for size in BUFFER_SIZES {
+                                    let latency = size as f32 / draft.sample_rate as f32 * 1000.0;
+                                    ui.selectable_value(&mut draft.buffer_size, size, format!("{} 帧 ({:.1} ms)", size, latency));
+                                }
+                            });
+                        ui.end_row();
+                        
+                        ui.label("通道:");
+                        ui.horizontal(|ui| {
+                            ui.radio_value(&mut draft.channels, 1, "单声道");
+                            ui.radio_value(&mut draft.channels, 2, "立体声");
+                        });
+                        ui.end_row();
+                        
+                        ui.label("抖动:");
+                        ui.checkbox(&mut draft.dither, "整数输出格式加 TPDF 抖动");
+                        ui.end_row();
+                    });
+                    
+                    // 上次启动时请求的配置不可用
+                    if let Some(reason) = self.engine.fallback_reason() {
+                        ui.separator();
+                        ui.colored_label(
+                            egui::Color32::from_rgb(255, 100, 100),
+                            format!("无法使用所选配置，已改用默认设备: {}", reason)
+                        );
+                    }
+                    
+                    ui.separator();
+                    if ui.button("应用").clicked() {
+                        apply = true;
+                    }
+                });
+            if apply {
+                self.apply_audio_settings(draft.clone());
+            }
+            if open {
+                self.audio_draft = Some(draft);
+            }
+        }
+        
+        // 工程窗口
+        if self.show_project_window {
+            let mut open = true;
+            egui::Window::new("工程")
+                .open(&mut open)
+                .default_width(400.0)
+                .show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("文件:");
+                        ui.text_edit_singleline(&mut self.project_path);
+                    });
+                    ui.horizontal(|ui| {
+                        if ui.button("📄 新建").clicked() {
+                            self.new_project();
+                        }
+                        if ui.button("📂 打开").clicked() {
+                            self.open_project();
+                        }
+                        if ui.button("💾 保存").clicked() {
+                            self.save_project(false);
+                        }
+                        if ui.button("💾 另存为").clicked() {
+                            self.save_project(true);
+                        }
+                        if ui.button("关闭").clicked() {
+                            self.projects.close_project();
+                            self.project_status.clear();
+                        }
+                    });
+                    match self.projects.get_current_project() {
+                        Some(project) => ui.label(format!("当前工程: {}（{} 个预设）", project.name, project.presets.len())),
+                        None => ui.label("没有打开的工程"),
+                    };
+                    ui.label(&self.project_status);
+                });
+            self.show_project_window = open;
+        }
+        
+        self.parameter_window(ctx);
+        self.rack_window(ctx);
+        
         // 扫描窗口
         if self.show_scan_window {
             egui::Window::new("插件扫描")
@@ -303,3 +930,96 @@ impl eframe::App for PluginLoaderApp {
     }
 }
 
+
+/// 并联机架编辑器：编辑经引擎的命令队列交给位置 index 上的机架，音频流运行中也可以编辑
+fn rack_editor(
+    ui: &mut egui::Ui,
+    processor: &mut AudioProcessorEngine,
+    index: usize,
+    loader: &mut PluginLoader,
+    selected: Option<&PluginInfo>,
+) -> Result<()> {
+    let mut remove_lane = None;
+    for lane in 0..processor.lane_count(index) {
+        let latencies = processor.lane_path_latencies(index, lane);
+        ui.horizontal(|ui| {
+            ui.strong(format!("支路 {}", lane + 1));
+            if let Some(&latency) = latencies.last().filter(|&&latency| latency > 0) {
+                ui.small(format!("延迟 {} 帧", latency));
+            }
+            if ui.button("❌").on_hover_text("移除支路").clicked() {
+                remove_lane = Some(lane);
+            }
+        });
+        
+        let mut gain_db = processor.lane_gain_db(index, lane).unwrap_or(0.0);
+        if ui.add(egui::Slider::new(&mut gain_db, -60.0..=12.0).text("增益 dB")).changed() {
+            processor.set_lane_gain_db(index, lane, gain_db);
+        }
+        let mut pan = processor.lane_pan(index, lane).unwrap_or(0.0);
+        if ui.add(egui::Slider::new(&mut pan, -1.0..=1.0).text("声像")).changed() {
+            processor.set_lane_pan(index, lane, pan);
+        }
+        
+        let names = processor.lane_plugin_names(index, lane);
+        if names.is_empty() {
+            ui.small("空支路（直通）");
+        }
+        for (slot, name) in names.iter().enumerate() {
+            ui.horizontal(|ui| -> Result<()> {
+                ui.label(format!("{}. {}", slot + 1, name));
+                ui.small(format!("累计延迟 {} 帧", latencies[slot]));
+                let mut settings = processor.lane_slot_settings(index, lane, slot).unwrap_or_default();
+                let mut changed = ui.checkbox(&mut settings.bypass, "旁通").changed();
+                changed |= ui.checkbox(&mut settings.mute, "静音").changed();
+                changed |= ui.checkbox(&mut settings.solo, "独奏").changed();
+                changed |= ui.add(egui::Slider::new(&mut settings.mix, 0.0..=1.0).text("干湿")).changed();
+                if changed {
+                    processor.set_lane_slot_settings(index, lane, slot, settings)?;
+                }
+                if slot > 0 && ui.button("⬆").clicked() {
+                    processor.move_lane_plugin(index, lane, slot, slot - 1)?;
+                }
+                if ui.button("❌").clicked() {
+                    processor.remove_lane_plugin(index, lane, slot)?;
+                }
+                Ok(())
+            })
+            .inner?;
+        }
+        ui.horizontal(|ui| -> Result<()> {
+            if ui.button("➕ 添加选中插件").clicked() {
+                let info = selected.ok_or_else(|| anyhow::anyhow!("请先在插件库中选择插件"))?;
+                processor.add_lane_plugin(index, lane, loader.load_from_info(info)?)?;
+            }
+            if ui.button("🗑️ 清空支路").clicked() {
+                processor.clear_lane(index, lane)?;
+            }
+            Ok(())
+        })
+        .inner?;
+        ui.separator();
+    }
+    
+    if let Some(lane) = remove_lane {
+        processor.remove_lane(index, lane)?;
+    }
+    if ui.button("➕ 添加支路").clicked() {
+        processor.add_lane(index)?;
+    }
+    Ok(())
+}
+
+/// 设备选择下拉框，None 显示为 none_label（系统默认设备或不使用）
+fn device_combo(ui: &mut egui::Ui, id: &str, none_label: &str, selected: &mut Option<String>, devices: &[String]) {
+    let text = selected.clone().unwrap_or_else(|| none_label.to_string());
+    egui::ComboBox::from_id_source(id)
+        .selected_text(text)
+        .width(250.0)
+        .show_ui(ui, |ui| {
+            ui.selectable_value(selected, None, none_label);
+            for name in devices {
+                ui.selectable_value(selected, Some(name.clone()), name);
+            }
+        });
+}
@@ -3,23 +3,46 @@
 
 use eframe::egui;
 
-/// 电平表组件
+use crate::audio::{format_db, Smoother, TunerReadout};
+
+/// 电平表回落到 -60 dB 的时间（毫秒）
+const METER_RELEASE_MS: f32 = 600.0;
+
+/// 电平表每秒更新的次数（与运行时的界面刷新间隔一致）
+const METER_UPDATES_PER_SECOND: f32 = 20.0;
+
+/// 电平表组件：峰值上升时立即显示，之后按指数曲线回落
 pub struct LevelMeter {
-    pub level: f32,
-    pub peak: f32,
+    level: Smoother,
 }
 
 impl LevelMeter {
     pub fn new() -> Self {
         Self {
-            level: 0.0,
-            peak: 0.0,
+            level: Smoother::exponential(METER_RELEASE_MS, METER_UPDATES_PER_SECOND),
         }
     }
     
-    pub fn ui(&mut self, ui: &mut egui::Ui) {
-        // TODO: 实现漂亮的电平表可视化
-        ui.add(egui::ProgressBar::new(self.level).show_percentage());
+    /// 送入本次刷新读到的峰值电平（dB）并绘制
+    pub fn ui(&mut self, ui: &mut egui::Ui, peak_db: f32) {
+        let peak = 10f32.powf(peak_db / 20.0);
+        if peak >= self.level.current() {
+            self.level.reset(peak);
+        } else {
+            self.level.set_target(peak);
+        }
+        let level = self.level.next_value();
+        let text = format_db(if level > 0.0 { 20.0 * level.log10() } else { -100.0 });
+        let color = if level >= 1.0 { egui::Color32::from_rgb(220, 60, 60) } else { egui::Color32::from_rgb(80, 180, 90) };
+        ui.add(egui::ProgressBar::new(level.min(1.0)).fill(color).text(text));
+    }
+}
+
+/// 按声道数显示一组电平表（声道数变化时增减）
+pub fn channel_meters(ui: &mut egui::Ui, meters: &mut Vec<LevelMeter>, peaks_db: &[f32]) {
+    meters.resize_with(peaks_db.len(), LevelMeter::new);
+    for (meter, &peak_db) in meters.iter_mut().zip(peaks_db) {
+        meter.ui(ui, peak_db);
     }
 }
 
@@ -29,3 +52,28 @@ impl Default for LevelMeter {
     }
 }
 
+/// 调音器读数组件
+pub struct TunerDisplay {
+    readout: TunerReadout,
+}
+
+impl TunerDisplay {
+    pub fn new(readout: TunerReadout) -> Self {
+        Self { readout }
+    }
+
+    pub fn ui(&self, ui: &mut egui::Ui) {
+        match self.readout.reading() {
+            Some(reading) => {
+                ui.label(egui::RichText::new(format!("{}{}", reading.note, reading.octave)).size(28.0).strong());
+                // 偏差条：正中间为准
+                ui.add(egui::ProgressBar::new((reading.cents + 50.0) / 100.0)
+                    .text(format!("{:+.0}¢", reading.cents)));
+                ui.label(format!("{:.1} Hz", reading.frequency));
+            }
+            None => {
+                ui.label(egui::RichText::new("--").size(28.0));
+            }
+        }
+    }
+}